@@ -0,0 +1,5 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "grpc-server")]
+    tonic_build::compile_protos("proto/mmr.proto")?;
+    Ok(())
+}
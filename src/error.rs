@@ -1,21 +1,73 @@
-use crate::store::{StoreKey, StoreValue};
+use crate::store::StoreKey;
+use crate::types::MmrId;
+use crate::verify::VerifyError;
+use std::fmt;
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum StoreError {
     #[error("store internal error: {0}")]
     Internal(String),
-    #[error("store type mismatch for key {key:?}: expected {expected}, got {actual:?}")]
+    #[error("store operation `{0}` is not supported by this backend")]
+    Unsupported(&'static str),
+    #[error("transient store error: {0}")]
+    Transient(String),
+    #[error("mmr id allocation conflicted with concurrent allocators {0} times")]
+    AllocationConflict(u32),
+    #[error("store type mismatch for key {key:?}: expected {expected}, got {actual}")]
     TypeMismatch {
         key: StoreKey,
         expected: &'static str,
-        actual: StoreValue,
+        actual: &'static str,
     },
     #[cfg(feature = "postgres-store")]
     #[error("sqlx error: {0}")]
     Sqlx(#[from] sqlx::Error),
 }
 
+/// What a failed store operation was working on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreOpTarget {
+    None,
+    Key(StoreKey),
+    Batch(usize),
+}
+
+impl fmt::Display for StoreOpTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => Ok(()),
+            Self::Key(key) => write!(f, " at {:?} {}", key.kind, key.index),
+            Self::Batch(size) => write!(f, " for a batch of {size}"),
+        }
+    }
+}
+
+impl StoreError {
+    /// True for failures that may succeed when retried unchanged: refused or reset
+    /// connections, pool timeouts, serialization failures and deadlocks.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Transient(_) => true,
+            #[cfg(feature = "postgres-store")]
+            Self::Sqlx(err) => match err {
+                sqlx::Error::Io(io_err) => matches!(
+                    io_err.kind(),
+                    std::io::ErrorKind::ConnectionReset | std::io::ErrorKind::ConnectionRefused
+                ),
+                sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed => true,
+                sqlx::Error::Database(db_err) => db_err.code().is_some_and(|code| {
+                    matches!(code.as_ref(), "40001" | "40P01" | "57P01" | "57P03")
+                        || code.starts_with("08")
+                }),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum HasherError {
     #[error("invalid hex value `{value}`: {source}")]
@@ -29,28 +81,172 @@ pub enum HasherError {
     InputTooLarge { value: String, max_bytes: usize },
     #[error("value `{value}` cannot be represented as a Starknet field element")]
     InvalidFieldElement { value: String },
+    #[error("hasher does not support `{0}`")]
+    Unsupported(&'static str),
 }
 
 #[derive(Debug, Error)]
 pub enum MmrError {
     #[error("store error: {0}")]
     Store(#[from] StoreError),
+    #[error("store `{operation}` failed for mmr {mmr_id}{target}: {source}")]
+    StoreOp {
+        mmr_id: MmrId,
+        operation: &'static str,
+        target: StoreOpTarget,
+        source: Box<StoreError>,
+    },
     #[error("hasher error: {0}")]
     Hasher(#[from] HasherError),
     #[error("cannot initialize from peaks for non-empty MMR")]
     NonEmptyMmr,
+    #[error("mmr {0} does not exist")]
+    MmrNotFound(crate::types::MmrId),
+    #[error("opening an existing mmr needs an mmr id")]
+    MissingMmrId,
+    #[error("mmr id 0 is reserved for the id allocator")]
+    ReservedMmrId,
+    #[error("mmr {0} is not a branch")]
+    NotABranch(crate::types::MmrId),
+    #[error("branch {sibling} still reads nodes of mmr {parent} that promoting would overwrite")]
+    PromoteSharedParent {
+        parent: crate::types::MmrId,
+        sibling: crate::types::MmrId,
+    },
+    #[error("hasher code {0} is not registered")]
+    UnknownHasher(u64),
+    #[error("hasher code 0 is reserved")]
+    ReservedHasherCode,
     #[error("invalid element count")]
     InvalidElementCount,
     #[error("invalid element index")]
     InvalidElementIndex,
+    #[error("invalid leaf index")]
+    InvalidLeafIndex,
     #[error("invalid peaks count")]
     InvalidPeaksCount,
+    #[error("invalid siblings count")]
+    InvalidSiblingsCount,
     #[error("invalid peaks count for the given element count")]
     InvalidPeaksCountForElements,
     #[error("cannot batch append an empty list of values")]
     EmptyBatchAppend,
+    #[error("external key {0} is already mapped")]
+    DuplicateExternalKey(u64),
+    #[error("external key {0} is not mapped")]
+    ExternalKeyNotFound(u64),
+    #[error("external key {0} was already appended with a different value")]
+    IdempotentValueMismatch(u64),
+    #[error("diff expects {expected} elements but the mmr has {actual}")]
+    DiffBaseMismatch { expected: u64, actual: u64 },
+    #[error("computed root does not match the announced root")]
+    RootMismatch,
+    #[error("append would grow the mmr to {requested} leaves, above the limit of {limit}")]
+    MaxLeavesExceeded { limit: u64, requested: u64 },
+    #[error("append would grow the mmr to {requested} elements, above the limit of {limit}")]
+    MaxElementsExceeded { limit: u64, requested: u64 },
+    #[error("existing mmr does not start with the expected genesis leaf")]
+    GenesisMismatch,
+    #[error("expected block {expected}, got {actual}")]
+    UnexpectedBlockNumber { expected: u64, actual: u64 },
+    #[error("accumulator starts at block {found}, not {expected}")]
+    FirstBlockMismatch { expected: u64, found: u64 },
+    #[error("block {0} is not in the accumulator")]
+    BlockNotAccumulated(u64),
+    #[error("invalid diff: {0}")]
+    InvalidDiff(&'static str),
+    #[error("append rejected by hook: {0}")]
+    HookRejected(String),
+    #[error("hash index probe limit of {0} slots exceeded")]
+    HashIndexProbeLimit(u32),
     #[error("no hash found for index {0}")]
     NoHashFoundForIndex(u64),
+    #[error("append conflicted with a concurrent writer after {0} attempts")]
+    ConcurrentAppendConflict(u32),
+    #[error("draft based on {0} elements conflicts with a write committed since")]
+    DraftConflict(u64),
+    #[error("could not claim a fresh mmr id after {0} attempts")]
+    CreateConflict(u32),
+    #[error("mmr {0} appears more than once in the batch")]
+    DuplicateMmrInBatch(u64),
+    #[error("mmr {0} was not opened from this registry")]
+    ForeignRegistryMmr(u64),
+    #[error("writer lease is held by another instance")]
+    WriterLeaseHeld,
+    #[error("writer lease expired")]
+    WriterLeaseExpired,
+    #[error("writer lease was taken over by another instance")]
+    WriterLeaseLost,
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid dump: {0}")]
+    InvalidDump(&'static str),
+    #[error("unsupported dump version {0}")]
+    UnsupportedDumpVersion(u8),
+    #[error("mmr went from {expected} to {actual} elements while it was exported")]
+    ExportConflict { expected: u64, actual: u64 },
+    #[error("dump checksum mismatch")]
+    DumpChecksumMismatch,
+    #[error("invalid herodotus import: {0}")]
+    InvalidHerodotusImport(&'static str),
+    #[error("invalid proof encoding: {0}")]
+    InvalidProofEncoding(&'static str),
+    #[error("invalid proof json field `{field}`: `{value}`")]
+    InvalidProofJson { field: &'static str, value: String },
+    #[error("unsupported proof encoding version {0}")]
+    UnsupportedProofEncodingVersion(u8),
+    #[error("invalid ssz encoding: {0}")]
+    InvalidSszEncoding(&'static str),
+    #[error("invalid node file: {0}")]
+    InvalidNodeFile(&'static str),
+    #[error("node file hasher code mismatch: expected {expected}, found {found}")]
+    NodeFileHasherMismatch { expected: u64, found: u64 },
+    #[error("invalid proof archive")]
+    InvalidProofArchive,
+    #[error("stored checkpoint is malformed")]
+    InvalidCheckpoint,
+    #[error(
+        "checkpoint at {checkpoint_elements_count} elements does not match current {elements_count} elements"
+    )]
+    CheckpointMismatch {
+        checkpoint_elements_count: u64,
+        elements_count: u64,
+    },
+    #[error("stored nodes on the path of element {0} do not hash to its peak")]
+    CorruptedProofPath(u64),
+    #[error("stored peaks of an mmr with {0} elements do not hash to the stored root")]
+    CorruptedPeaks(u64),
     #[error("arithmetic overflow")]
     Overflow,
+    #[error("mmr writer driver has stopped")]
+    WriterClosed,
+    /// A coalesced `MmrWriter` batch failed as a whole; every caller in it gets this error.
+    #[error("queued batch failed: {0}")]
+    WriterBatchFailed(Arc<MmrError>),
+    #[error("verification error: {0}")]
+    Verify(VerifyError),
+}
+
+impl MmrError {
+    /// The store failure behind this error, whether or not an `Mmr` tagged it.
+    pub fn store_error(&self) -> Option<&StoreError> {
+        match self {
+            Self::Store(source) => Some(source),
+            Self::StoreOp { source, .. } => Some(source),
+            Self::WriterBatchFailed(source) => source.store_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<VerifyError> for MmrError {
+    fn from(err: VerifyError) -> Self {
+        match err {
+            VerifyError::InvalidElementIndex => Self::InvalidElementIndex,
+            VerifyError::InvalidElementCount => Self::InvalidElementCount,
+            VerifyError::InvalidPeaksCount => Self::InvalidPeaksCount,
+            VerifyError::Overflow => Self::Overflow,
+            VerifyError::Hasher => Self::Verify(err),
+        }
+    }
 }
@@ -0,0 +1,195 @@
+//! `Arbitrary` impls for proofs, store keys and values, plus invariant checks that fuzz
+//! targets and property tests can run against arbitrary leaf sequences.
+
+use std::sync::Arc;
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::hasher::Hasher;
+use crate::mmr::{Mmr, find_peaks, try_leaf_count_to_mmr_size};
+use crate::store::{KeyKind, StoreKey, StoreValue};
+use crate::types::{Hash, Hash32, Proof};
+
+/// Upper bound on `LeafSequence` length, so a single input stays cheap to append.
+pub const MAX_FUZZ_LEAVES: usize = 256;
+
+/// Every `KeyKind`, in discriminant order.
+const KEY_KINDS: [KeyKind; 14] = [
+    KeyKind::LeafCount,
+    KeyKind::ElementsCount,
+    KeyKind::RootHash,
+    KeyKind::NodeHash,
+    KeyKind::WriterLease,
+    KeyKind::LeafData,
+    KeyKind::ExternalKey,
+    KeyKind::HashIndex,
+    KeyKind::RegistryEntry,
+    KeyKind::IdAllocator,
+    KeyKind::BranchParent,
+    KeyKind::Checkpoint,
+    KeyKind::Peaks,
+    KeyKind::LeafKey,
+];
+
+/// Matches exhaustively, so adding a `KeyKind` fails to build until it is handled here;
+/// the check below then fails until it is appended to `KEY_KINDS`.
+const fn last_key_kind(kind: KeyKind) -> KeyKind {
+    match kind {
+        KeyKind::LeafCount
+        | KeyKind::ElementsCount
+        | KeyKind::RootHash
+        | KeyKind::NodeHash
+        | KeyKind::WriterLease
+        | KeyKind::LeafData
+        | KeyKind::ExternalKey
+        | KeyKind::HashIndex
+        | KeyKind::RegistryEntry
+        | KeyKind::IdAllocator
+        | KeyKind::BranchParent
+        | KeyKind::Checkpoint
+        | KeyKind::Peaks
+        | KeyKind::LeafKey => KeyKind::LeafKey,
+    }
+}
+
+const _: () = {
+    assert!(KEY_KINDS.len() == last_key_kind(KeyKind::LeafCount) as usize + 1);
+    let mut index = 0;
+    while index < KEY_KINDS.len() {
+        assert!(KEY_KINDS[index] as usize == index);
+        index += 1;
+    }
+};
+
+impl<'a, const N: usize> Arbitrary<'a> for Hash<N> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Hash(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for KeyKind {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.choose(&KEY_KINDS).copied()
+    }
+}
+
+impl<'a> Arbitrary<'a> for StoreKey {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(StoreKey::new(
+            u.arbitrary()?,
+            u.arbitrary()?,
+            u.arbitrary()?,
+        ))
+    }
+}
+
+impl<'a> Arbitrary<'a> for StoreValue {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0u8..=2)? {
+            0 => StoreValue::U64(u.arbitrary()?),
+            1 => StoreValue::Hash(u.arbitrary()?),
+            _ => StoreValue::Bytes(u.arbitrary()?),
+        })
+    }
+}
+
+impl<'a, const N: usize> Arbitrary<'a> for Proof<N> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Proof {
+            element_index: u.arbitrary()?,
+            element_hash: u.arbitrary()?,
+            siblings_hashes: u.arbitrary()?,
+            peaks_hashes: u.arbitrary()?,
+            elements_count: u.arbitrary()?,
+            mmr_id: u.arbitrary()?,
+        })
+    }
+}
+
+/// Up to `MAX_FUZZ_LEAVES` leaves with the top bits cleared, so they are valid inputs for
+/// every built-in hasher, including `PoseidonHasher`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeafSequence(pub Vec<Hash32>);
+
+impl<'a> Arbitrary<'a> for LeafSequence {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = u.int_in_range(0..=MAX_FUZZ_LEAVES as u64)? as usize;
+        let mut leaves = Vec::with_capacity(len);
+        for _ in 0..len {
+            let mut leaf: [u8; 32] = u.arbitrary()?;
+            leaf[0] &= 0x07;
+            leaves.push(Hash32::new(leaf));
+        }
+        Ok(Self(leaves))
+    }
+}
+
+/// Appends `leaves` one by one and in a single batch to fresh in-memory MMRs and panics
+/// unless counts, peaks and roots agree after every step.
+pub async fn assert_append_invariants(hasher: Arc<dyn Hasher>, leaves: &[Hash32]) {
+    let sequential = Mmr::in_memory(hasher.clone());
+    for (appended, leaf) in leaves.iter().enumerate() {
+        let leaves_count = appended as u64 + 1;
+        let result = sequential.append(*leaf).await.unwrap();
+        let elements_count = try_leaf_count_to_mmr_size(leaves_count).unwrap();
+
+        assert_eq!(result.leaves_count, leaves_count);
+        assert_eq!(result.elements_count, elements_count);
+        assert_eq!(result.peaks_hashes.len(), find_peaks(elements_count).len());
+        assert_eq!(
+            sequential.get_root_hash().await.unwrap(),
+            Some(result.root_hash)
+        );
+    }
+
+    if leaves.is_empty() {
+        return;
+    }
+    let batched = Mmr::in_memory(hasher);
+    let result = batched.batch_append(leaves).await.unwrap();
+    assert_eq!(result.appended_count, leaves.len() as u64);
+    assert_eq!(
+        batched.get_root_hash().await.unwrap(),
+        sequential.get_root_hash().await.unwrap()
+    );
+}
+
+/// Builds an MMR from `leaves` and panics unless every leaf's proof verifies and stops
+/// verifying once its value is changed.
+pub async fn assert_proof_invariants(hasher: Arc<dyn Hasher>, leaves: &[Hash32]) {
+    let mmr = Mmr::in_memory(hasher);
+    let mut element_indices = Vec::with_capacity(leaves.len());
+    for leaf in leaves {
+        element_indices.push(mmr.append(*leaf).await.unwrap().element_index);
+    }
+
+    for (element_index, leaf) in element_indices.into_iter().zip(leaves) {
+        let proof = mmr.get_proof(element_index, None).await.unwrap();
+        assert_eq!(proof.element_hash, *leaf);
+        assert!(mmr.verify_proof(&proof, *leaf, None).await.unwrap());
+
+        let mut tampered = *leaf;
+        tampered.0[31] ^= 1;
+        assert!(!mmr.verify_proof(&proof, tampered, None).await.unwrap());
+    }
+}
+
+/// Verifies an arbitrary `proof` for `element_value` against an MMR built from `leaves`.
+/// Verification may fail or reject, but must not panic, and may only accept the value
+/// actually stored at `proof.element_index`.
+pub async fn assert_arbitrary_proof_is_sound(
+    hasher: Arc<dyn Hasher>,
+    leaves: &[Hash32],
+    proof: &Proof,
+    element_value: Hash32,
+) {
+    let mmr = Mmr::in_memory(hasher);
+    if !leaves.is_empty() {
+        mmr.batch_append(leaves).await.unwrap();
+    }
+
+    if let Ok(true) = mmr.verify_proof(proof, element_value, None).await {
+        let genuine = mmr.get_proof(proof.element_index, None).await.unwrap();
+        assert_eq!(genuine.element_hash, element_value);
+    }
+}
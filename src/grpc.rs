@@ -0,0 +1,238 @@
+use std::sync::Arc;
+
+use tonic::{Request, Response, Status};
+
+use crate::error::MmrError;
+use crate::mmr::Mmr;
+use crate::store::{InMemoryStore, Store};
+use crate::types::{Hash32, Proof};
+
+pub mod proto {
+    tonic::include_proto!("mmr.v1");
+}
+
+use proto::mmr_service_server::{MmrService, MmrServiceServer};
+
+/// tonic service over a shared `Mmr`. The service trait is implemented for the concrete
+/// stores shipped with this crate, whose futures are `Send`.
+pub struct GrpcMmrService<S: Store> {
+    mmr: Arc<Mmr<S>>,
+}
+
+impl<S: Store> GrpcMmrService<S> {
+    pub fn new(mmr: Arc<Mmr<S>>) -> Self {
+        Self { mmr }
+    }
+
+    async fn handle_append(
+        &self,
+        request: proto::AppendRequest,
+    ) -> Result<proto::AppendResponse, Status> {
+        let result = self
+            .mmr
+            .append(hash_from_bytes(&request.value)?)
+            .await
+            .map_err(to_status)?;
+        Ok(proto::AppendResponse {
+            leaves_count: result.leaves_count,
+            elements_count: result.elements_count,
+            element_index: result.element_index,
+            root_hash: result.root_hash_if_computed().map(|hash| hash.to_vec()),
+        })
+    }
+
+    async fn handle_batch_append(
+        &self,
+        request: proto::BatchAppendRequest,
+    ) -> Result<proto::BatchAppendResponse, Status> {
+        let values = request
+            .values
+            .iter()
+            .map(|value| hash_from_bytes(value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = self.mmr.batch_append(&values).await.map_err(to_status)?;
+        Ok(proto::BatchAppendResponse {
+            appended_count: result.appended_count,
+            first_element_index: result.first_element_index,
+            last_element_index: result.last_element_index,
+            leaves_count: result.leaves_count,
+            elements_count: result.elements_count,
+            root_hash: result.root_hash_if_computed().map(|hash| hash.to_vec()),
+        })
+    }
+
+    async fn handle_get_proof(
+        &self,
+        request: proto::GetProofRequest,
+    ) -> Result<proto::Proof, Status> {
+        let proof = self
+            .mmr
+            .get_proof(request.element_index, request.elements_count)
+            .await
+            .map_err(to_status)?;
+        Ok(proof_to_message(proof))
+    }
+
+    async fn handle_verify_proof(
+        &self,
+        request: proto::VerifyProofRequest,
+    ) -> Result<proto::VerifyProofResponse, Status> {
+        let proof = request
+            .proof
+            .ok_or_else(|| Status::invalid_argument("missing proof"))
+            .and_then(proof_from_message)?;
+        let element_value = hash_from_bytes(&request.element_value)?;
+        let valid = self
+            .mmr
+            .verify_proof(&proof, element_value, request.elements_count)
+            .await
+            .map_err(to_status)?;
+        Ok(proto::VerifyProofResponse { valid })
+    }
+
+    async fn handle_get_root(&self) -> Result<proto::GetRootResponse, Status> {
+        let elements_count = self.mmr.get_elements_count().await.map_err(to_status)?;
+        let root_hash = self.mmr.get_root_hash().await.map_err(to_status)?;
+        Ok(proto::GetRootResponse {
+            elements_count,
+            root_hash: root_hash.map(|hash| hash.to_vec()),
+        })
+    }
+
+    async fn handle_get_peaks(
+        &self,
+        request: proto::GetPeaksRequest,
+    ) -> Result<proto::GetPeaksResponse, Status> {
+        let peaks = self
+            .mmr
+            .get_peaks(request.elements_count)
+            .await
+            .map_err(to_status)?;
+        Ok(proto::GetPeaksResponse {
+            peaks_hashes: peaks.iter().map(|hash| hash.to_vec()).collect(),
+        })
+    }
+}
+
+macro_rules! impl_mmr_service {
+    ($store:ty) => {
+        impl GrpcMmrService<$store> {
+            pub fn into_server(self) -> MmrServiceServer<Self> {
+                MmrServiceServer::new(self)
+            }
+        }
+
+        #[tonic::async_trait]
+        impl MmrService for GrpcMmrService<$store> {
+            async fn append(
+                &self,
+                request: Request<proto::AppendRequest>,
+            ) -> Result<Response<proto::AppendResponse>, Status> {
+                self.handle_append(request.into_inner())
+                    .await
+                    .map(Response::new)
+            }
+
+            async fn batch_append(
+                &self,
+                request: Request<proto::BatchAppendRequest>,
+            ) -> Result<Response<proto::BatchAppendResponse>, Status> {
+                self.handle_batch_append(request.into_inner())
+                    .await
+                    .map(Response::new)
+            }
+
+            async fn get_proof(
+                &self,
+                request: Request<proto::GetProofRequest>,
+            ) -> Result<Response<proto::Proof>, Status> {
+                self.handle_get_proof(request.into_inner())
+                    .await
+                    .map(Response::new)
+            }
+
+            async fn verify_proof(
+                &self,
+                request: Request<proto::VerifyProofRequest>,
+            ) -> Result<Response<proto::VerifyProofResponse>, Status> {
+                self.handle_verify_proof(request.into_inner())
+                    .await
+                    .map(Response::new)
+            }
+
+            async fn get_root(
+                &self,
+                _request: Request<proto::GetRootRequest>,
+            ) -> Result<Response<proto::GetRootResponse>, Status> {
+                self.handle_get_root().await.map(Response::new)
+            }
+
+            async fn get_peaks(
+                &self,
+                request: Request<proto::GetPeaksRequest>,
+            ) -> Result<Response<proto::GetPeaksResponse>, Status> {
+                self.handle_get_peaks(request.into_inner())
+                    .await
+                    .map(Response::new)
+            }
+        }
+    };
+}
+
+impl_mmr_service!(InMemoryStore);
+impl_mmr_service!(Arc<InMemoryStore>);
+#[cfg(feature = "postgres-store")]
+impl_mmr_service!(crate::store::PostgresStore);
+#[cfg(feature = "postgres-store")]
+impl_mmr_service!(Arc<crate::store::PostgresStore>);
+
+fn hash_from_bytes(bytes: &[u8]) -> Result<Hash32, Status> {
+    Hash32::try_from(bytes).map_err(|_| Status::invalid_argument("hashes must be 32 bytes"))
+}
+
+fn proof_to_message(proof: Proof) -> proto::Proof {
+    proto::Proof {
+        element_index: proof.element_index,
+        element_hash: proof.element_hash.to_vec(),
+        siblings_hashes: proof
+            .siblings_hashes
+            .iter()
+            .map(|hash| hash.to_vec())
+            .collect(),
+        peaks_hashes: proof
+            .peaks_hashes
+            .iter()
+            .map(|hash| hash.to_vec())
+            .collect(),
+        elements_count: proof.elements_count,
+        mmr_id: proof.mmr_id,
+    }
+}
+
+fn proof_from_message(message: proto::Proof) -> Result<Proof, Status> {
+    let hashes = |hashes: &[Vec<u8>]| {
+        hashes
+            .iter()
+            .map(|hash| hash_from_bytes(hash))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    Ok(Proof {
+        element_index: message.element_index,
+        element_hash: hash_from_bytes(&message.element_hash)?,
+        siblings_hashes: hashes(&message.siblings_hashes)?,
+        peaks_hashes: hashes(&message.peaks_hashes)?,
+        elements_count: message.elements_count,
+        mmr_id: message.mmr_id,
+    })
+}
+
+fn to_status(err: MmrError) -> Status {
+    match err {
+        MmrError::Hasher(_)
+        | MmrError::InvalidElementIndex
+        | MmrError::InvalidElementCount
+        | MmrError::InvalidLeafIndex
+        | MmrError::InvalidPeaksCountForElements => Status::invalid_argument(err.to_string()),
+        _ => Status::internal(err.to_string()),
+    }
+}
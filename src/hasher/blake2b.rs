@@ -0,0 +1,41 @@
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+
+use crate::error::HasherError;
+use crate::types::Hash32;
+
+use super::Hasher;
+
+type Blake2b256 = Blake2b<U32>;
+
+/// BLAKE2b-256, as used by Substrate's default `BlakeTwo256` hashing; `hash_bytes` is
+/// `BlakeTwo256::hash`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Blake2bHasher;
+
+impl Blake2bHasher {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Hasher for Blake2bHasher {
+    fn hash_pair(&self, left: &Hash32, right: &Hash32) -> Result<Hash32, HasherError> {
+        let mut blake = Blake2b256::new();
+        blake.update(left);
+        blake.update(right);
+        Ok(Hash32::new(blake.finalize().into()))
+    }
+
+    fn hash_count_and_bag(&self, elements_count: u64, bag: &Hash32) -> Result<Hash32, HasherError> {
+        let mut count_hash = Hash32::default();
+        count_hash[24..].copy_from_slice(&elements_count.to_be_bytes());
+        self.hash_pair(&count_hash, bag)
+    }
+
+    fn hash_bytes(&self, data: &[u8]) -> Result<Hash32, HasherError> {
+        let mut blake = Blake2b256::new();
+        blake.update(data);
+        Ok(Hash32::new(blake.finalize().into()))
+    }
+}
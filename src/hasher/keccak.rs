@@ -17,8 +17,8 @@ impl KeccakHasher {
 impl Hasher for KeccakHasher {
     fn hash_pair(&self, left: &Hash32, right: &Hash32) -> Result<Hash32, HasherError> {
         let mut bytes = [0u8; 64];
-        bytes[..32].copy_from_slice(left);
-        bytes[32..].copy_from_slice(right);
+        bytes[..32].copy_from_slice(left.as_slice());
+        bytes[32..].copy_from_slice(right.as_slice());
 
         let mut keccak = Keccak::v256();
         keccak.update(&bytes);
@@ -26,14 +26,20 @@ impl Hasher for KeccakHasher {
     }
 
     fn hash_count_and_bag(&self, elements_count: u64, bag: &Hash32) -> Result<Hash32, HasherError> {
-        let mut count_hash = [0u8; 32];
+        let mut count_hash = Hash32::default();
         count_hash[24..].copy_from_slice(&elements_count.to_be_bytes());
         self.hash_pair(&count_hash, bag)
     }
+
+    fn hash_bytes(&self, data: &[u8]) -> Result<Hash32, HasherError> {
+        let mut keccak = Keccak::v256();
+        keccak.update(data);
+        Ok(finalize_keccak(keccak))
+    }
 }
 
 fn finalize_keccak(keccak: Keccak) -> Hash32 {
-    let mut output = [0u8; 32];
-    keccak.finalize(&mut output);
+    let mut output = Hash32::default();
+    keccak.finalize(&mut output.0);
     output
 }
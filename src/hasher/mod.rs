@@ -1,13 +1,57 @@
+#[cfg(feature = "blake2")]
+mod blake2b;
 mod keccak;
 mod poseidon;
 
-use crate::error::HasherError;
-use crate::types::Hash32;
+use crate::error::{HasherError, MmrError};
+use crate::types::Hash;
+use crate::verify::PairHasher;
 
+#[cfg(feature = "blake2")]
+pub use blake2b::Blake2bHasher;
 pub use keccak::KeccakHasher;
 pub use poseidon::PoseidonHasher;
 
-pub trait Hasher: Send + Sync {
-    fn hash_pair(&self, left: &Hash32, right: &Hash32) -> Result<Hash32, HasherError>;
-    fn hash_count_and_bag(&self, elements_count: u64, bag: &Hash32) -> Result<Hash32, HasherError>;
+/// Domain byte the default `hash_leaf` puts in front of the inner leaf digest.
+pub const LEAF_PREFIX: u8 = 0x00;
+
+/// Hashes `N`-byte nodes; `N` defaults to 32, the width `Mmr` and its stores use.
+pub trait Hasher<const N: usize = 32>: Send + Sync {
+    fn hash_pair(&self, left: &Hash<N>, right: &Hash<N>) -> Result<Hash<N>, HasherError>;
+    fn hash_count_and_bag(
+        &self,
+        elements_count: u64,
+        bag: &Hash<N>,
+    ) -> Result<Hash<N>, HasherError>;
+
+    /// Plain digest of `data` with no domain separation, for interop formats that define
+    /// their own leaf encoding.
+    fn hash_bytes(&self, _data: &[u8]) -> Result<Hash<N>, HasherError> {
+        Err(HasherError::Unsupported("hash_bytes"))
+    }
+
+    /// Hashes arbitrary leaf bytes into a `Hash<N>` suitable for appending.
+    ///
+    /// Defaults to `hash_bytes(LEAF_PREFIX || hash_bytes(data))`: the outer input is
+    /// `N + 1` bytes, so a leaf of any length can never hash like the `2N`-byte
+    /// `left || right` of an internal node.
+    fn hash_leaf(&self, data: &[u8]) -> Result<Hash<N>, HasherError> {
+        let inner = self.hash_bytes(data)?;
+        let mut prefixed = Vec::with_capacity(N + 1);
+        prefixed.push(LEAF_PREFIX);
+        prefixed.extend_from_slice(inner.as_slice());
+        self.hash_bytes(&prefixed)
+    }
+}
+
+impl<H: Hasher<N> + ?Sized, const N: usize> PairHasher<N> for H {
+    type Error = MmrError;
+
+    fn hash_pair(&self, left: &[u8; N], right: &[u8; N]) -> Result<[u8; N], MmrError> {
+        Ok(Hasher::hash_pair(self, &Hash(*left), &Hash(*right))?.0)
+    }
+
+    fn hash_count_and_bag(&self, elements_count: u64, bag: &[u8; N]) -> Result<[u8; N], MmrError> {
+        Ok(Hasher::hash_count_and_bag(self, elements_count, &Hash(*bag))?.0)
+    }
 }
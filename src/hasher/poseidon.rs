@@ -1,7 +1,7 @@
 use std::str::FromStr;
 
 use starknet::core::types::FieldElement;
-use starknet_crypto::{poseidon_hash, poseidon_hash_single};
+use starknet_crypto::{poseidon_hash, poseidon_hash_many, poseidon_hash_single};
 
 use crate::error::HasherError;
 use crate::types::{Hash32, ZERO_HASH};
@@ -17,43 +17,57 @@ impl PoseidonHasher {
     }
 
     pub fn genesis_hash(&self) -> Result<Hash32, HasherError> {
-        let mut seed = [0u8; 32];
+        let mut seed = Hash32::default();
         let seed_bytes = b"brave new world";
         let start = seed.len() - seed_bytes.len();
         seed[start..].copy_from_slice(seed_bytes);
-        let seed_fe = hash32_to_field_element(&seed)?;
-        Ok(field_element_to_hash32(&poseidon_hash_single(seed_fe)))
+        let seed_fe = FieldElement::try_from(seed)?;
+        Ok(poseidon_hash_single(seed_fe).into())
     }
 }
 
 impl Hasher for PoseidonHasher {
     fn hash_pair(&self, left: &Hash32, right: &Hash32) -> Result<Hash32, HasherError> {
-        let left_fe = hash32_to_field_element(left)?;
-        let right_fe = hash32_to_field_element(right)?;
-        let out = poseidon_hash(left_fe, right_fe);
-        Ok(field_element_to_hash32(&out))
+        let left_fe = FieldElement::try_from(*left)?;
+        let right_fe = FieldElement::try_from(*right)?;
+        Ok(poseidon_hash(left_fe, right_fe).into())
     }
 
     fn hash_count_and_bag(&self, elements_count: u64, bag: &Hash32) -> Result<Hash32, HasherError> {
         let count_fe = FieldElement::from(elements_count);
-        let bag_fe = hash32_to_field_element(bag)?;
-        let out = poseidon_hash(count_fe, bag_fe);
-        Ok(field_element_to_hash32(&out))
+        let bag_fe = FieldElement::try_from(*bag)?;
+        Ok(poseidon_hash(count_fe, bag_fe).into())
     }
-}
 
-fn hash32_to_field_element(value: &Hash32) -> Result<FieldElement, HasherError> {
-    if value == &ZERO_HASH {
-        return Ok(FieldElement::ZERO);
+    /// Packs `data` into 31-byte big-endian felts, prefixed by its byte length.
+    fn hash_leaf(&self, data: &[u8]) -> Result<Hash32, HasherError> {
+        let mut felts = Vec::with_capacity(1 + data.len().div_ceil(31));
+        felts.push(FieldElement::from(data.len()));
+        for chunk in data.chunks(31) {
+            let mut bytes = Hash32::default();
+            bytes[32 - chunk.len()..].copy_from_slice(chunk);
+            felts.push(FieldElement::try_from(bytes)?);
+        }
+        Ok(poseidon_hash_many(&felts).into())
     }
+}
+
+impl TryFrom<Hash32> for FieldElement {
+    type Error = HasherError;
 
-    let hex_value = format!("0x{}", hex::encode(value));
-    FieldElement::from_str(&hex_value)
-        .map_err(|_| HasherError::InvalidFieldElement { value: hex_value })
+    fn try_from(value: Hash32) -> Result<Self, Self::Error> {
+        if value == ZERO_HASH {
+            return Ok(FieldElement::ZERO);
+        }
+
+        let hex_value = value.to_string();
+        FieldElement::from_str(&hex_value)
+            .map_err(|_| HasherError::InvalidFieldElement { value: hex_value })
+    }
 }
 
-fn field_element_to_hash32(value: &FieldElement) -> Hash32 {
-    let mut out = [0u8; 32];
-    out.copy_from_slice(&value.to_bytes_be());
-    out
+impl From<FieldElement> for Hash32 {
+    fn from(value: FieldElement) -> Self {
+        Hash32::new(value.to_bytes_be())
+    }
 }
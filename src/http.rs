@@ -0,0 +1,233 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::error::MmrError;
+use crate::mmr::Mmr;
+use crate::mmr::json::parse_u64;
+use crate::store::{InMemoryStore, Store};
+use crate::types::{Hash32, ProofJson};
+
+/// JSON endpoints over a shared `Mmr`. Hashes are 0x-prefixed hex and counters are decimal
+/// strings, as in [`ProofJson`]. Routers are provided for the concrete stores shipped with
+/// this crate, whose futures are `Send`.
+///
+/// - `POST /append` `{"value"}`
+/// - `POST /batch-append` `{"values"}`
+/// - `GET /proofs/{element_index}?elementsCount=`
+/// - `POST /proofs/verify` `{"proof", "elementValue", "elementsCount"?}`
+/// - `GET /peaks?elementsCount=`
+/// - `GET /root`
+///
+/// A proof for an element past the end of the MMR is a 404.
+pub struct HttpMmrService<S: Store> {
+    mmr: Arc<Mmr<S>>,
+}
+
+impl<S: Store> HttpMmrService<S> {
+    pub fn new(mmr: Arc<Mmr<S>>) -> Self {
+        Self { mmr }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AppendRequest {
+    pub value: Hash32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppendResponse {
+    pub leaves_count: String,
+    pub elements_count: String,
+    pub element_index: String,
+    pub root_hash: Option<Hash32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchAppendRequest {
+    pub values: Vec<Hash32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchAppendResponse {
+    pub appended_count: String,
+    pub first_element_index: String,
+    pub last_element_index: String,
+    pub leaves_count: String,
+    pub elements_count: String,
+    pub root_hash: Option<Hash32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyProofRequest {
+    pub proof: ProofJson,
+    pub element_value: Hash32,
+    #[serde(default)]
+    pub elements_count: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyProofResponse {
+    pub valid: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeaksResponse {
+    pub peaks_hashes: Vec<Hash32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootResponse {
+    pub elements_count: String,
+    pub root_hash: Option<Hash32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementsCountQuery {
+    pub elements_count: Option<String>,
+}
+
+fn parse_elements_count(elements_count: Option<&str>) -> Result<Option<u64>, MmrError> {
+    elements_count
+        .map(|count| parse_u64("elementsCount", count))
+        .transpose()
+}
+
+/// `MmrError` mapped to a status code with a `{"error"}` body.
+#[derive(Debug)]
+pub struct HttpError(pub MmrError);
+
+impl From<MmrError> for HttpError {
+    fn from(err: MmrError) -> Self {
+        Self(err)
+    }
+}
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        let status = match self.0 {
+            MmrError::Hasher(_)
+            | MmrError::InvalidElementIndex
+            | MmrError::InvalidElementCount
+            | MmrError::InvalidLeafIndex
+            | MmrError::InvalidPeaksCountForElements
+            | MmrError::InvalidProofJson { .. }
+            | MmrError::UnsupportedProofEncodingVersion(_) => StatusCode::BAD_REQUEST,
+            MmrError::NoHashFoundForIndex(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = serde_json::json!({ "error": self.0.to_string() });
+        (status, Json(body)).into_response()
+    }
+}
+
+async fn append<S: Store>(
+    State(mmr): State<Arc<Mmr<S>>>,
+    Json(request): Json<AppendRequest>,
+) -> Result<Json<AppendResponse>, HttpError> {
+    let result = mmr.append(request.value).await?;
+    Ok(Json(AppendResponse {
+        leaves_count: result.leaves_count.to_string(),
+        elements_count: result.elements_count.to_string(),
+        element_index: result.element_index.to_string(),
+        root_hash: result.root_hash_if_computed(),
+    }))
+}
+
+async fn batch_append<S: Store>(
+    State(mmr): State<Arc<Mmr<S>>>,
+    Json(request): Json<BatchAppendRequest>,
+) -> Result<Json<BatchAppendResponse>, HttpError> {
+    let result = mmr.batch_append(&request.values).await?;
+    Ok(Json(BatchAppendResponse {
+        appended_count: result.appended_count.to_string(),
+        first_element_index: result.first_element_index.to_string(),
+        last_element_index: result.last_element_index.to_string(),
+        leaves_count: result.leaves_count.to_string(),
+        elements_count: result.elements_count.to_string(),
+        root_hash: result.root_hash_if_computed(),
+    }))
+}
+
+async fn get_proof<S: Store>(
+    State(mmr): State<Arc<Mmr<S>>>,
+    Path(element_index): Path<u64>,
+    Query(query): Query<ElementsCountQuery>,
+) -> Result<Json<ProofJson>, HttpError> {
+    let elements_count = parse_elements_count(query.elements_count.as_deref())?;
+    let proof = match mmr.get_proof(element_index, elements_count).await {
+        Ok(proof) => proof,
+        Err(MmrError::InvalidElementIndex) if element_index != 0 => {
+            return Err(MmrError::NoHashFoundForIndex(element_index).into());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    Ok(Json(proof.to_json()))
+}
+
+async fn verify_proof<S: Store>(
+    State(mmr): State<Arc<Mmr<S>>>,
+    Json(request): Json<VerifyProofRequest>,
+) -> Result<Json<VerifyProofResponse>, HttpError> {
+    let proof = request.proof.to_proof()?;
+    let elements_count = parse_elements_count(request.elements_count.as_deref())?;
+    let valid = mmr
+        .verify_proof(&proof, request.element_value, elements_count)
+        .await?;
+    Ok(Json(VerifyProofResponse { valid }))
+}
+
+async fn get_peaks<S: Store>(
+    State(mmr): State<Arc<Mmr<S>>>,
+    Query(query): Query<ElementsCountQuery>,
+) -> Result<Json<PeaksResponse>, HttpError> {
+    let elements_count = parse_elements_count(query.elements_count.as_deref())?;
+    let peaks_hashes = mmr.get_peaks(elements_count).await?;
+    Ok(Json(PeaksResponse { peaks_hashes }))
+}
+
+async fn get_root<S: Store>(
+    State(mmr): State<Arc<Mmr<S>>>,
+) -> Result<Json<RootResponse>, HttpError> {
+    let elements_count = mmr.get_elements_count().await?;
+    let root_hash = mmr.get_root_hash().await?;
+    Ok(Json(RootResponse {
+        elements_count: elements_count.to_string(),
+        root_hash,
+    }))
+}
+
+macro_rules! impl_http_router {
+    ($store:ty) => {
+        impl HttpMmrService<$store> {
+            pub fn into_router(self) -> Router {
+                Router::new()
+                    .route("/append", post(append::<$store>))
+                    .route("/batch-append", post(batch_append::<$store>))
+                    .route("/proofs/verify", post(verify_proof::<$store>))
+                    .route("/proofs/:element_index", get(get_proof::<$store>))
+                    .route("/peaks", get(get_peaks::<$store>))
+                    .route("/root", get(get_root::<$store>))
+                    .with_state(self.mmr)
+            }
+        }
+    };
+}
+
+impl_http_router!(InMemoryStore);
+impl_http_router!(Arc<InMemoryStore>);
+#[cfg(feature = "postgres-store")]
+impl_http_router!(crate::store::PostgresStore);
+#[cfg(feature = "postgres-store")]
+impl_http_router!(Arc<crate::store::PostgresStore>);
@@ -0,0 +1,122 @@
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::mmr::verify::climb_to_peak;
+use crate::mmr::{Mmr, find_peaks, get_peak_info};
+use crate::store::Store;
+use crate::types::{Hash32, Proof};
+
+/// Single-leaf proof in nervosnetwork/merkle-mountain-range layout: `proof_items` holds the
+/// peaks left of the leaf's peak, then its siblings bottom-up, then the bagged peaks to the
+/// right (omitted when there are none). The layout has no room for the advisory
+/// [`Proof::mmr_id`] label, which is dropped on conversion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CkbProof {
+    pub mmr_size: u64,
+    pub proof_items: Vec<Hash32>,
+}
+
+/// Converts a 1-based element index to ckb's 0-based position.
+pub fn element_index_to_ckb_position(element_index: u64) -> Result<u64, MmrError> {
+    element_index
+        .checked_sub(1)
+        .ok_or(MmrError::InvalidElementIndex)
+}
+
+/// ckb roots bag the peaks right to left without committing to the element count.
+pub fn ckb_root(hasher: &dyn Hasher, peaks_hashes: &[Hash32]) -> Result<Hash32, MmrError> {
+    if peaks_hashes.is_empty() {
+        return Err(MmrError::InvalidPeaksCountForElements);
+    }
+    bag_peaks_ckb(hasher, peaks_hashes)
+}
+
+/// ckb's `bag_rhs_peaks`: folds from the rightmost peak with `merge(right, left)`, so the
+/// accumulator is the left operand. This differs from [`crate::mmr::bag_peaks`], which
+/// hashes `(left, acc)`, whenever there are two or more peaks.
+pub fn bag_peaks_ckb(hasher: &dyn Hasher, peaks_hashes: &[Hash32]) -> Result<Hash32, MmrError> {
+    let Some((last, rest)) = peaks_hashes.split_last() else {
+        return Err(MmrError::InvalidPeaksCountForElements);
+    };
+    rest.iter()
+        .rev()
+        .try_fold(*last, |acc, peak| Ok(hasher.hash_pair(&acc, peak)?))
+}
+
+pub fn proof_to_ckb(hasher: &dyn Hasher, proof: &Proof) -> Result<CkbProof, MmrError> {
+    let (peak_index, _) = get_peak_info(proof.elements_count, proof.element_index)?;
+    if proof.peaks_hashes.len() != find_peaks(proof.elements_count).len() {
+        return Err(MmrError::InvalidPeaksCountForElements);
+    }
+
+    let mut proof_items = proof.peaks_hashes[..peak_index].to_vec();
+    proof_items.extend_from_slice(&proof.siblings_hashes);
+    let rhs_peaks = &proof.peaks_hashes[peak_index + 1..];
+    if !rhs_peaks.is_empty() {
+        proof_items.push(bag_peaks_ckb(hasher, rhs_peaks)?);
+    }
+
+    Ok(CkbProof {
+        mmr_size: proof.elements_count,
+        proof_items,
+    })
+}
+
+pub fn verify_ckb_proof(
+    hasher: &dyn Hasher,
+    root: &Hash32,
+    proof: &CkbProof,
+    position: u64,
+    leaf: Hash32,
+) -> Result<bool, MmrError> {
+    verify_ckb_items(
+        hasher,
+        root,
+        proof.mmr_size,
+        &proof.proof_items,
+        position,
+        leaf,
+    )
+}
+
+pub(crate) fn verify_ckb_items(
+    hasher: &dyn Hasher,
+    root: &Hash32,
+    mmr_size: u64,
+    proof_items: &[Hash32],
+    position: u64,
+    leaf: Hash32,
+) -> Result<bool, MmrError> {
+    let element_index = position.checked_add(1).ok_or(MmrError::Overflow)?;
+    let Ok((peak_index, peak_height)) = get_peak_info(mmr_size, element_index) else {
+        return Ok(false);
+    };
+    let has_rhs = peak_index + 1 < find_peaks(mmr_size).len();
+    if proof_items.len() != peak_index + peak_height + usize::from(has_rhs) {
+        return Ok(false);
+    }
+
+    let (lhs_peaks, rest) = proof_items.split_at(peak_index);
+    let (siblings, rhs) = rest.split_at(peak_height);
+    let mut peaks_hashes = lhs_peaks.to_vec();
+    peaks_hashes.push(climb_to_peak(hasher, element_index, siblings, leaf)?);
+    peaks_hashes.extend_from_slice(rhs);
+
+    Ok(ckb_root(hasher, &peaks_hashes)? == *root)
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn get_ckb_proof(
+        &self,
+        position: u64,
+        mmr_size: Option<u64>,
+    ) -> Result<CkbProof, MmrError> {
+        let element_index = position.checked_add(1).ok_or(MmrError::Overflow)?;
+        let proof = self.get_proof(element_index, mmr_size).await?;
+        proof_to_ckb(self.hasher.as_ref(), &proof)
+    }
+
+    pub async fn get_ckb_root(&self, mmr_size: Option<u64>) -> Result<Hash32, MmrError> {
+        let peaks_hashes = self.get_peaks(mmr_size).await?;
+        ckb_root(self.hasher.as_ref(), &peaks_hashes)
+    }
+}
@@ -0,0 +1,54 @@
+use tiny_keccak::{Hasher as TinyHasher, Keccak};
+
+use crate::types::{Hash32, Proof};
+
+pub const VERIFY_PROOF_SIGNATURE: &str =
+    "verifyProof(uint256,bytes32,bytes32[],bytes32[],uint256,bytes32)";
+
+const WORD: usize = 32;
+const HEAD_WORDS: usize = 6;
+
+pub fn verify_proof_selector() -> [u8; 4] {
+    let mut keccak = Keccak::v256();
+    keccak.update(VERIFY_PROOF_SIGNATURE.as_bytes());
+    let mut digest = [0u8; 32];
+    keccak.finalize(&mut digest);
+    [digest[0], digest[1], digest[2], digest[3]]
+}
+
+/// ABI-encodes `(index, value, proof, peaks, elementsCount, root)` without a selector.
+pub fn encode_proof(proof: &Proof, root: &Hash32) -> Vec<u8> {
+    let siblings_offset = HEAD_WORDS * WORD;
+    let peaks_offset = siblings_offset + (1 + proof.siblings_hashes.len()) * WORD;
+    let mut out = Vec::with_capacity(peaks_offset + (1 + proof.peaks_hashes.len()) * WORD);
+
+    out.extend_from_slice(&uint_word(proof.element_index));
+    out.extend_from_slice(proof.element_hash.as_slice());
+    out.extend_from_slice(&uint_word(siblings_offset as u64));
+    out.extend_from_slice(&uint_word(peaks_offset as u64));
+    out.extend_from_slice(&uint_word(proof.elements_count));
+    out.extend_from_slice(root.as_slice());
+    write_bytes32_array(&mut out, &proof.siblings_hashes);
+    write_bytes32_array(&mut out, &proof.peaks_hashes);
+    out
+}
+
+/// Selector-prefixed calldata for `verifyProof`.
+pub fn verify_proof_calldata(proof: &Proof, root: &Hash32) -> Vec<u8> {
+    let mut calldata = verify_proof_selector().to_vec();
+    calldata.extend(encode_proof(proof, root));
+    calldata
+}
+
+fn uint_word(value: u64) -> [u8; WORD] {
+    let mut word = [0u8; WORD];
+    word[WORD - 8..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn write_bytes32_array(out: &mut Vec<u8>, hashes: &[Hash32]) {
+    out.extend_from_slice(&uint_word(hashes.len() as u64));
+    for hash in hashes {
+        out.extend_from_slice(hash.as_slice());
+    }
+}
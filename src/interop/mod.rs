@@ -0,0 +1,4 @@
+pub mod ckb;
+pub mod evm;
+pub mod starknet;
+pub mod substrate;
@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use ::starknet::core::types::FieldElement;
+
+use crate::error::MmrError;
+use crate::hasher::PoseidonHasher;
+use crate::mmr::{
+    Mmr, map_leaf_index_to_element_index, root_from_peaks, try_leaf_count_to_mmr_size,
+};
+use crate::store::Store;
+use crate::types::{AppendResult, Hash32, MmrId, Proof};
+
+/// Serializes `proof` as Cairo calldata for `MMR { root, last_pos }.verify_proof(index, hash,
+/// peaks, proof)`: `[root, elements_count, element_index, element_hash, peaks_len, ..peaks,
+/// siblings_len, ..siblings]`. The Cairo verifier takes no `mmr_id`, so the advisory label
+/// is not encoded and [`proof_from_felts`] returns an unlabelled proof.
+pub fn proof_to_felts(proof: &Proof, root: &Hash32) -> Result<Vec<FieldElement>, MmrError> {
+    let mut felts = Vec::with_capacity(6 + proof.peaks_hashes.len() + proof.siblings_hashes.len());
+    felts.push(FieldElement::try_from(*root)?);
+    felts.push(FieldElement::from(proof.elements_count));
+    felts.push(FieldElement::from(proof.element_index));
+    felts.push(FieldElement::try_from(proof.element_hash)?);
+    push_span(&mut felts, &proof.peaks_hashes)?;
+    push_span(&mut felts, &proof.siblings_hashes)?;
+    Ok(felts)
+}
+
+/// Inverse of [`proof_to_felts`]; returns the proof and the root it was submitted with.
+pub fn proof_from_felts(felts: &[FieldElement]) -> Result<(Proof, Hash32), MmrError> {
+    let mut reader = FeltReader { felts };
+    let root = Hash32::from(reader.next()?);
+    let elements_count = reader.next_u64()?;
+    let element_index = reader.next_u64()?;
+    let element_hash = Hash32::from(reader.next()?);
+    let peaks_hashes = reader.next_span()?;
+    let siblings_hashes = reader.next_span()?;
+    if !reader.felts.is_empty() {
+        return Err(MmrError::InvalidProofEncoding("trailing felts"));
+    }
+
+    let proof = Proof {
+        element_index,
+        element_hash,
+        siblings_hashes,
+        peaks_hashes,
+        elements_count,
+        mmr_id: None,
+    };
+    Ok((proof, root))
+}
+
+fn push_span(felts: &mut Vec<FieldElement>, hashes: &[Hash32]) -> Result<(), MmrError> {
+    felts.push(FieldElement::from(hashes.len()));
+    for hash in hashes {
+        felts.push(FieldElement::try_from(*hash)?);
+    }
+    Ok(())
+}
+
+struct FeltReader<'a> {
+    felts: &'a [FieldElement],
+}
+
+impl FeltReader<'_> {
+    fn next(&mut self) -> Result<FieldElement, MmrError> {
+        let (first, rest) = self
+            .felts
+            .split_first()
+            .ok_or(MmrError::InvalidProofEncoding("unexpected end of felts"))?;
+        self.felts = rest;
+        Ok(*first)
+    }
+
+    fn next_u64(&mut self) -> Result<u64, MmrError> {
+        let bytes = Hash32::from(self.next()?);
+        if bytes[..24].iter().any(|byte| *byte != 0) {
+            return Err(MmrError::InvalidProofEncoding("felt exceeds u64"));
+        }
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(&bytes[24..]);
+        Ok(u64::from_be_bytes(raw))
+    }
+
+    fn next_span(&mut self) -> Result<Vec<Hash32>, MmrError> {
+        let len = usize::try_from(self.next_u64()?).map_err(|_| MmrError::Overflow)?;
+        if self.felts.len() < len {
+            return Err(MmrError::InvalidProofEncoding("span exceeds input"));
+        }
+        let (span, rest) = self.felts.split_at(len);
+        self.felts = rest;
+        Ok(span.iter().map(|felt| Hash32::from(*felt)).collect())
+    }
+}
+
+/// Poseidon MMR of Starknet block hashes. Leaf 0 is the Poseidon genesis hash and block
+/// `first_block + n` is leaf `n + 1`. `first_block` is stored as the genesis leaf's data, so
+/// reopening the MMR cannot shift the block numbering.
+pub struct StarknetAccumulator<S: Store> {
+    mmr: Mmr<S>,
+    first_block: u64,
+}
+
+impl<S: Store> StarknetAccumulator<S> {
+    /// Creates the accumulator, or reopens it if `mmr_id` already holds one starting at
+    /// `first_block`.
+    pub async fn new(store: S, mmr_id: Option<MmrId>, first_block: u64) -> Result<Self, MmrError> {
+        let hasher = PoseidonHasher::new();
+        let genesis_hash = hasher.genesis_hash()?;
+        let mmr = Mmr::init_with_genesis(
+            store,
+            Arc::new(hasher),
+            mmr_id,
+            genesis_hash,
+            Some(first_block.to_be_bytes().to_vec()),
+        )
+        .await?;
+        let found = stored_first_block(&mmr).await?;
+        if found != first_block {
+            return Err(MmrError::FirstBlockMismatch {
+                expected: first_block,
+                found,
+            });
+        }
+        Ok(Self { mmr, first_block })
+    }
+
+    /// Reopens an existing accumulator with the `first_block` it was created with.
+    pub async fn open(store: S, mmr_id: MmrId) -> Result<Self, MmrError> {
+        let hasher = PoseidonHasher::new();
+        let genesis_hash = hasher.genesis_hash()?;
+        let mmr = Mmr::new(store, Arc::new(hasher), Some(mmr_id))?;
+        match mmr.get_node_hash(1).await? {
+            Some(existing) if existing == genesis_hash => {}
+            Some(_) => return Err(MmrError::GenesisMismatch),
+            None => return Err(MmrError::MmrNotFound(mmr_id)),
+        }
+        let first_block = stored_first_block(&mmr).await?;
+        Ok(Self { mmr, first_block })
+    }
+
+    pub fn mmr(&self) -> &Mmr<S> {
+        &self.mmr
+    }
+
+    pub fn first_block(&self) -> u64 {
+        self.first_block
+    }
+
+    pub async fn next_block_number(&self) -> Result<u64, MmrError> {
+        let blocks_count = self.mmr.get_leaves_count().await? - 1;
+        self.first_block
+            .checked_add(blocks_count)
+            .ok_or(MmrError::Overflow)
+    }
+
+    /// Appends `block_hash` as `block_number`. The expected MMR size is part of the
+    /// compare-and-set, so a concurrent append of the same block fails instead of shifting
+    /// this hash onto the next block number.
+    pub async fn append_block(
+        &self,
+        block_number: u64,
+        block_hash: Hash32,
+    ) -> Result<AppendResult, MmrError> {
+        // Reject hashes Poseidon could not merge on a later append.
+        FieldElement::try_from(block_hash)?;
+        let Some(blocks_before) = block_number.checked_sub(self.first_block) else {
+            return Err(self.unexpected_block(block_number).await);
+        };
+        let leaves_before = blocks_before.checked_add(1).ok_or(MmrError::Overflow)?;
+        let expected_elements_count = try_leaf_count_to_mmr_size(leaves_before)?;
+        match self
+            .mmr
+            .append_at(block_hash, expected_elements_count)
+            .await
+        {
+            Ok(result) => Ok(result),
+            Err(MmrError::DiffBaseMismatch { .. }) => {
+                Err(self.unexpected_block(block_number).await)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn unexpected_block(&self, block_number: u64) -> MmrError {
+        match self.next_block_number().await {
+            Ok(expected) => MmrError::UnexpectedBlockNumber {
+                expected,
+                actual: block_number,
+            },
+            Err(err) => err,
+        }
+    }
+
+    pub async fn get_block_proof(&self, block_number: u64) -> Result<Proof, MmrError> {
+        if block_number < self.first_block || block_number >= self.next_block_number().await? {
+            return Err(MmrError::BlockNotAccumulated(block_number));
+        }
+        let leaf_index = block_number - self.first_block + 1;
+        self.mmr
+            .get_proof(map_leaf_index_to_element_index(leaf_index), None)
+            .await
+    }
+
+    /// Proof for `block_number` in the [`proof_to_felts`] calldata layout.
+    pub async fn get_block_proof_felts(
+        &self,
+        block_number: u64,
+    ) -> Result<Vec<FieldElement>, MmrError> {
+        let proof = self.get_block_proof(block_number).await?;
+        let root = root_from_peaks(
+            &PoseidonHasher::new(),
+            &proof.peaks_hashes,
+            proof.elements_count,
+        )?;
+        proof_to_felts(&proof, &root)
+    }
+
+    pub async fn verify_block(&self, proof: &Proof, block_hash: Hash32) -> Result<bool, MmrError> {
+        self.mmr.verify_proof(proof, block_hash, None).await
+    }
+}
+
+async fn stored_first_block<S: Store>(mmr: &Mmr<S>) -> Result<u64, MmrError> {
+    let data = mmr
+        .get_leaf_data(map_leaf_index_to_element_index(0))
+        .await?
+        .ok_or(MmrError::GenesisMismatch)?;
+    let bytes = <[u8; 8]>::try_from(data.as_slice()).map_err(|_| MmrError::GenesisMismatch)?;
+    Ok(u64::from_be_bytes(bytes))
+}
@@ -0,0 +1,88 @@
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::mmr::{Mmr, map_leaf_index_to_element_index, try_leaf_count_to_mmr_size};
+use crate::store::Store;
+use crate::types::{AppendResult, Hash32};
+
+use super::ckb::verify_ckb_items;
+
+/// pallet-mmr `LeafProof`. Substrate builds on the ckb layout: leaves are the plain
+/// `hash_bytes` digest of the SCALE-encoded leaf (see [`hash_substrate_leaf`]), nodes are
+/// `hash_pair(left, right)` and the root bags the peaks ckb-style without the element count.
+/// Only single-leaf proofs are produced and verified here.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubstrateLeafProof {
+    pub leaf_indices: Vec<u64>,
+    pub leaf_count: u64,
+    pub items: Vec<Hash32>,
+}
+
+/// pallet-mmr's `DataOrHash::Data` hash: `H(leaf.encode())` with no prefix, so the hasher
+/// must implement `hash_bytes` (`Blake2bHasher` and `KeccakHasher` do).
+pub fn hash_substrate_leaf(hasher: &dyn Hasher, encoded_leaf: &[u8]) -> Result<Hash32, MmrError> {
+    Ok(hasher.hash_bytes(encoded_leaf)?)
+}
+
+/// Verifies `encoded_leaf`, the SCALE encoding of the leaf, against a pallet-mmr root.
+pub fn verify_substrate_proof(
+    hasher: &dyn Hasher,
+    root: &Hash32,
+    proof: &SubstrateLeafProof,
+    encoded_leaf: &[u8],
+) -> Result<bool, MmrError> {
+    let [leaf_index] = proof.leaf_indices.as_slice() else {
+        return Err(MmrError::InvalidProofEncoding(
+            "expected exactly one leaf index",
+        ));
+    };
+    if *leaf_index >= proof.leaf_count {
+        return Ok(false);
+    }
+
+    let mmr_size = try_leaf_count_to_mmr_size(proof.leaf_count)?;
+    let position = map_leaf_index_to_element_index(*leaf_index) - 1;
+    let leaf_hash = hash_substrate_leaf(hasher, encoded_leaf)?;
+    verify_ckb_items(hasher, root, mmr_size, &proof.items, position, leaf_hash)
+}
+
+impl<S: Store> Mmr<S> {
+    /// Appends a SCALE-encoded leaf hashed the way pallet-mmr hashes it.
+    pub async fn append_substrate_leaf(
+        &self,
+        encoded_leaf: &[u8],
+    ) -> Result<AppendResult, MmrError> {
+        self.append(hash_substrate_leaf(self.hasher.as_ref(), encoded_leaf)?)
+            .await
+    }
+
+    pub async fn get_substrate_proof(
+        &self,
+        leaf_index: u64,
+        leaf_count: Option<u64>,
+    ) -> Result<SubstrateLeafProof, MmrError> {
+        let leaf_count = match leaf_count {
+            Some(count) => count,
+            None => self.get_leaves_count().await?,
+        };
+        if leaf_index >= leaf_count {
+            return Err(MmrError::InvalidLeafIndex);
+        }
+
+        let mmr_size = try_leaf_count_to_mmr_size(leaf_count)?;
+        let position = map_leaf_index_to_element_index(leaf_index) - 1;
+        let proof = self.get_ckb_proof(position, Some(mmr_size)).await?;
+        Ok(SubstrateLeafProof {
+            leaf_indices: vec![leaf_index],
+            leaf_count,
+            items: proof.proof_items,
+        })
+    }
+
+    pub async fn get_substrate_root(&self, leaf_count: Option<u64>) -> Result<Hash32, MmrError> {
+        let mmr_size = match leaf_count {
+            Some(count) => Some(try_leaf_count_to_mmr_size(count)?),
+            None => None,
+        };
+        self.get_ckb_root(mmr_size).await
+    }
+}
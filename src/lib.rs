@@ -1,17 +1,68 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
 pub mod error;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+#[cfg(feature = "grpc-server")]
+pub mod grpc;
+#[cfg(feature = "std")]
 pub mod hasher;
+#[cfg(feature = "http-server")]
+pub mod http;
+#[cfg(feature = "std")]
+pub mod interop;
+#[cfg(feature = "std")]
 pub mod mmr;
+#[cfg(feature = "std")]
 pub mod store;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+#[cfg(feature = "std")]
 pub mod types;
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "zkvm")]
+pub mod zkvm;
 
-pub use error::{HasherError, MmrError, StoreError};
+#[cfg(feature = "std")]
+pub use error::{HasherError, MmrError, StoreError, StoreOpTarget};
+#[cfg(feature = "blake2")]
+pub use hasher::Blake2bHasher;
+#[cfg(feature = "std")]
 pub use hasher::{Hasher, KeccakHasher, PoseidonHasher};
+#[cfg(feature = "std")]
 pub use mmr::{
-    Mmr, element_index_to_leaf_index, elements_count_to_leaf_count, find_peaks, find_siblings,
-    get_peak_info, leaf_count_to_append_no_merges, leaf_count_to_mmr_size,
-    leaf_count_to_peaks_count, map_leaf_index_to_element_index, mmr_size_to_leaf_count,
+    AppendHook, Checkpoint, CopyOptions, CopyProgress, DiffPacket, DraftMmr, IntegrityReport,
+    KECCAK_HASHER_CODE, LeafEncode, MAX_HASH_INDEX_PROBES, Mmr, MmrBuilder, MmrFollower, MmrInit,
+    MmrRegistry, MmrStats, MmrWriter, OfflineBuild, OpStats, POSEIDON_HASHER_CODE,
+    PROOF_ENCODING_VERSION, PROOF_JSON_VERSION, PeaksAccumulator, PeaksIter, PhaseTimings,
+    PinnedMmr, QueuedAppend, RebuildResult, SSZ_MAX_HASHES, SiblingsIter, TypedMmr, WitnessTracker,
+    WriterGuard, append_to_peaks, bag_peaks, bound_root_from_peaks, build_offline,
+    build_offline_with_threads, compute_peaks_from_leaves, compute_root_from_leaves, derive_mmr_id,
+    element_index_to_height, element_index_to_leaf_index, element_index_to_position,
+    elements_count_to_leaf_count, find_peaks, find_siblings, get_peak_info, iter_peaks,
+    iter_siblings, leaf_count_to_append_no_merges, leaf_count_to_mmr_size,
+    leaf_count_to_peaks_count, left_child_index, map_leaf_index_to_element_index,
+    mmr_size_to_leaf_count, padded_merkle_root, parent_index, position_to_element_index,
+    proof_siblings_count, prove_from_leaves, right_child_index, root_from_peaks, sibling_index,
+    try_leaf_count_to_mmr_size, try_map_leaf_index_to_element_index, verify_bound_proof,
+    verify_compact_proof, verify_merkle_proof, verify_path_proof, verify_proof,
+    verify_proof_against_root,
+};
+#[cfg(feature = "mmap-store")]
+pub use store::MmapStore;
+#[cfg(feature = "std")]
+pub use store::{
+    BranchStore, ID_ALLOCATOR_MMR_ID, InMemoryStore, KeyKind, OverlayStore, RetryPolicy,
+    RetryingStore, Store, StoreKey, StoreValue, WriteBatch, allocate_mmr_id_from_counter,
 };
-pub use store::{InMemoryStore, KeyKind, Store, StoreKey, StoreValue};
 #[cfg(feature = "postgres-store")]
 pub use store::{PostgresStore, PostgresStoreOptions};
-pub use types::{AppendResult, BatchAppendResult, Hash32, MmrId, Proof};
+#[cfg(feature = "std")]
+pub use types::{
+    AppendResult, BatchAppendResult, CompactProof, Hash32, MerkleProof, MmrId, PathProof,
+    PeaksCommitment, Proof, ProofJson, ProofVerification, ZeroBasedProof, hash_from_decimal,
+    hash_from_hex,
+};
@@ -0,0 +1,209 @@
+use std::sync::Arc;
+
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::store::{KeyKind, Store, StoreKey, StoreValue};
+use crate::types::{AppendResult, BatchAppendResult, Hash, MmrId};
+
+use super::helpers::{find_peaks, leaf_count_to_append_no_merges};
+use super::limits::SizeLimits;
+use super::verify::root_from_peaks;
+
+const MAX_APPEND_ATTEMPTS: u32 = 5;
+
+pub struct PeaksAccumulator<S: Store<N>, const N: usize = 32> {
+    pub mmr_id: MmrId,
+    store: S,
+    hasher: Arc<dyn Hasher<N>>,
+    size_limits: SizeLimits,
+}
+
+struct AccumulatorState<const N: usize> {
+    leaves_count: u64,
+    elements_count: u64,
+    peaks_hashes: Vec<Hash<N>>,
+}
+
+impl<S: Store<N>, const N: usize> PeaksAccumulator<S, N> {
+    pub fn new(store: S, hasher: Arc<dyn Hasher<N>>, mmr_id: MmrId) -> Self {
+        Self {
+            mmr_id,
+            store,
+            hasher,
+            size_limits: SizeLimits::default(),
+        }
+    }
+
+    pub fn with_max_leaves(mut self, max_leaves: u64) -> Self {
+        self.size_limits.max_leaves = Some(max_leaves);
+        self
+    }
+
+    pub fn with_max_elements(mut self, max_elements: u64) -> Self {
+        self.size_limits.max_elements = Some(max_elements);
+        self
+    }
+
+    pub async fn append(&self, value: Hash<N>) -> Result<AppendResult<N>, MmrError> {
+        let batch_result = self.batch_append(&[value]).await?;
+        Ok(AppendResult::from(batch_result))
+    }
+
+    pub async fn batch_append(&self, values: &[Hash<N>]) -> Result<BatchAppendResult<N>, MmrError> {
+        if values.is_empty() {
+            return Err(MmrError::EmptyBatchAppend);
+        }
+
+        for _ in 0..MAX_APPEND_ATTEMPTS {
+            let AccumulatorState {
+                mut leaves_count,
+                mut elements_count,
+                mut peaks_hashes,
+            } = self.load_state().await?;
+            self.size_limits.check_append(leaves_count, values.len())?;
+            let previous_elements_count = elements_count;
+            let previous_root_hash = if previous_elements_count == 0 {
+                None
+            } else {
+                Some(root_from_peaks(
+                    self.hasher.as_ref(),
+                    &peaks_hashes,
+                    previous_elements_count,
+                )?)
+            };
+
+            let first_element_index = elements_count.checked_add(1).ok_or(MmrError::Overflow)?;
+            let mut last_element_index = first_element_index;
+            for value in values {
+                elements_count = elements_count.checked_add(1).ok_or(MmrError::Overflow)?;
+                last_element_index = elements_count;
+                peaks_hashes.push(*value);
+
+                for _ in 0..leaf_count_to_append_no_merges(leaves_count) {
+                    elements_count = elements_count.checked_add(1).ok_or(MmrError::Overflow)?;
+                    let right_hash = peaks_hashes
+                        .pop()
+                        .ok_or(MmrError::NoHashFoundForIndex(elements_count))?;
+                    let left_hash = peaks_hashes
+                        .pop()
+                        .ok_or(MmrError::NoHashFoundForIndex(elements_count))?;
+                    peaks_hashes.push(self.hasher.hash_pair(&left_hash, &right_hash)?);
+                }
+
+                leaves_count = leaves_count.checked_add(1).ok_or(MmrError::Overflow)?;
+            }
+            let root_hash = root_from_peaks(self.hasher.as_ref(), &peaks_hashes, elements_count)?;
+
+            let committed = self
+                .store
+                .compare_and_set_many(
+                    &self.key(KeyKind::ElementsCount),
+                    previous_elements_count,
+                    vec![
+                        (
+                            self.key(KeyKind::Peaks),
+                            StoreValue::Bytes(
+                                peaks_hashes.iter().flat_map(|hash| hash.0).collect(),
+                            ),
+                        ),
+                        (self.key(KeyKind::LeafCount), StoreValue::U64(leaves_count)),
+                        (
+                            self.key(KeyKind::ElementsCount),
+                            StoreValue::U64(elements_count),
+                        ),
+                        (self.key(KeyKind::RootHash), StoreValue::Hash(root_hash)),
+                    ],
+                )
+                .await?;
+            if !committed {
+                continue;
+            }
+
+            return Ok(BatchAppendResult {
+                appended_count: u64::try_from(values.len()).map_err(|_| MmrError::Overflow)?,
+                first_element_index,
+                last_element_index,
+                leaves_count,
+                elements_count,
+                root_hash,
+                root_deferred: false,
+                peaks_hashes,
+                previous_elements_count,
+                previous_root_hash,
+            });
+        }
+
+        Err(MmrError::ConcurrentAppendConflict(MAX_APPEND_ATTEMPTS))
+    }
+
+    pub async fn get_peaks(&self) -> Result<Vec<Hash<N>>, MmrError> {
+        Ok(self.load_state().await?.peaks_hashes)
+    }
+
+    pub async fn get_root_hash(&self) -> Result<Option<Hash<N>>, MmrError> {
+        let key = self.key(KeyKind::RootHash);
+        match self.store.get(&key).await? {
+            Some(value) => Ok(Some(value.expect_hash(&key)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_leaves_count(&self) -> Result<u64, MmrError> {
+        self.get_counter(KeyKind::LeafCount).await
+    }
+
+    pub async fn get_elements_count(&self) -> Result<u64, MmrError> {
+        self.get_counter(KeyKind::ElementsCount).await
+    }
+
+    async fn load_state(&self) -> Result<AccumulatorState<N>, MmrError> {
+        let keys = [
+            self.key(KeyKind::LeafCount),
+            self.key(KeyKind::ElementsCount),
+            self.key(KeyKind::Peaks),
+        ];
+        let mut values = self.store.get_many(&keys).await?.into_iter();
+        let mut next_counter = |key: &StoreKey| -> Result<u64, MmrError> {
+            match values.next().flatten() {
+                Some(value) => Ok(value.expect_u64(key)?),
+                None => Ok(0),
+            }
+        };
+        let leaves_count = next_counter(&keys[0])?;
+        let elements_count = next_counter(&keys[1])?;
+        let peaks_bytes = match values.next().flatten() {
+            Some(value) => value.expect_bytes(&keys[2])?,
+            None => Vec::new(),
+        };
+
+        if peaks_bytes.len() % N != 0 || peaks_bytes.len() / N != find_peaks(elements_count).len() {
+            return Err(MmrError::InvalidPeaksCountForElements);
+        }
+        let peaks_hashes = peaks_bytes
+            .chunks_exact(N)
+            .map(|chunk| {
+                let mut hash = Hash::<N>::default();
+                hash.copy_from_slice(chunk);
+                hash
+            })
+            .collect();
+
+        Ok(AccumulatorState {
+            leaves_count,
+            elements_count,
+            peaks_hashes,
+        })
+    }
+
+    async fn get_counter(&self, kind: KeyKind) -> Result<u64, MmrError> {
+        let key = self.key(kind);
+        match self.store.get(&key).await? {
+            Some(value) => Ok(value.expect_u64(&key)?),
+            None => Ok(0),
+        }
+    }
+
+    fn key(&self, kind: KeyKind) -> StoreKey {
+        StoreKey::metadata(self.mmr_id, kind)
+    }
+}
@@ -0,0 +1,98 @@
+use std::borrow::Borrow;
+
+use rkyv::rancor::Error as RkyvError;
+use rkyv::util::AlignedVec;
+
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::types::{
+    ArchivedHash, ArchivedProof, ArchivedProofArchive, Hash32, Proof, ProofArchive,
+};
+use crate::verify;
+
+/// Lets the verifier read archived siblings in place.
+impl<const N: usize> Borrow<[u8; N]> for ArchivedHash<N> {
+    fn borrow(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl Proof {
+    pub fn to_archive_bytes(&self) -> Result<AlignedVec, MmrError> {
+        rkyv::to_bytes::<RkyvError>(self).map_err(|_| MmrError::InvalidProofArchive)
+    }
+}
+
+impl ArchivedProof {
+    pub fn from_archive_bytes(bytes: &[u8]) -> Result<&Self, MmrError> {
+        rkyv::access::<Self, RkyvError>(bytes).map_err(|_| MmrError::InvalidProofArchive)
+    }
+
+    /// Checks the proof against its own peaks without copying the archived hashes; like
+    /// [`crate::mmr::verify_proof`], it ignores the advisory `mmr_id` label.
+    pub fn verify(&self, hasher: &dyn Hasher, element_value: Hash32) -> Result<bool, MmrError> {
+        let peaks_hashes = self.peaks_hashes.as_slice();
+        match verify::compute_peak(
+            hasher,
+            self.element_index.to_native(),
+            &element_value.0,
+            self.siblings_hashes.as_slice(),
+            peaks_hashes.len(),
+            self.elements_count.to_native(),
+        )? {
+            Some((peak_index, peak_hash)) => {
+                Ok(peaks_hashes.get(peak_index).map(|hash| hash.0) == Some(peak_hash))
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Checks the proof against `bound_root` with its `mmr_id` hashed in, as
+    /// [`crate::mmr::verify_bound_proof`] does.
+    pub fn verify_bound(
+        &self,
+        hasher: &dyn Hasher,
+        element_value: Hash32,
+        bound_root: &Hash32,
+    ) -> Result<bool, MmrError> {
+        let Some(mmr_id) = self.mmr_id.as_ref() else {
+            return Ok(false);
+        };
+        verify::verify_proof_against_bound_root(
+            hasher,
+            self.element_index.to_native(),
+            &element_value.0,
+            self.siblings_hashes.as_slice(),
+            self.peaks_hashes.as_slice(),
+            self.elements_count.to_native(),
+            mmr_id.to_native(),
+            &bound_root.0,
+        )
+    }
+}
+
+impl ProofArchive {
+    pub fn to_archive_bytes(&self) -> Result<AlignedVec, MmrError> {
+        rkyv::to_bytes::<RkyvError>(self).map_err(|_| MmrError::InvalidProofArchive)
+    }
+}
+
+impl ArchivedProofArchive {
+    pub fn from_archive_bytes(bytes: &[u8]) -> Result<&Self, MmrError> {
+        rkyv::access::<Self, RkyvError>(bytes).map_err(|_| MmrError::InvalidProofArchive)
+    }
+
+    /// Checks every archived proof in place against the element value at the same
+    /// position; a count mismatch or any failing proof yields `false`.
+    pub fn verify(&self, hasher: &dyn Hasher, element_values: &[Hash32]) -> Result<bool, MmrError> {
+        if self.proofs.len() != element_values.len() {
+            return Ok(false);
+        }
+        for (proof, element_value) in self.proofs.iter().zip(element_values) {
+            if !proof.verify(hasher, *element_value)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
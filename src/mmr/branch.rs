@@ -0,0 +1,432 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::store::{BranchStore, KeyKind, Store, StoreKey, StoreValue, WriteBatch};
+use crate::types::{ElementIndex, Hash32, MmrId, ZERO_HASH};
+
+use super::core::Mmr;
+use super::hash_index::{MAX_HASH_INDEX_PROBES, initial_slot, next_slot};
+
+const BRANCH_PARENT_INDEX: u64 = 0;
+const BRANCH_FORK_POINT_INDEX: u64 = 1;
+/// Kept on the parent and bumped by every fork taken from it and every promote into it,
+/// so each can guard on the other not landing in between.
+const BRANCH_FORKS_INDEX: u64 = 2;
+const MAX_FORK_ATTEMPTS: u32 = 5;
+const PROMOTE_CHUNK_SIZE: u64 = 4096;
+
+fn forks_key(mmr_id: MmrId) -> StoreKey {
+    StoreKey::new(mmr_id, KeyKind::BranchParent, BRANCH_FORKS_INDEX)
+}
+
+async fn read_branch_record<S: Store>(
+    store: &S,
+    branch_id: MmrId,
+) -> Result<Option<(MmrId, u64)>, MmrError> {
+    let keys = [
+        StoreKey::new(branch_id, KeyKind::BranchParent, BRANCH_PARENT_INDEX),
+        StoreKey::new(branch_id, KeyKind::BranchParent, BRANCH_FORK_POINT_INDEX),
+    ];
+    let values = store.get_many(&keys).await?;
+    match (values[0].clone(), values[1].clone()) {
+        (Some(parent), Some(fork_point)) => Ok(Some((
+            parent.expect_u64(&keys[0])?,
+            fork_point.expect_u64(&keys[1])?,
+        ))),
+        _ => Ok(None),
+    }
+}
+
+impl<S: Store + Clone> Mmr<S> {
+    /// Branches this MMR at its current size. The branch keeps this instance's settings,
+    /// hooks included.
+    pub async fn fork(&self, branch_id: Option<MmrId>) -> Result<Mmr<BranchStore<S>>, MmrError> {
+        let branch_id = Self::resolve_mmr_id(self.store.inner(), branch_id).await?;
+        let branch_elements_count_key = StoreKey::metadata(branch_id, KeyKind::ElementsCount);
+        let forks_key = forks_key(self.mmr_id);
+        let metadata_keys = [
+            self.leaf_count_key(),
+            self.elements_count_key(),
+            self.root_hash_key(),
+        ];
+        let mut keys = metadata_keys.to_vec();
+        keys.push(forks_key.clone());
+        keys.push(branch_elements_count_key.clone());
+
+        for _ in 0..MAX_FORK_ATTEMPTS {
+            let mut values = self.store.get_many(&keys).await?;
+            if Self::extract_counter(&keys[4], values[4].clone())? != 0 {
+                return Err(MmrError::NonEmptyMmr);
+            }
+            let forks = Self::extract_counter(&keys[3], values[3].clone())?;
+            let fork_point = Self::extract_counter(&keys[1], values[1].clone())?;
+            values.truncate(metadata_keys.len());
+
+            let mut entries = vec![
+                (
+                    StoreKey::new(branch_id, KeyKind::BranchParent, BRANCH_PARENT_INDEX),
+                    StoreValue::U64(self.mmr_id),
+                ),
+                (
+                    StoreKey::new(branch_id, KeyKind::BranchParent, BRANCH_FORK_POINT_INDEX),
+                    StoreValue::U64(fork_point),
+                ),
+                (
+                    forks_key.clone(),
+                    StoreValue::U64(forks.checked_add(1).ok_or(MmrError::Overflow)?),
+                ),
+            ];
+            entries.extend(metadata_keys.iter().zip(values).filter_map(|(key, value)| {
+                value.map(|value| (StoreKey::new(branch_id, key.kind, key.index), value))
+            }));
+
+            // Guarding on the fork count makes a promote into this MMR that lands after the
+            // reads above fail the fork instead of recording a stale fork point.
+            let batch = WriteBatch::new()
+                .guard(branch_elements_count_key.clone(), 0)
+                .guard(forks_key.clone(), forks)
+                .set_many(entries);
+            if !self.store.write_batch(batch).await? {
+                continue;
+            }
+
+            let mut branch = Mmr::open_branch(self.store.clone(), self.hasher.shared(), branch_id)
+                .await?
+                .with_hash_index(self.hash_index)
+                .with_lazy_root(self.lazy_root)
+                .with_proof_binding(self.bind_proofs);
+            branch.size_limits = self.size_limits;
+            for hook in &self.hooks {
+                branch = branch.with_hook(hook.clone());
+            }
+            return Ok(branch);
+        }
+
+        Err(MmrError::ConcurrentAppendConflict(MAX_FORK_ATTEMPTS))
+    }
+
+    pub async fn open_branch(
+        store: S,
+        hasher: Arc<dyn Hasher>,
+        branch_id: MmrId,
+    ) -> Result<Mmr<BranchStore<S>>, MmrError> {
+        let mut segments = Vec::new();
+        let mut current = branch_id;
+        while let Some((parent, fork_point)) = read_branch_record(&store, current).await? {
+            segments.push((parent, fork_point));
+            current = parent;
+        }
+        if segments.is_empty() {
+            return Err(MmrError::NotABranch(branch_id));
+        }
+        segments.reverse();
+
+        Mmr::new(
+            BranchStore::new(store, branch_id, segments),
+            hasher,
+            Some(branch_id),
+        )
+    }
+}
+
+impl<S: Store> Mmr<BranchStore<S>> {
+    /// Makes this branch the canonical content of its parent: the parent's elements past the
+    /// fork point, with their leaf data, external keys and hash index entries, are replaced
+    /// by the branch's in one guarded batch, and its checkpoint by the branch's. Refused
+    /// while another branch still reads those parent elements.
+    pub async fn promote(&self) -> Result<MmrId, MmrError> {
+        let lease_owner = self.ensure_writer_lease().await?;
+        let (parent_id, fork_point) = *self
+            .store
+            .segments()
+            .last()
+            .ok_or(MmrError::NotABranch(self.mmr_id))?;
+        let base = self.store.base();
+        // Read before the sibling scan: a fork landing after it bumps the count and fails
+        // the batch's guard.
+        let parent_forks_key = forks_key(parent_id);
+        let parent_forks = match base.get(&parent_forks_key).await? {
+            Some(value) => value.expect_u64(&parent_forks_key)?,
+            None => 0,
+        };
+        self.ensure_no_branch_past(parent_id, fork_point).await?;
+
+        let leaves_count = self.get_leaves_count().await?;
+        self.size_limits.check(leaves_count)?;
+        let elements_count = self.get_elements_count().await?;
+        let root_hash = self.get_root_hash().await?;
+
+        let parent_elements_count_key = StoreKey::metadata(parent_id, KeyKind::ElementsCount);
+        let parent_elements_count = match base.get(&parent_elements_count_key).await? {
+            Some(value) => value.expect_u64(&parent_elements_count_key)?,
+            None => 0,
+        };
+
+        let mut entries = Vec::new();
+        let mut branch_external_keys = Vec::new();
+        let mut chunk_start = fork_point + 1;
+        while chunk_start <= elements_count {
+            let chunk_end = elements_count.min(chunk_start.saturating_add(PROMOTE_CHUNK_SIZE - 1));
+            let keys: Vec<StoreKey> = (chunk_start..=chunk_end)
+                .flat_map(|idx| {
+                    [
+                        StoreKey::new(self.mmr_id, KeyKind::NodeHash, idx),
+                        StoreKey::new(self.mmr_id, KeyKind::LeafData, idx),
+                        StoreKey::new(self.mmr_id, KeyKind::LeafKey, idx),
+                    ]
+                })
+                .collect();
+            let values = base.get_many(&keys).await?;
+            for (key, value) in keys.into_iter().zip(values) {
+                let Some(value) = value else {
+                    continue;
+                };
+                if key.kind == KeyKind::LeafKey {
+                    let external_key = value.clone().expect_u64(&key)?;
+                    branch_external_keys.push((
+                        StoreKey::new(self.mmr_id, KeyKind::ExternalKey, external_key),
+                        key.index,
+                    ));
+                }
+                entries.push((StoreKey::new(parent_id, key.kind, key.index), value));
+            }
+            chunk_start = chunk_end + 1;
+        }
+
+        let mut stale: Vec<StoreKey> = (fork_point + 1..=parent_elements_count.max(elements_count))
+            .flat_map(|idx| {
+                [
+                    StoreKey::new(parent_id, KeyKind::NodeHash, idx),
+                    StoreKey::new(parent_id, KeyKind::LeafData, idx),
+                    StoreKey::new(parent_id, KeyKind::LeafKey, idx),
+                ]
+            })
+            .collect();
+        stale.push(StoreKey::metadata(parent_id, KeyKind::Checkpoint));
+        if let Some(checkpoint) = self.get_checkpoint().await? {
+            entries.push((
+                StoreKey::metadata(parent_id, KeyKind::Checkpoint),
+                StoreValue::Bytes(checkpoint.to_bytes()),
+            ));
+        }
+
+        // The parent's external keys past the fork point are found through its leaf keys.
+        let mut chunk_start = fork_point + 1;
+        while chunk_start <= parent_elements_count {
+            let chunk_end =
+                parent_elements_count.min(chunk_start.saturating_add(PROMOTE_CHUNK_SIZE - 1));
+            let keys: Vec<StoreKey> = (chunk_start..=chunk_end)
+                .map(|idx| StoreKey::new(parent_id, KeyKind::LeafKey, idx))
+                .collect();
+            let values = base.get_many(&keys).await?;
+            for (key, value) in keys.iter().zip(values) {
+                if let Some(value) = value {
+                    let external_key = value.expect_u64(key)?;
+                    stale.push(StoreKey::new(parent_id, KeyKind::ExternalKey, external_key));
+                }
+            }
+            chunk_start = chunk_end + 1;
+        }
+        self.ensure_external_keys_not_inherited(&branch_external_keys)
+            .await?;
+        entries.extend(
+            branch_external_keys
+                .into_iter()
+                .map(|(key, element_index)| {
+                    (
+                        StoreKey::new(parent_id, KeyKind::ExternalKey, key.index),
+                        StoreValue::U64(element_index),
+                    )
+                }),
+        );
+        entries.extend(
+            self.stage_promoted_hash_index(parent_id, fork_point)
+                .await?,
+        );
+
+        entries.push((
+            StoreKey::metadata(parent_id, KeyKind::LeafCount),
+            StoreValue::U64(leaves_count),
+        ));
+        entries.push((
+            parent_elements_count_key.clone(),
+            StoreValue::U64(elements_count),
+        ));
+        entries.push((
+            StoreKey::metadata(parent_id, KeyKind::RootHash),
+            StoreValue::Hash(root_hash.unwrap_or(ZERO_HASH)),
+        ));
+        entries.push((
+            parent_forks_key.clone(),
+            StoreValue::U64(parent_forks.checked_add(1).ok_or(MmrError::Overflow)?),
+        ));
+
+        let mut batch = WriteBatch::new()
+            .guard(parent_elements_count_key, parent_elements_count)
+            .guard(parent_forks_key, parent_forks)
+            .delete_many(stale)
+            .set_many(entries);
+        if let Some((owner_key, owner)) = self.lease_guard(lease_owner) {
+            batch = batch.guard(owner_key, owner);
+        }
+        if !base.write_batch(batch).await? {
+            return Err(MmrError::ConcurrentAppendConflict(1));
+        }
+        self.clear_node_cache();
+
+        Ok(parent_id)
+    }
+
+    /// Fails if any other branch forked from `parent_id` after `fork_point`, since it still
+    /// reads the parent elements that promoting would replace.
+    async fn ensure_no_branch_past(
+        &self,
+        parent_id: MmrId,
+        fork_point: u64,
+    ) -> Result<(), MmrError> {
+        let base = self.store.base();
+        for mmr_id in base.list_mmr_ids().await? {
+            if mmr_id == self.mmr_id {
+                continue;
+            }
+            let mut current = mmr_id;
+            while let Some((owner, owner_fork_point)) = read_branch_record(base, current).await? {
+                if owner == parent_id && owner_fork_point > fork_point {
+                    return Err(MmrError::PromoteSharedParent {
+                        parent: parent_id,
+                        sibling: mmr_id,
+                    });
+                }
+                current = owner;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fails if an ancestor already maps one of the branch's external keys to an element
+    /// the branch shares, which promoting would silently remap.
+    async fn ensure_external_keys_not_inherited(
+        &self,
+        branch_external_keys: &[(StoreKey, ElementIndex)],
+    ) -> Result<(), MmrError> {
+        let keys: Vec<StoreKey> = branch_external_keys
+            .iter()
+            .map(|(key, _)| key.clone())
+            .collect();
+        for chunk in keys.chunks(PROMOTE_CHUNK_SIZE as usize) {
+            let inherited = self.store.get_inherited(chunk).await?;
+            if let Some((key, _)) = chunk
+                .iter()
+                .zip(inherited)
+                .find(|(_, value)| value.is_some())
+            {
+                return Err(MmrError::DuplicateExternalKey(key.index));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `kind` keys of `mmr_id` with the element index each one maps to.
+    async fn read_index_entries(
+        &self,
+        mmr_id: MmrId,
+        kind: KeyKind,
+    ) -> Result<Vec<(StoreKey, ElementIndex)>, MmrError> {
+        let base = self.store.base();
+        let keys: Vec<StoreKey> = base
+            .scan_keys(mmr_id)
+            .await?
+            .into_iter()
+            .filter(|key| key.kind == kind)
+            .collect();
+        let mut entries = Vec::with_capacity(keys.len());
+        for chunk in keys.chunks(PROMOTE_CHUNK_SIZE as usize) {
+            let values = base.get_many(chunk).await?;
+            for (key, value) in chunk.iter().zip(values) {
+                if let Some(value) = value {
+                    let element_index = value.expect_u64(key)?;
+                    entries.push((key.clone(), element_index));
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Hash index slots of `parent_id` for the leaves this branch indexed. Parent slots that
+    /// point past `fork_point` index replaced elements and are reused; the rest are probed
+    /// past exactly as `stage_hash_index_writes` does.
+    async fn stage_promoted_hash_index(
+        &self,
+        parent_id: MmrId,
+        fork_point: u64,
+    ) -> Result<Vec<(StoreKey, StoreValue)>, MmrError> {
+        let mut indexed: Vec<ElementIndex> = self
+            .read_index_entries(self.mmr_id, KeyKind::HashIndex)
+            .await?
+            .into_iter()
+            .map(|(_, element_index)| element_index)
+            .filter(|element_index| *element_index > fork_point)
+            .collect();
+        indexed.sort_unstable();
+        indexed.dedup();
+
+        let mut claimed: BTreeMap<u64, Hash32> = BTreeMap::new();
+        let mut writes = Vec::new();
+        for element_index in indexed {
+            let Some(hash) = self.get_node_hash(element_index).await? else {
+                continue;
+            };
+            let mut slot = initial_slot(&hash);
+            let mut probes = 0;
+            loop {
+                if probes == MAX_HASH_INDEX_PROBES {
+                    return Err(MmrError::HashIndexProbeLimit(MAX_HASH_INDEX_PROBES));
+                }
+                probes += 1;
+
+                if let Some(claimed_hash) = claimed.get(&slot) {
+                    if *claimed_hash == hash {
+                        break;
+                    }
+                    slot = next_slot(slot);
+                    continue;
+                }
+
+                // The slot as the branch inherits it: ancestor entries for shared elements
+                // stay, parent entries past the fork point index replaced elements.
+                let branch_slot_key = StoreKey::new(self.mmr_id, KeyKind::HashIndex, slot);
+                let occupant = match self
+                    .store
+                    .get_inherited(std::slice::from_ref(&branch_slot_key))
+                    .await?
+                    .pop()
+                    .flatten()
+                {
+                    Some(value) => Some(value.expect_u64(&branch_slot_key)?),
+                    None => None,
+                };
+                match occupant {
+                    Some(existing) => {
+                        if self.get_node_hash(existing).await? == Some(hash) {
+                            break;
+                        }
+                        slot = next_slot(slot);
+                    }
+                    None => {
+                        let slot_key = StoreKey::new(parent_id, KeyKind::HashIndex, slot);
+                        claimed.insert(slot, hash);
+                        writes.push((slot_key, StoreValue::U64(element_index)));
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(writes)
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use crate::error::MmrError;
+use crate::hasher::{Hasher, KeccakHasher};
+use crate::store::Store;
+use crate::types::{Hash32, MmrId};
+
+use super::core::Mmr;
+use super::helpers::mmr_size_to_leaf_count;
+use super::hooks::AppendHook;
+use super::limits::SizeLimits;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MmrInit {
+    #[default]
+    OpenOrCreate,
+    OpenExisting,
+    CreateNew,
+    FromPeaks {
+        peaks_hashes: Vec<Hash32>,
+        elements_count: u64,
+    },
+}
+
+pub struct MmrBuilder<S: Store> {
+    store: S,
+    hasher: Arc<dyn Hasher>,
+    mmr_id: Option<MmrId>,
+    init: MmrInit,
+    hash_index: bool,
+    size_limits: SizeLimits,
+    node_cache_capacity: usize,
+    always_reload: bool,
+    lazy_root: bool,
+    paranoid_reads: bool,
+    proof_binding: bool,
+    hooks: Vec<Arc<dyn AppendHook>>,
+}
+
+impl<S: Store> MmrBuilder<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            hasher: Arc::new(KeccakHasher::new()),
+            mmr_id: None,
+            init: MmrInit::default(),
+            hash_index: false,
+            size_limits: SizeLimits::default(),
+            node_cache_capacity: 0,
+            always_reload: false,
+            lazy_root: false,
+            paranoid_reads: false,
+            proof_binding: false,
+            hooks: Vec::new(),
+        }
+    }
+
+    pub fn hasher(mut self, hasher: Arc<dyn Hasher>) -> Self {
+        self.hasher = hasher;
+        self
+    }
+
+    pub fn mmr_id(mut self, mmr_id: MmrId) -> Self {
+        self.mmr_id = Some(mmr_id);
+        self
+    }
+
+    pub fn hash_index(mut self, enabled: bool) -> Self {
+        self.hash_index = enabled;
+        self
+    }
+
+    pub fn max_leaves(mut self, max_leaves: u64) -> Self {
+        self.size_limits.max_leaves = Some(max_leaves);
+        self
+    }
+
+    pub fn max_elements(mut self, max_elements: u64) -> Self {
+        self.size_limits.max_elements = Some(max_elements);
+        self
+    }
+
+    /// See `Mmr::with_node_cache`.
+    pub fn node_cache(mut self, capacity: usize) -> Self {
+        self.node_cache_capacity = capacity;
+        self
+    }
+
+    pub fn always_reload(mut self, enabled: bool) -> Self {
+        self.always_reload = enabled;
+        self
+    }
+
+    pub fn lazy_root(mut self, enabled: bool) -> Self {
+        self.lazy_root = enabled;
+        self
+    }
+
+    /// See `Mmr::with_paranoid_reads`.
+    pub fn paranoid_reads(mut self, enabled: bool) -> Self {
+        self.paranoid_reads = enabled;
+        self
+    }
+
+    /// See `Mmr::with_proof_binding`.
+    pub fn proof_binding(mut self, enabled: bool) -> Self {
+        self.proof_binding = enabled;
+        self
+    }
+
+    pub fn hook(mut self, hook: Arc<dyn AppendHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    pub fn init(mut self, init: MmrInit) -> Self {
+        self.init = init;
+        self
+    }
+
+    pub fn open_existing(self) -> Self {
+        self.init(MmrInit::OpenExisting)
+    }
+
+    pub fn create_new(self) -> Self {
+        self.init(MmrInit::CreateNew)
+    }
+
+    pub fn from_peaks(self, peaks_hashes: Vec<Hash32>, elements_count: u64) -> Self {
+        self.init(MmrInit::FromPeaks {
+            peaks_hashes,
+            elements_count,
+        })
+    }
+
+    pub async fn build(self) -> Result<Mmr<S>, MmrError> {
+        let hash_index = self.hash_index;
+        let size_limits = self.size_limits;
+        // Only a fresh MMR may draw an id from the allocator; opening one needs the caller's.
+        let mmr_id = match (&self.init, self.mmr_id) {
+            (MmrInit::FromPeaks { .. }, mmr_id) => mmr_id,
+            (MmrInit::OpenExisting, None) => return Err(MmrError::MissingMmrId),
+            (_, mmr_id) => Some(Mmr::resolve_mmr_id(&self.store, mmr_id).await?),
+        };
+        let mmr = match self.init {
+            MmrInit::FromPeaks {
+                peaks_hashes,
+                elements_count,
+            } => {
+                size_limits.check(mmr_size_to_leaf_count(elements_count))?;
+                Mmr::create_from_peaks(
+                    self.store,
+                    self.hasher,
+                    mmr_id,
+                    peaks_hashes,
+                    elements_count,
+                )
+                .await?
+            }
+            MmrInit::OpenOrCreate => Mmr::new(self.store, self.hasher, mmr_id)?,
+            MmrInit::OpenExisting => {
+                let mmr = Mmr::new(self.store, self.hasher, mmr_id)?;
+                if mmr.get_elements_count().await? == 0 {
+                    return Err(MmrError::MmrNotFound(mmr.mmr_id));
+                }
+                mmr
+            }
+            MmrInit::CreateNew => {
+                let mmr = Mmr::new(self.store, self.hasher, mmr_id)?;
+                if mmr.get_elements_count().await? != 0 {
+                    return Err(MmrError::NonEmptyMmr);
+                }
+                mmr
+            }
+        };
+
+        let mut mmr = mmr
+            .with_hash_index(hash_index)
+            .with_node_cache(self.node_cache_capacity)
+            .with_always_reload(self.always_reload)
+            .with_lazy_root(self.lazy_root)
+            .with_paranoid_reads(self.paranoid_reads)
+            .with_proof_binding(self.proof_binding);
+        mmr.size_limits = size_limits;
+        for hook in self.hooks {
+            mmr = mmr.with_hook(hook);
+        }
+        Ok(mmr)
+    }
+}
+
+impl<S: Store> Mmr<S> {
+    pub fn builder(store: S) -> MmrBuilder<S> {
+        MmrBuilder::new(store)
+    }
+}
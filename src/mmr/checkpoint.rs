@@ -0,0 +1,118 @@
+use crate::error::MmrError;
+use crate::store::{KeyKind, Store, StoreKey, StoreValue};
+use crate::types::{BatchAppendResult, Hash};
+
+use super::core::{AppendExtras, Mmr};
+
+/// Counts only; a checkpoint with a root appends its `N` bytes.
+const CHECKPOINT_COUNTS_LEN: usize = 8 + 8 + 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint<const N: usize = 32> {
+    pub cursor: u64,
+    pub leaves_count: u64,
+    pub elements_count: u64,
+    /// `None` when the checkpointed append ran in lazy-root mode.
+    pub root_hash: Option<Hash<N>>,
+}
+
+impl<const N: usize> Checkpoint<N> {
+    pub(crate) fn from_result(cursor: u64, result: &BatchAppendResult<N>) -> Self {
+        Self {
+            cursor,
+            leaves_count: result.leaves_count,
+            elements_count: result.elements_count,
+            root_hash: result.root_hash_if_computed(),
+        }
+    }
+
+    pub(crate) fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(CHECKPOINT_COUNTS_LEN + N);
+        bytes.extend_from_slice(&self.cursor.to_be_bytes());
+        bytes.extend_from_slice(&self.leaves_count.to_be_bytes());
+        bytes.extend_from_slice(&self.elements_count.to_be_bytes());
+        if let Some(root_hash) = self.root_hash {
+            bytes.extend_from_slice(root_hash.as_slice());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, MmrError> {
+        if bytes.len() != CHECKPOINT_COUNTS_LEN + N && bytes.len() != CHECKPOINT_COUNTS_LEN {
+            return Err(MmrError::InvalidCheckpoint);
+        }
+
+        let read_u64 = |offset: usize| {
+            let mut raw = [0u8; 8];
+            raw.copy_from_slice(&bytes[offset..offset + 8]);
+            u64::from_be_bytes(raw)
+        };
+        let root_hash = (bytes.len() > CHECKPOINT_COUNTS_LEN).then(|| {
+            let mut root_hash = Hash::<N>::default();
+            root_hash.copy_from_slice(&bytes[CHECKPOINT_COUNTS_LEN..]);
+            root_hash
+        });
+
+        Ok(Self {
+            cursor: read_u64(0),
+            leaves_count: read_u64(8),
+            elements_count: read_u64(16),
+            root_hash,
+        })
+    }
+}
+
+impl<S: Store<N>, const N: usize> Mmr<S, N> {
+    pub async fn batch_append_with_checkpoint(
+        &self,
+        values: &[Hash<N>],
+        cursor: u64,
+    ) -> Result<BatchAppendResult<N>, MmrError> {
+        let extras = AppendExtras {
+            checkpoint_cursor: Some(cursor),
+            ..AppendExtras::default()
+        };
+        let (result, _) = self.commit_batch_append(values, extras).await?;
+        Ok(result)
+    }
+
+    pub async fn get_checkpoint(&self) -> Result<Option<Checkpoint<N>>, MmrError> {
+        let key = self.checkpoint_key();
+        match self.store.get(&key).await? {
+            Some(value) => Ok(Some(Checkpoint::from_bytes(&value.expect_bytes(&key)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn resume_from_checkpoint(&self) -> Result<Option<Checkpoint<N>>, MmrError> {
+        let Some(checkpoint) = self.get_checkpoint().await? else {
+            return Ok(None);
+        };
+
+        let elements_count = self.get_elements_count().await?;
+        let root_hash = self.get_root_hash().await?;
+        if elements_count != checkpoint.elements_count || root_hash != checkpoint.root_hash {
+            return Err(MmrError::CheckpointMismatch {
+                checkpoint_elements_count: checkpoint.elements_count,
+                elements_count,
+            });
+        }
+
+        Ok(Some(checkpoint))
+    }
+
+    pub(crate) fn checkpoint_key(&self) -> StoreKey {
+        StoreKey::metadata(self.mmr_id, KeyKind::Checkpoint)
+    }
+
+    pub(crate) fn checkpoint_write(
+        &self,
+        cursor: u64,
+        result: &BatchAppendResult<N>,
+    ) -> (StoreKey, StoreValue<N>) {
+        (
+            self.checkpoint_key(),
+            StoreValue::Bytes(Checkpoint::from_result(cursor, result).to_bytes()),
+        )
+    }
+}
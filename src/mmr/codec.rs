@@ -0,0 +1,112 @@
+use crate::error::MmrError;
+use crate::types::{Hash32, Proof};
+
+/// Version written by [`Proof::to_bytes`]. Older versions are still accepted by
+/// [`Proof::from_bytes`] and can be rewritten with [`Proof::migrate_bytes`].
+pub const PROOF_ENCODING_VERSION: u8 = 2;
+
+/// Original layout without `mmr_id`.
+const PROOF_ENCODING_V1: u8 = 1;
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl ByteReader<'_> {
+    fn read<const N: usize>(&mut self) -> Result<[u8; N], MmrError> {
+        if self.bytes.len() < N {
+            return Err(MmrError::InvalidProofEncoding("unexpected end of input"));
+        }
+        let (head, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        let mut out = [0u8; N];
+        out.copy_from_slice(head);
+        Ok(out)
+    }
+
+    fn read_u64(&mut self) -> Result<u64, MmrError> {
+        Ok(u64::from_be_bytes(self.read()?))
+    }
+
+    fn read_hashes(&mut self) -> Result<Vec<Hash32>, MmrError> {
+        let count = u32::from_be_bytes(self.read()?) as usize;
+        if self.bytes.len() / 32 < count {
+            return Err(MmrError::InvalidProofEncoding("hash list exceeds input"));
+        }
+        (0..count).map(|_| self.read().map(Hash32::new)).collect()
+    }
+}
+
+fn write_hashes(bytes: &mut Vec<u8>, hashes: &[Hash32]) -> Result<(), MmrError> {
+    let count = u32::try_from(hashes.len()).map_err(|_| MmrError::Overflow)?;
+    bytes.extend_from_slice(&count.to_be_bytes());
+    for hash in hashes {
+        bytes.extend_from_slice(hash.as_slice());
+    }
+    Ok(())
+}
+
+impl Proof {
+    /// Layout (big-endian): `version: u8 = 2`, `element_index: u64`, `elements_count: u64`,
+    /// `element_hash: [u8; 32]`, then siblings and peaks, each as a `u32` count followed by
+    /// that many 32-byte hashes, then `mmr_id` as a presence byte and an optional `u64`.
+    /// Version 1 had no `mmr_id`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MmrError> {
+        let hashes_len = 32 * (self.siblings_hashes.len() + self.peaks_hashes.len());
+        let mut bytes = Vec::with_capacity(1 + 8 + 8 + 32 + 4 + 4 + hashes_len + 1 + 8);
+        bytes.push(PROOF_ENCODING_VERSION);
+        bytes.extend_from_slice(&self.element_index.to_be_bytes());
+        bytes.extend_from_slice(&self.elements_count.to_be_bytes());
+        bytes.extend_from_slice(self.element_hash.as_slice());
+        write_hashes(&mut bytes, &self.siblings_hashes)?;
+        write_hashes(&mut bytes, &self.peaks_hashes)?;
+        match self.mmr_id {
+            Some(mmr_id) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&mmr_id.to_be_bytes());
+            }
+            None => bytes.push(0),
+        }
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MmrError> {
+        let mut reader = ByteReader { bytes };
+        let [version] = reader.read()?;
+        if version != PROOF_ENCODING_V1 && version != PROOF_ENCODING_VERSION {
+            return Err(MmrError::UnsupportedProofEncodingVersion(version));
+        }
+
+        let element_index = reader.read_u64()?;
+        let elements_count = reader.read_u64()?;
+        let element_hash = Hash32::new(reader.read()?);
+        let siblings_hashes = reader.read_hashes()?;
+        let peaks_hashes = reader.read_hashes()?;
+        let mmr_id = if version == PROOF_ENCODING_VERSION {
+            match reader.read()? {
+                [0] => None,
+                [1] => Some(reader.read_u64()?),
+                _ => return Err(MmrError::InvalidProofEncoding("invalid mmr id tag")),
+            }
+        } else {
+            None
+        };
+        if !reader.bytes.is_empty() {
+            return Err(MmrError::InvalidProofEncoding("trailing bytes"));
+        }
+
+        Ok(Self {
+            element_index,
+            element_hash,
+            siblings_hashes,
+            peaks_hashes,
+            elements_count,
+            mmr_id,
+        })
+    }
+
+    /// Decodes proof bytes of any supported version and re-encodes them in the current one.
+    pub fn migrate_bytes(bytes: &[u8]) -> Result<Vec<u8>, MmrError> {
+        Self::from_bytes(bytes)?.to_bytes()
+    }
+}
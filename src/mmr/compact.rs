@@ -0,0 +1,83 @@
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::types::{CompactProof, Hash32, Proof};
+
+use super::helpers::{get_peak_info, leaf_count_to_peaks_count, mmr_size_to_leaf_count};
+use super::verify::{climb_to_peak, verify_proof_against_root};
+
+impl Proof {
+    pub fn to_compact(&self) -> Result<CompactProof, MmrError> {
+        let peak_index = target_peak_index(self.element_index, self.elements_count)?;
+        if self.peaks_hashes.len() != expected_peaks_count(self.elements_count) {
+            return Err(MmrError::InvalidPeaksCount);
+        }
+
+        let mut other_peaks_hashes = self.peaks_hashes.clone();
+        other_peaks_hashes.remove(peak_index);
+
+        Ok(CompactProof {
+            element_index: self.element_index,
+            element_hash: self.element_hash,
+            siblings_hashes: self.siblings_hashes.clone(),
+            other_peaks_hashes,
+            elements_count: self.elements_count,
+            mmr_id: self.mmr_id,
+        })
+    }
+}
+
+impl CompactProof {
+    pub fn to_proof(&self, hasher: &dyn Hasher) -> Result<Proof, MmrError> {
+        let peak_index = target_peak_index(self.element_index, self.elements_count)?;
+        if self.other_peaks_hashes.len() + 1 != expected_peaks_count(self.elements_count) {
+            return Err(MmrError::InvalidPeaksCount);
+        }
+
+        let (_, peak_height) = get_peak_info(self.elements_count, self.element_index)?;
+        if self.siblings_hashes.len() != peak_height {
+            return Err(MmrError::InvalidSiblingsCount);
+        }
+
+        let peak_hash = climb_to_peak(
+            hasher,
+            self.element_index,
+            &self.siblings_hashes,
+            self.element_hash,
+        )?;
+        let mut peaks_hashes = self.other_peaks_hashes.clone();
+        peaks_hashes.insert(peak_index, peak_hash);
+
+        Ok(Proof {
+            element_index: self.element_index,
+            element_hash: self.element_hash,
+            siblings_hashes: self.siblings_hashes.clone(),
+            peaks_hashes,
+            elements_count: self.elements_count,
+            mmr_id: self.mmr_id,
+        })
+    }
+}
+
+pub fn verify_compact_proof(
+    hasher: &dyn Hasher,
+    proof: &CompactProof,
+    element_value: Hash32,
+    root_hash: &Hash32,
+) -> Result<bool, MmrError> {
+    let full_proof = match proof.to_proof(hasher) {
+        Ok(full_proof) => full_proof,
+        Err(MmrError::InvalidSiblingsCount) => return Ok(false),
+        Err(err) => return Err(err),
+    };
+
+    verify_proof_against_root(hasher, &full_proof, element_value, root_hash)
+}
+
+fn target_peak_index(element_index: u64, elements_count: u64) -> Result<usize, MmrError> {
+    let (peak_index, _) = get_peak_info(elements_count, element_index)?;
+    Ok(peak_index)
+}
+
+fn expected_peaks_count(elements_count: u64) -> usize {
+    leaf_count_to_peaks_count(mmr_size_to_leaf_count(elements_count)) as usize
+}
@@ -0,0 +1,109 @@
+use crate::error::MmrError;
+use crate::store::{KeyKind, Store, StoreKey, StoreValue};
+use crate::types::{ElementIndex, MmrId};
+
+use super::core::Mmr;
+
+const DEFAULT_COPY_CHUNK_SIZE: u64 = 4096;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CopyOptions {
+    pub chunk_size: u64,
+    pub resume_from: ElementIndex,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_COPY_CHUNK_SIZE,
+            resume_from: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CopyProgress {
+    pub next_element_index: ElementIndex,
+    pub elements_count: u64,
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn copy_to<T: Store>(
+        &self,
+        target_store: T,
+        target_mmr_id: MmrId,
+        options: CopyOptions,
+        mut on_progress: impl FnMut(CopyProgress),
+    ) -> Result<Mmr<T>, MmrError> {
+        let metadata_keys = [
+            self.leaf_count_key(),
+            self.elements_count_key(),
+            self.root_hash_key(),
+        ];
+        let metadata = self.store.get_many(&metadata_keys).await?;
+        let elements_count = match metadata[1].clone() {
+            Some(value) => value.expect_u64(&metadata_keys[1])?,
+            None => 0,
+        };
+
+        let target = Mmr::new(target_store, self.hasher.shared(), Some(target_mmr_id))?;
+        let chunk_size = options.chunk_size.max(1);
+        let mut next_element_index = options.resume_from.max(1);
+
+        while next_element_index <= elements_count {
+            let chunk_end = elements_count.min(
+                next_element_index
+                    .checked_add(chunk_size - 1)
+                    .ok_or(MmrError::Overflow)?,
+            );
+            let target_keys: Vec<StoreKey> = (next_element_index..=chunk_end)
+                .map(|idx| target.node_key(idx))
+                .chain((next_element_index..=chunk_end).map(|idx| target.leaf_data_key(idx)))
+                .chain((next_element_index..=chunk_end).map(|idx| target.leaf_key_key(idx)))
+                .collect();
+            let source_keys: Vec<StoreKey> = target_keys
+                .iter()
+                .map(|key| StoreKey::new(self.mmr_id, key.kind, key.index))
+                .collect();
+            let values = self.store.get_many(&source_keys).await?;
+
+            let mut entries: Vec<(StoreKey, StoreValue)> = target_keys
+                .into_iter()
+                .zip(values)
+                .filter_map(|(key, value)| value.map(|value| (key, value)))
+                .collect();
+            let mut external_keys = Vec::new();
+            for (key, value) in &entries {
+                if key.kind == KeyKind::LeafKey {
+                    let external_key = value.clone().expect_u64(key)?;
+                    external_keys.push((
+                        target.external_key_key(external_key),
+                        StoreValue::U64(key.index),
+                    ));
+                }
+            }
+            entries.extend(external_keys);
+            target.store.set_many(entries).await?;
+
+            next_element_index = chunk_end + 1;
+            on_progress(CopyProgress {
+                next_element_index,
+                elements_count,
+            });
+        }
+
+        let target_metadata_keys = [
+            target.leaf_count_key(),
+            target.elements_count_key(),
+            target.root_hash_key(),
+        ];
+        let metadata_entries: Vec<(StoreKey, StoreValue)> = target_metadata_keys
+            .into_iter()
+            .zip(metadata)
+            .filter_map(|(key, value)| value.map(|value| (key, value)))
+            .collect();
+        target.store.set_many(metadata_entries).await?;
+
+        Ok(target)
+    }
+}
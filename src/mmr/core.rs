@@ -1,25 +1,48 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
 
+use futures::channel::mpsc::{self, Receiver, Sender};
+use futures::lock::{Mutex, MutexGuard};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 #[cfg(feature = "postgres-store")]
 use sqlx::{Postgres, Transaction};
 
-use crate::error::MmrError;
-use crate::hasher::Hasher;
+use crate::error::{MmrError, StoreError};
+use crate::hasher::{Hasher, KeccakHasher};
 #[cfg(feature = "postgres-store")]
 use crate::store::PostgresStore;
-use crate::store::{KeyKind, Store, StoreKey, StoreValue};
+use crate::store::{
+    ID_ALLOCATOR_MMR_ID, InMemoryStore, KeyKind, Store, StoreKey, StoreValue, WriteBatch,
+};
+#[cfg(feature = "postgres-store")]
+use crate::types::Hash32;
 use crate::types::{
-    AppendResult, BatchAppendResult, ElementIndex, Hash32, MmrId, Proof, ZERO_HASH,
+    AppendResult, BatchAppendResult, ElementIndex, Hash, MmrId, Proof, ProofVerification,
 };
 
+#[cfg(feature = "postgres-store")]
+use super::hash_index::KeyReader;
 use super::helpers::{
-    element_index_to_leaf_index, find_peaks, find_siblings, get_peak_info,
-    leaf_count_to_append_no_merges, leaf_count_to_peaks_count, mmr_size_to_leaf_count,
+    PeaksIter, element_index_to_leaf_index, find_peaks, find_siblings, get_peak_info, iter_peaks,
+    iter_siblings, leaf_count_to_append_no_merges, map_leaf_index_to_element_index,
+    mmr_size_to_leaf_count, try_leaf_count_to_mmr_size,
 };
-
-static NEXT_MMR_ID: AtomicU32 = AtomicU32::new(1);
+use super::hooks::AppendHook;
+use super::lease::WriterLease;
+use super::limits::SizeLimits;
+use super::node_cache::NodeCache;
+use super::op_stats::{CountedStore, CountingHasher, OpCounters};
+use super::verify::{bag_peaks, bound_root_from_peaks, climb_to_peak, compute_peak_from_proof};
+
+static NEXT_MMR_ID: AtomicU64 = AtomicU64::new(1);
+const LEAF_ITER_CHUNK_SIZE: u64 = 1024;
+const NODE_RANGE_CHUNK_SIZE: u64 = 4096;
+pub(crate) const MAX_APPEND_ATTEMPTS: u32 = 5;
+const PARALLEL_PROOF_CHUNK_SIZE: usize = 32;
+const SUBSCRIBER_BUFFER: usize = 64;
 
 #[derive(Debug, Clone, Copy)]
 struct CachedCounts {
@@ -27,47 +50,199 @@ struct CachedCounts {
     elements_count: u64,
 }
 
-pub struct Mmr<S: Store> {
+pub struct Mmr<S: Store<N>, const N: usize = 32> {
     pub mmr_id: MmrId,
-    store: S,
-    hasher: Arc<dyn Hasher>,
-    cached_counts: Option<CachedCounts>,
+    pub(crate) store: CountedStore<S, N>,
+    pub(crate) hasher: CountingHasher<N>,
+    cached_counts: Mutex<Option<CachedCounts>>,
+    writer_lease: StdMutex<Option<WriterLease>>,
+    pub(crate) hash_index: bool,
+    pub(crate) lazy_root: bool,
+    pub(crate) paranoid_reads: bool,
+    pub(crate) registry_id: Option<u64>,
+    pub(crate) size_limits: SizeLimits,
+    always_reload: bool,
+    pub(crate) bind_proofs: bool,
+    node_cache: Option<StdMutex<NodeCache<N>>>,
+    pub(crate) hooks: Vec<Arc<dyn AppendHook<N>>>,
+    subscribers: StdMutex<Vec<Sender<(u64, Hash<N>)>>>,
 }
 
-impl<S: Store> fmt::Debug for Mmr<S> {
+impl<S: Store<N>, const N: usize> fmt::Debug for Mmr<S, N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Mmr").field("mmr_id", &self.mmr_id).finish()
     }
 }
 
-impl<S: Store> Mmr<S> {
-    pub fn new(store: S, hasher: Arc<dyn Hasher>, mmr_id: Option<MmrId>) -> Result<Self, MmrError> {
+impl<const N: usize> Mmr<InMemoryStore<N>, N> {
+    pub fn in_memory(hasher: Arc<dyn Hasher<N>>) -> Self {
+        let resolved_id = NEXT_MMR_ID.fetch_add(1, Ordering::Relaxed);
+        Self::with_resolved_id(InMemoryStore::default(), hasher, resolved_id)
+    }
+}
+
+impl Mmr<InMemoryStore> {
+    pub fn in_memory_keccak() -> Self {
+        Self::in_memory(Arc::new(KeccakHasher::new()))
+    }
+}
+
+impl<S: Store<N>, const N: usize> Mmr<S, N> {
+    pub fn new(
+        store: S,
+        hasher: Arc<dyn Hasher<N>>,
+        mmr_id: Option<MmrId>,
+    ) -> Result<Self, MmrError> {
+        if mmr_id == Some(ID_ALLOCATOR_MMR_ID) {
+            return Err(MmrError::ReservedMmrId);
+        }
         let resolved_id = mmr_id.unwrap_or_else(|| NEXT_MMR_ID.fetch_add(1, Ordering::Relaxed));
+        Ok(Self::with_resolved_id(store, hasher, resolved_id))
+    }
 
-        Ok(Self {
+    fn with_resolved_id(store: S, hasher: Arc<dyn Hasher<N>>, resolved_id: MmrId) -> Self {
+        let counters = Arc::new(OpCounters::default());
+        Self {
             mmr_id: resolved_id,
-            store,
-            hasher,
-            cached_counts: None,
+            store: CountedStore::new(store, counters.clone(), resolved_id),
+            hasher: CountingHasher::new(hasher, counters),
+            cached_counts: Mutex::new(None),
+            writer_lease: StdMutex::new(None),
+            hash_index: false,
+            lazy_root: false,
+            paranoid_reads: false,
+            registry_id: None,
+            size_limits: SizeLimits::default(),
+            always_reload: false,
+            bind_proofs: false,
+            node_cache: None,
+            hooks: Vec::new(),
+            subscribers: StdMutex::new(Vec::new()),
+        }
+    }
+
+    pub async fn allocate(store: S, hasher: Arc<dyn Hasher<N>>) -> Result<Self, MmrError> {
+        let mmr_id = store.allocate_mmr_id().await?;
+        Self::new(store, hasher, Some(mmr_id))
+    }
+
+    pub(crate) async fn invalidate_cached_counts(&self) {
+        *self.cached_counts.lock().await = None;
+    }
+
+    pub(crate) async fn resolve_mmr_id(
+        store: &S,
+        mmr_id: Option<MmrId>,
+    ) -> Result<MmrId, MmrError> {
+        match mmr_id {
+            Some(mmr_id) => Ok(mmr_id),
+            None => Ok(store.allocate_mmr_id().await?),
+        }
+    }
+
+    pub fn with_hash_index(mut self, enabled: bool) -> Self {
+        self.hash_index = enabled;
+        self
+    }
+
+    pub fn with_lazy_root(mut self, enabled: bool) -> Self {
+        self.lazy_root = enabled;
+        self
+    }
+
+    pub fn with_always_reload(mut self, enabled: bool) -> Self {
+        self.always_reload = enabled;
+        self
+    }
+
+    /// Stamps generated proofs with this `mmr_id` and rejects proofs labelled for another
+    /// MMR or carrying no label. Verifiers holding `get_bound_root_hash` check proofs with
+    /// `verify_bound_proof`, which hashes the label into the root, so a proof issued by
+    /// another MMR fails even when both hold the same leaves.
+    pub fn with_proof_binding(mut self, enabled: bool) -> Self {
+        self.bind_proofs = enabled;
+        self
+    }
+
+    /// Keeps up to `capacity` node hashes in memory for `get_proof` and peak lookups;
+    /// 0 disables the cache. Paths of this instance that rewrite nodes clear it, so it is
+    /// only coherent while other writers append: after a `promote`, `destroy` or import
+    /// through another instance, call `refresh`.
+    pub fn with_node_cache(mut self, capacity: usize) -> Self {
+        self.node_cache = (capacity > 0).then(|| StdMutex::new(NodeCache::new(capacity)));
+        self
+    }
+
+    /// Capacity the node cache was created with; 0 when there is none.
+    pub(crate) fn node_cache_capacity(&self) -> usize {
+        self.node_cache.as_ref().map_or(0, |cache| {
+            cache
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .capacity()
         })
     }
 
+    pub async fn refresh(&self) {
+        self.invalidate_cached_counts().await;
+        self.clear_node_cache();
+    }
+
+    pub fn with_max_leaves(mut self, max_leaves: u64) -> Self {
+        self.size_limits.max_leaves = Some(max_leaves);
+        self
+    }
+
+    pub fn with_max_elements(mut self, max_elements: u64) -> Self {
+        self.size_limits.max_elements = Some(max_elements);
+        self
+    }
+
+    pub fn with_hook(mut self, hook: Arc<dyn AppendHook<N>>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Stream of `(elements_count, root_hash)` after each append; in lazy-root mode, after
+    /// each `finalize_root` instead. Each subscriber buffers a bounded number of updates; one that falls
+    /// further behind misses the updates published while its buffer is full.
+    pub fn subscribe(&self) -> Receiver<(u64, Hash<N>)> {
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_BUFFER);
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(sender);
+        receiver
+    }
+
+    fn publish_root_update(&self, elements_count: u64, root_hash: Hash<N>) {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .retain_mut(
+                |sender| match sender.try_send((elements_count, root_hash)) {
+                    Ok(()) => true,
+                    Err(err) => err.is_full(),
+                },
+            );
+    }
+
     pub async fn create_from_peaks(
         store: S,
-        hasher: Arc<dyn Hasher>,
+        hasher: Arc<dyn Hasher<N>>,
         mmr_id: Option<MmrId>,
-        peaks_hashes: Vec<Hash32>,
+        peaks_hashes: Vec<Hash<N>>,
         elements_count: u64,
     ) -> Result<Self, MmrError> {
-        let mut mmr = Self::new(store, hasher, mmr_id)?;
+        let mmr_id = Self::resolve_mmr_id(&store, mmr_id).await?;
+        let mut mmr = Self::new(store, hasher, Some(mmr_id))?;
 
         let current_elements_count = mmr.get_elements_count().await?;
         if current_elements_count != 0 {
             return Err(MmrError::NonEmptyMmr);
         }
 
-        let expected_peak_indices = find_peaks(elements_count);
-        if expected_peak_indices.len() != peaks_hashes.len() {
+        if iter_peaks(elements_count).count() != peaks_hashes.len() {
             return Err(MmrError::InvalidPeaksCountForElements);
         }
 
@@ -75,14 +250,14 @@ impl<S: Store> Mmr<S> {
         mmr.set_leaves_count(leaves_count).await?;
         mmr.set_elements_count(elements_count).await?;
 
-        for (peak_index, peak_hash) in expected_peak_indices.iter().zip(peaks_hashes.iter()) {
-            mmr.set_node_hash(*peak_index, *peak_hash).await?;
+        for (peak_index, peak_hash) in iter_peaks(elements_count).zip(peaks_hashes.iter()) {
+            mmr.set_node_hash(peak_index, *peak_hash).await?;
         }
 
         let bag = mmr.bag_the_peaks(Some(elements_count)).await?;
         let root_hash = mmr.calculate_root_hash(&bag, elements_count)?;
         mmr.set_root_hash(root_hash).await?;
-        mmr.cached_counts = Some(CachedCounts {
+        *mmr.cached_counts.get_mut() = Some(CachedCounts {
             leaves_count,
             elements_count,
         });
@@ -90,41 +265,670 @@ impl<S: Store> Mmr<S> {
         Ok(mmr)
     }
 
-    pub async fn append(&mut self, value: Hash32) -> Result<AppendResult, MmrError> {
+    pub async fn new_with_genesis(
+        store: S,
+        hasher: Arc<dyn Hasher<N>>,
+        mmr_id: Option<MmrId>,
+        genesis_hash: Hash<N>,
+    ) -> Result<Self, MmrError> {
+        Self::init_with_genesis(store, hasher, mmr_id, genesis_hash, None).await
+    }
+
+    /// `new_with_genesis` that also stores `genesis_data` as the genesis leaf's data in the
+    /// same write. An existing MMR keeps whatever data it was created with.
+    pub(crate) async fn init_with_genesis(
+        store: S,
+        hasher: Arc<dyn Hasher<N>>,
+        mmr_id: Option<MmrId>,
+        genesis_hash: Hash<N>,
+        genesis_data: Option<Vec<u8>>,
+    ) -> Result<Self, MmrError> {
+        let mmr_id = Self::resolve_mmr_id(&store, mmr_id).await?;
+        let mmr = Self::new(store, hasher, Some(mmr_id))?;
+
+        if mmr.has_genesis(genesis_hash).await? {
+            return Ok(mmr);
+        }
+
+        let leaf_data = genesis_data.map(|data| vec![data]);
+        let extras = AppendExtras {
+            leaf_data: leaf_data.as_deref(),
+            expected_elements_count: Some(0),
+            ..AppendExtras::default()
+        };
+        match mmr.commit_batch_append(&[genesis_hash], extras).await {
+            Ok(_) => Ok(mmr),
+            // Another writer created the MMR between the check and the append.
+            Err(MmrError::DiffBaseMismatch { .. }) if mmr.has_genesis(genesis_hash).await? => {
+                Ok(mmr)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// `false` for an empty MMR, `true` when the first leaf is `genesis_hash` and
+    /// `GenesisMismatch` otherwise.
+    async fn has_genesis(&self, genesis_hash: Hash<N>) -> Result<bool, MmrError> {
+        if self.get_elements_count().await? == 0 {
+            return Ok(false);
+        }
+        match self.get_node_hash(1).await? {
+            Some(existing) if existing == genesis_hash => Ok(true),
+            _ => Err(MmrError::GenesisMismatch),
+        }
+    }
+
+    pub async fn destroy(&self) -> Result<(), MmrError> {
+        let mut cached_counts = self.cached_counts.lock().await;
+        let lease_owner = self.ensure_writer_lease().await?;
+
+        // One guarded batch, so a failure leaves the MMR whole and an append that lands
+        // after the scan makes the delete retry instead of leaving its keys behind.
+        for _ in 0..MAX_APPEND_ATTEMPTS {
+            let elements_count = self.get_elements_count().await?;
+            let keys = self.store.scan_keys(self.mmr_id).await?;
+            let mut batch = WriteBatch::new()
+                .guard(self.elements_count_key(), elements_count)
+                .delete_many(keys);
+            if let Some((owner_key, owner)) = self.lease_guard(lease_owner) {
+                batch = batch.guard(owner_key, owner);
+            }
+            if self.store.write_batch(batch).await? {
+                *cached_counts = None;
+                self.clear_node_cache();
+                self.set_writer_lease(None)?;
+                return Ok(());
+            }
+        }
+
+        Err(MmrError::ConcurrentAppendConflict(MAX_APPEND_ATTEMPTS))
+    }
+
+    pub async fn append(&self, value: Hash<N>) -> Result<AppendResult<N>, MmrError> {
         let batch_result = self.batch_append(&[value]).await?;
+        Ok(AppendResult::from(batch_result))
+    }
+
+    /// `append` that only commits while the MMR holds `expected_elements_count` elements and
+    /// fails with `DiffBaseMismatch` otherwise.
+    pub(crate) async fn append_at(
+        &self,
+        value: Hash<N>,
+        expected_elements_count: u64,
+    ) -> Result<AppendResult<N>, MmrError> {
+        let extras = AppendExtras {
+            expected_elements_count: Some(expected_elements_count),
+            ..AppendExtras::default()
+        };
+        let (batch_result, _) = self.commit_batch_append(&[value], extras).await?;
+        Ok(AppendResult::from(batch_result))
+    }
+
+    pub async fn append_hex(&self, value: &str) -> Result<AppendResult<N>, MmrError> {
+        self.append(value.parse()?).await
+    }
+
+    pub async fn append_raw(&self, data: &[u8]) -> Result<AppendResult<N>, MmrError> {
+        self.append(self.hasher.hash_leaf(data)?).await
+    }
+
+    pub async fn batch_append(&self, values: &[Hash<N>]) -> Result<BatchAppendResult<N>, MmrError> {
+        let (result, _) = self
+            .commit_batch_append(values, AppendExtras::default())
+            .await?;
+        Ok(result)
+    }
+
+    pub async fn append_with_data(
+        &self,
+        value: Hash<N>,
+        data: Vec<u8>,
+    ) -> Result<AppendResult<N>, MmrError> {
+        let batch_result = self.batch_append_with_data(vec![(value, data)]).await?;
+        Ok(AppendResult::from(batch_result))
+    }
+
+    pub async fn batch_append_with_data(
+        &self,
+        entries: Vec<(Hash<N>, Vec<u8>)>,
+    ) -> Result<BatchAppendResult<N>, MmrError> {
+        let (values, leaf_data): (Vec<Hash<N>>, Vec<Vec<u8>>) = entries.into_iter().unzip();
+        let extras = AppendExtras {
+            leaf_data: Some(&leaf_data),
+            ..AppendExtras::default()
+        };
+        let (result, _) = self.commit_batch_append(&values, extras).await?;
+        Ok(result)
+    }
+
+    pub async fn append_with_key(
+        &self,
+        external_key: u64,
+        value: Hash<N>,
+    ) -> Result<AppendResult<N>, MmrError> {
+        let batch_result = self
+            .batch_append_with_keys(&[(external_key, value)])
+            .await?;
+        Ok(AppendResult::from(batch_result))
+    }
+
+    pub async fn batch_append_with_keys(
+        &self,
+        entries: &[(u64, Hash<N>)],
+    ) -> Result<BatchAppendResult<N>, MmrError> {
+        let (external_keys, values): (Vec<u64>, Vec<Hash<N>>) = entries.iter().copied().unzip();
+        let mut seen = BTreeSet::new();
+        for &external_key in &external_keys {
+            if !seen.insert(external_key) {
+                return Err(MmrError::DuplicateExternalKey(external_key));
+            }
+        }
+
+        let extras = AppendExtras {
+            external_keys: Some(&external_keys),
+            ..AppendExtras::default()
+        };
+        let (result, _) = self.commit_batch_append(&values, extras).await?;
+        Ok(result)
+    }
+
+    pub async fn get_element_index_by_key(
+        &self,
+        external_key: u64,
+    ) -> Result<Option<ElementIndex>, MmrError> {
+        let key = self.external_key_key(external_key);
+        match self.store.get(&key).await? {
+            Some(value) => Ok(Some(value.expect_u64(&key)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// External keys of the leaves in `first_element_index..=last_element_index` with the
+    /// element index each maps to, in index order. Read from the per-leaf `LeafKey` entries,
+    /// so the cost follows the range rather than the size of the MMR.
+    pub(crate) async fn external_keys_in_range(
+        &self,
+        first_element_index: ElementIndex,
+        last_element_index: ElementIndex,
+    ) -> Result<Vec<(u64, ElementIndex)>, MmrError> {
+        let entries = self
+            .values_in_range(first_element_index, last_element_index, |idx| {
+                self.leaf_key_key(idx)
+            })
+            .await?;
+        entries
+            .into_iter()
+            .map(|(key, value)| Ok((value.expect_u64(&key)?, key.index)))
+            .collect()
+    }
+
+    pub async fn get_proof_by_key(
+        &self,
+        external_key: u64,
+        elements_count: Option<u64>,
+    ) -> Result<Proof<N>, MmrError> {
+        let element_index = self
+            .get_element_index_by_key(external_key)
+            .await?
+            .ok_or(MmrError::ExternalKeyNotFound(external_key))?;
+        self.get_proof(element_index, elements_count).await
+    }
+
+    pub async fn append_idempotent(
+        &self,
+        external_id: u64,
+        value: Hash<N>,
+    ) -> Result<AppendResult<N>, MmrError> {
+        if let Some(element_index) = self.get_element_index_by_key(external_id).await? {
+            return self.replay_append(external_id, element_index, value).await;
+        }
+
+        match self.append_with_key(external_id, value).await {
+            Err(MmrError::DuplicateExternalKey(_)) => {
+                let element_index = self
+                    .get_element_index_by_key(external_id)
+                    .await?
+                    .ok_or(MmrError::ExternalKeyNotFound(external_id))?;
+                self.replay_append(external_id, element_index, value).await
+            }
+            result => result,
+        }
+    }
+
+    async fn replay_append(
+        &self,
+        external_id: u64,
+        element_index: ElementIndex,
+        value: Hash<N>,
+    ) -> Result<AppendResult<N>, MmrError> {
+        if self.get_node_hash(element_index).await? != Some(value) {
+            return Err(MmrError::IdempotentValueMismatch(external_id));
+        }
+
+        let leaves_count = element_index_to_leaf_index(element_index)?
+            .checked_add(1)
+            .ok_or(MmrError::Overflow)?;
+        let elements_count = try_leaf_count_to_mmr_size(leaves_count)?;
+        let previous_elements_count = element_index - 1;
+        // Like the original append, a lazy-root MMR reports no roots.
+        let previous_root_hash = if previous_elements_count == 0 || self.lazy_root {
+            None
+        } else {
+            let previous_bag = self.bag_the_peaks(Some(previous_elements_count)).await?;
+            Some(self.calculate_root_hash(&previous_bag, previous_elements_count)?)
+        };
+        let peaks_hashes = self.get_peaks(Some(elements_count)).await?;
+        let root_hash = if self.lazy_root {
+            Hash::ZERO
+        } else {
+            let bag = self.bag_peaks_hashes(iter_peaks(elements_count), &peaks_hashes)?;
+            self.calculate_root_hash(&bag, elements_count)?
+        };
+
         Ok(AppendResult {
-            leaves_count: batch_result.leaves_count,
-            elements_count: batch_result.elements_count,
-            element_index: batch_result.first_element_index,
-            root_hash: batch_result.root_hash,
+            leaves_count,
+            elements_count,
+            element_index,
+            root_hash,
+            root_deferred: self.lazy_root,
+            peaks_hashes,
+            previous_elements_count,
+            previous_root_hash,
         })
     }
 
-    pub async fn batch_append(&mut self, values: &[Hash32]) -> Result<BatchAppendResult, MmrError> {
+    pub async fn get_leaf_data(
+        &self,
+        element_index: ElementIndex,
+    ) -> Result<Option<Vec<u8>>, MmrError> {
+        let key = self.leaf_data_key(element_index);
+        match self.store.get(&key).await? {
+            Some(value) => Ok(Some(value.expect_bytes(&key)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Leaf data stored for elements `first_element_index..=last_element_index`, in index
+    /// order.
+    pub(crate) async fn leaf_data_in_range(
+        &self,
+        first_element_index: ElementIndex,
+        last_element_index: ElementIndex,
+    ) -> Result<Vec<(ElementIndex, Vec<u8>)>, MmrError> {
+        let entries = self
+            .values_in_range(first_element_index, last_element_index, |idx| {
+                self.leaf_data_key(idx)
+            })
+            .await?;
+        entries
+            .into_iter()
+            .map(|(key, value)| Ok((key.index, value.expect_bytes(&key)?)))
+            .collect()
+    }
+
+    /// The stored `key_of(idx)` entries for `first_element_index..=last_element_index`,
+    /// read in chunks, in index order.
+    async fn values_in_range(
+        &self,
+        first_element_index: ElementIndex,
+        last_element_index: ElementIndex,
+        key_of: impl Fn(ElementIndex) -> StoreKey,
+    ) -> Result<Vec<(StoreKey, StoreValue<N>)>, MmrError> {
+        let mut entries = Vec::new();
+        let mut chunk_start = first_element_index;
+        while chunk_start <= last_element_index {
+            let chunk_end =
+                last_element_index.min(chunk_start.saturating_add(NODE_RANGE_CHUNK_SIZE - 1));
+            let keys: Vec<StoreKey> = (chunk_start..=chunk_end).map(&key_of).collect();
+            let values = self.store.get_many(&keys).await?;
+            entries.extend(
+                keys.into_iter()
+                    .zip(values)
+                    .filter_map(|(key, value)| value.map(|value| (key, value))),
+            );
+            if chunk_end == u64::MAX {
+                break;
+            }
+            chunk_start = chunk_end + 1;
+        }
+
+        Ok(entries)
+    }
+
+    pub async fn get_proof_with_data(
+        &self,
+        element_index: ElementIndex,
+        elements_count: Option<u64>,
+    ) -> Result<(Proof<N>, Option<Vec<u8>>), MmrError> {
+        let proof = self.get_proof(element_index, elements_count).await?;
+        let data = self.get_leaf_data(element_index).await?;
+        Ok((proof, data))
+    }
+
+    pub async fn append_with_proof(
+        &self,
+        value: Hash<N>,
+    ) -> Result<(AppendResult<N>, Proof<N>), MmrError> {
+        let (batch_result, mut proofs) = self.batch_append_with_proofs(&[value]).await?;
+        let proof = proofs.pop().ok_or(MmrError::EmptyBatchAppend)?;
+        Ok((AppendResult::from(batch_result), proof))
+    }
+
+    pub async fn batch_append_with_proofs(
+        &self,
+        values: &[Hash<N>],
+    ) -> Result<(BatchAppendResult<N>, Vec<Proof<N>>), MmrError> {
+        let extras = AppendExtras {
+            keep_nodes: true,
+            ..AppendExtras::default()
+        };
+        let (result, known_nodes) = self.commit_batch_append(values, extras).await?;
+        let elements_count = result.elements_count;
+        let lookup = |idx: u64| {
+            known_nodes
+                .get(&idx)
+                .copied()
+                .ok_or(MmrError::NoHashFoundForIndex(idx))
+        };
+
+        let first_leaf_index = result.leaves_count - result.appended_count;
+        let mut proofs = Vec::with_capacity(values.len());
+        for (offset, value) in (0u64..).zip(values) {
+            let element_index = map_leaf_index_to_element_index(first_leaf_index + offset);
+            let siblings_hashes = find_siblings(element_index, elements_count)?
+                .into_iter()
+                .map(lookup)
+                .collect::<Result<Vec<_>, _>>()?;
+            proofs.push(Proof {
+                element_index,
+                element_hash: *value,
+                siblings_hashes,
+                peaks_hashes: result.peaks_hashes.clone(),
+                elements_count,
+                mmr_id: self.proof_mmr_id(),
+            });
+        }
+
+        Ok((result, proofs))
+    }
+
+    pub(crate) async fn commit_batch_append(
+        &self,
+        values: &[Hash<N>],
+        extras: AppendExtras<'_>,
+    ) -> Result<(BatchAppendResult<N>, BTreeMap<u64, Hash<N>>), MmrError> {
+        self.check_append(values)?;
+        self.commit_checked_append(values, extras).await
+    }
+
+    /// `commit_batch_append` for values that already passed `check_append`.
+    pub(crate) async fn commit_checked_append(
+        &self,
+        values: &[Hash<N>],
+        extras: AppendExtras<'_>,
+    ) -> Result<(BatchAppendResult<N>, BTreeMap<u64, Hash<N>>), MmrError> {
+        for _ in 0..MAX_APPEND_ATTEMPTS {
+            let mut staged = self.stage_append(values).await?;
+            let known_nodes = match self.stage_append_extras(&mut staged, &extras).await {
+                Ok(known_nodes) => known_nodes,
+                Err(err) => {
+                    staged.discard();
+                    return Err(err);
+                }
+            };
+            if let Some(result) = self.commit_staged(staged).await? {
+                return Ok((result, known_nodes));
+            }
+        }
+
+        Err(MmrError::ConcurrentAppendConflict(MAX_APPEND_ATTEMPTS))
+    }
+
+    /// Checks `extras` against a staged append and adds their writes to it. Returns the
+    /// nodes the append touched when `extras.keep_nodes` is set.
+    async fn stage_append_extras(
+        &self,
+        staged: &mut StagedAppend<'_, N>,
+        extras: &AppendExtras<'_>,
+    ) -> Result<BTreeMap<u64, Hash<N>>, MmrError> {
+        let expected_elements_count = staged.expected_elements_count();
+        match extras.expected_elements_count {
+            Some(expected) if expected != expected_elements_count => {
+                return Err(MmrError::DiffBaseMismatch {
+                    expected,
+                    actual: expected_elements_count,
+                });
+            }
+            _ => {}
+        }
+        if let Some(external_keys) = extras.external_keys {
+            self.ensure_external_keys_unmapped(external_keys).await?;
+        }
+        if let Some(entries) = extras.external_key_entries {
+            let external_keys: Vec<u64> = entries.iter().map(|(key, _)| *key).collect();
+            self.ensure_external_keys_unmapped(&external_keys).await?;
+        }
+
+        let result = &staged.result;
+        let staged_writes = &mut staged.staged_writes;
+        let first_leaf_index = result.leaves_count - result.appended_count;
+        if let Some(leaf_data) = extras.leaf_data {
+            staged_writes.extend(
+                (first_leaf_index..)
+                    .zip(leaf_data)
+                    .map(|(leaf_index, data)| {
+                        (
+                            self.leaf_data_key(map_leaf_index_to_element_index(leaf_index)),
+                            StoreValue::Bytes(data.clone()),
+                        )
+                    }),
+            );
+        }
+        if let Some(external_keys) = extras.external_keys {
+            for (leaf_index, external_key) in (first_leaf_index..).zip(external_keys) {
+                let element_index = map_leaf_index_to_element_index(leaf_index);
+                staged_writes.extend(self.external_key_writes(*external_key, element_index));
+            }
+        }
+        if let Some(entries) = extras.external_key_entries {
+            for (external_key, element_index) in entries {
+                staged_writes.extend(self.external_key_writes(*external_key, *element_index));
+            }
+        }
+        if let Some(entries) = extras.leaf_data_entries {
+            staged_writes.extend(entries.iter().map(|(element_index, data)| {
+                (
+                    self.leaf_data_key(*element_index),
+                    StoreValue::Bytes(data.clone()),
+                )
+            }));
+        }
+        if let Some(cursor) = extras.checkpoint_cursor {
+            staged_writes.push(self.checkpoint_write(cursor, result));
+        }
+
+        let mut known_nodes = BTreeMap::new();
+        if extras.keep_nodes {
+            known_nodes.extend(
+                iter_peaks(expected_elements_count)
+                    .zip(staged.previous_peaks_hashes.iter().copied()),
+            );
+            known_nodes.extend(staged_writes.iter().filter_map(|(key, value)| {
+                match (key.kind, value) {
+                    (KeyKind::NodeHash, StoreValue::Hash(hash)) => Some((key.index, *hash)),
+                    _ => None,
+                }
+            }));
+        }
+        Ok(known_nodes)
+    }
+
+    /// Commits a staged append guarded on the element count and the writer lease and
+    /// publishes it. Returns `None` when a concurrent append landed first, in which case
+    /// the caller restages, up to `MAX_APPEND_ATTEMPTS` times. Callers run `check_append`
+    /// once beforehand.
+    pub(crate) async fn commit_staged(
+        &self,
+        mut staged: StagedAppend<'_, N>,
+    ) -> Result<Option<BatchAppendResult<N>>, MmrError> {
+        let expected_elements_count = staged.expected_elements_count();
+        let writes = std::mem::take(&mut staged.staged_writes);
+        let deletes = std::mem::take(&mut staged.staged_deletes);
+        match self
+            .compare_and_write_leased(staged.lease_owner, expected_elements_count, writes, deletes)
+            .await
+        {
+            Ok(true) => Ok(Some(self.finish_staged_append(staged))),
+            Ok(false) => {
+                staged.discard();
+                Ok(None)
+            }
+            Err(err) => {
+                staged.discard();
+                Err(err)
+            }
+        }
+    }
+
+    async fn hash_index_writes(
+        &self,
+        values: &[Hash<N>],
+        result: &BatchAppendResult<N>,
+    ) -> Result<Vec<(StoreKey, StoreValue<N>)>, MmrError> {
+        if !self.hash_index {
+            return Ok(Vec::new());
+        }
+
+        self.stage_hash_index_writes(appended_leaves(values, result))
+            .await
+    }
+
+    /// Runs the checks `batch_append` makes before locking the append path, including the
+    /// `on_before_append` hooks. Call once per batch before `stage_append`.
+    pub(crate) fn check_append(&self, values: &[Hash<N>]) -> Result<(), MmrError> {
         if values.is_empty() {
             return Err(MmrError::EmptyBatchAppend);
         }
 
-        let append_state = self.prepare_append_state().await?;
-        let AppendComputation {
-            staged_writes,
-            result,
-        } = self.build_append_writes(values, append_state)?;
+        for hook in &self.hooks {
+            hook.on_before_append(self.mmr_id, values)?;
+        }
+        Ok(())
+    }
 
-        self.store.set_many(staged_writes).await?;
-        self.cached_counts = Some(CachedCounts {
+    /// Computes the writes of a plain `batch_append` without committing them. The append
+    /// path stays locked until the result is passed to `finish_staged_append` or
+    /// discarded. The writes must be committed guarded on `elements_count_key` at
+    /// `StagedAppend::expected_elements_count`.
+    pub(crate) async fn stage_append(
+        &self,
+        values: &[Hash<N>],
+    ) -> Result<StagedAppend<'_, N>, MmrError> {
+        let mut cached_counts = self.cached_counts.lock().await;
+        for _ in 0..MAX_APPEND_ATTEMPTS {
+            let Some((append_state, lease_owner)) =
+                self.prepare_append_state(&mut cached_counts).await?
+            else {
+                *cached_counts = None;
+                continue;
+            };
+
+            let previous_peaks_hashes = append_state.peaks_hashes.clone();
+            let AppendComputation {
+                mut staged_writes,
+                staged_deletes,
+                result,
+            } = self.build_append_writes(values, append_state)?;
+            staged_writes.extend(self.hash_index_writes(values, &result).await?);
+            return Ok(StagedAppend {
+                cached_counts,
+                staged_writes,
+                staged_deletes,
+                result,
+                previous_peaks_hashes,
+                lease_owner,
+            });
+        }
+
+        Err(MmrError::ConcurrentAppendConflict(MAX_APPEND_ATTEMPTS))
+    }
+
+    /// Publishes a staged append whose writes have been stored.
+    pub(crate) fn finish_staged_append(&self, staged: StagedAppend<'_, N>) -> BatchAppendResult<N> {
+        self.publish_append(staged.cached_counts, &staged.result);
+        staged.result
+    }
+
+    /// Publishes an append whose writes were committed outside the append path.
+    pub(crate) async fn publish_committed_append(&self, result: &BatchAppendResult<N>) {
+        let cached_counts = self.cached_counts.lock().await;
+        self.publish_append(cached_counts, result);
+    }
+
+    /// Caches the counts of a committed append, releases the append path and notifies
+    /// hooks and subscribers.
+    fn publish_append(
+        &self,
+        mut cached_counts: MutexGuard<'_, Option<CachedCounts>>,
+        result: &BatchAppendResult<N>,
+    ) {
+        *cached_counts = Some(CachedCounts {
             leaves_count: result.leaves_count,
             elements_count: result.elements_count,
         });
+        drop(cached_counts);
+        self.store.counters().record_appends(result.appended_count);
 
-        Ok(result)
+        for hook in &self.hooks {
+            hook.on_after_append(self.mmr_id, result);
+        }
+        if let Some(root_hash) = result.root_hash_if_computed() {
+            self.publish_root_update(result.elements_count, root_hash);
+        }
+    }
+
+    pub fn append_stream<'a, V>(
+        &'a self,
+        values: V,
+        chunk_size: usize,
+    ) -> impl Stream<Item = Result<BatchAppendResult<N>, MmrError>> + 'a
+    where
+        V: Stream<Item = Hash<N>> + 'a,
+    {
+        let chunks = Box::pin(values.chunks(chunk_size.max(1)));
+        stream::try_unfold((self, chunks), |(mmr, mut chunks)| async move {
+            let Some(chunk) = chunks.next().await else {
+                return Ok::<_, MmrError>(None);
+            };
+
+            let result = mmr.batch_append(&chunk).await?;
+            Ok(Some((result, (mmr, chunks))))
+        })
+    }
+
+    pub async fn merge_from<T: Store<N>>(
+        &self,
+        other: &Mmr<T, N>,
+        chunk_size: usize,
+    ) -> Result<Option<BatchAppendResult<N>>, MmrError> {
+        let mut chunks = Box::pin(other.iter_leaves().chunks(chunk_size.max(1)));
+        let mut last_result = None;
+        while let Some(chunk) = chunks.next().await {
+            let values = chunk
+                .into_iter()
+                .map(|leaf| leaf.map(|(_, _, hash)| hash))
+                .collect::<Result<Vec<_>, _>>()?;
+            last_result = Some(self.batch_append(&values).await?);
+        }
+
+        Ok(last_result)
     }
 
     pub async fn get_proof(
         &self,
         element_index: ElementIndex,
         elements_count: Option<u64>,
-    ) -> Result<Proof, MmrError> {
+    ) -> Result<Proof<N>, MmrError> {
         if element_index == 0 {
             return Err(MmrError::InvalidElementIndex);
         }
@@ -138,279 +942,707 @@ impl<S: Store> Mmr<S> {
             return Err(MmrError::InvalidElementIndex);
         }
 
-        let peaks = find_peaks(tree_size);
-        let siblings = find_siblings(element_index, tree_size)?;
-
-        let peaks_hashes = self.retrieve_peaks_hashes(peaks).await?;
-
-        let sibling_keys: Vec<StoreKey> = siblings.iter().map(|idx| self.node_key(*idx)).collect();
-        let sibling_values = self.store.get_many(&sibling_keys).await?;
-        let mut siblings_hashes = Vec::new();
-        for (key, value) in sibling_keys.iter().zip(sibling_values.into_iter()) {
-            if let Some(value) = value {
-                siblings_hashes.push(value.expect_hash(key)?);
-            }
-        }
+        let peaks_hashes = self.retrieve_peaks_hashes(tree_size).await?;
 
-        let element_hash = self
-            .get_node_hash(element_index)
-            .await?
+        let mut indices =
+            iter_siblings(element_index, tree_size)?.collect::<Result<Vec<u64>, _>>()?;
+        indices.push(element_index);
+        let mut hashes = self.get_node_hashes(&indices).await?;
+        let element_hash = hashes
+            .pop()
+            .flatten()
             .ok_or(MmrError::NoHashFoundForIndex(element_index))?;
+        let siblings_hashes = indices
+            .iter()
+            .zip(hashes)
+            .map(|(idx, hash)| hash.ok_or(MmrError::NoHashFoundForIndex(*idx)))
+            .collect::<Result<_, _>>()?;
 
-        Ok(Proof {
+        let proof = Proof {
             element_index,
             element_hash,
             siblings_hashes,
             peaks_hashes,
             elements_count: tree_size,
-        })
+            mmr_id: self.proof_mmr_id(),
+        };
+        self.check_proof_path(&proof)?;
+        Ok(proof)
+    }
+
+    pub async fn get_tip_proof(&self) -> Result<Option<Proof<N>>, MmrError> {
+        let elements_count = self.get_elements_count().await?;
+        let leaves_count = mmr_size_to_leaf_count(elements_count);
+        if leaves_count == 0 {
+            return Ok(None);
+        }
+
+        let element_index = map_leaf_index_to_element_index(leaves_count - 1);
+        Ok(Some(
+            self.get_proof(element_index, Some(elements_count)).await?,
+        ))
+    }
+
+    pub async fn get_proofs(
+        &self,
+        element_indices: &[ElementIndex],
+        elements_count: Option<u64>,
+    ) -> Result<Vec<Proof<N>>, MmrError> {
+        if element_indices.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tree_size = match elements_count {
+            Some(count) => count,
+            None => self.get_elements_count().await?,
+        };
+
+        let mut keys: Vec<StoreKey> = iter_peaks(tree_size)
+            .map(|idx| self.node_key(idx))
+            .collect();
+        let peaks_len = keys.len();
+        let layouts = self.proof_layouts(element_indices, tree_size, &mut keys)?;
+
+        let values = self.store.get_many(&keys).await?;
+        let peaks_hashes = Self::required_hashes(&keys[..peaks_len], &values[..peaks_len])?;
+        self.check_peaks(tree_size, &peaks_hashes).await?;
+        let proofs = self.assemble_proofs(layouts, &keys, &values, &peaks_hashes, tree_size)?;
+        for proof in &proofs {
+            self.check_proof_path(proof)?;
+        }
+        Ok(proofs)
+    }
+
+    /// Like `get_proofs`, but splits the indices into chunks whose store reads run
+    /// concurrently, at most `max_concurrency` at a time. With paranoid reads on, each chunk's
+    /// path checks run on the rayon pool when the `rayon` feature is enabled. Proofs are
+    /// returned in request order.
+    pub async fn get_proofs_parallel(
+        &self,
+        element_indices: &[ElementIndex],
+        elements_count: Option<u64>,
+        max_concurrency: usize,
+    ) -> Result<Vec<Proof<N>>, MmrError> {
+        if element_indices.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tree_size = match elements_count {
+            Some(count) => count,
+            None => self.get_elements_count().await?,
+        };
+        let peaks_hashes = self.retrieve_peaks_hashes(tree_size).await?;
+
+        let chunks: Vec<Vec<Proof<N>>> =
+            stream::iter(element_indices.chunks(PARALLEL_PROOF_CHUNK_SIZE))
+                .map(|chunk| {
+                    let peaks_hashes = &peaks_hashes;
+                    async move {
+                        let mut keys = Vec::new();
+                        let layouts = self.proof_layouts(chunk, tree_size, &mut keys)?;
+                        let values = self.store.get_many(&keys).await?;
+                        let proofs =
+                            self.assemble_proofs(layouts, &keys, &values, peaks_hashes, tree_size)?;
+                        self.check_proof_paths(proofs).await
+                    }
+                })
+                .buffered(max_concurrency.max(1))
+                .try_collect()
+                .await?;
+
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
+    /// Appends the element and sibling keys of each proof to `keys`, returning
+    /// `(element_index, element_position, siblings_len)` per proof.
+    fn proof_layouts(
+        &self,
+        element_indices: &[ElementIndex],
+        tree_size: u64,
+        keys: &mut Vec<StoreKey>,
+    ) -> Result<Vec<(u64, usize, usize)>, MmrError> {
+        let mut layouts = Vec::with_capacity(element_indices.len());
+        for &element_index in element_indices {
+            if element_index == 0 || element_index > tree_size {
+                return Err(MmrError::InvalidElementIndex);
+            }
+
+            let siblings = find_siblings(element_index, tree_size)?;
+            layouts.push((element_index, keys.len(), siblings.len()));
+            keys.push(self.node_key(element_index));
+            keys.extend(siblings.iter().map(|idx| self.node_key(*idx)));
+        }
+        Ok(layouts)
+    }
+
+    fn assemble_proofs(
+        &self,
+        layouts: Vec<(u64, usize, usize)>,
+        keys: &[StoreKey],
+        values: &[Option<StoreValue<N>>],
+        peaks_hashes: &[Hash<N>],
+        tree_size: u64,
+    ) -> Result<Vec<Proof<N>>, MmrError> {
+        let mut proofs = Vec::with_capacity(layouts.len());
+        for (element_index, position, siblings_len) in layouts {
+            let element_hash = values[position]
+                .clone()
+                .ok_or(MmrError::NoHashFoundForIndex(element_index))?
+                .expect_hash(&keys[position])?;
+            let siblings_range = position + 1..position + 1 + siblings_len;
+            let siblings_hashes =
+                Self::required_hashes(&keys[siblings_range.clone()], &values[siblings_range])?;
+
+            let proof = Proof {
+                element_index,
+                element_hash,
+                siblings_hashes,
+                peaks_hashes: peaks_hashes.to_vec(),
+                elements_count: tree_size,
+                mmr_id: self.proof_mmr_id(),
+            };
+            proofs.push(proof);
+        }
+
+        Ok(proofs)
+    }
+
+    fn proof_mmr_id(&self) -> Option<MmrId> {
+        self.bind_proofs.then_some(self.mmr_id)
+    }
+
+    /// With binding on, only proofs stamped with this `mmr_id` are accepted; an unbound
+    /// proof could otherwise be replayed by clearing the field.
+    fn is_foreign_proof(&self, proof: &Proof<N>) -> bool {
+        if self.bind_proofs {
+            return proof.mmr_id != Some(self.mmr_id);
+        }
+        proof.mmr_id.is_some_and(|mmr_id| mmr_id != self.mmr_id)
+    }
+
+    /// Node hashes for `keys`; an absent node is an error rather than a shorter list.
+    fn required_hashes(
+        keys: &[StoreKey],
+        values: &[Option<StoreValue<N>>],
+    ) -> Result<Vec<Hash<N>>, MmrError> {
+        let mut hashes = Vec::with_capacity(values.len());
+        for (key, value) in keys.iter().zip(values) {
+            let value = value
+                .clone()
+                .ok_or(MmrError::NoHashFoundForIndex(key.index))?;
+            hashes.push(value.expect_hash(key)?);
+        }
+
+        Ok(hashes)
     }
 
     pub async fn verify_proof(
         &self,
-        proof: &Proof,
-        element_value: Hash32,
+        proof: &Proof<N>,
+        element_value: Hash<N>,
         elements_count: Option<u64>,
     ) -> Result<bool, MmrError> {
+        if self.is_foreign_proof(proof) {
+            return Ok(false);
+        }
         let tree_size = match elements_count {
             Some(count) => count,
             None => self.get_elements_count().await?,
         };
-        let leaf_count = mmr_size_to_leaf_count(tree_size);
-        let expected_peaks = leaf_count_to_peaks_count(leaf_count) as usize;
 
-        if proof.peaks_hashes.len() != expected_peaks {
-            return Err(MmrError::InvalidPeaksCount);
-        }
+        let Some((peak_index, peak_hash)) =
+            compute_peak_from_proof(self.hasher.as_ref(), proof, element_value, tree_size)?
+        else {
+            return Ok(false);
+        };
 
-        if proof.element_index == 0 || proof.element_index > tree_size {
-            return Err(MmrError::InvalidElementIndex);
+        let peak_hashes = self.retrieve_peaks_hashes(tree_size).await?;
+
+        Ok(peak_hashes.get(peak_index).copied() == Some(peak_hash))
+    }
+
+    pub async fn verify_proof_detailed(
+        &self,
+        proof: &Proof<N>,
+        element_value: Hash<N>,
+        elements_count: Option<u64>,
+    ) -> Result<ProofVerification, MmrError> {
+        match proof.mmr_id {
+            Some(actual) if actual != self.mmr_id => {
+                return Ok(ProofVerification::WrongMmrId {
+                    expected: self.mmr_id,
+                    actual,
+                });
+            }
+            None if self.bind_proofs => {
+                return Ok(ProofVerification::UnboundProof {
+                    expected: self.mmr_id,
+                });
+            }
+            _ => {}
+        }
+        let tree_size = match elements_count {
+            Some(count) => count,
+            None => self.get_elements_count().await?,
+        };
+        if proof.elements_count != tree_size {
+            return Ok(ProofVerification::StaleElementsCount {
+                proof_elements_count: proof.elements_count,
+                elements_count: tree_size,
+            });
         }
+        let (peak_index, peak_height) = match get_peak_info(tree_size, proof.element_index) {
+            Ok(peak_info) => peak_info,
+            Err(MmrError::InvalidElementCount) => {
+                return Ok(ProofVerification::InvalidElementCount);
+            }
+            Err(_) => return Ok(ProofVerification::InvalidElementIndex),
+        };
 
-        let (peak_index, peak_height) = get_peak_info(tree_size, proof.element_index);
+        let peaks_count = iter_peaks(tree_size).count();
+        if proof.peaks_hashes.len() != peaks_count {
+            return Ok(ProofVerification::WrongPeaksCount {
+                expected: peaks_count,
+                actual: proof.peaks_hashes.len(),
+            });
+        }
         if proof.siblings_hashes.len() != peak_height {
-            return Ok(false);
+            return Ok(ProofVerification::WrongSiblingCount {
+                expected: peak_height,
+                actual: proof.siblings_hashes.len(),
+            });
         }
 
-        let mut hash = element_value;
-        let mut leaf_index = element_index_to_leaf_index(proof.element_index)?;
+        let peak_hash = climb_to_peak(
+            self.hasher.as_ref(),
+            proof.element_index,
+            &proof.siblings_hashes,
+            element_value,
+        )?;
+
+        let peak_element_index = iter_peaks(tree_size)
+            .nth(peak_index)
+            .ok_or(MmrError::InvalidElementIndex)?;
+        let peak_key = self.node_key(peak_element_index);
+        let Some(stored_peak) = self.store.get(&peak_key).await? else {
+            return Ok(ProofVerification::MissingStoreNode(peak_key.index));
+        };
+        if stored_peak.expect_hash(&peak_key)? != peak_hash {
+            return Ok(ProofVerification::PeakMismatch { peak_index });
+        }
 
-        for sibling_hash in &proof.siblings_hashes {
-            let is_right = leaf_index % 2 == 1;
-            leaf_index /= 2;
-            hash = if is_right {
-                self.hasher.hash_pair(sibling_hash, &hash)?
-            } else {
-                self.hasher.hash_pair(&hash, sibling_hash)?
-            };
+        Ok(ProofVerification::Valid)
+    }
+
+    /// Verifies each proof against the stored peaks of its own `elements_count`, reading the
+    /// peaks of every size in one batch. A failed read fails the call; a proof that cannot
+    /// be checked, e.g. because its peaks are missing, only fails its own entry.
+    pub async fn verify_proofs(
+        &self,
+        items: &[(Proof<N>, Hash<N>)],
+    ) -> Result<Vec<Result<bool, MmrError>>, MmrError> {
+        let mut ranges: BTreeMap<u64, Range<usize>> = BTreeMap::new();
+        let mut keys = Vec::new();
+        for (proof, _) in items {
+            ranges.entry(proof.elements_count).or_insert_with(|| {
+                let start = keys.len();
+                keys.extend(iter_peaks(proof.elements_count).map(|idx| self.node_key(idx)));
+                start..keys.len()
+            });
         }
 
-        let peak_hashes = self.retrieve_peaks_hashes(find_peaks(tree_size)).await?;
+        let values = self.store.get_many(&keys).await?;
+        let peaks_by_size: BTreeMap<u64, Option<Vec<Hash<N>>>> = ranges
+            .iter()
+            .map(|(tree_size, range)| {
+                let peaks_hashes =
+                    Self::required_hashes(&keys[range.clone()], &values[range.clone()]).ok();
+                (*tree_size, peaks_hashes)
+            })
+            .collect();
+
+        let verify_one = |proof: &Proof<N>, element_value: Hash<N>| {
+            if self.is_foreign_proof(proof) {
+                return Ok(false);
+            }
+            let Some((peak_index, peak_hash)) = compute_peak_from_proof(
+                self.hasher.as_ref(),
+                proof,
+                element_value,
+                proof.elements_count,
+            )?
+            else {
+                return Ok(false);
+            };
+            let Some(peaks_hashes) = &peaks_by_size[&proof.elements_count] else {
+                // Re-derive the read error for this entry; `MmrError` is not `Clone`.
+                let range = ranges[&proof.elements_count].clone();
+                return Self::required_hashes(&keys[range.clone()], &values[range]).map(|_| false);
+            };
+            Ok(peaks_hashes.get(peak_index) == Some(&peak_hash))
+        };
 
-        Ok(peak_hashes.get(peak_index).copied() == Some(hash))
+        Ok(items
+            .iter()
+            .map(|(proof, element_value)| verify_one(proof, *element_value))
+            .collect())
     }
 
     #[cfg(feature = "stateless-verify")]
     pub async fn verify_proof_stateless(
         &self,
-        proof: &Proof,
-        element_value: Hash32,
+        proof: &Proof<N>,
+        element_value: Hash<N>,
         elements_count: Option<u64>,
     ) -> Result<bool, MmrError> {
+        if self.is_foreign_proof(proof) {
+            return Ok(false);
+        }
         let tree_size = match elements_count {
             Some(count) => count,
             None => self.get_elements_count().await?,
         };
-        let leaf_count = mmr_size_to_leaf_count(tree_size);
-        let expected_peaks = leaf_count_to_peaks_count(leaf_count) as usize;
 
-        if proof.peaks_hashes.len() != expected_peaks {
-            return Err(MmrError::InvalidPeaksCount);
+        match compute_peak_from_proof(self.hasher.as_ref(), proof, element_value, tree_size)? {
+            Some((peak_index, peak_hash)) => {
+                Ok(proof.peaks_hashes.get(peak_index).copied() == Some(peak_hash))
+            }
+            None => Ok(false),
         }
+    }
+
+    pub async fn get_peaks(&self, elements_count: Option<u64>) -> Result<Vec<Hash<N>>, MmrError> {
+        let tree_size = match elements_count {
+            Some(count) => count,
+            None => self.get_elements_count().await?,
+        };
+        self.retrieve_peaks_hashes(tree_size).await
+    }
 
-        if proof.element_index == 0 || proof.element_index > tree_size {
+    /// Nodes past the current elements count are never read: `to_element_index` is clamped
+    /// to it, so a span that starts beyond the MMR comes back empty.
+    pub async fn get_nodes_in_range(
+        &self,
+        from_element_index: ElementIndex,
+        to_element_index: ElementIndex,
+    ) -> Result<Vec<(ElementIndex, Hash<N>)>, MmrError> {
+        if from_element_index == 0 || from_element_index > to_element_index {
             return Err(MmrError::InvalidElementIndex);
         }
-
-        let (peak_index, peak_height) = get_peak_info(tree_size, proof.element_index);
-        if proof.siblings_hashes.len() != peak_height {
-            return Ok(false);
+        let to_element_index = to_element_index.min(self.get_elements_count().await?);
+        if from_element_index > to_element_index {
+            return Ok(Vec::new());
         }
 
-        let mut hash = element_value;
-        let mut leaf_index = element_index_to_leaf_index(proof.element_index)?;
+        let mut nodes = Vec::new();
+        let mut chunk_start = from_element_index;
+        loop {
+            let chunk_end =
+                to_element_index.min(chunk_start.saturating_add(NODE_RANGE_CHUNK_SIZE - 1));
+            let keys: Vec<StoreKey> = (chunk_start..=chunk_end)
+                .map(|idx| self.node_key(idx))
+                .collect();
+            let values = self.store.get_many(&keys).await?;
+            for (key, value) in keys.iter().zip(values) {
+                if let Some(value) = value {
+                    nodes.push((key.index, value.expect_hash(key)?));
+                }
+            }
 
-        for sibling_hash in &proof.siblings_hashes {
-            let is_right = leaf_index % 2 == 1;
-            leaf_index /= 2;
-            hash = if is_right {
-                self.hasher.hash_pair(sibling_hash, &hash)?
-            } else {
-                self.hasher.hash_pair(&hash, sibling_hash)?
-            };
+            if chunk_end == to_element_index {
+                return Ok(nodes);
+            }
+            chunk_start = chunk_end + 1;
         }
-
-        Ok(proof.peaks_hashes.get(peak_index).copied() == Some(hash))
     }
 
-    pub async fn get_peaks(&self, elements_count: Option<u64>) -> Result<Vec<Hash32>, MmrError> {
+    pub async fn get_peaks_with_heights(
+        &self,
+        elements_count: Option<u64>,
+    ) -> Result<Vec<(ElementIndex, u32, Hash<N>)>, MmrError> {
         let tree_size = match elements_count {
             Some(count) => count,
             None => self.get_elements_count().await?,
         };
-        self.retrieve_peaks_hashes(find_peaks(tree_size)).await
+        let keys: Vec<StoreKey> = iter_peaks(tree_size)
+            .map(|idx| self.node_key(idx))
+            .collect();
+        let values = self.store.get_many(&keys).await?;
+
+        let mut peaks = Vec::with_capacity(keys.len());
+        let mut previous_peak_index = 0;
+        for (key, value) in keys.iter().zip(values) {
+            let hash = value
+                .ok_or(MmrError::NoHashFoundForIndex(key.index))?
+                .expect_hash(key)?;
+            let mountain_elements_count = key.index - previous_peak_index;
+            peaks.push((key.index, (mountain_elements_count + 1).ilog2() - 1, hash));
+            previous_peak_index = key.index;
+        }
+
+        Ok(peaks)
     }
 
-    pub async fn bag_the_peaks(&self, elements_count: Option<u64>) -> Result<Hash32, MmrError> {
+    pub async fn bag_the_peaks(&self, elements_count: Option<u64>) -> Result<Hash<N>, MmrError> {
         let tree_size = match elements_count {
             Some(count) => count,
             None => self.get_elements_count().await?,
         };
-        let peaks_idxs = find_peaks(tree_size);
-        let peaks_hashes = self.retrieve_peaks_hashes(peaks_idxs.clone()).await?;
-        self.bag_peaks_hashes(&peaks_idxs, &peaks_hashes)
+        let peaks_hashes = self.retrieve_peaks_hashes(tree_size).await?;
+        self.bag_peaks_hashes(iter_peaks(tree_size), &peaks_hashes)
     }
 
     fn bag_peaks_hashes(
         &self,
-        peak_indices: &[u64],
-        peak_hashes: &[Hash32],
-    ) -> Result<Hash32, MmrError> {
-        match peak_indices.len() {
-            0 => Ok(ZERO_HASH),
-            1 => peak_hashes
+        mut peak_indices: PeaksIter,
+        peak_hashes: &[Hash<N>],
+    ) -> Result<Hash<N>, MmrError> {
+        match (peak_indices.next(), peak_indices.next()) {
+            (None, _) => Ok(Hash::ZERO),
+            (Some(first_peak), None) => peak_hashes
                 .first()
                 .copied()
-                .ok_or(MmrError::NoHashFoundForIndex(peak_indices[0])),
-            _ => {
+                .ok_or(MmrError::NoHashFoundForIndex(first_peak)),
+            (Some(first_peak), Some(_)) => {
                 if peak_hashes.len() < 2 {
-                    return Err(MmrError::NoHashFoundForIndex(peak_indices[0]));
+                    return Err(MmrError::NoHashFoundForIndex(first_peak));
                 }
 
-                let mut acc = self.hasher.hash_pair(
-                    &peak_hashes[peak_hashes.len() - 2],
-                    &peak_hashes[peak_hashes.len() - 1],
-                )?;
-
-                for peak in peak_hashes[..peak_hashes.len() - 2].iter().rev() {
-                    acc = self.hasher.hash_pair(peak, &acc)?;
-                }
-
-                Ok(acc)
+                bag_peaks(self.hasher.as_ref(), peak_hashes)
             }
         }
     }
 
     pub fn calculate_root_hash(
         &self,
-        bag: &Hash32,
+        bag: &Hash<N>,
         elements_count: u64,
-    ) -> Result<Hash32, MmrError> {
+    ) -> Result<Hash<N>, MmrError> {
         Ok(self.hasher.hash_count_and_bag(elements_count, bag)?)
     }
 
-    pub async fn get_root_hash(&self) -> Result<Option<Hash32>, MmrError> {
+    /// The root committed to this `mmr_id` (see `bound_root_from_peaks`), computed from the
+    /// peaks of `elements_count` (the current count by default). It is not stored: the
+    /// stored root stays the plain `hash_count_and_bag` one.
+    pub async fn get_bound_root_hash(
+        &self,
+        elements_count: Option<u64>,
+    ) -> Result<Hash<N>, MmrError> {
+        let tree_size = match elements_count {
+            Some(count) => count,
+            None => self.get_elements_count().await?,
+        };
+        let peaks_hashes = self.retrieve_peaks_hashes(tree_size).await?;
+        bound_root_from_peaks(self.hasher.as_ref(), &peaks_hashes, tree_size, self.mmr_id)
+    }
+
+    /// `None` for an empty MMR and, in lazy-root mode, until `finalize_root` is called.
+    pub async fn get_root_hash(&self) -> Result<Option<Hash<N>>, MmrError> {
         match self.store.get(&self.root_hash_key()).await? {
             Some(value) => Ok(Some(value.expect_hash(&self.root_hash_key())?)),
             None => Ok(None),
         }
     }
 
-    async fn retrieve_peaks_hashes(&self, peak_idxs: Vec<u64>) -> Result<Vec<Hash32>, MmrError> {
-        let keys: Vec<StoreKey> = peak_idxs.iter().map(|idx| self.node_key(*idx)).collect();
-        let values = self.store.get_many(&keys).await?;
+    pub async fn finalize_root(&self) -> Result<Hash<N>, MmrError> {
+        let _cached_counts = self.cached_counts.lock().await;
+        let lease_owner = self.ensure_writer_lease().await?;
+        let elements_count = self.get_elements_count().await?;
+        let bag = self.bag_the_peaks(Some(elements_count)).await?;
+        let root_hash = self.calculate_root_hash(&bag, elements_count)?;
 
-        let mut hashes = Vec::with_capacity(values.len());
-        for (key, value) in keys.iter().zip(values.into_iter()) {
+        let committed = self
+            .compare_and_set_leased(
+                lease_owner,
+                elements_count,
+                vec![(self.root_hash_key(), StoreValue::Hash(root_hash))],
+            )
+            .await?;
+        if !committed {
+            return Err(MmrError::ConcurrentAppendConflict(1));
+        }
+
+        self.publish_root_update(elements_count, root_hash);
+        Ok(root_hash)
+    }
+
+    async fn retrieve_peaks_hashes(&self, tree_size: u64) -> Result<Vec<Hash<N>>, MmrError> {
+        let indices = find_peaks(tree_size);
+        let hashes = self.get_node_hashes(&indices).await?;
+        let peaks_hashes = indices
+            .iter()
+            .zip(hashes)
+            .map(|(idx, hash)| hash.ok_or(MmrError::NoHashFoundForIndex(*idx)))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.check_peaks(tree_size, &peaks_hashes).await?;
+        Ok(peaks_hashes)
+    }
+
+    /// Node hashes for `indices`, served from the node cache when enabled and fetched with a
+    /// single `get_many` otherwise.
+    async fn get_node_hashes(&self, indices: &[u64]) -> Result<Vec<Option<Hash<N>>>, MmrError> {
+        let Some(node_cache) = &self.node_cache else {
+            let keys: Vec<StoreKey> = indices.iter().map(|idx| self.node_key(*idx)).collect();
+            let values = self.store.get_many(&keys).await?;
+            return keys
+                .iter()
+                .zip(values)
+                .map(|(key, value)| value.map(|value| value.expect_hash(key)).transpose())
+                .collect::<Result<_, _>>()
+                .map_err(MmrError::from);
+        };
+
+        let mut hashes: Vec<Option<Hash<N>>> = {
+            let mut cache = node_cache
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            indices.iter().map(|idx| cache.get(*idx)).collect()
+        };
+        let missing: Vec<usize> = (0..indices.len())
+            .filter(|position| hashes[*position].is_none())
+            .collect();
+        if missing.is_empty() {
+            return Ok(hashes);
+        }
+
+        let keys: Vec<StoreKey> = missing
+            .iter()
+            .map(|position| self.node_key(indices[*position]))
+            .collect();
+        let values = self.store.get_many(&keys).await?;
+        let mut cache = node_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for ((position, key), value) in missing.into_iter().zip(&keys).zip(values) {
             if let Some(value) = value {
-                hashes.push(value.expect_hash(key)?);
+                let hash = value.expect_hash(key)?;
+                cache.insert(indices[position], hash);
+                hashes[position] = Some(hash);
             }
         }
 
         Ok(hashes)
     }
 
-    async fn prepare_append_state(&mut self) -> Result<AppendState, MmrError> {
-        let cached_counts = self.load_cached_counts().await?;
+    pub(crate) fn clear_node_cache(&self) {
+        if let Some(node_cache) = &self.node_cache {
+            node_cache
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clear();
+        }
+    }
+
+    /// Loads the append state together with the lease owner the commit must be guarded on.
+    /// The lease keys ride along with the counter reads, so checking it costs no extra
+    /// round trip in steady state.
+    async fn prepare_append_state(
+        &self,
+        cached_counts: &mut Option<CachedCounts>,
+    ) -> Result<Option<(AppendState<N>, u64)>, MmrError> {
+        let (cached_counts, stored_lease) = self.load_cached_counts(cached_counts).await?;
         if cached_counts.elements_count == 0 {
-            return Ok(AppendState {
+            let stored_lease = match stored_lease {
+                Some(stored_lease) => stored_lease,
+                None => self.read_writer_lease().await?,
+            };
+            let lease_owner = self.check_writer_lease(stored_lease)?;
+            let append_state = AppendState {
                 leaves_count: cached_counts.leaves_count,
                 elements_count: cached_counts.elements_count,
                 peaks_hashes: Vec::new(),
-            });
+            };
+            return Ok(Some((append_state, lease_owner)));
         }
 
-        let peak_indices = find_peaks(cached_counts.elements_count);
-        let append_state = self.load_append_state(&peak_indices).await?;
+        let (append_state, stored_lease) =
+            self.load_append_state(cached_counts.elements_count).await?;
+        let lease_owner = self.check_writer_lease(stored_lease)?;
 
         if append_state.leaves_count != cached_counts.leaves_count
             || append_state.elements_count != cached_counts.elements_count
         {
-            return Err(MmrError::Store(crate::error::StoreError::Internal(
-                "mmr metadata changed unexpectedly; multiple writers for same mmr_id are not supported"
-                    .to_string(),
-            )));
+            return Ok(None);
         }
 
-        Ok(append_state)
+        Ok(Some((append_state, lease_owner)))
     }
 
-    async fn load_cached_counts(&mut self) -> Result<CachedCounts, MmrError> {
-        if let Some(cached_counts) = self.cached_counts {
-            return Ok(cached_counts);
+    /// Returns the counters, plus the stored writer lease when they had to be read.
+    async fn load_cached_counts(
+        &self,
+        cached_counts: &mut Option<CachedCounts>,
+    ) -> Result<(CachedCounts, Option<(u64, u64)>), MmrError> {
+        match *cached_counts {
+            Some(cached_counts) if !self.always_reload => return Ok((cached_counts, None)),
+            _ => {}
         }
 
         let leaf_count_key = self.leaf_count_key();
         let elements_count_key = self.elements_count_key();
-        let keys = vec![leaf_count_key.clone(), elements_count_key.clone()];
+        let mut keys = vec![leaf_count_key.clone(), elements_count_key.clone()];
+        keys.extend(self.lease_keys());
         let values = self.store.get_many(&keys).await?;
 
         let leaves_count =
             Self::extract_counter(&leaf_count_key, values.first().cloned().flatten())?;
         let elements_count =
             Self::extract_counter(&elements_count_key, values.get(1).cloned().flatten())?;
+        let stored_lease = Self::decode_writer_lease(&keys[2..], &values[2..])?;
 
-        let cached_counts = CachedCounts {
+        let loaded_counts = CachedCounts {
             leaves_count,
             elements_count,
         };
-        self.cached_counts = Some(cached_counts);
-        Ok(cached_counts)
+        *cached_counts = Some(loaded_counts);
+        Ok((loaded_counts, Some(stored_lease)))
     }
 
-    async fn load_append_state(&self, peak_indices: &[u64]) -> Result<AppendState, MmrError> {
+    /// Loads the counters and the stored writer lease together with the peaks of an MMR of
+    /// `expected_elements_count` elements. Peaks are only returned when the loaded count
+    /// matches; otherwise the caller's view is stale and they may legitimately be absent.
+    async fn load_append_state(
+        &self,
+        expected_elements_count: u64,
+    ) -> Result<(AppendState<N>, (u64, u64)), MmrError> {
+        let peak_indices = iter_peaks(expected_elements_count);
         let leaf_count_key = self.leaf_count_key();
         let elements_count_key = self.elements_count_key();
-        let mut keys = Vec::with_capacity(2 + peak_indices.len());
+        let mut keys = Vec::with_capacity(4 + peak_indices.size_hint().1.unwrap_or_default());
         keys.push(leaf_count_key.clone());
         keys.push(elements_count_key.clone());
-        keys.extend(peak_indices.iter().map(|idx| self.node_key(*idx)));
+        keys.extend(self.lease_keys());
+        keys.extend(peak_indices.map(|idx| self.node_key(idx)));
 
         let values = self.store.get_many(&keys).await?;
         let leaves_count =
             Self::extract_counter(&leaf_count_key, values.first().cloned().flatten())?;
         let elements_count =
             Self::extract_counter(&elements_count_key, values.get(1).cloned().flatten())?;
+        let stored_lease = Self::decode_writer_lease(&keys[2..4], &values[2..4])?;
 
-        let mut peaks_hashes = Vec::with_capacity(peak_indices.len());
-        for (key, value) in keys[2..].iter().zip(values.into_iter().skip(2)) {
-            if let Some(value) = value {
-                peaks_hashes.push(value.expect_hash(key)?);
-            }
-        }
+        let peaks_hashes = if elements_count == expected_elements_count {
+            Self::required_hashes(&keys[4..], &values[4..])?
+        } else {
+            Vec::new()
+        };
 
-        Ok(AppendState {
+        let append_state = AppendState {
             leaves_count,
             elements_count,
             peaks_hashes,
-        })
+        };
+        Ok((append_state, stored_lease))
     }
 
     fn build_append_writes(
         &self,
-        values: &[Hash32],
-        append_state: AppendState,
-    ) -> Result<AppendComputation, MmrError> {
+        values: &[Hash<N>],
+        append_state: AppendState<N>,
+    ) -> Result<AppendComputation<N>, MmrError> {
+        self.size_limits
+            .check_append(append_state.leaves_count, values.len())?;
+
         let mut leaves_count = append_state.leaves_count;
         let mut elements_count = append_state.elements_count;
         let mut peaks = append_state.peaks_hashes;
@@ -422,6 +1654,15 @@ impl<S: Store> Mmr<S> {
                 .ok_or(MmrError::Overflow)?,
         );
 
+        let previous_elements_count = elements_count;
+        let previous_root_hash = if previous_elements_count == 0 || self.lazy_root {
+            None
+        } else {
+            let previous_bag =
+                self.bag_peaks_hashes(iter_peaks(previous_elements_count), &peaks)?;
+            Some(self.calculate_root_hash(&previous_bag, previous_elements_count)?)
+        };
+
         let first_element_index = elements_count.checked_add(1).ok_or(MmrError::Overflow)?;
         let mut last_element_index = first_element_index;
 
@@ -453,18 +1694,27 @@ impl<S: Store> Mmr<S> {
             leaves_count = leaves_count.checked_add(1).ok_or(MmrError::Overflow)?;
         }
 
-        let peak_indices = find_peaks(elements_count);
-        let bag = self.bag_peaks_hashes(&peak_indices, &peaks)?;
-        let root_hash = self.calculate_root_hash(&bag, elements_count)?;
+        // Lazy-root mode deletes the root key, so `get_root_hash` reports the root as unset
+        // until `finalize_root` writes it again.
+        let mut staged_deletes = Vec::new();
+        let root_hash = if self.lazy_root {
+            staged_deletes.push(self.root_hash_key());
+            Hash::ZERO
+        } else {
+            let bag = self.bag_peaks_hashes(iter_peaks(elements_count), &peaks)?;
+            let root_hash = self.calculate_root_hash(&bag, elements_count)?;
+            staged_writes.push((self.root_hash_key(), StoreValue::Hash(root_hash)));
+            root_hash
+        };
 
         staged_writes.push((self.elements_count_key(), StoreValue::U64(elements_count)));
-        staged_writes.push((self.root_hash_key(), StoreValue::Hash(root_hash)));
         staged_writes.push((self.leaf_count_key(), StoreValue::U64(leaves_count)));
 
         let appended_count = u64::try_from(values.len()).map_err(|_| MmrError::Overflow)?;
 
         Ok(AppendComputation {
             staged_writes,
+            staged_deletes,
             result: BatchAppendResult {
                 appended_count,
                 first_element_index,
@@ -472,12 +1722,33 @@ impl<S: Store> Mmr<S> {
                 leaves_count,
                 elements_count,
                 root_hash,
+                root_deferred: self.lazy_root,
                 peaks_hashes: peaks,
+                previous_elements_count,
+                previous_root_hash,
             },
         })
     }
 
-    fn extract_counter(key: &StoreKey, value: Option<StoreValue>) -> Result<u64, MmrError> {
+    async fn ensure_external_keys_unmapped(&self, external_keys: &[u64]) -> Result<(), MmrError> {
+        let keys: Vec<StoreKey> = external_keys
+            .iter()
+            .map(|external_key| self.external_key_key(*external_key))
+            .collect();
+        let values = self.store.get_many(&keys).await?;
+        for (external_key, value) in external_keys.iter().zip(values) {
+            if value.is_some() {
+                return Err(MmrError::DuplicateExternalKey(*external_key));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn extract_counter(
+        key: &StoreKey,
+        value: Option<StoreValue<N>>,
+    ) -> Result<u64, MmrError> {
         match value {
             Some(value) => Ok(value.expect_u64(key)?),
             None => Ok(0),
@@ -491,11 +1762,90 @@ impl<S: Store> Mmr<S> {
         }
     }
 
+    pub async fn get_leaf_hash(&self, leaf_index: u64) -> Result<Hash<N>, MmrError> {
+        if leaf_index >= self.get_leaves_count().await? {
+            return Err(MmrError::InvalidLeafIndex);
+        }
+
+        let element_index = map_leaf_index_to_element_index(leaf_index);
+        self.get_node_hash(element_index)
+            .await?
+            .ok_or(MmrError::NoHashFoundForIndex(element_index))
+    }
+
+    pub fn iter_leaves(
+        &self,
+    ) -> impl Stream<Item = Result<(u64, ElementIndex, Hash<N>), MmrError>> + '_ {
+        stream::try_unfold(
+            LeafCursor {
+                next_leaf_index: 0,
+                leaves_count: None,
+            },
+            move |cursor| self.next_leaves_chunk(cursor),
+        )
+        .map_ok(|leaves| stream::iter(leaves.into_iter().map(Ok::<_, MmrError>)))
+        .try_flatten()
+    }
+
+    async fn next_leaves_chunk(
+        &self,
+        cursor: LeafCursor,
+    ) -> Result<Option<(Vec<(u64, ElementIndex, Hash<N>)>, LeafCursor)>, MmrError> {
+        let leaves_count = match cursor.leaves_count {
+            Some(count) => count,
+            None => self.get_leaves_count().await?,
+        };
+        if cursor.next_leaf_index >= leaves_count {
+            return Ok(None);
+        }
+
+        let chunk_end =
+            leaves_count.min(cursor.next_leaf_index.saturating_add(LEAF_ITER_CHUNK_SIZE));
+        let leaves = self
+            .load_leaves_chunk(cursor.next_leaf_index, chunk_end)
+            .await?;
+
+        Ok(Some((
+            leaves,
+            LeafCursor {
+                next_leaf_index: chunk_end,
+                leaves_count: Some(leaves_count),
+            },
+        )))
+    }
+
+    async fn load_leaves_chunk(
+        &self,
+        from_leaf_index: u64,
+        to_leaf_index: u64,
+    ) -> Result<Vec<(u64, ElementIndex, Hash<N>)>, MmrError> {
+        let element_indices: Vec<ElementIndex> = (from_leaf_index..to_leaf_index)
+            .map(map_leaf_index_to_element_index)
+            .collect();
+        let keys: Vec<StoreKey> = element_indices
+            .iter()
+            .map(|idx| self.node_key(*idx))
+            .collect();
+        let values = self.store.get_many(&keys).await?;
+
+        let mut leaves = Vec::with_capacity(keys.len());
+        for ((leaf_index, element_index), (key, value)) in (from_leaf_index..to_leaf_index)
+            .zip(element_indices)
+            .zip(keys.iter().zip(values))
+        {
+            let hash = value
+                .ok_or(MmrError::NoHashFoundForIndex(element_index))?
+                .expect_hash(key)?;
+            leaves.push((leaf_index, element_index, hash));
+        }
+
+        Ok(leaves)
+    }
+
     async fn set_leaves_count(&self, value: u64) -> Result<(), MmrError> {
         self.store
             .set(self.leaf_count_key(), StoreValue::U64(value))
             .await
-            .map_err(MmrError::from)
     }
 
     pub async fn get_elements_count(&self) -> Result<u64, MmrError> {
@@ -509,17 +1859,15 @@ impl<S: Store> Mmr<S> {
         self.store
             .set(self.elements_count_key(), StoreValue::U64(value))
             .await
-            .map_err(MmrError::from)
     }
 
-    async fn set_root_hash(&self, hash: Hash32) -> Result<(), MmrError> {
+    async fn set_root_hash(&self, hash: Hash<N>) -> Result<(), MmrError> {
         self.store
             .set(self.root_hash_key(), StoreValue::Hash(hash))
             .await
-            .map_err(MmrError::from)
     }
 
-    async fn get_node_hash(&self, index: u64) -> Result<Option<Hash32>, MmrError> {
+    pub(crate) async fn get_node_hash(&self, index: u64) -> Result<Option<Hash<N>>, MmrError> {
         let key = self.node_key(index);
         match self.store.get(&key).await? {
             Some(value) => Ok(Some(value.expect_hash(&key)?)),
@@ -527,66 +1875,151 @@ impl<S: Store> Mmr<S> {
         }
     }
 
-    async fn set_node_hash(&self, index: u64, hash: Hash32) -> Result<(), MmrError> {
+    async fn set_node_hash(&self, index: u64, hash: Hash<N>) -> Result<(), MmrError> {
         self.store
             .set(self.node_key(index), StoreValue::Hash(hash))
             .await
-            .map_err(MmrError::from)
     }
 
-    fn leaf_count_key(&self) -> StoreKey {
+    pub(crate) fn current_writer_lease(&self) -> Result<Option<WriterLease>, MmrError> {
+        let guard = self
+            .writer_lease
+            .lock()
+            .map_err(|_| StoreError::Internal("writer lease mutex poisoned".to_string()))?;
+        Ok(*guard)
+    }
+
+    pub(crate) fn set_writer_lease(&self, lease: Option<WriterLease>) -> Result<(), MmrError> {
+        let mut guard = self
+            .writer_lease
+            .lock()
+            .map_err(|_| StoreError::Internal("writer lease mutex poisoned".to_string()))?;
+        *guard = lease;
+        Ok(())
+    }
+
+    pub(crate) fn leaf_count_key(&self) -> StoreKey {
         StoreKey::metadata(self.mmr_id, KeyKind::LeafCount)
     }
 
-    fn elements_count_key(&self) -> StoreKey {
+    pub(crate) fn elements_count_key(&self) -> StoreKey {
         StoreKey::metadata(self.mmr_id, KeyKind::ElementsCount)
     }
 
-    fn root_hash_key(&self) -> StoreKey {
+    pub(crate) fn root_hash_key(&self) -> StoreKey {
         StoreKey::metadata(self.mmr_id, KeyKind::RootHash)
     }
 
-    fn node_key(&self, index: u64) -> StoreKey {
+    pub(crate) fn external_key_key(&self, external_key: u64) -> StoreKey {
+        StoreKey::new(self.mmr_id, KeyKind::ExternalKey, external_key)
+    }
+
+    /// Reverse of `external_key_key`: the external key of the leaf at `element_index`.
+    pub(crate) fn leaf_key_key(&self, element_index: u64) -> StoreKey {
+        StoreKey::new(self.mmr_id, KeyKind::LeafKey, element_index)
+    }
+
+    /// Maps `external_key` to `element_index` both ways, so range reads can find the keys
+    /// of new leaves without scanning.
+    pub(crate) fn external_key_writes(
+        &self,
+        external_key: u64,
+        element_index: ElementIndex,
+    ) -> [(StoreKey, StoreValue<N>); 2] {
+        [
+            (
+                self.external_key_key(external_key),
+                StoreValue::U64(element_index),
+            ),
+            (
+                self.leaf_key_key(element_index),
+                StoreValue::U64(external_key),
+            ),
+        ]
+    }
+
+    pub(crate) fn leaf_data_key(&self, element_index: u64) -> StoreKey {
+        StoreKey::new(self.mmr_id, KeyKind::LeafData, element_index)
+    }
+
+    pub(crate) fn node_key(&self, index: u64) -> StoreKey {
         StoreKey::new(self.mmr_id, KeyKind::NodeHash, index)
     }
 }
 
 #[cfg(feature = "postgres-store")]
 impl Mmr<Arc<PostgresStore>> {
+    /// `append` inside the caller's transaction; publish it as described on
+    /// `batch_append_in_tx`, converting the result with `BatchAppendResult::from`.
     pub async fn append_in_tx(
-        &mut self,
+        &self,
         tx: &mut Transaction<'_, Postgres>,
         value: Hash32,
     ) -> Result<AppendResult, MmrError> {
         let batch_result = self.batch_append_in_tx(tx, &[value]).await?;
-        Ok(AppendResult {
-            leaves_count: batch_result.leaves_count,
-            elements_count: batch_result.elements_count,
-            element_index: batch_result.first_element_index,
-            root_hash: batch_result.root_hash,
-        })
+        Ok(AppendResult::from(batch_result))
     }
 
+    /// `batch_append` inside the caller's transaction. `on_before_append` hooks run here,
+    /// but `on_after_append` hooks, subscribers and op stats only learn of the append once
+    /// `tx` has committed: pass the result to `publish_tx_append` then.
     pub async fn batch_append_in_tx(
-        &mut self,
+        &self,
         tx: &mut Transaction<'_, Postgres>,
         values: &[Hash32],
     ) -> Result<BatchAppendResult, MmrError> {
-        if values.is_empty() {
-            return Err(MmrError::EmptyBatchAppend);
-        }
+        self.check_append(values)?;
+
+        let mut cached_counts = self.cached_counts.lock().await;
+        *cached_counts = None;
+        for _ in 0..MAX_APPEND_ATTEMPTS {
+            let lease_owner = self.ensure_writer_lease().await?;
+            let append_state = self.prepare_append_state_in_tx(tx).await?;
+            let expected_elements_count = append_state.elements_count;
+            let AppendComputation {
+                mut staged_writes,
+                staged_deletes,
+                result,
+            } = self.build_append_writes(values, append_state)?;
+            if self.hash_index {
+                // Read through `tx` so slots claimed by earlier appends in it are seen.
+                let mut reader = TxReader {
+                    store: &self.store,
+                    tx: &mut *tx,
+                };
+                staged_writes.extend(
+                    self.stage_hash_index_writes_with(
+                        appended_leaves(values, &result),
+                        &mut reader,
+                    )
+                    .await?,
+                );
+            }
 
-        self.cached_counts = None;
-        let append_state = self.prepare_append_state_in_tx(tx).await?;
-        let AppendComputation {
-            staged_writes,
-            result,
-        } = self.build_append_writes(values, append_state)?;
+            // Holds the mmr's lock until `tx` ends; a failed guard means another writer
+            // committed since the read above, and the retry reads its counts, or that the
+            // lease was taken over, which the retry's `ensure_writer_lease` reports.
+            let mut batch = WriteBatch::new()
+                .guard(self.elements_count_key(), expected_elements_count)
+                .set_many(staged_writes)
+                .delete_many(staged_deletes);
+            if let Some((owner_key, owner)) = self.lease_guard(lease_owner) {
+                batch = batch.guard(owner_key, owner);
+            }
+            let committed = self.store.write_batch_in_tx(tx, batch).await?;
+            if committed {
+                return Ok(result);
+            }
+        }
 
-        self.store.set_many_in_tx(tx, staged_writes).await?;
-        self.cached_counts = None;
+        Err(MmrError::ConcurrentAppendConflict(MAX_APPEND_ATTEMPTS))
+    }
 
-        Ok(result)
+    /// Notifies `on_after_append` hooks and subscribers of an append made with
+    /// `batch_append_in_tx` whose transaction has committed. Call it once per append, in
+    /// append order; a rolled back append must not be published.
+    pub async fn publish_tx_append(&self, result: &BatchAppendResult) {
+        self.publish_committed_append(result).await;
     }
 
     async fn prepare_append_state_in_tx(
@@ -611,21 +2044,21 @@ impl Mmr<Arc<PostgresStore>> {
             });
         }
 
-        let peak_indices = find_peaks(elements_count);
-        self.load_append_state_in_tx(tx, &peak_indices).await
+        self.load_append_state_in_tx(tx, iter_peaks(elements_count))
+            .await
     }
 
     async fn load_append_state_in_tx(
         &self,
         tx: &mut Transaction<'_, Postgres>,
-        peak_indices: &[u64],
+        peak_indices: PeaksIter,
     ) -> Result<AppendState, MmrError> {
         let leaf_count_key = self.leaf_count_key();
         let elements_count_key = self.elements_count_key();
-        let mut keys = Vec::with_capacity(2 + peak_indices.len());
+        let mut keys = Vec::with_capacity(2 + peak_indices.size_hint().1.unwrap_or_default());
         keys.push(leaf_count_key.clone());
         keys.push(elements_count_key.clone());
-        keys.extend(peak_indices.iter().map(|idx| self.node_key(*idx)));
+        keys.extend(peak_indices.map(|idx| self.node_key(idx)));
 
         let values = self.store.get_many_in_tx(tx, &keys).await?;
         let leaves_count =
@@ -633,12 +2066,7 @@ impl Mmr<Arc<PostgresStore>> {
         let elements_count =
             Self::extract_counter(&elements_count_key, values.get(1).cloned().flatten())?;
 
-        let mut peaks_hashes = Vec::with_capacity(peak_indices.len());
-        for (key, value) in keys[2..].iter().zip(values.into_iter().skip(2)) {
-            if let Some(value) = value {
-                peaks_hashes.push(value.expect_hash(key)?);
-            }
-        }
+        let peaks_hashes = Self::required_hashes(&keys[2..], &values[2..])?;
 
         Ok(AppendState {
             leaves_count,
@@ -648,13 +2076,82 @@ impl Mmr<Arc<PostgresStore>> {
     }
 }
 
-struct AppendComputation {
-    staged_writes: Vec<(StoreKey, StoreValue)>,
-    result: BatchAppendResult,
+#[cfg(feature = "postgres-store")]
+struct TxReader<'a, 'tx> {
+    store: &'a CountedStore<Arc<PostgresStore>>,
+    tx: &'a mut Transaction<'tx, Postgres>,
+}
+
+#[cfg(feature = "postgres-store")]
+impl KeyReader for TxReader<'_, '_> {
+    async fn get_many(&mut self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue>>, MmrError> {
+        self.store.get_many_in_tx(self.tx, keys).await
+    }
+}
+
+/// `(hash, element_index)` of each leaf `result` appended, in append order.
+fn appended_leaves<const N: usize>(
+    values: &[Hash<N>],
+    result: &BatchAppendResult<N>,
+) -> Vec<(Hash<N>, ElementIndex)> {
+    let first_leaf_index = result.leaves_count - result.appended_count;
+    (first_leaf_index..)
+        .zip(values)
+        .map(|(leaf_index, value)| (*value, map_leaf_index_to_element_index(leaf_index)))
+        .collect()
+}
+
+struct LeafCursor {
+    next_leaf_index: u64,
+    leaves_count: Option<u64>,
+}
+
+struct AppendComputation<const N: usize = 32> {
+    staged_writes: Vec<(StoreKey, StoreValue<N>)>,
+    staged_deletes: Vec<StoreKey>,
+    result: BatchAppendResult<N>,
+}
+
+impl<const N: usize> StagedAppend<'_, N> {
+    /// Releases a staged append whose writes may or may not have been stored; the next
+    /// append reloads the counts.
+    pub(crate) fn discard(mut self) {
+        *self.cached_counts = None;
+    }
+
+    pub(crate) fn expected_elements_count(&self) -> u64 {
+        self.result.previous_elements_count
+    }
+}
+
+pub(crate) struct StagedAppend<'a, const N: usize = 32> {
+    cached_counts: MutexGuard<'a, Option<CachedCounts>>,
+    pub(crate) staged_writes: Vec<(StoreKey, StoreValue<N>)>,
+    /// Keys the append removes: the root key in lazy-root mode.
+    pub(crate) staged_deletes: Vec<StoreKey>,
+    pub(crate) result: BatchAppendResult<N>,
+    /// Peaks of the MMR the append was staged on.
+    pub(crate) previous_peaks_hashes: Vec<Hash<N>>,
+    pub(crate) lease_owner: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct AppendExtras<'a> {
+    pub(crate) keep_nodes: bool,
+    pub(crate) leaf_data: Option<&'a [Vec<u8>]>,
+    /// Leaf data with the element index it belongs to, for leaves replayed from elsewhere
+    /// where only some carry data.
+    pub(crate) leaf_data_entries: Option<&'a [(ElementIndex, Vec<u8>)]>,
+    pub(crate) external_keys: Option<&'a [u64]>,
+    /// External keys with the element index each maps to, for leaves that were keyed
+    /// elsewhere and are replayed here.
+    pub(crate) external_key_entries: Option<&'a [(u64, ElementIndex)]>,
+    pub(crate) checkpoint_cursor: Option<u64>,
+    pub(crate) expected_elements_count: Option<u64>,
 }
 
-struct AppendState {
+struct AppendState<const N: usize = 32> {
     leaves_count: u64,
     elements_count: u64,
-    peaks_hashes: Vec<Hash32>,
+    peaks_hashes: Vec<Hash<N>>,
 }
@@ -0,0 +1,138 @@
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+use crate::error::MmrError;
+use crate::store::{OverlayStore, Store};
+use crate::types::{BatchAppendResult, Hash32, MmrId};
+
+use super::core::Mmr;
+use super::hooks::AppendHook;
+
+pub struct DraftMmr<'a, S: Store> {
+    base: &'a Mmr<S>,
+    draft: Mmr<OverlayStore<'a, S>>,
+    base_elements_count: u64,
+    appended: Arc<DraftAppends>,
+}
+
+/// Runs only the `on_before_append` side of a base hook, so hooks can reject draft appends
+/// but observe only what is committed.
+struct BeforeAppendOnly(Arc<dyn AppendHook>);
+
+impl AppendHook for BeforeAppendOnly {
+    fn on_before_append(&self, mmr_id: MmrId, values: &[Hash32]) -> Result<(), MmrError> {
+        self.0.on_before_append(mmr_id, values)
+    }
+}
+
+/// Folds the draft's appends into one result, published on the base at commit.
+#[derive(Default)]
+struct DraftAppends(Mutex<Option<BatchAppendResult>>);
+
+impl AppendHook for DraftAppends {
+    fn on_after_append(&self, _mmr_id: MmrId, result: &BatchAppendResult) {
+        let mut combined = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match combined.as_mut() {
+            Some(combined) => {
+                combined.appended_count += result.appended_count;
+                combined.last_element_index = result.last_element_index;
+                combined.leaves_count = result.leaves_count;
+                combined.elements_count = result.elements_count;
+                combined.root_hash = result.root_hash;
+                combined.root_deferred = result.root_deferred;
+                combined.peaks_hashes.clone_from(&result.peaks_hashes);
+            }
+            None => *combined = Some(result.clone()),
+        }
+    }
+}
+
+impl<'a, S: Store> Deref for DraftMmr<'a, S> {
+    type Target = Mmr<OverlayStore<'a, S>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.draft
+    }
+}
+
+impl<'a, S: Store> DraftMmr<'a, S> {
+    pub fn staged_len(&self) -> Result<usize, MmrError> {
+        Ok(self.draft.store.staged_len()?)
+    }
+
+    pub async fn commit(self) -> Result<usize, MmrError> {
+        let Self {
+            base,
+            draft,
+            base_elements_count,
+            appended,
+        } = self;
+        let staged = draft.store.into_inner().into_staged()?;
+        if staged.is_empty() {
+            return Ok(0);
+        }
+
+        let lease_owner = base.ensure_writer_lease().await?;
+        let staged_len = staged.len();
+        let committed = base
+            .compare_and_write_leased(
+                lease_owner,
+                base_elements_count,
+                staged.sets,
+                staged.deletes,
+            )
+            .await?;
+        base.clear_node_cache();
+        if !committed {
+            base.invalidate_cached_counts().await;
+            return Err(MmrError::DraftConflict(base_elements_count));
+        }
+
+        let appended = appended
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        match appended {
+            Some(result) => base.publish_committed_append(&result).await,
+            None => base.invalidate_cached_counts().await,
+        }
+        Ok(staged_len)
+    }
+
+    pub fn discard(self) {}
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn draft(&self) -> Result<DraftMmr<'_, S>, MmrError> {
+        let base_elements_count = self.get_elements_count().await?;
+        let mut draft = Mmr::new(
+            OverlayStore::new(self.store.inner()),
+            self.hasher.shared(),
+            Some(self.mmr_id),
+        )?
+        .with_hash_index(self.hash_index)
+        .with_lazy_root(self.lazy_root)
+        .with_paranoid_reads(self.paranoid_reads)
+        .with_proof_binding(self.bind_proofs)
+        .with_node_cache(self.node_cache_capacity());
+        draft.size_limits = self.size_limits;
+        for hook in &self.hooks {
+            draft = draft.with_hook(Arc::new(BeforeAppendOnly(hook.clone())));
+        }
+        let appended = Arc::new(DraftAppends::default());
+        draft = draft.with_hook(appended.clone());
+        // The overlay reads the base lease keys, so the draft writes under the base's lease.
+        draft.set_writer_lease(self.current_writer_lease()?)?;
+
+        Ok(DraftMmr {
+            base: self,
+            draft,
+            base_elements_count,
+            appended,
+        })
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use tiny_keccak::{Hasher as TinyHasher, Keccak};
+
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::store::{Store, StoreKey, StoreValue};
+use crate::types::{Hash32, MmrId, ZERO_HASH};
+
+use super::core::Mmr;
+use super::helpers::{element_index_to_height, find_peaks, mmr_size_to_leaf_count};
+use super::verify::bag_peaks;
+
+const DUMP_MAGIC: &[u8; 8] = b"MMRDUMP\0";
+const DUMP_VERSION: u8 = 1;
+const DUMP_CHUNK_SIZE: u64 = 4096;
+
+struct ChecksumWriter<'a, W: Write> {
+    inner: &'a mut W,
+    keccak: Keccak,
+}
+
+impl<W: Write> ChecksumWriter<'_, W> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), MmrError> {
+        self.keccak.update(bytes);
+        self.inner.write_all(bytes)?;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), MmrError> {
+        let mut checksum = [0u8; 32];
+        self.keccak.finalize(&mut checksum);
+        self.inner.write_all(&checksum)?;
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+struct ChecksumReader<'a, R: Read> {
+    inner: &'a mut R,
+    keccak: Keccak,
+}
+
+impl<R: Read> ChecksumReader<'_, R> {
+    fn read<const N: usize>(&mut self) -> Result<[u8; N], MmrError> {
+        let mut bytes = [0u8; N];
+        self.inner.read_exact(&mut bytes)?;
+        self.keccak.update(&bytes);
+        Ok(bytes)
+    }
+
+    fn read_u64(&mut self) -> Result<u64, MmrError> {
+        Ok(u64::from_be_bytes(self.read()?))
+    }
+
+    /// `len` comes from the input, so only what is actually read is allocated.
+    fn read_bytes(&mut self, len: u64) -> Result<Vec<u8>, MmrError> {
+        let mut bytes = Vec::new();
+        (&mut *self.inner).take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        self.keccak.update(&bytes);
+        Ok(bytes)
+    }
+
+    fn finish(self) -> Result<(), MmrError> {
+        let mut expected = [0u8; 32];
+        self.keccak.finalize(&mut expected);
+        let mut actual = [0u8; 32];
+        self.inner.read_exact(&mut actual)?;
+        if expected != actual {
+            return Err(MmrError::DumpChecksumMismatch);
+        }
+        Ok(())
+    }
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn export_to_writer<W: Write>(&self, writer: &mut W) -> Result<(), MmrError> {
+        let metadata_keys = [
+            self.leaf_count_key(),
+            self.elements_count_key(),
+            self.root_hash_key(),
+        ];
+        let metadata = self.store.get_many(&metadata_keys).await?;
+        let leaves_count = Self::extract_counter(&metadata_keys[0], metadata[0].clone())?;
+        let elements_count = Self::extract_counter(&metadata_keys[1], metadata[1].clone())?;
+        let root_hash = match metadata[2].clone() {
+            Some(value) => Some(value.expect_hash(&metadata_keys[2])?),
+            None => None,
+        }
+        .filter(|root_hash| *root_hash != ZERO_HASH);
+
+        let mut out = ChecksumWriter {
+            inner: writer,
+            keccak: Keccak::v256(),
+        };
+        out.write(DUMP_MAGIC)?;
+        out.write(&[DUMP_VERSION])?;
+        out.write(&self.mmr_id.to_be_bytes())?;
+        out.write(&leaves_count.to_be_bytes())?;
+        out.write(&elements_count.to_be_bytes())?;
+        match root_hash {
+            Some(root_hash) => {
+                out.write(&[1])?;
+                out.write(root_hash.as_slice())?;
+            }
+            None => out.write(&[0])?,
+        }
+
+        // Every node up to the header's count is written, one chunk at a time as it is read.
+        out.write(&elements_count.to_be_bytes())?;
+        let mut chunk_start = 1u64;
+        while chunk_start <= elements_count {
+            let chunk_end = elements_count.min(chunk_start.saturating_add(DUMP_CHUNK_SIZE - 1));
+            let keys: Vec<StoreKey> = (chunk_start..=chunk_end)
+                .map(|idx| self.node_key(idx))
+                .collect();
+            let values = self.store.get_many(&keys).await?;
+            for (key, value) in keys.iter().zip(values) {
+                let hash = value
+                    .ok_or(MmrError::NoHashFoundForIndex(key.index))?
+                    .expect_hash(key)?;
+                out.write(&key.index.to_be_bytes())?;
+                out.write(hash.as_slice())?;
+            }
+            chunk_start = chunk_end + 1;
+        }
+
+        let external_keys = self.external_keys_in_range(1, elements_count).await?;
+        out.write(&(external_keys.len() as u64).to_be_bytes())?;
+        for (external_key, element_index) in &external_keys {
+            out.write(&external_key.to_be_bytes())?;
+            out.write(&element_index.to_be_bytes())?;
+        }
+        // Leaf data is written as it is read, each entry led by its non-zero element index
+        // and the section closed by a zero one.
+        let mut chunk_start = 1u64;
+        while chunk_start <= elements_count {
+            let chunk_end = elements_count.min(chunk_start.saturating_add(DUMP_CHUNK_SIZE - 1));
+            for (element_index, data) in self.leaf_data_in_range(chunk_start, chunk_end).await? {
+                out.write(&element_index.to_be_bytes())?;
+                out.write(&(data.len() as u64).to_be_bytes())?;
+                out.write(&data)?;
+            }
+            chunk_start = chunk_end + 1;
+        }
+        out.write(&0u64.to_be_bytes())?;
+
+        // Nodes never change once written, so only a count that moved, e.g. through a
+        // destroy or promote, can have mixed two states into the sections above.
+        let current_elements_count = self.get_elements_count().await?;
+        if current_elements_count != elements_count {
+            return Err(MmrError::ExportConflict {
+                expected: elements_count,
+                actual: current_elements_count,
+            });
+        }
+        out.finish()
+    }
+
+    pub async fn import_from_reader<R: Read>(
+        store: S,
+        hasher: Arc<dyn Hasher>,
+        mmr_id: Option<MmrId>,
+        reader: &mut R,
+    ) -> Result<Self, MmrError> {
+        let mmr_id = Self::resolve_mmr_id(&store, mmr_id).await?;
+        let mmr = Self::new(store, hasher, Some(mmr_id))?;
+        if mmr.get_elements_count().await? != 0 {
+            return Err(MmrError::NonEmptyMmr);
+        }
+
+        let mut writer = ImportWriter {
+            mmr: &mmr,
+            pending: Vec::new(),
+            written: false,
+        };
+        if let Err(err) = mmr.import_dump(reader, &mut writer).await {
+            // The counts are written last, so the MMR still reads as empty and whatever
+            // was stored before the failure can be dropped.
+            if writer.written {
+                let _ = mmr.destroy().await;
+            }
+            return Err(err);
+        }
+        mmr.clear_node_cache();
+
+        Ok(mmr)
+    }
+
+    async fn import_dump<R: Read>(
+        &self,
+        reader: &mut R,
+        writer: &mut ImportWriter<'_, S>,
+    ) -> Result<(), MmrError> {
+        let mut input = ChecksumReader {
+            inner: reader,
+            keccak: Keccak::v256(),
+        };
+        if &input.read::<8>()? != DUMP_MAGIC {
+            return Err(MmrError::InvalidDump("bad magic"));
+        }
+        let [version] = input.read::<1>()?;
+        if version != DUMP_VERSION {
+            return Err(MmrError::UnsupportedDumpVersion(version));
+        }
+        let _source_mmr_id = MmrId::from_be_bytes(input.read()?);
+        let leaves_count = input.read_u64()?;
+        let elements_count = input.read_u64()?;
+        if mmr_size_to_leaf_count(elements_count) != leaves_count {
+            return Err(MmrError::InvalidDump(
+                "leaves count does not match elements count",
+            ));
+        }
+        let root_hash = match input.read::<1>()? {
+            [0] => None,
+            [1] => Some(Hash32::new(input.read()?)),
+            _ => return Err(MmrError::InvalidDump("bad root flag")),
+        };
+
+        let peak_indices = find_peaks(elements_count);
+        let mut peaks: HashMap<u64, Hash32> = HashMap::new();
+        let nodes_count = input.read_u64()?;
+        if nodes_count > elements_count {
+            return Err(MmrError::InvalidDump("too many nodes"));
+        }
+        let mut previous_index = 0u64;
+        for _ in 0..nodes_count {
+            let idx = input.read_u64()?;
+            if idx <= previous_index || idx > elements_count {
+                return Err(MmrError::InvalidDump("node index out of order or range"));
+            }
+            previous_index = idx;
+            let hash = Hash32::new(input.read()?);
+            if peak_indices.contains(&idx) {
+                peaks.insert(idx, hash);
+            }
+            writer
+                .push([(self.node_key(idx), StoreValue::Hash(hash))])
+                .await?;
+        }
+        if let Some(root_hash) = root_hash {
+            let peaks_hashes = peak_indices
+                .iter()
+                .map(|idx| peaks.get(idx).copied())
+                .collect::<Option<Vec<_>>>()
+                .ok_or(MmrError::InvalidDump("missing peak node"))?;
+            let bag = bag_peaks(self.hasher.as_ref(), &peaks_hashes)?;
+            if self.calculate_root_hash(&bag, elements_count)? != root_hash {
+                return Err(MmrError::InvalidDump("root hash does not match peaks"));
+            }
+        }
+
+        let external_keys_count = input.read_u64()?;
+        if external_keys_count > leaves_count {
+            return Err(MmrError::InvalidDump("too many external keys"));
+        }
+        for _ in 0..external_keys_count {
+            let external_key = input.read_u64()?;
+            let element_index = input.read_u64()?;
+            if element_index == 0 || element_index > elements_count {
+                return Err(MmrError::InvalidDump("external key out of range"));
+            }
+            writer
+                .push(self.external_key_writes(external_key, element_index))
+                .await?;
+        }
+        let mut previous_index = 0u64;
+        loop {
+            let element_index = input.read_u64()?;
+            if element_index == 0 {
+                break;
+            }
+            if element_index <= previous_index
+                || element_index > elements_count
+                || element_index_to_height(element_index)? != 0
+            {
+                return Err(MmrError::InvalidDump("leaf data out of order or range"));
+            }
+            previous_index = element_index;
+            let len = input.read_u64()?;
+            let data = input.read_bytes(len)?;
+            writer
+                .push([(self.leaf_data_key(element_index), StoreValue::Bytes(data))])
+                .await?;
+        }
+        input.finish()?;
+        writer.flush().await?;
+
+        let mut metadata = vec![
+            (self.leaf_count_key(), StoreValue::U64(leaves_count)),
+            (self.elements_count_key(), StoreValue::U64(elements_count)),
+        ];
+        if let Some(root_hash) = root_hash {
+            metadata.push((self.root_hash_key(), StoreValue::Hash(root_hash)));
+        }
+        writer.push(metadata).await?;
+        writer.flush().await
+    }
+}
+
+/// Stores imported entries a chunk at a time as they are read.
+struct ImportWriter<'a, S: Store> {
+    mmr: &'a Mmr<S>,
+    pending: Vec<(StoreKey, StoreValue)>,
+    /// Set once any write was attempted, even one that failed part way.
+    written: bool,
+}
+
+impl<S: Store> ImportWriter<'_, S> {
+    async fn push(
+        &mut self,
+        entries: impl IntoIterator<Item = (StoreKey, StoreValue)>,
+    ) -> Result<(), MmrError> {
+        self.pending.extend(entries);
+        if self.pending.len() >= DUMP_CHUNK_SIZE as usize {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), MmrError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        self.written = true;
+        self.mmr
+            .store
+            .set_many(std::mem::take(&mut self.pending))
+            .await?;
+        Ok(())
+    }
+}
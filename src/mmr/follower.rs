@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::types::{Hash32, Proof};
+
+use super::helpers::{find_peaks, mmr_size_to_leaf_count};
+use super::sync::replay_appends;
+use super::verify::{compute_peak_from_proof, root_from_peaks};
+
+pub struct MmrFollower {
+    hasher: Arc<dyn Hasher>,
+    elements_count: u64,
+    peaks_hashes: Vec<Hash32>,
+}
+
+impl MmrFollower {
+    pub fn new(hasher: Arc<dyn Hasher>) -> Self {
+        Self {
+            hasher,
+            elements_count: 0,
+            peaks_hashes: Vec::new(),
+        }
+    }
+
+    pub fn from_peaks(
+        hasher: Arc<dyn Hasher>,
+        elements_count: u64,
+        peaks_hashes: Vec<Hash32>,
+    ) -> Result<Self, MmrError> {
+        if find_peaks(elements_count).len() != peaks_hashes.len() {
+            return Err(MmrError::InvalidPeaksCountForElements);
+        }
+
+        Ok(Self {
+            hasher,
+            elements_count,
+            peaks_hashes,
+        })
+    }
+
+    pub fn elements_count(&self) -> u64 {
+        self.elements_count
+    }
+
+    pub fn leaves_count(&self) -> u64 {
+        mmr_size_to_leaf_count(self.elements_count)
+    }
+
+    pub fn peaks(&self) -> &[Hash32] {
+        &self.peaks_hashes
+    }
+
+    pub fn root_hash(&self) -> Result<Hash32, MmrError> {
+        root_from_peaks(
+            self.hasher.as_ref(),
+            &self.peaks_hashes,
+            self.elements_count,
+        )
+    }
+
+    pub fn apply_append(
+        &mut self,
+        values: &[Hash32],
+        announced_root: Option<&Hash32>,
+    ) -> Result<Hash32, MmrError> {
+        if values.is_empty() {
+            return Err(MmrError::EmptyBatchAppend);
+        }
+
+        let (nodes, peaks_hashes) = replay_appends(
+            self.hasher.as_ref(),
+            self.leaves_count(),
+            self.peaks_hashes.clone(),
+            values,
+        )?;
+        let elements_count = self
+            .elements_count
+            .checked_add(nodes.len() as u64)
+            .ok_or(MmrError::Overflow)?;
+        let root_hash = root_from_peaks(self.hasher.as_ref(), &peaks_hashes, elements_count)?;
+        if announced_root.is_some_and(|announced| *announced != root_hash) {
+            return Err(MmrError::RootMismatch);
+        }
+
+        self.elements_count = elements_count;
+        self.peaks_hashes = peaks_hashes;
+        Ok(root_hash)
+    }
+
+    pub fn apply_peak_update(
+        &mut self,
+        elements_count: u64,
+        peaks_hashes: Vec<Hash32>,
+        announced_root: &Hash32,
+    ) -> Result<(), MmrError> {
+        if elements_count < self.elements_count {
+            return Err(MmrError::InvalidElementCount);
+        }
+        if find_peaks(elements_count).len() != peaks_hashes.len() {
+            return Err(MmrError::InvalidPeaksCountForElements);
+        }
+        if root_from_peaks(self.hasher.as_ref(), &peaks_hashes, elements_count)? != *announced_root
+        {
+            return Err(MmrError::RootMismatch);
+        }
+
+        self.elements_count = elements_count;
+        self.peaks_hashes = peaks_hashes;
+        Ok(())
+    }
+
+    pub fn verify_proof(&self, proof: &Proof, element_value: Hash32) -> Result<bool, MmrError> {
+        if proof.elements_count != self.elements_count {
+            return Ok(false);
+        }
+
+        match compute_peak_from_proof(
+            self.hasher.as_ref(),
+            proof,
+            element_value,
+            self.elements_count,
+        )? {
+            Some((peak_index, peak_hash)) => {
+                Ok(self.peaks_hashes.get(peak_index) == Some(&peak_hash))
+            }
+            None => Ok(false),
+        }
+    }
+}
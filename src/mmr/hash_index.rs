@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use crate::error::MmrError;
+use crate::store::{KeyKind, Store, StoreKey, StoreValue};
+use crate::types::{ElementIndex, Hash};
+
+use super::core::Mmr;
+use super::op_stats::CountedStore;
+
+/// Slots stay within 63 bits so they fit Postgres' signed `INT8` index column.
+const SLOT_MASK: u64 = i64::MAX as u64;
+
+/// Longest run of occupied slots a lookup or insert walks before giving up.
+pub const MAX_HASH_INDEX_PROBES: u32 = 256;
+
+pub(super) fn initial_slot<const N: usize>(hash: &Hash<N>) -> u64 {
+    let mut prefix = [0u8; 8];
+    let len = N.min(8);
+    prefix[..len].copy_from_slice(&hash[..len]);
+    u64::from_be_bytes(prefix) & SLOT_MASK
+}
+
+pub(super) fn next_slot(slot: u64) -> u64 {
+    slot.wrapping_add(1) & SLOT_MASK
+}
+
+/// Where `stage_hash_index_writes_with` reads slots and nodes from.
+pub(crate) trait KeyReader<const N: usize = 32> {
+    async fn get_many(&mut self, keys: &[StoreKey])
+    -> Result<Vec<Option<StoreValue<N>>>, MmrError>;
+}
+
+impl<S: Store<N>, const N: usize> KeyReader<N> for &CountedStore<S, N> {
+    async fn get_many(
+        &mut self,
+        keys: &[StoreKey],
+    ) -> Result<Vec<Option<StoreValue<N>>>, MmrError> {
+        CountedStore::get_many(self, keys).await
+    }
+}
+
+impl<S: Store<N>, const N: usize> Mmr<S, N> {
+    pub async fn find_element_index(
+        &self,
+        hash: &Hash<N>,
+    ) -> Result<Option<ElementIndex>, MmrError> {
+        let mut slot = initial_slot(hash);
+        for _ in 0..MAX_HASH_INDEX_PROBES {
+            let slot_key = self.hash_index_key(slot);
+            let Some(value) = self.store.get(&slot_key).await? else {
+                return Ok(None);
+            };
+            let element_index = value.expect_u64(&slot_key)?;
+            if self.get_node_hash(element_index).await? == Some(*hash) {
+                return Ok(Some(element_index));
+            }
+            slot = next_slot(slot);
+        }
+        Err(MmrError::HashIndexProbeLimit(MAX_HASH_INDEX_PROBES))
+    }
+
+    pub async fn contains(&self, hash: &Hash<N>) -> Result<bool, MmrError> {
+        Ok(self.find_element_index(hash).await?.is_some())
+    }
+
+    pub(crate) async fn stage_hash_index_writes(
+        &self,
+        leaves: Vec<(Hash<N>, ElementIndex)>,
+    ) -> Result<Vec<(StoreKey, StoreValue<N>)>, MmrError> {
+        self.stage_hash_index_writes_with(leaves, &mut &self.store)
+            .await
+    }
+
+    /// `stage_hash_index_writes` reading slots and nodes through `reader`, e.g. inside an
+    /// open transaction whose earlier appends the store does not show yet.
+    pub(crate) async fn stage_hash_index_writes_with(
+        &self,
+        leaves: Vec<(Hash<N>, ElementIndex)>,
+        reader: &mut impl KeyReader<N>,
+    ) -> Result<Vec<(StoreKey, StoreValue<N>)>, MmrError> {
+        let mut pending: Vec<(Hash<N>, ElementIndex, u64)> = leaves
+            .into_iter()
+            .map(|(hash, element_index)| (hash, element_index, initial_slot(&hash)))
+            .collect();
+        let mut probes = 0;
+        let mut claimed: BTreeMap<u64, Hash<N>> = BTreeMap::new();
+        let mut writes = Vec::new();
+
+        while !pending.is_empty() {
+            if probes == MAX_HASH_INDEX_PROBES {
+                return Err(MmrError::HashIndexProbeLimit(MAX_HASH_INDEX_PROBES));
+            }
+            probes += 1;
+            let slot_keys: Vec<StoreKey> = pending
+                .iter()
+                .map(|(_, _, slot)| self.hash_index_key(*slot))
+                .collect();
+            let slot_values = reader.get_many(&slot_keys).await?;
+            let mut occupants = Vec::with_capacity(pending.len());
+            for (key, value) in slot_keys.iter().zip(slot_values) {
+                occupants.push(value.map(|value| value.expect_u64(key)).transpose()?);
+            }
+
+            let node_keys: Vec<StoreKey> = occupants
+                .iter()
+                .flatten()
+                .map(|element_index| self.node_key(*element_index))
+                .collect();
+            let node_values = reader.get_many(&node_keys).await?;
+            let mut node_hashes = BTreeMap::new();
+            for (key, value) in node_keys.iter().zip(node_values) {
+                if let Some(value) = value {
+                    node_hashes.insert(key.index, value.expect_hash(key)?);
+                }
+            }
+
+            let mut next_pending = Vec::new();
+            for ((hash, element_index, slot), occupant) in pending.into_iter().zip(occupants) {
+                if let Some(claimed_hash) = claimed.get(&slot) {
+                    if *claimed_hash != hash {
+                        next_pending.push((hash, element_index, next_slot(slot)));
+                    }
+                    continue;
+                }
+
+                match occupant {
+                    None => {
+                        claimed.insert(slot, hash);
+                        writes.push((self.hash_index_key(slot), StoreValue::U64(element_index)));
+                    }
+                    Some(existing) if node_hashes.get(&existing) == Some(&hash) => {}
+                    Some(_) => next_pending.push((hash, element_index, next_slot(slot))),
+                }
+            }
+            pending = next_pending;
+        }
+
+        Ok(writes)
+    }
+
+    fn hash_index_key(&self, slot: u64) -> StoreKey {
+        StoreKey::new(self.mmr_id, KeyKind::HashIndex, slot)
+    }
+}
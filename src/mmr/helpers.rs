@@ -1,164 +1,174 @@
 use crate::error::MmrError;
+use crate::verify::{self, bit_length};
 
-pub fn find_peaks(elements_count: u64) -> Vec<u64> {
-    let mut remaining = elements_count as u128;
-    let mut shift = 0u128;
-    let mut peaks = Vec::new();
-
-    let mut mountain_elements_count = if elements_count == 0 {
-        0u128
-    } else {
-        (1u128 << bit_length(elements_count)) - 1
-    };
+pub use crate::verify::{
+    PeaksIter, iter_peaks, leaf_count_to_append_no_merges, leaf_count_to_mmr_size,
+    leaf_count_to_peaks_count, map_leaf_index_to_element_index, mmr_size_to_leaf_count,
+};
 
-    while mountain_elements_count > 0 {
-        if mountain_elements_count <= remaining {
-            shift += mountain_elements_count;
-            peaks.push(shift as u64);
-            remaining -= mountain_elements_count;
-        }
-        mountain_elements_count >>= 1;
-    }
-
-    if remaining > 0 { Vec::new() } else { peaks }
-}
-
-pub fn map_leaf_index_to_element_index(leaf_index: u64) -> u64 {
-    2 * leaf_index + 1 - u64::from(leaf_index.count_ones())
-}
-
-pub fn leaf_count_to_peaks_count(leaf_count: u64) -> u32 {
-    leaf_count.count_ones()
+pub fn find_peaks(elements_count: u64) -> Vec<u64> {
+    iter_peaks(elements_count).collect()
 }
 
-pub fn leaf_count_to_mmr_size(leaf_count: u64) -> u64 {
-    2 * leaf_count - u64::from(leaf_count_to_peaks_count(leaf_count))
+pub fn try_map_leaf_index_to_element_index(leaf_index: u64) -> Result<u64, MmrError> {
+    let element_index = 2 * u128::from(leaf_index) + 1 - u128::from(leaf_index.count_ones());
+    u64::try_from(element_index).map_err(|_| MmrError::Overflow)
 }
 
-pub fn leaf_count_to_append_no_merges(leaf_count: u64) -> u64 {
-    u64::from(leaf_count.trailing_ones())
+#[derive(Debug, Clone)]
+pub struct SiblingsIter {
+    elements_count: u64,
+    leaf_index: u64,
+    height: u32,
+    current_index: u64,
+    done: bool,
 }
 
-pub fn find_siblings(element_index: u64, elements_count: u64) -> Result<Vec<u64>, MmrError> {
-    let mut leaf_index = element_index_to_leaf_index(element_index)?;
-    let mut height = 0u32;
-    let mut siblings = Vec::new();
-    let mut current_index = element_index;
+impl SiblingsIter {
+    fn step(&mut self) -> Result<Option<u64>, MmrError> {
+        if self.current_index > self.elements_count {
+            return Ok(None);
+        }
 
-    while current_index <= elements_count {
-        let siblings_offset_u128 = (2u128 << height) - 1;
+        let siblings_offset_u128 = (2u128 << self.height) - 1;
         let siblings_offset =
             u64::try_from(siblings_offset_u128).map_err(|_| MmrError::Overflow)?;
 
-        if leaf_index % 2 == 1 {
-            if current_index < siblings_offset {
+        let (sibling, next_index) = if self.leaf_index % 2 == 1 {
+            if self.current_index < siblings_offset {
                 return Err(MmrError::Overflow);
             }
-            siblings.push(current_index - siblings_offset);
-            current_index = current_index.checked_add(1).ok_or(MmrError::Overflow)?;
-        } else {
-            siblings.push(
-                current_index
-                    .checked_add(siblings_offset)
+            (
+                self.current_index - siblings_offset,
+                self.current_index
+                    .checked_add(1)
                     .ok_or(MmrError::Overflow)?,
-            );
-            current_index = current_index
+            )
+        } else {
+            let sibling = self
+                .current_index
                 .checked_add(siblings_offset)
-                .and_then(|v| v.checked_add(1))
                 .ok_or(MmrError::Overflow)?;
-        }
+            (sibling, sibling.checked_add(1).ok_or(MmrError::Overflow)?)
+        };
 
-        leaf_index /= 2;
-        height += 1;
+        self.leaf_index /= 2;
+        self.height += 1;
+        self.current_index = next_index;
+        Ok((next_index <= self.elements_count).then_some(sibling))
     }
+}
+
+impl Iterator for SiblingsIter {
+    type Item = Result<u64, MmrError>;
 
-    siblings.pop();
-    Ok(siblings)
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let step = self.step();
+        if !matches!(step, Ok(Some(_))) {
+            self.done = true;
+        }
+        step.transpose()
+    }
+}
+
+pub fn iter_siblings(element_index: u64, elements_count: u64) -> Result<SiblingsIter, MmrError> {
+    Ok(SiblingsIter {
+        elements_count,
+        leaf_index: element_index_to_leaf_index(element_index)?,
+        height: 0,
+        current_index: element_index,
+        done: false,
+    })
+}
+
+pub fn find_siblings(element_index: u64, elements_count: u64) -> Result<Vec<u64>, MmrError> {
+    iter_siblings(element_index, elements_count)?.collect()
 }
 
 pub fn element_index_to_leaf_index(element_index: u64) -> Result<u64, MmrError> {
-    if element_index == 0 {
-        return Err(MmrError::InvalidElementIndex);
-    }
-    elements_count_to_leaf_count(element_index - 1)
+    Ok(verify::element_index_to_leaf_index(element_index)?)
 }
 
 pub fn elements_count_to_leaf_count(elements_count: u64) -> Result<u64, MmrError> {
-    let mut leaf_count = 0u128;
-    let mut current = elements_count as u128;
-
-    let mut mountain_leaf_count = if elements_count == 0 {
-        1u128
-    } else {
-        1u128 << bit_length(elements_count)
-    };
+    Ok(verify::elements_count_to_leaf_count(elements_count)?)
+}
 
-    while mountain_leaf_count > 0 {
-        let mountain_elements_count = 2 * mountain_leaf_count - 1;
-        if mountain_elements_count <= current {
-            leaf_count += mountain_leaf_count;
-            current -= mountain_elements_count;
-        }
-        mountain_leaf_count >>= 1;
-    }
+pub fn get_peak_info(elements_count: u64, element_index: u64) -> Result<(usize, usize), MmrError> {
+    Ok(verify::get_peak_info(elements_count, element_index)?)
+}
 
-    if current > 0 {
-        Err(MmrError::InvalidElementCount)
-    } else {
-        u64::try_from(leaf_count).map_err(|_| MmrError::Overflow)
-    }
+pub fn try_leaf_count_to_mmr_size(leaf_count: u64) -> Result<u64, MmrError> {
+    Ok(verify::try_leaf_count_to_mmr_size(leaf_count)?)
 }
 
-pub fn get_peak_info(mut elements_count: u64, mut element_index: u64) -> (usize, usize) {
-    let mut mountain_height = bit_length(elements_count);
-    let mut mountain_elements_count = if mountain_height == 0 {
-        0u128
-    } else {
-        (1u128 << mountain_height) - 1
-    };
-    let mut mountain_index = 0usize;
+pub fn proof_siblings_count(elements_count: u64, element_index: u64) -> Result<usize, MmrError> {
+    Ok(get_peak_info(elements_count, element_index)?.1)
+}
 
-    loop {
-        if mountain_elements_count <= elements_count as u128 {
-            if element_index as u128 <= mountain_elements_count {
-                return (mountain_index, mountain_height.saturating_sub(1) as usize);
-            }
-            elements_count -= mountain_elements_count as u64;
-            element_index -= mountain_elements_count as u64;
-            mountain_index += 1;
-        }
+pub fn element_index_to_height(element_index: u64) -> Result<u32, MmrError> {
+    if element_index == 0 {
+        return Err(MmrError::InvalidElementIndex);
+    }
 
-        if mountain_height == 0 {
-            return (mountain_index, 0);
-        }
+    let mut position = element_index;
+    while !is_all_ones(position) {
+        position -= (1u64 << (bit_length(position) - 1)) - 1;
+    }
+    Ok(bit_length(position) - 1)
+}
 
-        mountain_elements_count >>= 1;
-        mountain_height -= 1;
+pub fn left_child_index(element_index: u64) -> Result<Option<u64>, MmrError> {
+    let height = element_index_to_height(element_index)?;
+    if height == 0 {
+        return Ok(None);
     }
+    Ok(Some(element_index - (1u64 << height)))
 }
 
-pub fn mmr_size_to_leaf_count(mmr_size: u64) -> u64 {
-    let mut remaining = mmr_size as u128;
-    let bits = bit_length_u128(remaining + 1);
-    let mut mountain_tips = 1u128 << bits.saturating_sub(1);
-    let mut leaf_count = 0u128;
+pub fn right_child_index(element_index: u64) -> Result<Option<u64>, MmrError> {
+    let height = element_index_to_height(element_index)?;
+    if height == 0 {
+        return Ok(None);
+    }
+    Ok(Some(element_index - 1))
+}
 
-    while mountain_tips != 0 {
-        let mountain_size = 2 * mountain_tips - 1;
-        if mountain_size <= remaining {
-            remaining -= mountain_size;
-            leaf_count += mountain_tips;
-        }
-        mountain_tips >>= 1;
+pub fn sibling_index(element_index: u64) -> Result<u64, MmrError> {
+    let height = element_index_to_height(element_index)?;
+    let offset = subtree_size(height)?;
+    if is_right_child(element_index, height)? {
+        Ok(element_index - offset)
+    } else {
+        element_index.checked_add(offset).ok_or(MmrError::Overflow)
     }
+}
+
+pub fn parent_index(element_index: u64) -> Result<u64, MmrError> {
+    let height = element_index_to_height(element_index)?;
+    let step = if is_right_child(element_index, height)? {
+        1
+    } else {
+        subtree_size(height)?
+            .checked_add(1)
+            .ok_or(MmrError::Overflow)?
+    };
+    element_index.checked_add(step).ok_or(MmrError::Overflow)
+}
 
-    leaf_count as u64
+fn is_right_child(element_index: u64, height: u32) -> Result<bool, MmrError> {
+    let next_index = element_index.checked_add(1).ok_or(MmrError::Overflow)?;
+    Ok(element_index_to_height(next_index)? > height)
 }
 
-fn bit_length(num: u64) -> u32 {
-    64 - num.leading_zeros()
+fn subtree_size(height: u32) -> Result<u64, MmrError> {
+    1u64.checked_shl(height + 1)
+        .map(|size| size - 1)
+        .ok_or(MmrError::Overflow)
 }
 
-fn bit_length_u128(num: u128) -> u32 {
-    128 - num.leading_zeros()
+fn is_all_ones(num: u64) -> bool {
+    num != 0 && num & num.wrapping_add(1) == 0
 }
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::store::Store;
+use crate::types::{Hash32, MmrId};
+
+use super::core::Mmr;
+use super::helpers::{find_peaks, try_leaf_count_to_mmr_size};
+use super::offline::OfflineBuild;
+use super::verify::root_from_peaks;
+
+impl<S: Store> Mmr<S> {
+    /// Imports an MMR from the string key/value layout of the Herodotus `accumulators`
+    /// libraries (`{id}:leaf_count`, `{id}:elements_count`, `{id}:root_hash` and
+    /// `{id}:hashes:{index}`, with decimal counts and hex hashes), e.g. rows of their sqlite
+    /// `store` table. The stored root must match the one recomputed from the imported peaks.
+    pub async fn import_herodotus<I>(
+        store: S,
+        hasher: Arc<dyn Hasher>,
+        mmr_id: Option<MmrId>,
+        source_mmr_id: &str,
+        entries: I,
+    ) -> Result<Self, MmrError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mmr_id = Self::resolve_mmr_id(&store, mmr_id).await?;
+        let mmr = Self::new(store, hasher, Some(mmr_id))?;
+
+        let prefix = format!("{source_mmr_id}:");
+        let mut leaves_count = None;
+        let mut elements_count = None;
+        let mut root_hash = None;
+        let mut hashes: HashMap<u64, Hash32> = HashMap::new();
+        for (key, value) in entries {
+            let Some(field) = key.strip_prefix(&prefix) else {
+                continue;
+            };
+            match field {
+                "leaf_count" => leaves_count = Some(parse_count(&value)?),
+                "elements_count" => elements_count = Some(parse_count(&value)?),
+                "root_hash" => root_hash = Some(value.parse::<Hash32>()?),
+                _ => {
+                    let Some(index) = field.strip_prefix("hashes:") else {
+                        continue;
+                    };
+                    let index = index
+                        .parse()
+                        .map_err(|_| MmrError::InvalidHerodotusImport("invalid node index"))?;
+                    hashes.insert(index, value.parse()?);
+                }
+            }
+        }
+
+        let leaves_count =
+            leaves_count.ok_or(MmrError::InvalidHerodotusImport("missing leaf_count"))?;
+        let elements_count =
+            elements_count.ok_or(MmrError::InvalidHerodotusImport("missing elements_count"))?;
+        let root_hash = root_hash.ok_or(MmrError::InvalidHerodotusImport("missing root_hash"))?;
+        if try_leaf_count_to_mmr_size(leaves_count)? != elements_count {
+            return Err(MmrError::InvalidHerodotusImport(
+                "leaf_count does not match elements_count",
+            ));
+        }
+        if hashes.len() as u64 != elements_count {
+            return Err(MmrError::InvalidHerodotusImport(
+                "node count does not match elements_count",
+            ));
+        }
+
+        let nodes = (1..=elements_count)
+            .map(|index| hashes.get(&index).copied())
+            .collect::<Option<Vec<_>>>()
+            .ok_or(MmrError::InvalidHerodotusImport("missing node hash"))?;
+        let peaks_hashes: Vec<Hash32> = find_peaks(elements_count)
+            .into_iter()
+            .map(|index| nodes[index as usize - 1])
+            .collect();
+        if root_from_peaks(mmr.hasher.as_ref(), &peaks_hashes, elements_count)? != root_hash {
+            return Err(MmrError::RootMismatch);
+        }
+
+        mmr.bulk_load(&OfflineBuild {
+            nodes,
+            leaves_count,
+            elements_count,
+            peaks_hashes,
+            root_hash,
+        })
+        .await?;
+        Ok(mmr)
+    }
+}
+
+fn parse_count(value: &str) -> Result<u64, MmrError> {
+    value
+        .parse()
+        .map_err(|_| MmrError::InvalidHerodotusImport("invalid count"))
+}
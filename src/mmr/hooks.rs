@@ -0,0 +1,13 @@
+use crate::error::MmrError;
+use crate::types::{BatchAppendResult, Hash, MmrId};
+
+pub trait AppendHook<const N: usize = 32>: Send + Sync {
+    fn on_before_append(&self, mmr_id: MmrId, values: &[Hash<N>]) -> Result<(), MmrError> {
+        let _ = (mmr_id, values);
+        Ok(())
+    }
+
+    fn on_after_append(&self, mmr_id: MmrId, result: &BatchAppendResult<N>) {
+        let _ = (mmr_id, result);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::error::MmrError;
+use crate::store::Store;
+use crate::types::{ElementIndex, Proof, ZeroBasedProof};
+
+use super::core::Mmr;
+
+pub fn element_index_to_position(element_index: ElementIndex) -> Result<u64, MmrError> {
+    element_index
+        .checked_sub(1)
+        .ok_or(MmrError::InvalidElementIndex)
+}
+
+pub fn position_to_element_index(position: u64) -> Result<ElementIndex, MmrError> {
+    position.checked_add(1).ok_or(MmrError::Overflow)
+}
+
+impl Proof {
+    pub fn to_zero_based(&self) -> Result<ZeroBasedProof, MmrError> {
+        Ok(ZeroBasedProof {
+            position: element_index_to_position(self.element_index)?,
+            element_hash: self.element_hash,
+            siblings_hashes: self.siblings_hashes.clone(),
+            peaks_hashes: self.peaks_hashes.clone(),
+            elements_count: self.elements_count,
+            mmr_id: self.mmr_id,
+        })
+    }
+}
+
+impl ZeroBasedProof {
+    pub fn to_proof(&self) -> Result<Proof, MmrError> {
+        Ok(Proof {
+            element_index: position_to_element_index(self.position)?,
+            element_hash: self.element_hash,
+            siblings_hashes: self.siblings_hashes.clone(),
+            peaks_hashes: self.peaks_hashes.clone(),
+            elements_count: self.elements_count,
+            mmr_id: self.mmr_id,
+        })
+    }
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn get_zero_based_proof(
+        &self,
+        position: u64,
+        elements_count: Option<u64>,
+    ) -> Result<ZeroBasedProof, MmrError> {
+        let element_index = position_to_element_index(position)?;
+        self.get_proof(element_index, elements_count)
+            .await?
+            .to_zero_based()
+    }
+}
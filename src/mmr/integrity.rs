@@ -0,0 +1,207 @@
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::store::{Store, StoreKey, StoreValue};
+use crate::types::{ElementIndex, Hash32};
+
+use super::core::Mmr;
+use super::helpers::{find_peaks, mmr_size_to_leaf_count};
+use super::verify::bag_peaks;
+
+const INTEGRITY_CHUNK_SIZE: u64 = 4096;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntegrityReport {
+    pub elements_count: u64,
+    pub leaves_count: u64,
+    pub counts_consistent: bool,
+    pub missing_nodes: Vec<ElementIndex>,
+    pub mismatched_nodes: Vec<ElementIndex>,
+    /// `None` when the root was not checked: a lazy-root MMR before `finalize_root`.
+    pub root_matches: Option<bool>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.counts_consistent
+            && self.missing_nodes.is_empty()
+            && self.mismatched_nodes.is_empty()
+            && self.root_matches != Some(false)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RebuildResult {
+    pub rewritten_nodes: Vec<ElementIndex>,
+    pub root_hash: Hash32,
+    pub root_rewritten: bool,
+}
+
+struct StackEntry {
+    height: u32,
+    hash: Option<Hash32>,
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn verify_integrity(&self) -> Result<IntegrityReport, MmrError> {
+        let elements_count = self.get_elements_count().await?;
+        let leaves_count = self.get_leaves_count().await?;
+        let stored_root = self.get_root_hash().await?;
+
+        let mut report = IntegrityReport {
+            elements_count,
+            leaves_count,
+            ..IntegrityReport::default()
+        };
+
+        let peak_indices = find_peaks(elements_count);
+        if (elements_count > 0 && peak_indices.is_empty())
+            || mmr_size_to_leaf_count(elements_count) != leaves_count
+        {
+            return Ok(report);
+        }
+        report.counts_consistent = true;
+
+        // Nodes are stored in post-order, so a node is the parent of the two
+        // entries on top of the stack exactly when they have equal heights.
+        let mut stack: Vec<StackEntry> = Vec::new();
+        let mut chunk_start = 1u64;
+        while chunk_start <= elements_count {
+            let chunk_end =
+                elements_count.min(chunk_start.saturating_add(INTEGRITY_CHUNK_SIZE - 1));
+            let keys: Vec<StoreKey> = (chunk_start..=chunk_end)
+                .map(|idx| self.node_key(idx))
+                .collect();
+            let values = self.store.get_many(&keys).await?;
+
+            for ((idx, key), value) in (chunk_start..=chunk_end).zip(keys.iter()).zip(values) {
+                let hash = value.map(|value| value.expect_hash(key)).transpose()?;
+                if hash.is_none() {
+                    report.missing_nodes.push(idx);
+                }
+
+                let is_parent = matches!(
+                    stack.as_slice(),
+                    [.., left, right] if left.height == right.height
+                );
+                if !is_parent {
+                    stack.push(StackEntry { height: 0, hash });
+                    continue;
+                }
+
+                let right = stack.pop().ok_or(MmrError::InvalidElementCount)?;
+                let left = stack.pop().ok_or(MmrError::InvalidElementCount)?;
+                if let (Some(stored), Some(left_hash), Some(right_hash)) =
+                    (hash, left.hash, right.hash)
+                    && self.hasher.hash_pair(&left_hash, &right_hash)? != stored
+                {
+                    report.mismatched_nodes.push(idx);
+                }
+                stack.push(StackEntry {
+                    height: left.height + 1,
+                    hash,
+                });
+            }
+
+            chunk_start = chunk_end + 1;
+        }
+
+        let peaks_hashes = stack
+            .into_iter()
+            .map(|entry| entry.hash)
+            .collect::<Option<Vec<_>>>();
+        report.root_matches = match (peaks_hashes, stored_root) {
+            (_, None) if self.lazy_root && elements_count > 0 => None,
+            (_, None) => Some(elements_count == 0),
+            (Some(peaks_hashes), Some(stored_root)) => {
+                let bag = bag_peaks(self.hasher.as_ref(), &peaks_hashes)?;
+                Some(self.calculate_root_hash(&bag, elements_count)? == stored_root)
+            }
+            (None, Some(_)) => Some(false),
+        };
+
+        Ok(report)
+    }
+
+    pub async fn rebuild_interior_nodes(&self) -> Result<RebuildResult, MmrError> {
+        let lease_owner = self.ensure_writer_lease().await?;
+        let elements_count = self.get_elements_count().await?;
+        let stored_root = self.get_root_hash().await?;
+        if elements_count > 0 && find_peaks(elements_count).is_empty() {
+            return Err(MmrError::InvalidElementCount);
+        }
+
+        let mut rewritten_nodes = Vec::new();
+        let mut stack: Vec<(u32, Hash32)> = Vec::new();
+        let mut chunk_start = 1u64;
+        while chunk_start <= elements_count {
+            let chunk_end =
+                elements_count.min(chunk_start.saturating_add(INTEGRITY_CHUNK_SIZE - 1));
+            let keys: Vec<StoreKey> = (chunk_start..=chunk_end)
+                .map(|idx| self.node_key(idx))
+                .collect();
+            let values = self.store.get_many(&keys).await?;
+
+            let mut repairs = Vec::new();
+            for ((idx, key), value) in (chunk_start..=chunk_end).zip(keys).zip(values) {
+                let stored = value.map(|value| value.expect_hash(&key)).transpose()?;
+
+                let is_parent = matches!(
+                    stack.as_slice(),
+                    [.., (left_height, _), (right_height, _)] if left_height == right_height
+                );
+                if !is_parent {
+                    let leaf_hash = stored.ok_or(MmrError::NoHashFoundForIndex(idx))?;
+                    stack.push((0, leaf_hash));
+                    continue;
+                }
+
+                let (_, right_hash) = stack.pop().ok_or(MmrError::InvalidElementCount)?;
+                let (left_height, left_hash) = stack.pop().ok_or(MmrError::InvalidElementCount)?;
+                let parent_hash = self.hasher.hash_pair(&left_hash, &right_hash)?;
+                if stored != Some(parent_hash) {
+                    repairs.push((key, StoreValue::Hash(parent_hash)));
+                    rewritten_nodes.push(idx);
+                }
+                stack.push((left_height + 1, parent_hash));
+            }
+            // Each chunk is guarded like the root below, so repairs stop as soon as another
+            // writer appends or takes the lease over.
+            if !repairs.is_empty()
+                && !self
+                    .compare_and_set_leased(lease_owner, elements_count, repairs)
+                    .await?
+            {
+                return Err(MmrError::ConcurrentAppendConflict(1));
+            }
+
+            chunk_start = chunk_end + 1;
+        }
+
+        if !rewritten_nodes.is_empty() {
+            self.clear_node_cache();
+        }
+
+        let peaks_hashes: Vec<Hash32> = stack.into_iter().map(|(_, hash)| hash).collect();
+        let bag = bag_peaks(self.hasher.as_ref(), &peaks_hashes)?;
+        let root_hash = self.calculate_root_hash(&bag, elements_count)?;
+        let root_rewritten = stored_root != Some(root_hash);
+        if root_rewritten {
+            let committed = self
+                .compare_and_set_leased(
+                    lease_owner,
+                    elements_count,
+                    vec![(self.root_hash_key(), StoreValue::Hash(root_hash))],
+                )
+                .await?;
+            if !committed {
+                return Err(MmrError::ConcurrentAppendConflict(1));
+            }
+        }
+
+        Ok(RebuildResult {
+            rewritten_nodes,
+            root_hash,
+            root_rewritten,
+        })
+    }
+}
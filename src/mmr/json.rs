@@ -0,0 +1,74 @@
+use crate::error::MmrError;
+use crate::types::{Hash32, Proof, ProofJson};
+
+/// Version written by [`Proof::to_json`].
+pub const PROOF_JSON_VERSION: u8 = 1;
+
+impl Proof {
+    pub fn to_json(&self) -> ProofJson {
+        ProofJson {
+            version: PROOF_JSON_VERSION,
+            element_index: self.element_index.to_string(),
+            element_hash: self.element_hash.to_string(),
+            siblings_hashes: self
+                .siblings_hashes
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            peaks_hashes: self.peaks_hashes.iter().map(ToString::to_string).collect(),
+            elements_count: self.elements_count.to_string(),
+            mmr_id: self.mmr_id.map(|mmr_id| mmr_id.to_string()),
+        }
+    }
+}
+
+impl ProofJson {
+    pub fn to_proof(&self) -> Result<Proof, MmrError> {
+        if self.version != PROOF_JSON_VERSION {
+            return Err(MmrError::UnsupportedProofEncodingVersion(self.version));
+        }
+
+        Ok(Proof {
+            element_index: parse_u64("elementIndex", &self.element_index)?,
+            element_hash: parse_hash("elementHash", &self.element_hash)?,
+            siblings_hashes: self
+                .siblings_hashes
+                .iter()
+                .map(|hash| parse_hash("siblingsHashes", hash))
+                .collect::<Result<_, _>>()?,
+            peaks_hashes: self
+                .peaks_hashes
+                .iter()
+                .map(|hash| parse_hash("peaksHashes", hash))
+                .collect::<Result<_, _>>()?,
+            elements_count: parse_u64("elementsCount", &self.elements_count)?,
+            mmr_id: self
+                .mmr_id
+                .as_deref()
+                .map(|mmr_id| parse_u64("mmrId", mmr_id))
+                .transpose()?,
+        })
+    }
+}
+
+pub(crate) fn parse_u64(field: &'static str, value: &str) -> Result<u64, MmrError> {
+    value.parse().map_err(|_| invalid(field, value))
+}
+
+/// Only the full `0x`-prefixed 64-digit form `to_json` writes; a short value is rejected
+/// rather than zero-padded.
+fn parse_hash(field: &'static str, value: &str) -> Result<Hash32, MmrError> {
+    let mut out = [0u8; 32];
+    value
+        .strip_prefix("0x")
+        .and_then(|digits| hex::decode_to_slice(digits, &mut out).ok())
+        .ok_or_else(|| invalid(field, value))?;
+    Ok(Hash32::new(out))
+}
+
+fn invalid(field: &'static str, value: &str) -> MmrError {
+    MmrError::InvalidProofJson {
+        field,
+        value: value.to_string(),
+    }
+}
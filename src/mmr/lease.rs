@@ -0,0 +1,234 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{MmrError, StoreError};
+use crate::store::{KeyKind, Store, StoreKey, StoreValue, WriteBatch};
+
+use super::core::Mmr;
+
+const LEASE_OWNER_INDEX: u64 = 0;
+const LEASE_EXPIRY_INDEX: u64 = 1;
+
+static NEXT_LEASE_NONCE: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WriterLease {
+    pub(crate) owner: u64,
+    pub(crate) expires_at_ms: u64,
+}
+
+#[derive(Debug)]
+pub struct WriterGuard<'a, S: Store<N>, const N: usize = 32> {
+    mmr: &'a Mmr<S, N>,
+    owner: u64,
+}
+
+impl<S: Store<N>, const N: usize> Mmr<S, N> {
+    pub async fn acquire_writer_lease(
+        &self,
+        ttl: Duration,
+    ) -> Result<WriterGuard<'_, S, N>, MmrError> {
+        let owner = match self.current_writer_lease()? {
+            Some(lease) => lease.owner,
+            None => new_lease_owner(),
+        };
+        self.claim_writer_lease(owner, ttl).await?;
+
+        Ok(WriterGuard { mmr: self, owner })
+    }
+
+    async fn claim_writer_lease(&self, owner: u64, ttl: Duration) -> Result<(), MmrError> {
+        let owner_key = self.lease_owner_key();
+        let expiry_key = self.lease_expiry_key();
+        let (current_owner, current_expiry) = self.read_writer_lease().await?;
+
+        let now_ms = unix_time_ms()?;
+        if current_owner != 0 && current_owner != owner && current_expiry > now_ms {
+            return Err(MmrError::WriterLeaseHeld);
+        }
+
+        let ttl_ms = u64::try_from(ttl.as_millis()).map_err(|_| MmrError::Overflow)?;
+        let expires_at_ms = now_ms.checked_add(ttl_ms).ok_or(MmrError::Overflow)?;
+        let claimed = self
+            .store
+            .compare_and_set_many(
+                &owner_key,
+                current_owner,
+                vec![
+                    (owner_key.clone(), StoreValue::U64(owner)),
+                    (expiry_key, StoreValue::U64(expires_at_ms)),
+                ],
+            )
+            .await?;
+        if !claimed {
+            return Err(MmrError::WriterLeaseHeld);
+        }
+
+        self.set_writer_lease(Some(WriterLease {
+            owner,
+            expires_at_ms,
+        }))
+    }
+
+    /// Checks that this instance may write and returns the lease owner its commit must be
+    /// guarded on (0 when no lease was ever claimed).
+    pub(crate) async fn ensure_writer_lease(&self) -> Result<u64, MmrError> {
+        let stored_lease = self.read_writer_lease().await?;
+        self.check_writer_lease(stored_lease)
+    }
+
+    /// `ensure_writer_lease` against an already read `(owner, expires_at_ms)`. An instance
+    /// without a lease is refused while another instance holds a live one.
+    pub(crate) fn check_writer_lease(
+        &self,
+        (current_owner, current_expiry): (u64, u64),
+    ) -> Result<u64, MmrError> {
+        let now_ms = unix_time_ms()?;
+        let Some(lease) = self.current_writer_lease()? else {
+            if current_owner != 0 && current_expiry > now_ms {
+                return Err(MmrError::WriterLeaseHeld);
+            }
+            return Ok(current_owner);
+        };
+
+        if now_ms >= lease.expires_at_ms {
+            return Err(MmrError::WriterLeaseExpired);
+        }
+        if current_owner != lease.owner {
+            return Err(MmrError::WriterLeaseLost);
+        }
+
+        Ok(lease.owner)
+    }
+
+    /// Guard on the lease owner key for a commit made under `lease_owner`, or `None` when no
+    /// lease was claimed, in which case the element count guard alone is used.
+    pub(crate) fn lease_guard(&self, lease_owner: u64) -> Option<(StoreKey, u64)> {
+        (lease_owner != 0).then(|| (self.lease_owner_key(), lease_owner))
+    }
+
+    /// `compare_and_set_many` on the element count that also requires the lease owner to
+    /// still be `lease_owner`, so a writer whose lease was taken over cannot commit.
+    pub(crate) async fn compare_and_set_leased(
+        &self,
+        lease_owner: u64,
+        expected_elements_count: u64,
+        entries: Vec<(StoreKey, StoreValue<N>)>,
+    ) -> Result<bool, MmrError> {
+        self.compare_and_write_leased(lease_owner, expected_elements_count, entries, Vec::new())
+            .await
+    }
+
+    /// `compare_and_set_leased` that also deletes `deletes`, which needs a store whose
+    /// `write_batch` takes deletes.
+    pub(crate) async fn compare_and_write_leased(
+        &self,
+        lease_owner: u64,
+        expected_elements_count: u64,
+        entries: Vec<(StoreKey, StoreValue<N>)>,
+        deletes: Vec<StoreKey>,
+    ) -> Result<bool, MmrError> {
+        let elements_count_key = self.elements_count_key();
+        let lease_guard = self.lease_guard(lease_owner);
+        if lease_guard.is_none() && deletes.is_empty() {
+            return self
+                .store
+                .compare_and_set_many(&elements_count_key, expected_elements_count, entries)
+                .await;
+        }
+
+        let mut batch = WriteBatch::new()
+            .guard(elements_count_key, expected_elements_count)
+            .set_many(entries)
+            .delete_many(deletes);
+        if let Some((owner_key, owner)) = lease_guard {
+            batch = batch.guard(owner_key, owner);
+        }
+        self.store.write_batch(batch).await
+    }
+
+    pub(crate) async fn read_writer_lease(&self) -> Result<(u64, u64), MmrError> {
+        let keys = self.lease_keys();
+        let values = self.store.get_many(&keys).await?;
+        Self::decode_writer_lease(&keys, &values)
+    }
+
+    /// Owner and expiry keys, in the order `decode_writer_lease` expects.
+    pub(crate) fn lease_keys(&self) -> [StoreKey; 2] {
+        [self.lease_owner_key(), self.lease_expiry_key()]
+    }
+
+    pub(crate) fn decode_writer_lease(
+        keys: &[StoreKey],
+        values: &[Option<StoreValue<N>>],
+    ) -> Result<(u64, u64), MmrError> {
+        let mut decoded = [0u64; 2];
+        for ((slot, key), value) in decoded.iter_mut().zip(keys).zip(values) {
+            if let Some(value) = value.clone() {
+                *slot = value.expect_u64(key)?;
+            }
+        }
+        let [owner, expires_at_ms] = decoded;
+        Ok((owner, expires_at_ms))
+    }
+
+    fn lease_owner_key(&self) -> StoreKey {
+        StoreKey::new(self.mmr_id, KeyKind::WriterLease, LEASE_OWNER_INDEX)
+    }
+
+    fn lease_expiry_key(&self) -> StoreKey {
+        StoreKey::new(self.mmr_id, KeyKind::WriterLease, LEASE_EXPIRY_INDEX)
+    }
+}
+
+impl<S: Store<N>, const N: usize> WriterGuard<'_, S, N> {
+    pub fn owner(&self) -> u64 {
+        self.owner
+    }
+
+    pub async fn renew(&self, ttl: Duration) -> Result<(), MmrError> {
+        self.mmr.ensure_writer_lease().await?;
+        self.mmr.claim_writer_lease(self.owner, ttl).await
+    }
+
+    pub async fn release(self) -> Result<(), MmrError> {
+        let owner_key = self.mmr.lease_owner_key();
+        let released = self
+            .mmr
+            .store
+            .compare_and_set_many(
+                &owner_key,
+                self.owner,
+                vec![
+                    (owner_key.clone(), StoreValue::U64(0)),
+                    (self.mmr.lease_expiry_key(), StoreValue::U64(0)),
+                ],
+            )
+            .await?;
+
+        self.mmr.set_writer_lease(None)?;
+        if !released {
+            return Err(MmrError::WriterLeaseLost);
+        }
+
+        Ok(())
+    }
+}
+
+fn new_lease_owner() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default();
+    let nonce = NEXT_LEASE_NONCE.fetch_add(1, Ordering::Relaxed);
+    let owner = (u64::from(std::process::id()) << 32) ^ nanos ^ nonce.rotate_left(48);
+
+    owner.max(1)
+}
+
+fn unix_time_ms() -> Result<u64, MmrError> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| StoreError::Internal("system clock is before unix epoch".to_string()))?;
+    u64::try_from(elapsed.as_millis()).map_err(|_| MmrError::Overflow)
+}
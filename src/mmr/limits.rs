@@ -0,0 +1,48 @@
+use crate::error::MmrError;
+
+use super::helpers::try_leaf_count_to_mmr_size;
+
+/// Optional caps on an accumulator's size. Every append path checks the leaf count it would
+/// reach before staging any write.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct SizeLimits {
+    pub(crate) max_leaves: Option<u64>,
+    pub(crate) max_elements: Option<u64>,
+}
+
+impl SizeLimits {
+    pub(crate) fn check(&self, leaves_count: u64) -> Result<(), MmrError> {
+        match self.max_leaves {
+            Some(limit) if leaves_count > limit => {
+                return Err(MmrError::MaxLeavesExceeded {
+                    limit,
+                    requested: leaves_count,
+                });
+            }
+            _ => {}
+        }
+
+        let elements_count = try_leaf_count_to_mmr_size(leaves_count)?;
+        match self.max_elements {
+            Some(limit) if elements_count > limit => Err(MmrError::MaxElementsExceeded {
+                limit,
+                requested: elements_count,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// `check` for appending `appended_count` leaves to `leaves_count`.
+    pub(crate) fn check_append(
+        &self,
+        leaves_count: u64,
+        appended_count: usize,
+    ) -> Result<(), MmrError> {
+        let appended_count = u64::try_from(appended_count).map_err(|_| MmrError::Overflow)?;
+        self.check(
+            leaves_count
+                .checked_add(appended_count)
+                .ok_or(MmrError::Overflow)?,
+        )
+    }
+}
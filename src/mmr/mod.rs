@@ -1,9 +1,83 @@
+mod accumulator;
+#[cfg(feature = "rkyv")]
+mod archive;
+mod branch;
+mod builder;
+mod checkpoint;
+mod codec;
+mod compact;
+mod copy;
 mod core;
+mod draft;
+mod dump;
+mod follower;
+mod hash_index;
 mod helpers;
+mod herodotus;
+mod hooks;
+mod indexing;
+mod integrity;
+pub(crate) mod json;
+mod lease;
+mod limits;
+mod node_cache;
+pub(crate) mod node_file;
+mod offline;
+mod op_stats;
+mod padded;
+mod paranoid;
+mod path;
+mod pinned;
+mod proof_update;
+mod registry;
+mod ssz;
+mod stateless;
+mod stats;
+mod sync;
+mod typed;
+pub(crate) mod verify;
+mod witness;
+mod writer;
 
+pub use accumulator::PeaksAccumulator;
+pub use builder::{MmrBuilder, MmrInit};
+pub use checkpoint::Checkpoint;
+pub use codec::PROOF_ENCODING_VERSION;
+pub use compact::verify_compact_proof;
+pub use copy::{CopyOptions, CopyProgress};
 pub use core::Mmr;
+pub use draft::DraftMmr;
+pub use follower::MmrFollower;
+pub use hash_index::MAX_HASH_INDEX_PROBES;
 pub use helpers::{
-    element_index_to_leaf_index, elements_count_to_leaf_count, find_peaks, find_siblings,
-    get_peak_info, leaf_count_to_append_no_merges, leaf_count_to_mmr_size,
-    leaf_count_to_peaks_count, map_leaf_index_to_element_index, mmr_size_to_leaf_count,
+    PeaksIter, SiblingsIter, element_index_to_height, element_index_to_leaf_index,
+    elements_count_to_leaf_count, find_peaks, find_siblings, get_peak_info, iter_peaks,
+    iter_siblings, leaf_count_to_append_no_merges, leaf_count_to_mmr_size,
+    leaf_count_to_peaks_count, left_child_index, map_leaf_index_to_element_index,
+    mmr_size_to_leaf_count, parent_index, proof_siblings_count, right_child_index, sibling_index,
+    try_leaf_count_to_mmr_size, try_map_leaf_index_to_element_index,
 };
+pub use hooks::AppendHook;
+pub use indexing::{element_index_to_position, position_to_element_index};
+pub use integrity::{IntegrityReport, RebuildResult};
+pub use json::PROOF_JSON_VERSION;
+pub use lease::WriterGuard;
+pub use offline::{OfflineBuild, build_offline, build_offline_with_threads};
+pub use op_stats::{OpStats, PhaseTimings};
+pub use padded::{padded_merkle_root, verify_merkle_proof};
+pub use path::verify_path_proof;
+pub use pinned::PinnedMmr;
+pub use registry::{KECCAK_HASHER_CODE, MmrRegistry, POSEIDON_HASHER_CODE, derive_mmr_id};
+pub use ssz::SSZ_MAX_HASHES;
+pub use stateless::{
+    append_to_peaks, compute_peaks_from_leaves, compute_root_from_leaves, prove_from_leaves,
+};
+pub use stats::MmrStats;
+pub use sync::DiffPacket;
+pub use typed::{LeafEncode, TypedMmr};
+pub use verify::{
+    bag_peaks, bound_root_from_peaks, root_from_peaks, verify_bound_proof, verify_proof,
+    verify_proof_against_root,
+};
+pub use witness::WitnessTracker;
+pub use writer::{MmrWriter, QueuedAppend};
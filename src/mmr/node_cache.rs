@@ -0,0 +1,63 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::types::Hash;
+
+/// Bounded least-recently-used map of element index to node hash. Appends never change a
+/// written node, and the paths that do (promote, repair, destroy, imports) clear the whole
+/// cache, so entries are only evicted, never updated.
+#[derive(Debug)]
+pub(crate) struct NodeCache<const N: usize = 32> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<u64, (Hash<N>, u64)>,
+    recency: BTreeMap<u64, u64>,
+}
+
+impl<const N: usize> NodeCache<N> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::with_capacity(capacity),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn get(&mut self, element_index: u64) -> Option<Hash<N>> {
+        let tick = self.next_tick();
+        let (hash, last_used) = self.entries.get_mut(&element_index)?;
+        self.recency.remove(last_used);
+        *last_used = tick;
+        self.recency.insert(tick, element_index);
+        Some(*hash)
+    }
+
+    pub(crate) fn insert(&mut self, element_index: u64, hash: Hash<N>) {
+        let tick = self.next_tick();
+        if let Some((_, last_used)) = self.entries.insert(element_index, (hash, tick)) {
+            self.recency.remove(&last_used);
+        }
+        self.recency.insert(tick, element_index);
+
+        while self.entries.len() > self.capacity {
+            let Some((_, evicted)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&evicted);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
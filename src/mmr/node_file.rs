@@ -0,0 +1,161 @@
+use std::io::Write;
+
+use crate::error::MmrError;
+use crate::store::Store;
+use crate::types::{Hash32, MmrId};
+
+use super::core::Mmr;
+use super::registry::registry_key;
+
+const NODE_FILE_MAGIC: &[u8; 8] = b"MMRNODE\0";
+const NODE_FILE_VERSION: u8 = 2;
+const NODE_FILE_CHUNK_SIZE: u64 = 4096;
+/// Magic, version, mmr id, hasher code, leaves count, elements count, root flag and root
+/// hash.
+pub(crate) const NODE_FILE_HEADER_LEN: usize = 8 + 1 + 8 + 8 + 8 + 8 + 1 + 32;
+
+/// Fixed-size header of a node file. The header is followed by every node hash in element
+/// index order, so node `i` lives at `NODE_FILE_HEADER_LEN + (i - 1) * 32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct NodeFileHeader {
+    pub(crate) mmr_id: MmrId,
+    /// Registry code of the hasher the nodes were built with, e.g. `KECCAK_HASHER_CODE`.
+    pub(crate) hasher_code: u64,
+    pub(crate) leaves_count: u64,
+    pub(crate) elements_count: u64,
+    pub(crate) root_hash: Option<Hash32>,
+}
+
+impl NodeFileHeader {
+    fn encode(&self) -> [u8; NODE_FILE_HEADER_LEN] {
+        let mut out = [0u8; NODE_FILE_HEADER_LEN];
+        out[..8].copy_from_slice(NODE_FILE_MAGIC);
+        out[8] = NODE_FILE_VERSION;
+        out[9..17].copy_from_slice(&self.mmr_id.to_be_bytes());
+        out[17..25].copy_from_slice(&self.hasher_code.to_be_bytes());
+        out[25..33].copy_from_slice(&self.leaves_count.to_be_bytes());
+        out[33..41].copy_from_slice(&self.elements_count.to_be_bytes());
+        if let Some(root_hash) = self.root_hash {
+            out[41] = 1;
+            out[42..].copy_from_slice(root_hash.as_slice());
+        }
+        out
+    }
+
+    /// Parses the header and checks that `bytes` holds exactly the nodes it announces.
+    #[cfg(feature = "mmap-store")]
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self, MmrError> {
+        let header = bytes
+            .get(..NODE_FILE_HEADER_LEN)
+            .ok_or(MmrError::InvalidNodeFile("truncated header"))?;
+        if &header[..8] != NODE_FILE_MAGIC {
+            return Err(MmrError::InvalidNodeFile("bad magic"));
+        }
+        if header[8] != NODE_FILE_VERSION {
+            return Err(MmrError::InvalidNodeFile("unsupported version"));
+        }
+
+        let read_u64 = |offset: usize| {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&header[offset..offset + 8]);
+            u64::from_be_bytes(word)
+        };
+        let mmr_id = read_u64(9);
+        let hasher_code = read_u64(17);
+        let leaves_count = read_u64(25);
+        let elements_count = read_u64(33);
+        if super::helpers::mmr_size_to_leaf_count(elements_count) != leaves_count {
+            return Err(MmrError::InvalidNodeFile(
+                "leaves count does not match elements count",
+            ));
+        }
+        let root_hash = match header[41] {
+            0 => None,
+            1 => {
+                let mut root_hash = [0u8; 32];
+                root_hash.copy_from_slice(&header[42..]);
+                Some(Hash32::new(root_hash))
+            }
+            _ => return Err(MmrError::InvalidNodeFile("bad root flag")),
+        };
+
+        let expected_len = u128::from(elements_count) * 32 + NODE_FILE_HEADER_LEN as u128;
+        if bytes.len() as u128 != expected_len {
+            return Err(MmrError::InvalidNodeFile(
+                "node section length does not match elements count",
+            ));
+        }
+
+        Ok(Self {
+            mmr_id,
+            hasher_code,
+            leaves_count,
+            elements_count,
+            root_hash,
+        })
+    }
+}
+
+impl<S: Store> Mmr<S> {
+    /// Writes a node file: a fixed header followed by every node hash, densely packed by
+    /// element index. Every node must be present in the store. `hasher_code` names the
+    /// hasher in the header so `MmapStore::open` can refuse a mismatched one; it must
+    /// match the MMR's registry entry when there is one.
+    pub async fn export_node_file<W: Write>(
+        &self,
+        writer: &mut W,
+        hasher_code: u64,
+    ) -> Result<(), MmrError> {
+        // One read, so the counts, the root and the registry entry come from the same state.
+        let keys = [
+            self.leaf_count_key(),
+            self.elements_count_key(),
+            self.root_hash_key(),
+            registry_key(self.mmr_id),
+        ];
+        let values = self.store.get_many(&keys).await?;
+        let [leaves_count, elements_count, root_hash, registered]: [Option<_>; 4] = values
+            .try_into()
+            .map_err(|_| MmrError::InvalidNodeFile("store returned a short read"))?;
+        if let Some(registered) = registered {
+            let registered = registered.expect_u64(&keys[3])?;
+            if registered != hasher_code {
+                return Err(MmrError::NodeFileHasherMismatch {
+                    expected: registered,
+                    found: hasher_code,
+                });
+            }
+        }
+        let header = NodeFileHeader {
+            mmr_id: self.mmr_id,
+            hasher_code,
+            leaves_count: leaves_count.map_or(Ok(0), |value| value.expect_u64(&keys[0]))?,
+            elements_count: elements_count.map_or(Ok(0), |value| value.expect_u64(&keys[1]))?,
+            root_hash: root_hash
+                .map(|value| value.expect_hash(&keys[2]))
+                .transpose()?,
+        };
+        writer.write_all(&header.encode())?;
+
+        let mut chunk_start = 1u64;
+        while chunk_start <= header.elements_count {
+            let chunk_end = header
+                .elements_count
+                .min(chunk_start.saturating_add(NODE_FILE_CHUNK_SIZE - 1));
+            let keys: Vec<_> = (chunk_start..=chunk_end)
+                .map(|idx| self.node_key(idx))
+                .collect();
+            let values = self.store.get_many(&keys).await?;
+            for ((idx, key), value) in (chunk_start..=chunk_end).zip(keys.iter()).zip(values) {
+                let hash = value
+                    .ok_or(MmrError::NoHashFoundForIndex(idx))?
+                    .expect_hash(key)?;
+                writer.write_all(hash.as_slice())?;
+            }
+            chunk_start = chunk_end + 1;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use std::thread;
+
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::store::{Store, StoreValue};
+use crate::types::{BatchAppendResult, Hash32, ZERO_HASH};
+
+use super::core::Mmr;
+use super::helpers::{leaf_count_to_mmr_size, map_leaf_index_to_element_index};
+use super::verify::root_from_peaks;
+
+const BULK_LOAD_CHUNK_SIZE: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfflineBuild {
+    pub nodes: Vec<Hash32>,
+    pub leaves_count: u64,
+    pub elements_count: u64,
+    pub peaks_hashes: Vec<Hash32>,
+    pub root_hash: Hash32,
+}
+
+pub fn build_offline(hasher: &dyn Hasher, leaves: &[Hash32]) -> Result<OfflineBuild, MmrError> {
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    build_offline_with_threads(hasher, leaves, threads)
+}
+
+pub fn build_offline_with_threads(
+    hasher: &dyn Hasher,
+    leaves: &[Hash32],
+    threads: usize,
+) -> Result<OfflineBuild, MmrError> {
+    let leaves_count = u64::try_from(leaves.len()).map_err(|_| MmrError::Overflow)?;
+    let elements_count = leaf_count_to_mmr_size(leaves_count);
+    let nodes_len = usize::try_from(elements_count).map_err(|_| MmrError::Overflow)?;
+    let parallel_depth = threads.max(1).next_power_of_two().trailing_zeros();
+
+    let mut nodes = vec![ZERO_HASH; nodes_len];
+    let mut peaks_hashes = Vec::new();
+    let mut remaining_leaves = leaves;
+    let mut remaining_nodes = nodes.as_mut_slice();
+    while !remaining_leaves.is_empty() {
+        let mountain_leaves = 1usize << remaining_leaves.len().ilog2();
+        let (leaves_chunk, rest_leaves) = remaining_leaves.split_at(mountain_leaves);
+        let (nodes_chunk, rest_nodes) = remaining_nodes.split_at_mut(2 * mountain_leaves - 1);
+        peaks_hashes.push(build_mountain(
+            hasher,
+            leaves_chunk,
+            nodes_chunk,
+            parallel_depth,
+        )?);
+        remaining_leaves = rest_leaves;
+        remaining_nodes = rest_nodes;
+    }
+
+    let root_hash = root_from_peaks(hasher, &peaks_hashes, elements_count)?;
+
+    Ok(OfflineBuild {
+        nodes,
+        leaves_count,
+        elements_count,
+        peaks_hashes,
+        root_hash,
+    })
+}
+
+fn build_mountain(
+    hasher: &dyn Hasher,
+    leaves: &[Hash32],
+    out: &mut [Hash32],
+    parallel_depth: u32,
+) -> Result<Hash32, MmrError> {
+    if let [leaf] = leaves {
+        out[0] = *leaf;
+        return Ok(*leaf);
+    }
+
+    let (left_leaves, right_leaves) = leaves.split_at(leaves.len() / 2);
+    let subtree_len = out.len() / 2;
+    let (left_out, rest) = out.split_at_mut(subtree_len);
+    let (right_out, root_out) = rest.split_at_mut(subtree_len);
+
+    let (left, right) = if parallel_depth > 0 {
+        thread::scope(|scope| {
+            let left =
+                scope.spawn(|| build_mountain(hasher, left_leaves, left_out, parallel_depth - 1));
+            let right = build_mountain(hasher, right_leaves, right_out, parallel_depth - 1);
+            let left = left
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            (left, right)
+        })
+    } else {
+        (
+            build_mountain(hasher, left_leaves, left_out, 0),
+            build_mountain(hasher, right_leaves, right_out, 0),
+        )
+    };
+
+    let root = hasher.hash_pair(&left?, &right?)?;
+    root_out[0] = root;
+    Ok(root)
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn bulk_load(&self, build: &OfflineBuild) -> Result<BatchAppendResult, MmrError> {
+        let lease_owner = self.ensure_writer_lease().await?;
+        if self.get_elements_count().await? != 0 {
+            return Err(MmrError::NonEmptyMmr);
+        }
+        self.size_limits.check(build.leaves_count)?;
+
+        for (chunk_index, chunk) in build.nodes.chunks(BULK_LOAD_CHUNK_SIZE).enumerate() {
+            let first_index = (chunk_index * BULK_LOAD_CHUNK_SIZE) as u64 + 1;
+            // Chunks are written in index order, so a chunk whose last node is already stored
+            // was loaded by an earlier, interrupted `bulk_load` and is skipped on rerun.
+            let last_key = self.node_key(first_index + chunk.len() as u64 - 1);
+            if let Some(value) = self.store.get(&last_key).await?
+                && Some(&value.expect_hash(&last_key)?) == chunk.last()
+            {
+                continue;
+            }
+            let entries = (first_index..)
+                .zip(chunk)
+                .map(|(idx, hash)| (self.node_key(idx), StoreValue::Hash(*hash)))
+                .collect();
+            self.store.bulk_load(entries).await?;
+        }
+
+        if self.hash_index {
+            for first_leaf in (0..build.leaves_count).step_by(BULK_LOAD_CHUNK_SIZE) {
+                let last_leaf = (first_leaf + BULK_LOAD_CHUNK_SIZE as u64).min(build.leaves_count);
+                let leaves = (first_leaf..last_leaf)
+                    .map(|leaf_index| {
+                        let element_index = map_leaf_index_to_element_index(leaf_index);
+                        (build.nodes[element_index as usize - 1], element_index)
+                    })
+                    .collect();
+                let writes = self.stage_hash_index_writes(leaves).await?;
+                self.store.set_many(writes).await?;
+            }
+        }
+
+        let committed = self
+            .compare_and_set_leased(
+                lease_owner,
+                0,
+                vec![
+                    (self.leaf_count_key(), StoreValue::U64(build.leaves_count)),
+                    (
+                        self.elements_count_key(),
+                        StoreValue::U64(build.elements_count),
+                    ),
+                    (self.root_hash_key(), StoreValue::Hash(build.root_hash)),
+                ],
+            )
+            .await?;
+        self.invalidate_cached_counts().await;
+        self.clear_node_cache();
+        if !committed {
+            return Err(MmrError::ConcurrentAppendConflict(1));
+        }
+
+        Ok(BatchAppendResult {
+            appended_count: build.leaves_count,
+            first_element_index: 1,
+            last_element_index: build.elements_count,
+            leaves_count: build.leaves_count,
+            elements_count: build.elements_count,
+            root_hash: build.root_hash,
+            root_deferred: false,
+            peaks_hashes: build.peaks_hashes.clone(),
+            previous_elements_count: 0,
+            previous_root_hash: None,
+        })
+    }
+}
@@ -0,0 +1,391 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::error::{HasherError, MmrError, StoreError, StoreOpTarget};
+use crate::hasher::Hasher;
+#[cfg(feature = "postgres-store")]
+use crate::store::PostgresStore;
+use crate::store::{DedupedKeys, Store, StoreKey, StoreValue, WriteBatch};
+use crate::types::{Hash, MmrId};
+#[cfg(feature = "postgres-store")]
+use sqlx::{Postgres, Transaction};
+
+use super::core::Mmr;
+
+/// Operation counters of one `Mmr` instance since it was created or last reset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpStats {
+    /// Leaves appended through `append`/`batch_append`.
+    pub appends: u64,
+    /// Calls made to the store.
+    pub store_round_trips: u64,
+    /// `hash_pair`, `hash_count_and_bag` and `hash_leaf` calls.
+    pub hashes: u64,
+}
+
+/// Wall-clock time spent per phase while profiling is enabled with `Mmr::with_profiling`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    pub store_reads: Duration,
+    pub hashing: Duration,
+    pub store_writes: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    StoreRead,
+    Hashing,
+    StoreWrite,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct OpCounters {
+    appends: AtomicU64,
+    store_round_trips: AtomicU64,
+    hashes: AtomicU64,
+    profiling: AtomicBool,
+    store_read_nanos: AtomicU64,
+    hashing_nanos: AtomicU64,
+    store_write_nanos: AtomicU64,
+}
+
+impl OpCounters {
+    pub(crate) fn record_appends(&self, count: u64) {
+        self.appends.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn record_round_trip(&self) {
+        self.store_round_trips.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_hash(&self) {
+        self.hashes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn start_phase(&self) -> Option<Instant> {
+        self.profiling.load(Ordering::Relaxed).then(Instant::now)
+    }
+
+    fn finish_phase(&self, phase: Phase, started: Option<Instant>) {
+        let Some(started) = started else {
+            return;
+        };
+        let nanos = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        let total = match phase {
+            Phase::StoreRead => &self.store_read_nanos,
+            Phase::Hashing => &self.hashing_nanos,
+            Phase::StoreWrite => &self.store_write_nanos,
+        };
+        total.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    fn timings(&self) -> PhaseTimings {
+        PhaseTimings {
+            store_reads: Duration::from_nanos(self.store_read_nanos.load(Ordering::Relaxed)),
+            hashing: Duration::from_nanos(self.hashing_nanos.load(Ordering::Relaxed)),
+            store_writes: Duration::from_nanos(self.store_write_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn snapshot(&self) -> OpStats {
+        OpStats {
+            appends: self.appends.load(Ordering::Relaxed),
+            store_round_trips: self.store_round_trips.load(Ordering::Relaxed),
+            hashes: self.hashes.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        self.appends.store(0, Ordering::Relaxed);
+        self.store_round_trips.store(0, Ordering::Relaxed);
+        self.hashes.store(0, Ordering::Relaxed);
+        self.store_read_nanos.store(0, Ordering::Relaxed);
+        self.hashing_nanos.store(0, Ordering::Relaxed);
+        self.store_write_nanos.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Store adapter owned by `Mmr` that counts every store call and tags its errors with
+/// the mmr id and operation. Backend-specific methods are reachable through `Deref`.
+pub(crate) struct CountedStore<S, const N: usize = 32> {
+    inner: S,
+    counters: Arc<OpCounters>,
+    mmr_id: MmrId,
+    width: PhantomData<[u8; N]>,
+}
+
+impl<S, const N: usize> CountedStore<S, N> {
+    pub(crate) fn new(inner: S, counters: Arc<OpCounters>, mmr_id: MmrId) -> Self {
+        Self {
+            inner,
+            counters,
+            mmr_id,
+            width: PhantomData,
+        }
+    }
+
+    pub(crate) fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub(crate) fn into_inner(self) -> S {
+        self.inner
+    }
+
+    pub(crate) fn counters(&self) -> &OpCounters {
+        &self.counters
+    }
+
+    async fn call<T>(
+        &self,
+        phase: Phase,
+        operation: &'static str,
+        target: impl FnOnce() -> StoreOpTarget,
+        call: impl Future<Output = Result<T, StoreError>>,
+    ) -> Result<T, MmrError> {
+        self.counters.record_round_trip();
+        let started = self.counters.start_phase();
+        let out = call.await;
+        self.counters.finish_phase(phase, started);
+        out.map_err(|source| MmrError::StoreOp {
+            mmr_id: self.mmr_id,
+            operation,
+            target: target(),
+            source: Box::new(source),
+        })
+    }
+}
+
+impl<S, const N: usize> Deref for CountedStore<S, N> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.inner
+    }
+}
+
+/// The `Store` surface `Mmr` uses. Failures come back as `MmrError::StoreOp`, so the
+/// original `StoreError` variant stays matchable next to the mmr id and operation.
+impl<S: Store<N>, const N: usize> CountedStore<S, N> {
+    pub(crate) async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue<N>>, MmrError> {
+        self.call(
+            Phase::StoreRead,
+            "get",
+            || StoreOpTarget::Key(key.clone()),
+            self.inner.get(key),
+        )
+        .await
+    }
+
+    pub(crate) async fn set(&self, key: StoreKey, value: StoreValue<N>) -> Result<(), MmrError> {
+        let target_key = key.clone();
+        self.call(
+            Phase::StoreWrite,
+            "set",
+            || StoreOpTarget::Key(target_key),
+            self.inner.set(key, value),
+        )
+        .await
+    }
+
+    pub(crate) async fn set_many(
+        &self,
+        entries: Vec<(StoreKey, StoreValue<N>)>,
+    ) -> Result<(), MmrError> {
+        let batch_size = entries.len();
+        self.call(
+            Phase::StoreWrite,
+            "set_many",
+            || StoreOpTarget::Batch(batch_size),
+            self.inner.set_many(entries),
+        )
+        .await
+    }
+
+    pub(crate) async fn bulk_load(
+        &self,
+        entries: Vec<(StoreKey, StoreValue<N>)>,
+    ) -> Result<(), MmrError> {
+        let batch_size = entries.len();
+        self.call(
+            Phase::StoreWrite,
+            "bulk_load",
+            || StoreOpTarget::Batch(batch_size),
+            self.inner.bulk_load(entries),
+        )
+        .await
+    }
+
+    pub(crate) async fn get_many(
+        &self,
+        keys: &[StoreKey],
+    ) -> Result<Vec<Option<StoreValue<N>>>, MmrError> {
+        let deduped = DedupedKeys::new(keys);
+        let requested = deduped.as_ref().map_or(keys, |deduped| &deduped.unique);
+        let values = self
+            .call(
+                Phase::StoreRead,
+                "get_many",
+                || StoreOpTarget::Batch(requested.len()),
+                self.inner.get_many(requested),
+            )
+            .await?;
+        Ok(match deduped {
+            Some(deduped) => deduped.fan_out(values)?,
+            None => values,
+        })
+    }
+
+    pub(crate) async fn compare_and_set_many(
+        &self,
+        counter_key: &StoreKey,
+        expected_counter: u64,
+        entries: Vec<(StoreKey, StoreValue<N>)>,
+    ) -> Result<bool, MmrError> {
+        let batch_size = entries.len();
+        self.call(
+            Phase::StoreWrite,
+            "compare_and_set_many",
+            || StoreOpTarget::Batch(batch_size),
+            self.inner
+                .compare_and_set_many(counter_key, expected_counter, entries),
+        )
+        .await
+    }
+
+    pub(crate) async fn write_batch(&self, batch: WriteBatch<N>) -> Result<bool, MmrError> {
+        let batch_size = batch.len();
+        self.call(
+            Phase::StoreWrite,
+            "write_batch",
+            || StoreOpTarget::Batch(batch_size),
+            self.inner.write_batch(batch),
+        )
+        .await
+    }
+
+    pub(crate) async fn scan_keys(&self, mmr_id: MmrId) -> Result<Vec<StoreKey>, MmrError> {
+        self.call(
+            Phase::StoreRead,
+            "scan_keys",
+            || StoreOpTarget::None,
+            self.inner.scan_keys(mmr_id),
+        )
+        .await
+    }
+}
+
+#[cfg(feature = "postgres-store")]
+impl CountedStore<Arc<PostgresStore>> {
+    pub(crate) async fn get_many_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        keys: &[StoreKey],
+    ) -> Result<Vec<Option<StoreValue>>, MmrError> {
+        self.call(
+            Phase::StoreRead,
+            "get_many_in_tx",
+            || StoreOpTarget::Batch(keys.len()),
+            self.inner.get_many_in_tx(tx, keys),
+        )
+        .await
+    }
+
+    pub(crate) async fn write_batch_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        batch: WriteBatch,
+    ) -> Result<bool, MmrError> {
+        let batch_size = batch.len();
+        self.call(
+            Phase::StoreWrite,
+            "write_batch_in_tx",
+            || StoreOpTarget::Batch(batch_size),
+            self.inner.write_batch_in_tx(tx, batch),
+        )
+        .await
+    }
+}
+
+/// Hasher adapter owned by `Mmr` that counts hash computations.
+#[derive(Clone)]
+pub(crate) struct CountingHasher<const N: usize = 32> {
+    inner: Arc<dyn Hasher<N>>,
+    counters: Arc<OpCounters>,
+}
+
+impl<const N: usize> CountingHasher<N> {
+    pub(crate) fn new(inner: Arc<dyn Hasher<N>>, counters: Arc<OpCounters>) -> Self {
+        Self { inner, counters }
+    }
+
+    /// The wrapped hasher, for handing to other `Mmr` instances.
+    pub(crate) fn shared(&self) -> Arc<dyn Hasher<N>> {
+        self.inner.clone()
+    }
+
+    fn timed<T>(&self, hash: impl FnOnce() -> T) -> T {
+        self.counters.record_hash();
+        let started = self.counters.start_phase();
+        let out = hash();
+        self.counters.finish_phase(Phase::Hashing, started);
+        out
+    }
+}
+
+impl<const N: usize> AsRef<dyn Hasher<N>> for CountingHasher<N> {
+    fn as_ref(&self) -> &(dyn Hasher<N> + 'static) {
+        self
+    }
+}
+
+impl<const N: usize> Hasher<N> for CountingHasher<N> {
+    fn hash_pair(&self, left: &Hash<N>, right: &Hash<N>) -> Result<Hash<N>, HasherError> {
+        self.timed(|| self.inner.hash_pair(left, right))
+    }
+
+    fn hash_count_and_bag(
+        &self,
+        elements_count: u64,
+        bag: &Hash<N>,
+    ) -> Result<Hash<N>, HasherError> {
+        self.timed(|| self.inner.hash_count_and_bag(elements_count, bag))
+    }
+
+    fn hash_bytes(&self, data: &[u8]) -> Result<Hash<N>, HasherError> {
+        self.timed(|| self.inner.hash_bytes(data))
+    }
+
+    fn hash_leaf(&self, data: &[u8]) -> Result<Hash<N>, HasherError> {
+        self.timed(|| self.inner.hash_leaf(data))
+    }
+}
+
+impl<S: Store<N>, const N: usize> Mmr<S, N> {
+    pub fn op_stats(&self) -> OpStats {
+        self.store.counters().snapshot()
+    }
+
+    pub fn reset_op_stats(&self) {
+        self.store.counters().reset();
+    }
+
+    /// Records time spent in store reads, hashing and store writes, readable through
+    /// `phase_timings`.
+    pub fn with_profiling(self) -> Self {
+        self.store
+            .counters()
+            .profiling
+            .store(true, Ordering::Relaxed);
+        self
+    }
+
+    /// Zero unless profiling is enabled; cleared by `reset_op_stats`.
+    pub fn phase_timings(&self) -> PhaseTimings {
+        self.store.counters().timings()
+    }
+}
@@ -0,0 +1,157 @@
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::store::Store;
+use crate::types::{Hash32, MerkleProof, Proof};
+
+use super::core::Mmr;
+use super::helpers::{element_index_to_leaf_index, find_peaks, mmr_size_to_leaf_count};
+
+struct PaddedTree<'a> {
+    hasher: &'a dyn Hasher,
+    leaves_count: u64,
+    height: u32,
+    mountains: Vec<(u64, u32, Hash32)>,
+    fillers: Vec<Hash32>,
+}
+
+impl<'a> PaddedTree<'a> {
+    fn new(
+        hasher: &'a dyn Hasher,
+        elements_count: u64,
+        peaks_hashes: &[Hash32],
+        filler: &Hash32,
+    ) -> Result<Self, MmrError> {
+        if elements_count == 0 || find_peaks(elements_count).is_empty() {
+            return Err(MmrError::InvalidElementCount);
+        }
+        let leaves_count = mmr_size_to_leaf_count(elements_count);
+        if peaks_hashes.len() != leaves_count.count_ones() as usize {
+            return Err(MmrError::InvalidPeaksCount);
+        }
+
+        let mut mountains = Vec::with_capacity(peaks_hashes.len());
+        let mut start = 0u64;
+        let heights = (0..u64::BITS)
+            .rev()
+            .filter(|bit| leaves_count >> bit & 1 == 1);
+        for (height, peak_hash) in heights.zip(peaks_hashes) {
+            mountains.push((start, height, *peak_hash));
+            start += 1 << height;
+        }
+
+        let height = match leaves_count {
+            1 => 0,
+            _ => u64::BITS - (leaves_count - 1).leading_zeros(),
+        };
+        let mut fillers = vec![*filler];
+        for level in 0..height as usize {
+            fillers.push(hasher.hash_pair(&fillers[level], &fillers[level])?);
+        }
+
+        Ok(Self {
+            hasher,
+            leaves_count,
+            height,
+            mountains,
+            fillers,
+        })
+    }
+
+    fn node(&self, level: u32, start: u64) -> Result<Hash32, MmrError> {
+        if start >= self.leaves_count {
+            return Ok(self.fillers[level as usize]);
+        }
+        if let Some((_, _, hash)) = self
+            .mountains
+            .iter()
+            .find(|(mountain_start, height, _)| *mountain_start == start && *height == level)
+        {
+            return Ok(*hash);
+        }
+        if level == 0 {
+            return Err(MmrError::NoHashFoundForIndex(start));
+        }
+
+        let left = self.node(level - 1, start)?;
+        let right = self.node(level - 1, start + (1 << (level - 1)))?;
+        Ok(self.hasher.hash_pair(&left, &right)?)
+    }
+}
+
+pub fn padded_merkle_root(
+    hasher: &dyn Hasher,
+    elements_count: u64,
+    peaks_hashes: &[Hash32],
+    filler: &Hash32,
+) -> Result<Hash32, MmrError> {
+    let tree = PaddedTree::new(hasher, elements_count, peaks_hashes, filler)?;
+    tree.node(tree.height, 0)
+}
+
+pub fn verify_merkle_proof(
+    hasher: &dyn Hasher,
+    proof: &MerkleProof,
+    leaf: Hash32,
+    root_hash: &Hash32,
+) -> Result<bool, MmrError> {
+    let mut hash = leaf;
+    for (level, sibling_hash) in proof.siblings_hashes.iter().enumerate() {
+        hash = if proof.leaf_index.checked_shr(level as u32).unwrap_or(0) & 1 == 1 {
+            hasher.hash_pair(sibling_hash, &hash)?
+        } else {
+            hasher.hash_pair(&hash, sibling_hash)?
+        };
+    }
+
+    Ok(&hash == root_hash)
+}
+
+impl Proof {
+    pub fn to_padded_merkle_proof(
+        &self,
+        hasher: &dyn Hasher,
+        filler: &Hash32,
+    ) -> Result<MerkleProof, MmrError> {
+        let tree = PaddedTree::new(hasher, self.elements_count, &self.peaks_hashes, filler)?;
+        if self.element_index == 0 || self.element_index > self.elements_count {
+            return Err(MmrError::InvalidElementIndex);
+        }
+        let leaf_index = element_index_to_leaf_index(self.element_index)?;
+        let (_, mountain_height, _) = tree
+            .mountains
+            .iter()
+            .rev()
+            .find(|(start, _, _)| *start <= leaf_index)
+            .copied()
+            .ok_or(MmrError::InvalidElementIndex)?;
+        if self.siblings_hashes.len() != mountain_height as usize {
+            return Err(MmrError::InvalidSiblingsCount);
+        }
+
+        let mut siblings_hashes = self.siblings_hashes.clone();
+        for level in mountain_height..tree.height {
+            let sibling_start = (leaf_index >> level ^ 1) << level;
+            siblings_hashes.push(tree.node(level, sibling_start)?);
+        }
+
+        Ok(MerkleProof {
+            leaf_index,
+            siblings_hashes,
+        })
+    }
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn padded_merkle_root(
+        &self,
+        filler: &Hash32,
+        elements_count: Option<u64>,
+    ) -> Result<Hash32, MmrError> {
+        let tree_size = match elements_count {
+            Some(count) => count,
+            None => self.get_elements_count().await?,
+        };
+        let peaks_hashes = self.get_peaks(Some(tree_size)).await?;
+        padded_merkle_root(self.hasher.as_ref(), tree_size, &peaks_hashes, filler)
+    }
+}
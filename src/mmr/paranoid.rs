@@ -0,0 +1,104 @@
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::store::Store;
+use crate::types::{Hash, Proof};
+
+use super::core::Mmr;
+use super::verify::{bag_peaks, compute_peak_from_proof};
+
+impl<S: Store<N>, const N: usize> Mmr<S, N> {
+    /// Cross-checks nodes read for proofs before returning them: each proof's element and
+    /// siblings must hash up to its peak, and peaks must hash to the stored root. A mismatch
+    /// fails with `CorruptedProofPath` or `CorruptedPeaks`.
+    pub fn with_paranoid_reads(mut self, enabled: bool) -> Self {
+        self.paranoid_reads = enabled;
+        self
+    }
+
+    pub(crate) fn check_proof_path(&self, proof: &Proof<N>) -> Result<(), MmrError> {
+        if !self.paranoid_reads {
+            return Ok(());
+        }
+        proof_path_matches(self.hasher.as_ref(), proof)
+    }
+
+    /// `check_proof_path` for a whole chunk of proofs. With the `rayon` feature the
+    /// re-hashing runs on the rayon pool, so large chunks do not stall the async executor.
+    pub(crate) async fn check_proof_paths(
+        &self,
+        proofs: Vec<Proof<N>>,
+    ) -> Result<Vec<Proof<N>>, MmrError> {
+        if !self.paranoid_reads {
+            return Ok(proofs);
+        }
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            let hasher = self.hasher.clone();
+            let (tx, rx) = futures::channel::oneshot::channel();
+            rayon::spawn(move || {
+                let checked = proofs
+                    .par_iter()
+                    .try_for_each(|proof| proof_path_matches(&hasher, proof));
+                let _ = tx.send(checked.map(|()| proofs));
+            });
+            rx.await
+                .expect("rayon proof path check finished without a result")
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            for proof in &proofs {
+                proof_path_matches(self.hasher.as_ref(), proof)?;
+            }
+            Ok(proofs)
+        }
+    }
+
+    /// Compares `peaks_hashes` with the stored root when that root belongs to `tree_size`.
+    /// A lazy-root MMR has no stored root until `finalize_root`, so there is nothing to check.
+    pub(crate) async fn check_peaks(
+        &self,
+        tree_size: u64,
+        peaks_hashes: &[Hash<N>],
+    ) -> Result<(), MmrError> {
+        if !self.paranoid_reads || tree_size == 0 {
+            return Ok(());
+        }
+
+        let keys = [self.elements_count_key(), self.root_hash_key()];
+        let mut values = self.store.get_many(&keys).await?.into_iter();
+        let elements_count = match values.next().flatten() {
+            Some(value) => value.expect_u64(&keys[0])?,
+            None => 0,
+        };
+        let Some(root_hash) = values.next().flatten() else {
+            return Ok(());
+        };
+        let root_hash = root_hash.expect_hash(&keys[1])?;
+        if elements_count != tree_size {
+            return Ok(());
+        }
+
+        let bag = bag_peaks(self.hasher.as_ref(), peaks_hashes)?;
+        if self.calculate_root_hash(&bag, tree_size)? != root_hash {
+            return Err(MmrError::CorruptedPeaks(tree_size));
+        }
+        Ok(())
+    }
+}
+
+fn proof_path_matches<const N: usize>(
+    hasher: &dyn Hasher<N>,
+    proof: &Proof<N>,
+) -> Result<(), MmrError> {
+    let climbed = compute_peak_from_proof(hasher, proof, proof.element_hash, proof.elements_count)?;
+    match climbed {
+        Some((peak_index, peak_hash)) if proof.peaks_hashes.get(peak_index) == Some(&peak_hash) => {
+            Ok(())
+        }
+        _ => Err(MmrError::CorruptedProofPath(proof.element_index)),
+    }
+}
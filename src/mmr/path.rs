@@ -0,0 +1,79 @@
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::store::Store;
+use crate::types::{ElementIndex, Hash32, PathProof, Proof};
+
+use super::core::Mmr;
+use super::helpers::{
+    element_index_to_leaf_index, get_peak_info, leaf_count_to_peaks_count, mmr_size_to_leaf_count,
+};
+
+impl Proof {
+    pub fn to_path_proof(&self) -> Result<PathProof, MmrError> {
+        if self.element_index == 0 || self.element_index > self.elements_count {
+            return Err(MmrError::InvalidElementIndex);
+        }
+        let expected_peaks =
+            leaf_count_to_peaks_count(mmr_size_to_leaf_count(self.elements_count)) as usize;
+        if self.peaks_hashes.len() != expected_peaks {
+            return Err(MmrError::InvalidPeaksCount);
+        }
+
+        let (peak_index, peak_height) = get_peak_info(self.elements_count, self.element_index)?;
+        if self.siblings_hashes.len() != peak_height {
+            return Err(MmrError::InvalidSiblingsCount);
+        }
+
+        let leaf_index = element_index_to_leaf_index(self.element_index)?;
+        let directions = match 1u64.checked_shl(peak_height as u32) {
+            Some(bound) => leaf_index & (bound - 1),
+            None => leaf_index,
+        };
+
+        Ok(PathProof {
+            element_index: self.element_index,
+            element_hash: self.element_hash,
+            siblings_hashes: self.siblings_hashes.clone(),
+            directions,
+            peak_index: peak_index as u64,
+            peaks_hashes: self.peaks_hashes.clone(),
+            elements_count: self.elements_count,
+        })
+    }
+}
+
+pub fn verify_path_proof(
+    hasher: &dyn Hasher,
+    proof: &PathProof,
+    element_value: Hash32,
+) -> Result<bool, MmrError> {
+    if proof.siblings_hashes.len() > 64 {
+        return Err(MmrError::InvalidSiblingsCount);
+    }
+
+    let mut hash = element_value;
+    for (bit, sibling_hash) in proof.siblings_hashes.iter().enumerate() {
+        hash = if proof.directions >> bit & 1 == 1 {
+            hasher.hash_pair(sibling_hash, &hash)?
+        } else {
+            hasher.hash_pair(&hash, sibling_hash)?
+        };
+    }
+
+    let peak = usize::try_from(proof.peak_index)
+        .ok()
+        .and_then(|peak_index| proof.peaks_hashes.get(peak_index));
+    Ok(peak == Some(&hash))
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn get_path_proof(
+        &self,
+        element_index: ElementIndex,
+        elements_count: Option<u64>,
+    ) -> Result<PathProof, MmrError> {
+        self.get_proof(element_index, elements_count)
+            .await?
+            .to_path_proof()
+    }
+}
@@ -0,0 +1,69 @@
+use crate::error::MmrError;
+use crate::store::Store;
+use crate::types::{ElementIndex, Hash32, Proof};
+
+use super::core::Mmr;
+use super::helpers::mmr_size_to_leaf_count;
+
+pub struct PinnedMmr<'a, S: Store> {
+    mmr: &'a Mmr<S>,
+    elements_count: u64,
+}
+
+impl<'a, S: Store> PinnedMmr<'a, S> {
+    pub fn elements_count(&self) -> u64 {
+        self.elements_count
+    }
+
+    pub fn leaves_count(&self) -> u64 {
+        mmr_size_to_leaf_count(self.elements_count)
+    }
+
+    pub async fn get_proof(&self, element_index: ElementIndex) -> Result<Proof, MmrError> {
+        self.mmr
+            .get_proof(element_index, Some(self.elements_count))
+            .await
+    }
+
+    pub async fn get_proofs(
+        &self,
+        element_indices: &[ElementIndex],
+    ) -> Result<Vec<Proof>, MmrError> {
+        self.mmr
+            .get_proofs(element_indices, Some(self.elements_count))
+            .await
+    }
+
+    pub async fn get_peaks(&self) -> Result<Vec<Hash32>, MmrError> {
+        self.mmr.get_peaks(Some(self.elements_count)).await
+    }
+
+    pub async fn get_root_hash(&self) -> Result<Hash32, MmrError> {
+        let bag = self.mmr.bag_the_peaks(Some(self.elements_count)).await?;
+        self.mmr.calculate_root_hash(&bag, self.elements_count)
+    }
+
+    pub async fn verify_proof(
+        &self,
+        proof: &Proof,
+        element_value: Hash32,
+    ) -> Result<bool, MmrError> {
+        self.mmr
+            .verify_proof(proof, element_value, Some(self.elements_count))
+            .await
+    }
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn at_current_size(&self) -> Result<PinnedMmr<'_, S>, MmrError> {
+        let elements_count = self.get_elements_count().await?;
+        Ok(self.at_size(elements_count))
+    }
+
+    pub fn at_size(&self, elements_count: u64) -> PinnedMmr<'_, S> {
+        PinnedMmr {
+            mmr: self,
+            elements_count,
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use crate::error::MmrError;
+use crate::store::{Store, StoreKey};
+use crate::types::{Hash32, Proof};
+
+use super::core::Mmr;
+use super::helpers::{find_peaks, find_siblings};
+
+impl Proof {
+    pub fn update(
+        &self,
+        elements_count: u64,
+        appended_nodes: &BTreeMap<u64, Hash32>,
+    ) -> Result<Proof, MmrError> {
+        let (sibling_indices, peak_indices) = self.update_indices(elements_count)?;
+
+        let old_peak_indices = find_peaks(self.elements_count);
+        if old_peak_indices.len() != self.peaks_hashes.len() {
+            return Err(MmrError::InvalidPeaksCountForElements);
+        }
+        let old_peaks: BTreeMap<u64, Hash32> = old_peak_indices
+            .into_iter()
+            .zip(self.peaks_hashes.iter().copied())
+            .collect();
+        let resolve = |index: u64| {
+            old_peaks
+                .get(&index)
+                .or_else(|| appended_nodes.get(&index))
+                .copied()
+                .ok_or(MmrError::NoHashFoundForIndex(index))
+        };
+
+        let mut siblings_hashes = self.siblings_hashes.clone();
+        for index in sibling_indices {
+            siblings_hashes.push(resolve(index)?);
+        }
+        let peaks_hashes = peak_indices
+            .into_iter()
+            .map(resolve)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Proof {
+            element_index: self.element_index,
+            element_hash: self.element_hash,
+            siblings_hashes,
+            peaks_hashes,
+            elements_count,
+            mmr_id: self.mmr_id,
+        })
+    }
+
+    pub(crate) fn update_indices(
+        &self,
+        elements_count: u64,
+    ) -> Result<(Vec<u64>, Vec<u64>), MmrError> {
+        if elements_count < self.elements_count {
+            return Err(MmrError::InvalidElementCount);
+        }
+        let peak_indices = find_peaks(elements_count);
+        if peak_indices.is_empty() {
+            return Err(MmrError::InvalidElementCount);
+        }
+
+        let mut sibling_indices = find_siblings(self.element_index, elements_count)?;
+        if sibling_indices.len() < self.siblings_hashes.len() {
+            return Err(MmrError::InvalidSiblingsCount);
+        }
+        sibling_indices.drain(..self.siblings_hashes.len());
+
+        Ok((sibling_indices, peak_indices))
+    }
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn refresh_proof(
+        &self,
+        proof: &Proof,
+        elements_count: Option<u64>,
+    ) -> Result<Proof, MmrError> {
+        let tree_size = match elements_count {
+            Some(count) => count,
+            None => self.get_elements_count().await?,
+        };
+        let (sibling_indices, peak_indices) = proof.update_indices(tree_size)?;
+
+        let keys: Vec<StoreKey> = sibling_indices
+            .into_iter()
+            .chain(peak_indices)
+            .filter(|index| *index > proof.elements_count)
+            .map(|index| self.node_key(index))
+            .collect();
+        let values = self.store.get_many(&keys).await?;
+
+        let mut appended_nodes = BTreeMap::new();
+        for (key, value) in keys.iter().zip(values) {
+            if let Some(value) = value {
+                appended_nodes.insert(key.index, value.expect_hash(key)?);
+            }
+        }
+
+        proof.update(tree_size, &appended_nodes)
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tiny_keccak::{Hasher as TinyHasher, Keccak};
+
+use crate::error::MmrError;
+use crate::hasher::{Hasher, KeccakHasher, PoseidonHasher};
+use crate::store::{ID_ALLOCATOR_MMR_ID, KeyKind, Store, StoreKey, StoreValue, WriteBatch};
+use crate::types::{BatchAppendResult, Hash32, MmrId};
+
+use super::core::{MAX_APPEND_ATTEMPTS, Mmr};
+
+pub const KECCAK_HASHER_CODE: u64 = 1;
+pub const POSEIDON_HASHER_CODE: u64 = 2;
+
+const MAX_CREATE_ATTEMPTS: u32 = 5;
+
+static NEXT_REGISTRY_ID: AtomicU64 = AtomicU64::new(1);
+
+pub struct MmrRegistry<S: Store + Clone> {
+    store: S,
+    hashers: BTreeMap<u64, Arc<dyn Hasher>>,
+    registry_id: u64,
+}
+
+impl<S: Store + Clone> MmrRegistry<S> {
+    pub fn new(store: S) -> Self {
+        let mut hashers: BTreeMap<u64, Arc<dyn Hasher>> = BTreeMap::new();
+        hashers.insert(KECCAK_HASHER_CODE, Arc::new(KeccakHasher::new()));
+        hashers.insert(POSEIDON_HASHER_CODE, Arc::new(PoseidonHasher::new()));
+        Self {
+            store,
+            hashers,
+            registry_id: NEXT_REGISTRY_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    pub fn with_hasher(mut self, code: u64, hasher: Arc<dyn Hasher>) -> Result<Self, MmrError> {
+        if code == 0 {
+            return Err(MmrError::ReservedHasherCode);
+        }
+        self.hashers.insert(code, hasher);
+        Ok(self)
+    }
+
+    pub async fn list(&self) -> Result<Vec<MmrId>, MmrError> {
+        let mut mmr_ids = self.store.list_mmr_ids().await?;
+        mmr_ids.retain(|mmr_id| *mmr_id != ID_ALLOCATOR_MMR_ID);
+        Ok(mmr_ids)
+    }
+
+    pub async fn hasher_code(&self, mmr_id: MmrId) -> Result<Option<u64>, MmrError> {
+        let key = registry_key(mmr_id);
+        match self.store.get(&key).await? {
+            Some(value) => Ok(Some(value.expect_u64(&key)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn open(&self, mmr_id: MmrId) -> Result<Mmr<S>, MmrError> {
+        let code = self
+            .hasher_code(mmr_id)
+            .await?
+            .ok_or(MmrError::MmrNotFound(mmr_id))?;
+        let hasher = self.hasher(code)?;
+        Ok(self.bind(Mmr::new(self.store.clone(), hasher, Some(mmr_id))?))
+    }
+
+    pub async fn create(&self, hasher_code: u64) -> Result<Mmr<S>, MmrError> {
+        let hasher = self.hasher(hasher_code)?;
+        for _ in 0..MAX_CREATE_ATTEMPTS {
+            let mmr_id = self.store.allocate_mmr_id().await?;
+
+            let key = registry_key(mmr_id);
+            let claimed = self
+                .store
+                .compare_and_set_many(&key, 0, vec![(key.clone(), StoreValue::U64(hasher_code))])
+                .await?;
+            if claimed {
+                return Ok(self.bind(Mmr::new(self.store.clone(), hasher, Some(mmr_id))?));
+            }
+        }
+
+        Err(MmrError::CreateConflict(MAX_CREATE_ATTEMPTS))
+    }
+
+    /// Appends each batch to its MMR and stores the writes of all of them with a single
+    /// `write_batch` guarded on every MMR's element count, so either every append lands or
+    /// none does. A concurrent append to any of the MMRs restages the whole call, up to a
+    /// fixed number of attempts. The MMRs must have been opened from this registry.
+    pub async fn append_to_many(
+        &self,
+        batches: &[(&Mmr<S>, &[Hash32])],
+    ) -> Result<Vec<BatchAppendResult>, MmrError> {
+        let mut order: Vec<usize> = (0..batches.len()).collect();
+        order.sort_by_key(|&position| batches[position].0.mmr_id);
+        for pair in order.windows(2) {
+            let mmr_id = batches[pair[0]].0.mmr_id;
+            if mmr_id == batches[pair[1]].0.mmr_id {
+                return Err(MmrError::DuplicateMmrInBatch(mmr_id));
+            }
+        }
+        for (mmr, values) in batches {
+            if mmr.registry_id != Some(self.registry_id) {
+                return Err(MmrError::ForeignRegistryMmr(mmr.mmr_id));
+            }
+            mmr.check_append(values)?;
+        }
+
+        for _ in 0..MAX_APPEND_ATTEMPTS {
+            // Lock in mmr id order so overlapping calls cannot deadlock.
+            let mut staged = Vec::with_capacity(order.len());
+            for &position in &order {
+                let (mmr, values) = batches[position];
+                match mmr.stage_append(values).await {
+                    Ok(append) => staged.push((position, append)),
+                    Err(err) => {
+                        staged.into_iter().for_each(|(_, append)| append.discard());
+                        return Err(err);
+                    }
+                }
+            }
+
+            let mut batch = WriteBatch::new();
+            for (position, append) in &mut staged {
+                let mmr = batches[*position].0;
+                batch = batch
+                    .guard(mmr.elements_count_key(), append.expected_elements_count())
+                    .set_many(std::mem::take(&mut append.staged_writes))
+                    .delete_many(std::mem::take(&mut append.staged_deletes));
+                if let Some((owner_key, owner)) = mmr.lease_guard(append.lease_owner) {
+                    batch = batch.guard(owner_key, owner);
+                }
+            }
+            match self.store.write_batch(batch).await {
+                Ok(true) => {
+                    let mut results = vec![None; batches.len()];
+                    for (position, append) in staged {
+                        results[position] = Some(batches[position].0.finish_staged_append(append));
+                    }
+                    return Ok(results.into_iter().flatten().collect());
+                }
+                Ok(false) => staged.into_iter().for_each(|(_, append)| append.discard()),
+                Err(err) => {
+                    staged.into_iter().for_each(|(_, append)| append.discard());
+                    return Err(err.into());
+                }
+            }
+        }
+
+        Err(MmrError::ConcurrentAppendConflict(MAX_APPEND_ATTEMPTS))
+    }
+
+    pub async fn delete(&self, mmr_id: MmrId) -> Result<(), MmrError> {
+        let mmr = self.open(mmr_id).await?;
+        mmr.destroy().await
+    }
+
+    fn bind(&self, mut mmr: Mmr<S>) -> Mmr<S> {
+        mmr.registry_id = Some(self.registry_id);
+        mmr
+    }
+
+    fn hasher(&self, code: u64) -> Result<Arc<dyn Hasher>, MmrError> {
+        self.hashers
+            .get(&code)
+            .cloned()
+            .ok_or(MmrError::UnknownHasher(code))
+    }
+}
+
+pub(crate) fn registry_key(mmr_id: MmrId) -> StoreKey {
+    StoreKey::metadata(mmr_id, KeyKind::RegistryEntry)
+}
+
+pub fn derive_mmr_id(namespace: &[&str]) -> MmrId {
+    let mut keccak = Keccak::v256();
+    for part in namespace {
+        keccak.update(&(part.len() as u64).to_be_bytes());
+        keccak.update(part.as_bytes());
+    }
+    let mut digest = [0u8; 32];
+    keccak.finalize(&mut digest);
+
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    // Id 0 holds the allocator counter, so a namespace hashing to it takes id 1.
+    (u64::from_be_bytes(prefix) & (i64::MAX as u64)).max(ID_ALLOCATOR_MMR_ID + 1)
+}
@@ -0,0 +1,217 @@
+#[cfg(feature = "ssz")]
+use sha2::{Digest, Sha256};
+
+use crate::error::MmrError;
+use crate::store::Store;
+use crate::types::{Hash32, PeaksCommitment, Proof};
+
+use super::core::Mmr;
+
+pub const SSZ_MAX_HASHES: usize = 64;
+
+const PROOF_FIXED_LEN: usize = 8 + 32 + 4 + 4 + 8 + 4;
+const PEAKS_COMMITMENT_FIXED_LEN: usize = 8 + 4;
+
+impl Proof {
+    /// Container `{ element_index: uint64, element_hash: Bytes32, siblings_hashes:
+    /// List[Bytes32, 64], peaks_hashes: List[Bytes32, 64], elements_count: uint64, mmr_id:
+    /// List[uint64, 1] }`, with an empty `mmr_id` list for unbound proofs.
+    pub fn to_ssz_bytes(&self) -> Result<Vec<u8>, MmrError> {
+        let siblings_offset = PROOF_FIXED_LEN;
+        let peaks_offset = siblings_offset + ssz_hashes_len(&self.siblings_hashes)?;
+        let mmr_id_offset = peaks_offset + ssz_hashes_len(&self.peaks_hashes)?;
+        let mut bytes = Vec::with_capacity(mmr_id_offset + 8);
+        bytes.extend_from_slice(&self.element_index.to_le_bytes());
+        bytes.extend_from_slice(self.element_hash.as_slice());
+        bytes.extend_from_slice(&ssz_offset(siblings_offset)?);
+        bytes.extend_from_slice(&ssz_offset(peaks_offset)?);
+        bytes.extend_from_slice(&self.elements_count.to_le_bytes());
+        bytes.extend_from_slice(&ssz_offset(mmr_id_offset)?);
+        bytes.extend(self.siblings_hashes.iter().flat_map(|hash| hash.0));
+        bytes.extend(self.peaks_hashes.iter().flat_map(|hash| hash.0));
+        if let Some(mmr_id) = self.mmr_id {
+            bytes.extend_from_slice(&mmr_id.to_le_bytes());
+        }
+        Ok(bytes)
+    }
+
+    pub fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, MmrError> {
+        if bytes.len() < PROOF_FIXED_LEN {
+            return Err(MmrError::InvalidSszEncoding(
+                "proof is shorter than its fixed part",
+            ));
+        }
+
+        let siblings_offset = read_offset(bytes, 40);
+        let peaks_offset = read_offset(bytes, 44);
+        let mmr_id_offset = read_offset(bytes, 56);
+        if siblings_offset != PROOF_FIXED_LEN
+            || peaks_offset < siblings_offset
+            || mmr_id_offset < peaks_offset
+            || mmr_id_offset > bytes.len()
+        {
+            return Err(MmrError::InvalidSszEncoding("invalid list offsets"));
+        }
+        let mmr_id = match bytes.len() - mmr_id_offset {
+            0 => None,
+            8 => Some(read_u64(bytes, mmr_id_offset)),
+            _ => return Err(MmrError::InvalidSszEncoding("invalid mmr id list length")),
+        };
+
+        Ok(Self {
+            element_index: read_u64(bytes, 0),
+            element_hash: read_hash(&bytes[8..40]),
+            siblings_hashes: read_hashes(&bytes[siblings_offset..peaks_offset])?,
+            peaks_hashes: read_hashes(&bytes[peaks_offset..mmr_id_offset])?,
+            elements_count: read_u64(bytes, 48),
+            mmr_id,
+        })
+    }
+
+    #[cfg(feature = "ssz")]
+    pub fn hash_tree_root(&self) -> Result<Hash32, MmrError> {
+        Ok(merkleize(
+            &[
+                u64_chunk(self.element_index),
+                self.element_hash,
+                hashes_list_root(&self.siblings_hashes)?,
+                hashes_list_root(&self.peaks_hashes)?,
+                u64_chunk(self.elements_count),
+                hash_pair(
+                    &self.mmr_id.map(u64_chunk).unwrap_or_default(),
+                    &u64_chunk(u64::from(self.mmr_id.is_some())),
+                ),
+            ],
+            8,
+        ))
+    }
+}
+
+impl PeaksCommitment {
+    pub fn to_ssz_bytes(&self) -> Result<Vec<u8>, MmrError> {
+        let mut bytes =
+            Vec::with_capacity(PEAKS_COMMITMENT_FIXED_LEN + ssz_hashes_len(&self.peaks_hashes)?);
+        bytes.extend_from_slice(&self.elements_count.to_le_bytes());
+        bytes.extend_from_slice(&ssz_offset(PEAKS_COMMITMENT_FIXED_LEN)?);
+        bytes.extend(self.peaks_hashes.iter().flat_map(|hash| hash.0));
+        Ok(bytes)
+    }
+
+    pub fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, MmrError> {
+        if bytes.len() < PEAKS_COMMITMENT_FIXED_LEN {
+            return Err(MmrError::InvalidSszEncoding(
+                "peaks commitment is shorter than its fixed part",
+            ));
+        }
+        if read_offset(bytes, 8) != PEAKS_COMMITMENT_FIXED_LEN {
+            return Err(MmrError::InvalidSszEncoding("invalid list offsets"));
+        }
+
+        Ok(Self {
+            elements_count: read_u64(bytes, 0),
+            peaks_hashes: read_hashes(&bytes[PEAKS_COMMITMENT_FIXED_LEN..])?,
+        })
+    }
+
+    #[cfg(feature = "ssz")]
+    pub fn hash_tree_root(&self) -> Result<Hash32, MmrError> {
+        Ok(merkleize(
+            &[
+                u64_chunk(self.elements_count),
+                hashes_list_root(&self.peaks_hashes)?,
+            ],
+            2,
+        ))
+    }
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn get_peaks_commitment(
+        &self,
+        elements_count: Option<u64>,
+    ) -> Result<PeaksCommitment, MmrError> {
+        let elements_count = match elements_count {
+            Some(count) => count,
+            None => self.get_elements_count().await?,
+        };
+        Ok(PeaksCommitment {
+            elements_count,
+            peaks_hashes: self.get_peaks(Some(elements_count)).await?,
+        })
+    }
+}
+
+fn ssz_hashes_len(hashes: &[Hash32]) -> Result<usize, MmrError> {
+    if hashes.len() > SSZ_MAX_HASHES {
+        return Err(MmrError::InvalidSszEncoding("hash list exceeds its limit"));
+    }
+    Ok(hashes.len() * 32)
+}
+
+fn ssz_offset(offset: usize) -> Result<[u8; 4], MmrError> {
+    Ok(u32::try_from(offset)
+        .map_err(|_| MmrError::Overflow)?
+        .to_le_bytes())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(raw)
+}
+
+fn read_offset(bytes: &[u8], offset: usize) -> usize {
+    let mut raw = [0u8; 4];
+    raw.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(raw) as usize
+}
+
+fn read_hash(bytes: &[u8]) -> Hash32 {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(bytes);
+    Hash32::new(hash)
+}
+
+fn read_hashes(bytes: &[u8]) -> Result<Vec<Hash32>, MmrError> {
+    if !bytes.len().is_multiple_of(32) || bytes.len() / 32 > SSZ_MAX_HASHES {
+        return Err(MmrError::InvalidSszEncoding("invalid hash list length"));
+    }
+    Ok(bytes.chunks_exact(32).map(read_hash).collect())
+}
+
+#[cfg(feature = "ssz")]
+fn u64_chunk(value: u64) -> Hash32 {
+    let mut chunk = [0u8; 32];
+    chunk[..8].copy_from_slice(&value.to_le_bytes());
+    Hash32::new(chunk)
+}
+
+#[cfg(feature = "ssz")]
+fn hash_pair(left: &Hash32, right: &Hash32) -> Hash32 {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    Hash32::new(hasher.finalize().into())
+}
+
+#[cfg(feature = "ssz")]
+fn merkleize(chunks: &[Hash32], limit: usize) -> Hash32 {
+    let mut layer = chunks.to_vec();
+    layer.resize(limit.next_power_of_two(), Hash32::default());
+    while layer.len() > 1 {
+        layer = layer
+            .chunks_exact(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+    }
+    layer[0]
+}
+
+#[cfg(feature = "ssz")]
+fn hashes_list_root(hashes: &[Hash32]) -> Result<Hash32, MmrError> {
+    ssz_hashes_len(hashes)?;
+    Ok(hash_pair(
+        &merkleize(hashes, SSZ_MAX_HASHES),
+        &u64_chunk(hashes.len() as u64),
+    ))
+}
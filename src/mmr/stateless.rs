@@ -0,0 +1,109 @@
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::types::{Hash32, Proof};
+
+use super::helpers::{
+    elements_count_to_leaf_count, iter_peaks, leaf_count_to_append_no_merges,
+    leaf_count_to_mmr_size, map_leaf_index_to_element_index,
+};
+use super::verify::root_from_peaks;
+
+pub fn compute_peaks_from_leaves(
+    hasher: &dyn Hasher,
+    leaves: &[Hash32],
+) -> Result<(u64, Vec<Hash32>), MmrError> {
+    let (elements_count, peaks_hashes, _) = fold_leaves(hasher, leaves, None)?;
+    Ok((elements_count, peaks_hashes))
+}
+
+pub fn compute_root_from_leaves(
+    hasher: &dyn Hasher,
+    leaves: &[Hash32],
+) -> Result<Hash32, MmrError> {
+    let (elements_count, peaks_hashes) = compute_peaks_from_leaves(hasher, leaves)?;
+    root_from_peaks(hasher, &peaks_hashes, elements_count)
+}
+
+/// Appends `leaf` to an MMR known only by its peaks, returning the new elements count and peaks.
+pub fn append_to_peaks(
+    hasher: &dyn Hasher,
+    elements_count: u64,
+    peaks_hashes: &[Hash32],
+    leaf: Hash32,
+) -> Result<(u64, Vec<Hash32>), MmrError> {
+    let leaves_count = elements_count_to_leaf_count(elements_count)?;
+    if peaks_hashes.len() != iter_peaks(elements_count).count() {
+        return Err(MmrError::InvalidPeaksCountForElements);
+    }
+
+    let merges = leaf_count_to_append_no_merges(leaves_count);
+    let mut peaks = peaks_hashes.to_vec();
+    let mut hash = leaf;
+    for _ in 0..merges {
+        let left_hash = peaks.pop().ok_or(MmrError::InvalidPeaksCountForElements)?;
+        hash = hasher.hash_pair(&left_hash, &hash)?;
+    }
+    peaks.push(hash);
+
+    let elements_count = elements_count
+        .checked_add(merges + 1)
+        .ok_or(MmrError::Overflow)?;
+    Ok((elements_count, peaks))
+}
+
+pub fn prove_from_leaves(
+    hasher: &dyn Hasher,
+    leaves: &[Hash32],
+    leaf_index: u64,
+) -> Result<Proof, MmrError> {
+    let element_hash = usize::try_from(leaf_index)
+        .ok()
+        .and_then(|index| leaves.get(index))
+        .copied()
+        .ok_or(MmrError::InvalidLeafIndex)?;
+
+    let (elements_count, peaks_hashes, siblings_hashes) =
+        fold_leaves(hasher, leaves, Some(leaf_index))?;
+
+    Ok(Proof {
+        element_index: map_leaf_index_to_element_index(leaf_index),
+        element_hash,
+        siblings_hashes,
+        peaks_hashes,
+        elements_count,
+        mmr_id: None,
+    })
+}
+
+fn fold_leaves(
+    hasher: &dyn Hasher,
+    leaves: &[Hash32],
+    target_leaf_index: Option<u64>,
+) -> Result<(u64, Vec<Hash32>, Vec<Hash32>), MmrError> {
+    let mut peaks: Vec<(Hash32, bool)> = Vec::new();
+    let mut siblings_hashes = Vec::new();
+    for (leaves_count, leaf) in (0u64..).zip(leaves) {
+        let mut hash = *leaf;
+        let mut on_path = target_leaf_index == Some(leaves_count);
+        for _ in 0..leaf_count_to_append_no_merges(leaves_count) {
+            let (left_hash, left_on_path) =
+                peaks.pop().ok_or(MmrError::InvalidPeaksCountForElements)?;
+            if left_on_path {
+                siblings_hashes.push(hash);
+            } else if on_path {
+                siblings_hashes.push(left_hash);
+            }
+            hash = hasher.hash_pair(&left_hash, &hash)?;
+            on_path |= left_on_path;
+        }
+        peaks.push((hash, on_path));
+    }
+
+    let leaves_count = u64::try_from(leaves.len()).map_err(|_| MmrError::Overflow)?;
+    let peaks_hashes = peaks.into_iter().map(|(hash, _)| hash).collect();
+    Ok((
+        leaf_count_to_mmr_size(leaves_count),
+        peaks_hashes,
+        siblings_hashes,
+    ))
+}
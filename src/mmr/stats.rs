@@ -0,0 +1,44 @@
+use crate::error::MmrError;
+use crate::store::Store;
+
+use super::core::Mmr;
+use super::helpers::{iter_peaks, mmr_size_to_leaf_count};
+
+/// Bytes per stored node hash and root.
+const HASH_BYTES: u64 = 32;
+/// Bytes of the stored root plus the leaf and element counters.
+const METADATA_BYTES: u64 = HASH_BYTES + 8 + 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MmrStats {
+    pub leaves_count: u64,
+    pub elements_count: u64,
+    pub peaks_count: u64,
+    pub height: u32,
+    pub persisted_nodes: u64,
+    /// Node hashes, root and counters; leaf data and index entries are not counted.
+    pub approximate_bytes: u64,
+}
+
+impl<S: Store> Mmr<S> {
+    /// Derived from the stored element count alone, so it costs one read however large the
+    /// MMR is.
+    pub async fn stats(&self) -> Result<MmrStats, MmrError> {
+        let elements_count = self.get_elements_count().await?;
+        let leaves_count = mmr_size_to_leaf_count(elements_count);
+        let approximate_bytes = if elements_count == 0 {
+            0
+        } else {
+            elements_count * HASH_BYTES + METADATA_BYTES
+        };
+
+        Ok(MmrStats {
+            leaves_count,
+            elements_count,
+            peaks_count: iter_peaks(elements_count).count() as u64,
+            height: leaves_count.checked_ilog2().unwrap_or(0),
+            persisted_nodes: elements_count,
+            approximate_bytes,
+        })
+    }
+}
@@ -0,0 +1,169 @@
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::store::Store;
+use crate::types::{BatchAppendResult, ElementIndex, Hash32};
+
+use super::core::{AppendExtras, Mmr};
+use super::helpers::{
+    element_index_to_height, find_peaks, leaf_count_to_append_no_merges,
+    map_leaf_index_to_element_index, mmr_size_to_leaf_count,
+};
+use super::verify::bag_peaks;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffPacket {
+    pub previous_elements_count: u64,
+    pub leaves_count: u64,
+    pub elements_count: u64,
+    pub root_hash: Hash32,
+    pub nodes: Vec<(ElementIndex, Hash32)>,
+    /// External keys mapped to leaves in the new range, with their element indices.
+    pub external_keys: Vec<(u64, ElementIndex)>,
+    /// Data stored with leaves in the new range, by element index.
+    pub leaf_data: Vec<(ElementIndex, Vec<u8>)>,
+}
+
+impl<S: Store> Mmr<S> {
+    pub async fn export_diff(&self, since_elements_count: u64) -> Result<DiffPacket, MmrError> {
+        let elements_count = self.get_elements_count().await?;
+        if since_elements_count > elements_count
+            || (since_elements_count > 0 && find_peaks(since_elements_count).is_empty())
+        {
+            return Err(MmrError::InvalidElementCount);
+        }
+
+        let nodes = if since_elements_count == elements_count {
+            Vec::new()
+        } else {
+            self.get_nodes_in_range(since_elements_count + 1, elements_count)
+                .await?
+        };
+        let bag = self.bag_the_peaks(Some(elements_count)).await?;
+        let root_hash = self.calculate_root_hash(&bag, elements_count)?;
+        let external_keys = self
+            .external_keys_in_range(since_elements_count + 1, elements_count)
+            .await?;
+        let leaf_data = self
+            .leaf_data_in_range(since_elements_count + 1, elements_count)
+            .await?;
+
+        Ok(DiffPacket {
+            previous_elements_count: since_elements_count,
+            leaves_count: mmr_size_to_leaf_count(elements_count),
+            elements_count,
+            root_hash,
+            nodes,
+            external_keys,
+            leaf_data,
+        })
+    }
+
+    pub async fn apply_diff(
+        &self,
+        packet: &DiffPacket,
+    ) -> Result<Option<BatchAppendResult>, MmrError> {
+        let elements_count = self.get_elements_count().await?;
+        if elements_count != packet.previous_elements_count {
+            return Err(MmrError::DiffBaseMismatch {
+                expected: packet.previous_elements_count,
+                actual: elements_count,
+            });
+        }
+        if packet.elements_count < packet.previous_elements_count
+            || mmr_size_to_leaf_count(packet.elements_count) != packet.leaves_count
+            || (packet.elements_count > 0 && find_peaks(packet.elements_count).is_empty())
+        {
+            return Err(MmrError::InvalidDiff("inconsistent counters"));
+        }
+        let expected_indices = (packet.previous_elements_count + 1)..=packet.elements_count;
+        if packet.nodes.len() as u64 != packet.elements_count - packet.previous_elements_count
+            || !packet
+                .nodes
+                .iter()
+                .map(|(idx, _)| *idx)
+                .eq(expected_indices)
+        {
+            return Err(MmrError::InvalidDiff("nodes do not cover the new range"));
+        }
+        if packet.external_keys.iter().any(|(_, element_index)| {
+            *element_index <= packet.previous_elements_count
+                || *element_index > packet.elements_count
+        }) {
+            return Err(MmrError::InvalidDiff("external key outside the new range"));
+        }
+        if packet.leaf_data.iter().any(|(element_index, _)| {
+            *element_index <= packet.previous_elements_count
+                || *element_index > packet.elements_count
+                || element_index_to_height(*element_index).ok() != Some(0)
+        }) {
+            return Err(MmrError::InvalidDiff("leaf data outside the new leaves"));
+        }
+        if packet.nodes.is_empty() {
+            return Ok(None);
+        }
+
+        let first_leaf_index = mmr_size_to_leaf_count(packet.previous_elements_count);
+        let leaves: Vec<Hash32> = (first_leaf_index..packet.leaves_count)
+            .map(|leaf_index| {
+                let offset = map_leaf_index_to_element_index(leaf_index)
+                    - packet.previous_elements_count
+                    - 1;
+                packet.nodes[offset as usize].1
+            })
+            .collect();
+
+        let peaks_hashes = self.get_peaks(Some(packet.previous_elements_count)).await?;
+        let (nodes, peaks_hashes) = replay_appends(
+            self.hasher.as_ref(),
+            first_leaf_index,
+            peaks_hashes,
+            &leaves,
+        )?;
+        if !nodes.iter().eq(packet.nodes.iter().map(|(_, hash)| hash)) {
+            return Err(MmrError::InvalidDiff(
+                "node hashes do not match the old peaks",
+            ));
+        }
+        let bag = bag_peaks(self.hasher.as_ref(), &peaks_hashes)?;
+        if self.calculate_root_hash(&bag, packet.elements_count)? != packet.root_hash {
+            return Err(MmrError::InvalidDiff(
+                "root hash does not match the new peaks",
+            ));
+        }
+
+        let extras = AppendExtras {
+            external_key_entries: Some(&packet.external_keys),
+            leaf_data_entries: Some(&packet.leaf_data),
+            expected_elements_count: Some(packet.previous_elements_count),
+            ..AppendExtras::default()
+        };
+        let (result, _) = self.commit_batch_append(&leaves, extras).await?;
+        self.clear_node_cache();
+        Ok(Some(result))
+    }
+}
+
+pub(crate) fn replay_appends(
+    hasher: &dyn Hasher,
+    mut leaves_count: u64,
+    mut peaks_hashes: Vec<Hash32>,
+    leaves: &[Hash32],
+) -> Result<(Vec<Hash32>, Vec<Hash32>), MmrError> {
+    let mut nodes = Vec::new();
+    for leaf in leaves {
+        nodes.push(*leaf);
+        peaks_hashes.push(*leaf);
+        for _ in 0..leaf_count_to_append_no_merges(leaves_count) {
+            let (Some(right_hash), Some(left_hash)) = (peaks_hashes.pop(), peaks_hashes.pop())
+            else {
+                return Err(MmrError::InvalidPeaksCountForElements);
+            };
+            let parent_hash = hasher.hash_pair(&left_hash, &right_hash)?;
+            nodes.push(parent_hash);
+            peaks_hashes.push(parent_hash);
+        }
+        leaves_count += 1;
+    }
+
+    Ok((nodes, peaks_hashes))
+}
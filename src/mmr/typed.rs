@@ -0,0 +1,88 @@
+use std::marker::PhantomData;
+
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::store::Store;
+use crate::types::{AppendResult, BatchAppendResult, ElementIndex, Hash32, Proof};
+
+use super::core::Mmr;
+
+/// Canonical byte encoding of a leaf; the bytes are hashed with `Hasher::hash_leaf`.
+pub trait LeafEncode {
+    fn leaf_bytes(&self) -> Vec<u8>;
+}
+
+impl LeafEncode for Vec<u8> {
+    fn leaf_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+impl LeafEncode for String {
+    fn leaf_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl LeafEncode for u64 {
+    fn leaf_bytes(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+}
+
+pub struct TypedMmr<T, S: Store> {
+    mmr: Mmr<S>,
+    _leaf: PhantomData<fn(&T)>,
+}
+
+impl<T: LeafEncode, S: Store> TypedMmr<T, S> {
+    pub fn new(mmr: Mmr<S>) -> Self {
+        Self {
+            mmr,
+            _leaf: PhantomData,
+        }
+    }
+
+    pub fn inner(&self) -> &Mmr<S> {
+        &self.mmr
+    }
+
+    pub fn into_inner(self) -> Mmr<S> {
+        self.mmr
+    }
+
+    pub fn leaf_hash(&self, value: &T) -> Result<Hash32, MmrError> {
+        Ok(self.mmr.hasher.hash_leaf(&value.leaf_bytes())?)
+    }
+
+    pub async fn append(&self, value: &T) -> Result<AppendResult, MmrError> {
+        self.mmr.append(self.leaf_hash(value)?).await
+    }
+
+    pub async fn batch_append(&self, values: &[T]) -> Result<BatchAppendResult, MmrError> {
+        let hashes = values
+            .iter()
+            .map(|value| self.leaf_hash(value))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.mmr.batch_append(&hashes).await
+    }
+
+    pub async fn get_proof(
+        &self,
+        element_index: ElementIndex,
+        elements_count: Option<u64>,
+    ) -> Result<Proof, MmrError> {
+        self.mmr.get_proof(element_index, elements_count).await
+    }
+
+    pub async fn verify_proof(
+        &self,
+        proof: &Proof,
+        value: &T,
+        elements_count: Option<u64>,
+    ) -> Result<bool, MmrError> {
+        self.mmr
+            .verify_proof(proof, self.leaf_hash(value)?, elements_count)
+            .await
+    }
+}
@@ -0,0 +1,139 @@
+use crate::error::MmrError;
+use crate::hasher::Hasher;
+use crate::types::{Hash, MmrId, Proof};
+use crate::verify;
+
+pub fn verify_proof<const N: usize>(
+    hasher: &dyn Hasher<N>,
+    proof: &Proof<N>,
+    element_value: Hash<N>,
+) -> Result<bool, MmrError> {
+    verify::verify_proof(
+        hasher,
+        proof.element_index,
+        &element_value.0,
+        &proof.siblings_hashes,
+        &proof.peaks_hashes,
+        proof.elements_count,
+    )
+}
+
+/// Verifies a proof from an MMR created with `with_proof_binding(true)` against its
+/// `bound_root` (see [`bound_root_from_peaks`]). The proof's `mmr_id` goes into the
+/// recomputed root, so an unlabelled proof, or one issued by another MMR, does not verify
+/// even when both MMRs hold the same leaves.
+pub fn verify_bound_proof<const N: usize>(
+    hasher: &dyn Hasher<N>,
+    proof: &Proof<N>,
+    element_value: Hash<N>,
+    bound_root: &Hash<N>,
+) -> Result<bool, MmrError> {
+    let Some(mmr_id) = proof.mmr_id else {
+        return Ok(false);
+    };
+    verify::verify_proof_against_bound_root(
+        hasher,
+        proof.element_index,
+        &element_value.0,
+        &proof.siblings_hashes,
+        &proof.peaks_hashes,
+        proof.elements_count,
+        mmr_id,
+        &bound_root.0,
+    )
+}
+
+pub fn verify_proof_against_root<const N: usize>(
+    hasher: &dyn Hasher<N>,
+    proof: &Proof<N>,
+    element_value: Hash<N>,
+    root_hash: &Hash<N>,
+) -> Result<bool, MmrError> {
+    verify::verify_proof_against_root(
+        hasher,
+        proof.element_index,
+        &element_value.0,
+        &proof.siblings_hashes,
+        &proof.peaks_hashes,
+        proof.elements_count,
+        &root_hash.0,
+    )
+}
+
+pub fn root_from_peaks<const N: usize>(
+    hasher: &dyn Hasher<N>,
+    peaks_hashes: &[Hash<N>],
+    elements_count: u64,
+) -> Result<Hash<N>, MmrError> {
+    match verify::root_from_peaks(hasher, peaks_hashes, elements_count) {
+        Ok(root) => Ok(Hash(root)),
+        Err(MmrError::InvalidPeaksCount) => Err(MmrError::InvalidPeaksCountForElements),
+        Err(err) => Err(err),
+    }
+}
+
+/// The root of `peaks_hashes` committed to `mmr_id`, which `verify_bound_proof` checks
+/// proofs against.
+pub fn bound_root_from_peaks<const N: usize>(
+    hasher: &dyn Hasher<N>,
+    peaks_hashes: &[Hash<N>],
+    elements_count: u64,
+    mmr_id: MmrId,
+) -> Result<Hash<N>, MmrError> {
+    match verify::bound_root_from_peaks(hasher, peaks_hashes, elements_count, mmr_id) {
+        Ok(root) => Ok(Hash(root)),
+        Err(MmrError::InvalidPeaksCount) => Err(MmrError::InvalidPeaksCountForElements),
+        Err(err) => Err(err),
+    }
+}
+
+pub fn bag_peaks<const N: usize>(
+    hasher: &dyn Hasher<N>,
+    peaks_hashes: &[Hash<N>],
+) -> Result<Hash<N>, MmrError> {
+    verify::bag_peaks(hasher, peaks_hashes).map(Hash)
+}
+
+pub(crate) fn compute_peak_from_proof<const N: usize>(
+    hasher: &dyn Hasher<N>,
+    proof: &Proof<N>,
+    element_value: Hash<N>,
+    tree_size: u64,
+) -> Result<Option<(usize, Hash<N>)>, MmrError> {
+    compute_peak_from_parts(
+        hasher,
+        proof.element_index,
+        &proof.siblings_hashes,
+        proof.peaks_hashes.len(),
+        element_value,
+        tree_size,
+    )
+}
+
+pub(crate) fn compute_peak_from_parts<const N: usize>(
+    hasher: &dyn Hasher<N>,
+    element_index: u64,
+    siblings_hashes: &[Hash<N>],
+    peaks_count: usize,
+    element_value: Hash<N>,
+    tree_size: u64,
+) -> Result<Option<(usize, Hash<N>)>, MmrError> {
+    let peak = verify::compute_peak(
+        hasher,
+        element_index,
+        &element_value.0,
+        siblings_hashes,
+        peaks_count,
+        tree_size,
+    )?;
+    Ok(peak.map(|(peak_index, hash)| (peak_index, Hash(hash))))
+}
+
+pub(crate) fn climb_to_peak<const N: usize>(
+    hasher: &dyn Hasher<N>,
+    element_index: u64,
+    siblings_hashes: &[Hash<N>],
+    element_value: Hash<N>,
+) -> Result<Hash<N>, MmrError> {
+    verify::climb_to_peak(hasher, element_index, &element_value.0, siblings_hashes).map(Hash)
+}
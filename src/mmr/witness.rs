@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use futures::lock::Mutex;
+
+use crate::error::MmrError;
+use crate::store::{KeyKind, Store, StoreValue};
+use crate::types::{AppendResult, BatchAppendResult, ElementIndex, Hash32, Proof};
+
+use super::core::{MAX_APPEND_ATTEMPTS, Mmr, StagedAppend};
+
+pub struct WitnessTracker<'a, S: Store> {
+    mmr: &'a Mmr<S>,
+    witnesses: Mutex<BTreeMap<ElementIndex, Proof>>,
+}
+
+impl<'a, S: Store> WitnessTracker<'a, S> {
+    pub async fn track(&self, element_index: ElementIndex) -> Result<Proof, MmrError> {
+        let mut witnesses = self.witnesses.lock().await;
+        let proof = self.mmr.get_proof(element_index, None).await?;
+        witnesses.insert(element_index, proof.clone());
+        Ok(proof)
+    }
+
+    pub async fn untrack(&self, element_index: ElementIndex) -> bool {
+        self.witnesses.lock().await.remove(&element_index).is_some()
+    }
+
+    pub async fn tracked(&self) -> Vec<ElementIndex> {
+        self.witnesses.lock().await.keys().copied().collect()
+    }
+
+    pub async fn get_witness(&self, element_index: ElementIndex) -> Option<Proof> {
+        self.witnesses.lock().await.get(&element_index).cloned()
+    }
+
+    pub async fn witnesses(&self) -> Vec<Proof> {
+        self.witnesses.lock().await.values().cloned().collect()
+    }
+
+    pub async fn append(&self, value: Hash32) -> Result<AppendResult, MmrError> {
+        let batch_result = self.batch_append(&[value]).await?;
+        Ok(AppendResult::from(batch_result))
+    }
+
+    /// Appends `values` and moves every tracked witness to the new size. The witnesses are
+    /// updated from the staged writes before they are committed, so a witness that cannot
+    /// be updated fails the append instead of following a committed one.
+    pub async fn batch_append(&self, values: &[Hash32]) -> Result<BatchAppendResult, MmrError> {
+        let mut witnesses = self.witnesses.lock().await;
+        self.mmr.check_append(values)?;
+
+        for _ in 0..MAX_APPEND_ATTEMPTS {
+            let staged = self.mmr.stage_append(values).await?;
+            let updated = match self.updated_witnesses(&witnesses, &staged).await {
+                Ok(updated) => updated,
+                Err(err) => {
+                    staged.discard();
+                    return Err(err);
+                }
+            };
+            if let Some(result) = self.mmr.commit_staged(staged).await? {
+                *witnesses = updated;
+                return Ok(result);
+            }
+        }
+
+        Err(MmrError::ConcurrentAppendConflict(MAX_APPEND_ATTEMPTS))
+    }
+
+    async fn updated_witnesses(
+        &self,
+        witnesses: &BTreeMap<ElementIndex, Proof>,
+        staged: &StagedAppend<'_>,
+    ) -> Result<BTreeMap<ElementIndex, Proof>, MmrError> {
+        let previous_elements_count = staged.expected_elements_count();
+        let appended_nodes: BTreeMap<u64, Hash32> = staged
+            .staged_writes
+            .iter()
+            .filter_map(|(key, value)| match (key.kind, value) {
+                (KeyKind::NodeHash, StoreValue::Hash(hash)) => Some((key.index, *hash)),
+                _ => None,
+            })
+            .collect();
+
+        let mut updated = BTreeMap::new();
+        for (element_index, proof) in witnesses {
+            let proof = if proof.elements_count == previous_elements_count {
+                proof.update(staged.result.elements_count, &appended_nodes)?
+            } else {
+                self.mmr
+                    .refresh_proof(proof, Some(previous_elements_count))
+                    .await?
+                    .update(staged.result.elements_count, &appended_nodes)?
+            };
+            updated.insert(*element_index, proof);
+        }
+
+        Ok(updated)
+    }
+
+    pub async fn sync(&self) -> Result<u64, MmrError> {
+        let mut witnesses = self.witnesses.lock().await;
+        let elements_count = self.mmr.get_elements_count().await?;
+        for proof in witnesses.values_mut() {
+            if proof.elements_count != elements_count {
+                *proof = self.mmr.refresh_proof(proof, Some(elements_count)).await?;
+            }
+        }
+
+        Ok(elements_count)
+    }
+}
+
+impl<S: Store> Mmr<S> {
+    pub fn witness_tracker(&self) -> WitnessTracker<'_, S> {
+        WitnessTracker {
+            mmr: self,
+            witnesses: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use futures::channel::{mpsc, oneshot};
+use futures::{SinkExt, StreamExt};
+
+use crate::error::MmrError;
+use crate::store::Store;
+use crate::types::{AppendResult, ElementIndex, ElementsCount, Hash32};
+
+use super::core::{AppendExtras, Mmr};
+use super::helpers::map_leaf_index_to_element_index;
+
+/// Outcome of one append that went through an [`MmrWriter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedAppend {
+    pub element_index: ElementIndex,
+    /// Elements count after the coalesced batch that included this append.
+    pub elements_count: ElementsCount,
+    /// The zero hash when `root_deferred` is set, as in `AppendResult`.
+    pub root_hash: Hash32,
+    pub root_deferred: bool,
+}
+
+impl QueuedAppend {
+    /// The root after the batch, or `None` when lazy-root mode deferred it.
+    pub fn root_hash_if_computed(&self) -> Option<Hash32> {
+        (!self.root_deferred).then_some(self.root_hash)
+    }
+}
+
+impl From<AppendResult> for QueuedAppend {
+    fn from(result: AppendResult) -> Self {
+        Self {
+            element_index: result.element_index,
+            elements_count: result.elements_count,
+            root_hash: result.root_hash,
+            root_deferred: result.root_deferred,
+        }
+    }
+}
+
+type Reply = oneshot::Sender<Result<QueuedAppend, MmrError>>;
+
+/// Cloneable handle that queues appends for a single driver, which commits everything
+/// queued since its last commit as one `batch_append`.
+#[derive(Debug, Clone)]
+pub struct MmrWriter {
+    sender: mpsc::Sender<(Hash32, Reply)>,
+}
+
+impl MmrWriter {
+    /// Returns the handle and the driver future. The driver must be spawned (or otherwise
+    /// polled) and finishes once every handle is dropped and the queue is drained.
+    /// `max_batch` caps the leaves per `batch_append` and the queue holds about one more
+    /// batch, so `append` waits while the driver is behind.
+    pub fn new<S: Store>(mmr: Arc<Mmr<S>>, max_batch: usize) -> (Self, impl Future<Output = ()>) {
+        let (sender, receiver) = mpsc::channel(max_batch.max(1));
+        let driver = async move {
+            let mut batches = receiver.ready_chunks(max_batch.max(1));
+            while let Some(requests) = batches.next().await {
+                commit_queued(&mmr, requests).await;
+            }
+        };
+        (Self { sender }, driver)
+    }
+
+    pub async fn append(&self, value: Hash32) -> Result<QueuedAppend, MmrError> {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .clone()
+            .send((value, reply))
+            .await
+            .map_err(|_| MmrError::WriterClosed)?;
+        response.await.map_err(|_| MmrError::WriterClosed)?
+    }
+}
+
+async fn commit_queued<S: Store>(mmr: &Mmr<S>, requests: Vec<(Hash32, Reply)>) {
+    let values: Vec<Hash32> = requests.iter().map(|(value, _)| *value).collect();
+    // A hook rejecting the batch says nothing about any single value, so each value is
+    // appended on its own and gets its own verdict.
+    if mmr.check_append(&values).is_err() {
+        for (value, reply) in requests {
+            let _ = reply.send(mmr.append(value).await.map(QueuedAppend::from));
+        }
+        return;
+    }
+
+    match mmr
+        .commit_checked_append(&values, AppendExtras::default())
+        .await
+    {
+        Ok((result, _)) => {
+            let first_leaf_index = result.leaves_count - result.appended_count;
+            for (offset, (_, reply)) in (0u64..).zip(requests) {
+                let _ = reply.send(Ok(QueuedAppend {
+                    element_index: map_leaf_index_to_element_index(first_leaf_index + offset),
+                    elements_count: result.elements_count,
+                    root_hash: result.root_hash,
+                    root_deferred: result.root_deferred,
+                }));
+            }
+        }
+        // A size limit only refuses the values past it, so the ones that fit still land.
+        // The hooks already accepted the whole batch and are not asked again.
+        Err(MmrError::MaxLeavesExceeded { .. } | MmrError::MaxElementsExceeded { .. }) => {
+            for (value, reply) in requests {
+                let outcome = mmr
+                    .commit_checked_append(&[value], AppendExtras::default())
+                    .await
+                    .map(|(result, _)| QueuedAppend::from(AppendResult::from(result)));
+                let _ = reply.send(outcome);
+            }
+        }
+        // Anything else, e.g. a store error, may have left the batch committed or not, so
+        // retrying could append it twice. Every caller gets the error instead.
+        Err(err) => {
+            let err = Arc::new(err);
+            for (_, reply) in requests {
+                let _ = reply.send(Err(MmrError::WriterBatchFailed(err.clone())));
+            }
+        }
+    }
+}
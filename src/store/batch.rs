@@ -0,0 +1,43 @@
+use super::{StoreKey, StoreValue};
+
+/// Writes applied atomically by `Store::write_batch`, but only if every guard key still
+/// holds its expected counter (a missing key reads as 0). Deletes are applied before
+/// sets, so a key that appears in both ends up set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteBatch<const N: usize = 32> {
+    pub guards: Vec<(StoreKey, u64)>,
+    pub sets: Vec<(StoreKey, StoreValue<N>)>,
+    pub deletes: Vec<StoreKey>,
+}
+
+impl<const N: usize> WriteBatch<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn guard(mut self, counter_key: StoreKey, expected_counter: u64) -> Self {
+        self.guards.push((counter_key, expected_counter));
+        self
+    }
+
+    pub fn set_many(
+        mut self,
+        entries: impl IntoIterator<Item = (StoreKey, StoreValue<N>)>,
+    ) -> Self {
+        self.sets.extend(entries);
+        self
+    }
+
+    pub fn delete_many(mut self, keys: impl IntoIterator<Item = StoreKey>) -> Self {
+        self.deletes.extend(keys);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.sets.len() + self.deletes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty() && self.deletes.is_empty()
+    }
+}
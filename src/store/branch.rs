@@ -0,0 +1,174 @@
+use crate::error::StoreError;
+use crate::types::MmrId;
+
+use super::{KeyKind, Store, StoreKey, StoreValue, WriteBatch};
+
+#[derive(Debug, Clone)]
+pub struct BranchStore<S> {
+    base: S,
+    branch_id: MmrId,
+    segments: Vec<(MmrId, u64)>,
+}
+
+impl<S> BranchStore<S> {
+    pub fn new(base: S, branch_id: MmrId, segments: Vec<(MmrId, u64)>) -> Self {
+        Self {
+            base,
+            branch_id,
+            segments,
+        }
+    }
+
+    pub fn base(&self) -> &S {
+        &self.base
+    }
+
+    pub fn branch_id(&self) -> MmrId {
+        self.branch_id
+    }
+
+    pub fn segments(&self) -> &[(MmrId, u64)] {
+        &self.segments
+    }
+
+    /// Where node hashes, leaf data and leaf keys at or below a fork point are read from:
+    /// the ancestor that wrote them.
+    fn resolve(&self, key: &StoreKey) -> StoreKey {
+        if key.mmr_id != self.branch_id
+            || !matches!(
+                key.kind,
+                KeyKind::NodeHash | KeyKind::LeafData | KeyKind::LeafKey
+            )
+        {
+            return key.clone();
+        }
+
+        match self
+            .segments
+            .iter()
+            .find(|(_, fork_point)| key.index <= *fork_point)
+        {
+            Some((owner, _)) => StoreKey::new(*owner, key.kind, key.index),
+            None => key.clone(),
+        }
+    }
+
+    /// External keys and hash index slots are keyed by value rather than element index,
+    /// so a branch key with no value of its own falls back to its ancestors.
+    fn is_inherited(&self, key: &StoreKey) -> bool {
+        key.mmr_id == self.branch_id
+            && matches!(key.kind, KeyKind::ExternalKey | KeyKind::HashIndex)
+    }
+
+    /// What the ancestors hold for the `ExternalKey` or `HashIndex` keys of this branch in
+    /// `keys`. The nearest ancestor wins, but only with an element at or below the fork
+    /// point the branch sees it at; later entries belong to elements the branch replaced.
+    pub(crate) async fn get_inherited<const N: usize>(
+        &self,
+        keys: &[StoreKey],
+    ) -> Result<Vec<Option<StoreValue<N>>>, StoreError>
+    where
+        S: Store<N>,
+    {
+        let mut values = vec![None; keys.len()];
+        let mut pending: Vec<usize> = (0..keys.len()).collect();
+        for (owner, fork_point) in self.segments.iter().rev() {
+            if pending.is_empty() {
+                break;
+            }
+            let owner_keys: Vec<StoreKey> = pending
+                .iter()
+                .map(|&position| StoreKey::new(*owner, keys[position].kind, keys[position].index))
+                .collect();
+            let owner_values = self.base.get_many(&owner_keys).await?;
+            let mut unresolved = Vec::new();
+            for ((position, key), value) in pending.into_iter().zip(&owner_keys).zip(owner_values) {
+                match value {
+                    Some(value) if value.clone().expect_u64(key)? <= *fork_point => {
+                        values[position] = Some(value);
+                    }
+                    _ => unresolved.push(position),
+                }
+            }
+            pending = unresolved;
+        }
+
+        Ok(values)
+    }
+}
+
+impl<S: Store<N>, const N: usize> Store<N> for BranchStore<S> {
+    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue<N>>, StoreError> {
+        let value = self.base.get(&self.resolve(key)).await?;
+        if value.is_some() || !self.is_inherited(key) {
+            return Ok(value);
+        }
+
+        Ok(self
+            .get_inherited(std::slice::from_ref(key))
+            .await?
+            .pop()
+            .flatten())
+    }
+
+    async fn set(&self, key: StoreKey, value: StoreValue<N>) -> Result<(), StoreError> {
+        self.base.set(key, value).await
+    }
+
+    async fn set_many(&self, entries: Vec<(StoreKey, StoreValue<N>)>) -> Result<(), StoreError> {
+        self.base.set_many(entries).await
+    }
+
+    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue<N>>>, StoreError> {
+        let resolved: Vec<StoreKey> = keys.iter().map(|key| self.resolve(key)).collect();
+        let mut values = self.base.get_many(&resolved).await?;
+        let missing: Vec<usize> = (0..keys.len())
+            .filter(|&position| values[position].is_none() && self.is_inherited(&keys[position]))
+            .collect();
+        if missing.is_empty() {
+            return Ok(values);
+        }
+
+        let missing_keys: Vec<StoreKey> = missing
+            .iter()
+            .map(|&position| keys[position].clone())
+            .collect();
+        let inherited = self.get_inherited(&missing_keys).await?;
+        for (position, value) in missing.into_iter().zip(inherited) {
+            values[position] = value;
+        }
+
+        Ok(values)
+    }
+
+    async fn compare_and_set_many(
+        &self,
+        counter_key: &StoreKey,
+        expected_counter: u64,
+        entries: Vec<(StoreKey, StoreValue<N>)>,
+    ) -> Result<bool, StoreError> {
+        self.base
+            .compare_and_set_many(counter_key, expected_counter, entries)
+            .await
+    }
+
+    async fn write_batch(&self, batch: WriteBatch<N>) -> Result<bool, StoreError> {
+        self.base.write_batch(batch).await
+    }
+
+    async fn delete_many(&self, keys: &[StoreKey]) -> Result<(), StoreError> {
+        self.base.delete_many(keys).await
+    }
+
+    async fn scan_keys(&self, mmr_id: MmrId) -> Result<Vec<StoreKey>, StoreError> {
+        self.base.scan_keys(mmr_id).await
+    }
+
+    async fn list_mmr_ids(&self) -> Result<Vec<MmrId>, StoreError> {
+        self.base.list_mmr_ids().await
+    }
+
+    async fn allocate_mmr_id(&self) -> Result<MmrId, StoreError> {
+        self.base.allocate_mmr_id().await
+    }
+}
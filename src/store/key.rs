@@ -1,4 +1,7 @@
-use crate::types::{Hash32, MmrId};
+use std::collections::HashMap;
+
+use crate::error::StoreError;
+use crate::types::{Hash, MmrId};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
@@ -7,6 +10,16 @@ pub enum KeyKind {
     ElementsCount = 1,
     RootHash = 2,
     NodeHash = 3,
+    WriterLease = 4,
+    LeafData = 5,
+    ExternalKey = 6,
+    HashIndex = 7,
+    RegistryEntry = 8,
+    IdAllocator = 9,
+    BranchParent = 10,
+    Checkpoint = 11,
+    Peaks = 12,
+    LeafKey = 13,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -31,7 +44,82 @@ impl StoreKey {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum StoreValue {
+pub enum StoreValue<const N: usize = 32> {
     U64(u64),
-    Hash(Hash32),
+    Hash(Hash<N>),
+    Bytes(Vec<u8>),
+}
+
+/// Distinct keys of a batched read, remembering where each requested key went so the
+/// values can be fanned back out in request order.
+pub(crate) struct DedupedKeys {
+    pub(crate) unique: Vec<StoreKey>,
+    positions: Vec<usize>,
+}
+
+impl DedupedKeys {
+    /// `None` when `keys` has no repeats, so callers can pass them through untouched.
+    pub(crate) fn new(keys: &[StoreKey]) -> Option<Self> {
+        let mut seen = HashMap::with_capacity(keys.len());
+        let mut unique = Vec::new();
+        let positions = keys
+            .iter()
+            .map(|key| {
+                *seen.entry(key).or_insert_with(|| {
+                    unique.push(key.clone());
+                    unique.len() - 1
+                })
+            })
+            .collect();
+        if unique.len() == keys.len() {
+            return None;
+        }
+
+        Some(Self { unique, positions })
+    }
+
+    /// Fails when the backend answered with a different number of values than distinct
+    /// keys, instead of reading the missing ones as absent.
+    pub(crate) fn fan_out<const N: usize>(
+        &self,
+        values: Vec<Option<StoreValue<N>>>,
+    ) -> Result<Vec<Option<StoreValue<N>>>, StoreError> {
+        if values.len() != self.unique.len() {
+            return Err(StoreError::Internal(format!(
+                "get_many returned {} values for {} keys",
+                values.len(),
+                self.unique.len()
+            )));
+        }
+
+        Ok(self
+            .positions
+            .iter()
+            .map(|&position| values[position].clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DedupedKeys, KeyKind, StoreKey, StoreValue};
+    use crate::error::StoreError;
+
+    #[test]
+    fn fan_out_rejects_a_short_reply() {
+        let key = StoreKey::new(1, KeyKind::NodeHash, 3);
+        let other = StoreKey::new(1, KeyKind::NodeHash, 7);
+        let deduped = DedupedKeys::new(&[key.clone(), other, key]).unwrap();
+
+        assert_eq!(
+            deduped
+                .fan_out::<32>(vec![Some(StoreValue::U64(1)), None])
+                .unwrap(),
+            vec![Some(StoreValue::U64(1)), None, Some(StoreValue::U64(1))]
+        );
+        assert!(matches!(
+            deduped.fan_out::<32>(vec![Some(StoreValue::U64(1))]),
+            Err(StoreError::Internal(_))
+        ));
+    }
 }
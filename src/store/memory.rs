@@ -1,13 +1,24 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::RwLock;
 
 use crate::error::StoreError;
+use crate::types::MmrId;
 
-use super::{Store, StoreKey, StoreValue};
+use super::{Store, StoreKey, StoreValue, WriteBatch, allocate_mmr_id_from_counter};
 
-#[derive(Debug, Default)]
-pub struct InMemoryStore {
-    inner: RwLock<HashMap<StoreKey, StoreValue>>,
+/// `InMemoryStore::new()` is the 32-byte store; name the width for another one, e.g.
+/// `InMemoryStore::<20>::default()`.
+#[derive(Debug)]
+pub struct InMemoryStore<const N: usize = 32> {
+    inner: RwLock<HashMap<StoreKey, StoreValue<N>>>,
+}
+
+impl<const N: usize> Default for InMemoryStore<N> {
+    fn default() -> Self {
+        Self {
+            inner: RwLock::new(HashMap::new()),
+        }
+    }
 }
 
 impl InMemoryStore {
@@ -16,8 +27,8 @@ impl InMemoryStore {
     }
 }
 
-impl Store for InMemoryStore {
-    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue>, StoreError> {
+impl<const N: usize> Store<N> for InMemoryStore<N> {
+    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue<N>>, StoreError> {
         let guard = self
             .inner
             .read()
@@ -25,7 +36,7 @@ impl Store for InMemoryStore {
         Ok(guard.get(key).cloned())
     }
 
-    async fn set(&self, key: StoreKey, value: StoreValue) -> Result<(), StoreError> {
+    async fn set(&self, key: StoreKey, value: StoreValue<N>) -> Result<(), StoreError> {
         let mut guard = self
             .inner
             .write()
@@ -34,7 +45,7 @@ impl Store for InMemoryStore {
         Ok(())
     }
 
-    async fn set_many(&self, entries: Vec<(StoreKey, StoreValue)>) -> Result<(), StoreError> {
+    async fn set_many(&self, entries: Vec<(StoreKey, StoreValue<N>)>) -> Result<(), StoreError> {
         let mut guard = self
             .inner
             .write()
@@ -47,19 +58,98 @@ impl Store for InMemoryStore {
         Ok(())
     }
 
-    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue>>, StoreError> {
+    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue<N>>>, StoreError> {
         let guard = self
             .inner
             .read()
             .map_err(|_| StoreError::Internal("rwlock poisoned (read)".to_string()))?;
         Ok(keys.iter().map(|key| guard.get(key).cloned()).collect())
     }
+
+    async fn compare_and_set_many(
+        &self,
+        counter_key: &StoreKey,
+        expected_counter: u64,
+        entries: Vec<(StoreKey, StoreValue<N>)>,
+    ) -> Result<bool, StoreError> {
+        self.write_batch(
+            WriteBatch::new()
+                .guard(counter_key.clone(), expected_counter)
+                .set_many(entries),
+        )
+        .await
+    }
+
+    async fn write_batch(&self, batch: WriteBatch<N>) -> Result<bool, StoreError> {
+        let mut guard = self
+            .inner
+            .write()
+            .map_err(|_| StoreError::Internal("rwlock poisoned (write)".to_string()))?;
+
+        for (counter_key, expected_counter) in &batch.guards {
+            let current_counter = match guard.get(counter_key) {
+                Some(value) => value.clone().expect_u64(counter_key)?,
+                None => 0,
+            };
+            if current_counter != *expected_counter {
+                return Ok(false);
+            }
+        }
+
+        for key in &batch.deletes {
+            guard.remove(key);
+        }
+        for (key, value) in batch.sets {
+            guard.insert(key, value);
+        }
+
+        Ok(true)
+    }
+
+    async fn delete_many(&self, keys: &[StoreKey]) -> Result<(), StoreError> {
+        let mut guard = self
+            .inner
+            .write()
+            .map_err(|_| StoreError::Internal("rwlock poisoned (write)".to_string()))?;
+
+        for key in keys {
+            guard.remove(key);
+        }
+
+        Ok(())
+    }
+
+    async fn scan_keys(&self, mmr_id: MmrId) -> Result<Vec<StoreKey>, StoreError> {
+        let guard = self
+            .inner
+            .read()
+            .map_err(|_| StoreError::Internal("rwlock poisoned (read)".to_string()))?;
+        Ok(guard
+            .keys()
+            .filter(|key| key.mmr_id == mmr_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn list_mmr_ids(&self) -> Result<Vec<MmrId>, StoreError> {
+        let guard = self
+            .inner
+            .read()
+            .map_err(|_| StoreError::Internal("rwlock poisoned (read)".to_string()))?;
+        let ids: BTreeSet<MmrId> = guard.keys().map(|key| key.mmr_id).collect();
+        Ok(ids.into_iter().collect())
+    }
+
+    async fn allocate_mmr_id(&self) -> Result<MmrId, StoreError> {
+        allocate_mmr_id_from_counter(self).await
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{InMemoryStore, Store, StoreKey, StoreValue};
+    use super::{InMemoryStore, Store, StoreKey, StoreValue, WriteBatch};
     use crate::store::KeyKind;
+    use crate::types::Hash32;
 
     #[tokio::test]
     async fn set_many_writes_all_entries() {
@@ -71,7 +161,7 @@ mod tests {
             ),
             (
                 StoreKey::new(1, KeyKind::NodeHash, 10),
-                StoreValue::Hash([3u8; 32]),
+                StoreValue::Hash(Hash32::new([3u8; 32])),
             ),
         ];
 
@@ -99,4 +189,102 @@ mod tests {
             [3u8; 32]
         );
     }
+
+    #[tokio::test]
+    async fn compare_and_set_many_rejects_stale_counter() {
+        let store = InMemoryStore::new();
+        let counter_key = StoreKey::metadata(1, KeyKind::ElementsCount);
+        let node_key = StoreKey::new(1, KeyKind::NodeHash, 1);
+
+        assert!(
+            store
+                .compare_and_set_many(
+                    &counter_key,
+                    0,
+                    vec![
+                        (node_key.clone(), StoreValue::Hash(Hash32::new([1u8; 32]))),
+                        (counter_key.clone(), StoreValue::U64(1)),
+                    ],
+                )
+                .await
+                .unwrap()
+        );
+        assert!(
+            !store
+                .compare_and_set_many(
+                    &counter_key,
+                    0,
+                    vec![(node_key.clone(), StoreValue::Hash(Hash32::new([2u8; 32])))],
+                )
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(
+            store.get(&node_key).await.unwrap(),
+            Some(StoreValue::Hash(Hash32::new([1u8; 32])))
+        );
+    }
+
+    #[tokio::test]
+    async fn write_batch_checks_every_guard_before_writing() {
+        let store = InMemoryStore::new();
+        let first = StoreKey::metadata(1, KeyKind::ElementsCount);
+        let second = StoreKey::metadata(2, KeyKind::ElementsCount);
+        let stale = StoreKey::new(1, KeyKind::NodeHash, 2);
+        store
+            .set_many(vec![
+                (first.clone(), StoreValue::U64(2)),
+                (stale.clone(), StoreValue::Hash(Hash32::new([2u8; 32]))),
+            ])
+            .await
+            .unwrap();
+
+        let rejected = WriteBatch::new()
+            .guard(first.clone(), 2)
+            .guard(second.clone(), 1)
+            .delete_many([stale.clone()])
+            .set_many([(first.clone(), StoreValue::U64(1))]);
+        assert!(!store.write_batch(rejected).await.unwrap());
+        assert!(store.get(&stale).await.unwrap().is_some());
+
+        let applied = WriteBatch::new()
+            .guard(first.clone(), 2)
+            .guard(second, 0)
+            .delete_many([stale.clone()])
+            .set_many([(first.clone(), StoreValue::U64(1))]);
+        assert!(store.write_batch(applied).await.unwrap());
+        assert!(store.get(&stale).await.unwrap().is_none());
+        assert_eq!(store.get(&first).await.unwrap(), Some(StoreValue::U64(1)));
+    }
+
+    #[tokio::test]
+    async fn scan_and_delete_are_scoped_to_mmr_id() {
+        let store = InMemoryStore::new();
+        store
+            .set_many(vec![
+                (
+                    StoreKey::new(1, KeyKind::NodeHash, 1),
+                    StoreValue::Hash(Hash32::new([1u8; 32])),
+                ),
+                (
+                    StoreKey::metadata(1, KeyKind::LeafCount),
+                    StoreValue::U64(1),
+                ),
+                (
+                    StoreKey::new(2, KeyKind::NodeHash, 1),
+                    StoreValue::Hash(Hash32::new([2u8; 32])),
+                ),
+            ])
+            .await
+            .unwrap();
+
+        let keys = store.scan_keys(1).await.unwrap();
+        assert_eq!(keys.len(), 2);
+
+        store.delete_many(&keys).await.unwrap();
+
+        assert!(store.scan_keys(1).await.unwrap().is_empty());
+        assert_eq!(store.scan_keys(2).await.unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,114 @@
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::error::{MmrError, StoreError};
+use crate::mmr::node_file::{NODE_FILE_HEADER_LEN, NodeFileHeader};
+use crate::types::{Hash32, MmrId};
+
+use super::{KeyKind, Store, StoreKey, StoreValue};
+
+/// Read-only store over a memory-mapped node file written by `Mmr::export_node_file`.
+/// Only the exported MMR's counters, root, node hashes and hasher code (as its registry
+/// entry) are served; every write fails with `StoreError::Unsupported`.
+#[derive(Debug)]
+pub struct MmapStore {
+    map: Mmap,
+    header: NodeFileHeader,
+}
+
+impl MmapStore {
+    /// Maps the file and checks that it was exported for the hasher registered under
+    /// `hasher_code`, e.g. `KECCAK_HASHER_CODE`.
+    pub fn open(path: impl AsRef<Path>, hasher_code: u64) -> Result<Self, MmrError> {
+        let file = File::open(path)?;
+        // SAFETY: node files are immutable once written; callers must not modify or
+        // truncate the file while it is mapped.
+        let map = unsafe { Mmap::map(&file)? };
+        let header = NodeFileHeader::decode(&map)?;
+        if header.hasher_code != hasher_code {
+            return Err(MmrError::NodeFileHasherMismatch {
+                expected: hasher_code,
+                found: header.hasher_code,
+            });
+        }
+        Ok(Self { map, header })
+    }
+
+    /// The MMR id recorded in the file; pass it to `Mmr::new` to read through this store.
+    pub fn mmr_id(&self) -> MmrId {
+        self.header.mmr_id
+    }
+
+    pub fn elements_count(&self) -> u64 {
+        self.header.elements_count
+    }
+
+    pub fn hasher_code(&self) -> u64 {
+        self.header.hasher_code
+    }
+
+    fn node_hash(&self, element_index: u64) -> Option<Hash32> {
+        if element_index == 0 || element_index > self.header.elements_count {
+            return None;
+        }
+        let offset = NODE_FILE_HEADER_LEN + (element_index as usize - 1) * 32;
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&self.map[offset..offset + 32]);
+        Some(Hash32::new(hash))
+    }
+
+    fn lookup(&self, key: &StoreKey) -> Option<StoreValue> {
+        if key.mmr_id != self.header.mmr_id {
+            return None;
+        }
+        match key.kind {
+            KeyKind::LeafCount if self.header.elements_count > 0 => {
+                Some(StoreValue::U64(self.header.leaves_count))
+            }
+            KeyKind::ElementsCount if self.header.elements_count > 0 => {
+                Some(StoreValue::U64(self.header.elements_count))
+            }
+            KeyKind::RootHash => self.header.root_hash.map(StoreValue::Hash),
+            KeyKind::NodeHash => self.node_hash(key.index).map(StoreValue::Hash),
+            KeyKind::RegistryEntry => Some(StoreValue::U64(self.header.hasher_code)),
+            _ => None,
+        }
+    }
+}
+
+impl Store for MmapStore {
+    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue>, StoreError> {
+        Ok(self.lookup(key))
+    }
+
+    async fn set(&self, _key: StoreKey, _value: StoreValue) -> Result<(), StoreError> {
+        Err(StoreError::Unsupported("set"))
+    }
+
+    async fn set_many(&self, _entries: Vec<(StoreKey, StoreValue)>) -> Result<(), StoreError> {
+        Err(StoreError::Unsupported("set_many"))
+    }
+
+    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue>>, StoreError> {
+        Ok(keys.iter().map(|key| self.lookup(key)).collect())
+    }
+
+    async fn compare_and_set_many(
+        &self,
+        _counter_key: &StoreKey,
+        _expected_counter: u64,
+        _entries: Vec<(StoreKey, StoreValue)>,
+    ) -> Result<bool, StoreError> {
+        Err(StoreError::Unsupported("compare_and_set_many"))
+    }
+
+    async fn list_mmr_ids(&self) -> Result<Vec<MmrId>, StoreError> {
+        Ok(vec![self.header.mmr_id])
+    }
+
+    async fn allocate_mmr_id(&self) -> Result<MmrId, StoreError> {
+        Err(StoreError::Unsupported("allocate_mmr_id"))
+    }
+}
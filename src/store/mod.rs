@@ -1,69 +1,243 @@
+mod batch;
+mod branch;
 mod key;
 mod memory;
+#[cfg(feature = "mmap-store")]
+mod mmap;
+mod overlay;
 #[cfg(feature = "postgres-store")]
 mod postgres;
+mod retry;
 
 use std::sync::Arc;
 
 use crate::error::StoreError;
+use crate::types::{Hash, MmrId};
 
+pub use batch::WriteBatch;
+pub use branch::BranchStore;
+pub(crate) use key::DedupedKeys;
 pub use key::{KeyKind, StoreKey, StoreValue};
+
+pub const ID_ALLOCATOR_MMR_ID: MmrId = 0;
+const MAX_ALLOCATE_ATTEMPTS: u32 = 16;
 pub use memory::InMemoryStore;
+#[cfg(feature = "mmap-store")]
+pub use mmap::MmapStore;
+pub use overlay::OverlayStore;
 #[cfg(feature = "postgres-store")]
 pub use postgres::{PostgresStore, PostgresStoreOptions};
+pub use retry::{RetryPolicy, RetryingStore};
 
+/// Key-value backend for an `Mmr` whose node hashes are `N` bytes wide. Backends that
+/// persist a fixed layout, such as Postgres and the mmap file, implement the 32-byte
+/// `Store` only.
 #[allow(async_fn_in_trait)]
-pub trait Store: Send + Sync {
-    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue>, StoreError>;
-    async fn set(&self, key: StoreKey, value: StoreValue) -> Result<(), StoreError>;
-    async fn set_many(&self, entries: Vec<(StoreKey, StoreValue)>) -> Result<(), StoreError> {
+pub trait Store<const N: usize = 32>: Send + Sync {
+    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue<N>>, StoreError>;
+    async fn set(&self, key: StoreKey, value: StoreValue<N>) -> Result<(), StoreError>;
+    async fn set_many(&self, entries: Vec<(StoreKey, StoreValue<N>)>) -> Result<(), StoreError> {
         for (key, value) in entries {
             self.set(key, value).await?;
         }
 
         Ok(())
     }
-    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue>>, StoreError>;
+    async fn bulk_load(&self, entries: Vec<(StoreKey, StoreValue<N>)>) -> Result<(), StoreError> {
+        self.set_many(entries).await
+    }
+    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue<N>>>, StoreError>;
+    /// Writes `entries` only if `counter_key` (missing reads as 0) still holds
+    /// `expected_counter`. Backends that can should make the check and the writes one
+    /// atomic step; this fallback reads the counter and then calls `set_many`, so two
+    /// writers can both pass the check and it is only safe with one writer per MMR.
+    async fn compare_and_set_many(
+        &self,
+        counter_key: &StoreKey,
+        expected_counter: u64,
+        entries: Vec<(StoreKey, StoreValue<N>)>,
+    ) -> Result<bool, StoreError> {
+        let current_counter = match self.get(counter_key).await? {
+            Some(value) => value.expect_u64(counter_key)?,
+            None => 0,
+        };
+        if current_counter != expected_counter {
+            return Ok(false);
+        }
+
+        self.set_many(entries).await?;
+        Ok(true)
+    }
+    /// Applies `batch` atomically if all of its guards match and reports whether it did.
+    /// Backends without multi-key transactions only accept a batch that maps onto
+    /// `set_many` or `compare_and_set_many`.
+    async fn write_batch(&self, batch: WriteBatch<N>) -> Result<bool, StoreError> {
+        if !batch.deletes.is_empty() {
+            return Err(StoreError::Unsupported("write_batch"));
+        }
+        match batch.guards.as_slice() {
+            [] => {
+                self.set_many(batch.sets).await?;
+                Ok(true)
+            }
+            [(counter_key, expected_counter)] => {
+                self.compare_and_set_many(counter_key, *expected_counter, batch.sets)
+                    .await
+            }
+            _ => Err(StoreError::Unsupported("write_batch")),
+        }
+    }
+    async fn delete_many(&self, keys: &[StoreKey]) -> Result<(), StoreError> {
+        let _ = keys;
+        Err(StoreError::Unsupported("delete_many"))
+    }
+    async fn scan_keys(&self, mmr_id: MmrId) -> Result<Vec<StoreKey>, StoreError> {
+        let _ = mmr_id;
+        Err(StoreError::Unsupported("scan_keys"))
+    }
+    async fn list_mmr_ids(&self) -> Result<Vec<MmrId>, StoreError> {
+        Err(StoreError::Unsupported("list_mmr_ids"))
+    }
+    /// Hands out a fresh `mmr_id` that no other process sharing the store can mint. This
+    /// needs an atomic `compare_and_set_many`, so the default is unsupported rather than
+    /// racing on the read-then-write fallback; backends with an atomic one override it,
+    /// usually with [`allocate_mmr_id_from_counter`].
+    async fn allocate_mmr_id(&self) -> Result<MmrId, StoreError> {
+        Err(StoreError::Unsupported("allocate_mmr_id"))
+    }
+}
+
+/// `Store::allocate_mmr_id` for backends whose `compare_and_set_many` is atomic: hands out
+/// ids above a persisted counter. The first allocation seeds the counter with the highest
+/// id already in the store, so ids chosen explicitly before the counter existed are never
+/// reissued; ids chosen explicitly later are not tracked.
+pub async fn allocate_mmr_id_from_counter<S: Store<N> + ?Sized, const N: usize>(
+    store: &S,
+) -> Result<MmrId, StoreError> {
+    let counter_key = StoreKey::metadata(ID_ALLOCATOR_MMR_ID, KeyKind::IdAllocator);
+    for _ in 0..MAX_ALLOCATE_ATTEMPTS {
+        let (stored, floor) = match store.get(&counter_key).await? {
+            Some(value) => {
+                let stored = value.expect_u64(&counter_key)?;
+                (stored, stored)
+            }
+            None => match store.list_mmr_ids().await {
+                Ok(mmr_ids) => (0, mmr_ids.into_iter().max().unwrap_or(0)),
+                Err(StoreError::Unsupported(_)) => (0, 0),
+                Err(err) => return Err(err),
+            },
+        };
+        let next = floor
+            .checked_add(1)
+            .ok_or_else(|| StoreError::Internal("mmr_id allocator exhausted".to_string()))?;
+        let claimed = store
+            .compare_and_set_many(
+                &counter_key,
+                stored,
+                vec![(counter_key.clone(), StoreValue::U64(next))],
+            )
+            .await?;
+        if claimed {
+            return Ok(next);
+        }
+    }
+
+    Err(StoreError::AllocationConflict(MAX_ALLOCATE_ATTEMPTS))
 }
 
-impl<T: Store + ?Sized> Store for Arc<T> {
-    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue>, StoreError> {
+impl<T: Store<N> + ?Sized, const N: usize> Store<N> for Arc<T> {
+    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue<N>>, StoreError> {
         (**self).get(key).await
     }
 
-    async fn set(&self, key: StoreKey, value: StoreValue) -> Result<(), StoreError> {
+    async fn set(&self, key: StoreKey, value: StoreValue<N>) -> Result<(), StoreError> {
         (**self).set(key, value).await
     }
 
-    async fn set_many(&self, entries: Vec<(StoreKey, StoreValue)>) -> Result<(), StoreError> {
+    async fn set_many(&self, entries: Vec<(StoreKey, StoreValue<N>)>) -> Result<(), StoreError> {
         (**self).set_many(entries).await
     }
 
-    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue>>, StoreError> {
+    async fn bulk_load(&self, entries: Vec<(StoreKey, StoreValue<N>)>) -> Result<(), StoreError> {
+        (**self).bulk_load(entries).await
+    }
+
+    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue<N>>>, StoreError> {
         (**self).get_many(keys).await
     }
+
+    async fn compare_and_set_many(
+        &self,
+        counter_key: &StoreKey,
+        expected_counter: u64,
+        entries: Vec<(StoreKey, StoreValue<N>)>,
+    ) -> Result<bool, StoreError> {
+        (**self)
+            .compare_and_set_many(counter_key, expected_counter, entries)
+            .await
+    }
+
+    async fn write_batch(&self, batch: WriteBatch<N>) -> Result<bool, StoreError> {
+        (**self).write_batch(batch).await
+    }
+
+    async fn delete_many(&self, keys: &[StoreKey]) -> Result<(), StoreError> {
+        (**self).delete_many(keys).await
+    }
+
+    async fn scan_keys(&self, mmr_id: MmrId) -> Result<Vec<StoreKey>, StoreError> {
+        (**self).scan_keys(mmr_id).await
+    }
+
+    async fn list_mmr_ids(&self) -> Result<Vec<MmrId>, StoreError> {
+        (**self).list_mmr_ids().await
+    }
+
+    async fn allocate_mmr_id(&self) -> Result<MmrId, StoreError> {
+        (**self).allocate_mmr_id().await
+    }
 }
 
-impl StoreValue {
+impl<const N: usize> StoreValue<N> {
     pub fn expect_u64(self, key: &StoreKey) -> Result<u64, StoreError> {
         match self {
             StoreValue::U64(value) => Ok(value),
             other => Err(StoreError::TypeMismatch {
                 key: key.clone(),
                 expected: "u64",
-                actual: other,
+                actual: other.kind_name(),
             }),
         }
     }
 
-    pub fn expect_hash(self, key: &StoreKey) -> Result<crate::types::Hash32, StoreError> {
+    pub fn expect_hash(self, key: &StoreKey) -> Result<Hash<N>, StoreError> {
         match self {
             StoreValue::Hash(value) => Ok(value),
             other => Err(StoreError::TypeMismatch {
                 key: key.clone(),
-                expected: "hash32",
-                actual: other,
+                expected: "hash",
+                actual: other.kind_name(),
             }),
         }
     }
+
+    pub fn expect_bytes(self, key: &StoreKey) -> Result<Vec<u8>, StoreError> {
+        match self {
+            StoreValue::Bytes(value) => Ok(value),
+            other => Err(StoreError::TypeMismatch {
+                key: key.clone(),
+                expected: "bytes",
+                actual: other.kind_name(),
+            }),
+        }
+    }
+
+    /// Name of the variant, for type mismatch errors.
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            StoreValue::U64(_) => "u64",
+            StoreValue::Hash(_) => "hash",
+            StoreValue::Bytes(_) => "bytes",
+        }
+    }
 }
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::error::StoreError;
+
+use super::{Store, StoreKey, StoreValue, WriteBatch};
+
+/// Stages writes over a borrowed base store; a staged `None` is a delete that hides the
+/// base value.
+#[derive(Debug)]
+pub struct OverlayStore<'a, S: Store<N>, const N: usize = 32> {
+    base: &'a S,
+    staged: RwLock<HashMap<StoreKey, Option<StoreValue<N>>>>,
+}
+
+impl<'a, S: Store<N>, const N: usize> OverlayStore<'a, S, N> {
+    pub fn new(base: &'a S) -> Self {
+        Self {
+            base,
+            staged: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn staged_len(&self) -> Result<usize, StoreError> {
+        let guard = self
+            .staged
+            .read()
+            .map_err(|_| StoreError::Internal("rwlock poisoned (read)".to_string()))?;
+        Ok(guard.len())
+    }
+
+    /// The staged sets and deletes, as an unguarded batch.
+    pub fn into_staged(self) -> Result<WriteBatch<N>, StoreError> {
+        let staged = self
+            .staged
+            .into_inner()
+            .map_err(|_| StoreError::Internal("rwlock poisoned (into_inner)".to_string()))?;
+        let mut batch = WriteBatch::new();
+        for (key, value) in staged {
+            match value {
+                Some(value) => batch.sets.push((key, value)),
+                None => batch.deletes.push(key),
+            }
+        }
+        Ok(batch)
+    }
+
+    fn stage(
+        &self,
+        deletes: Vec<StoreKey>,
+        sets: Vec<(StoreKey, StoreValue<N>)>,
+    ) -> Result<(), StoreError> {
+        let mut guard = self
+            .staged
+            .write()
+            .map_err(|_| StoreError::Internal("rwlock poisoned (write)".to_string()))?;
+
+        for key in deletes {
+            guard.insert(key, None);
+        }
+        for (key, value) in sets {
+            guard.insert(key, Some(value));
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: Store<N>, const N: usize> Store<N> for OverlayStore<'_, S, N> {
+    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue<N>>, StoreError> {
+        let staged = {
+            let guard = self
+                .staged
+                .read()
+                .map_err(|_| StoreError::Internal("rwlock poisoned (read)".to_string()))?;
+            guard.get(key).cloned()
+        };
+
+        match staged {
+            Some(value) => Ok(value),
+            None => self.base.get(key).await,
+        }
+    }
+
+    async fn set(&self, key: StoreKey, value: StoreValue<N>) -> Result<(), StoreError> {
+        self.set_many(vec![(key, value)]).await
+    }
+
+    async fn set_many(&self, entries: Vec<(StoreKey, StoreValue<N>)>) -> Result<(), StoreError> {
+        self.stage(Vec::new(), entries)
+    }
+
+    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue<N>>>, StoreError> {
+        let mut out = Vec::with_capacity(keys.len());
+        let mut missing_positions = Vec::new();
+        let mut missing_keys = Vec::new();
+        {
+            let guard = self
+                .staged
+                .read()
+                .map_err(|_| StoreError::Internal("rwlock poisoned (read)".to_string()))?;
+            for (position, key) in keys.iter().enumerate() {
+                let value = guard.get(key).cloned();
+                if value.is_none() {
+                    missing_positions.push(position);
+                    missing_keys.push(key.clone());
+                }
+                out.push(value.flatten());
+            }
+        }
+
+        if !missing_keys.is_empty() {
+            let base_values = self.base.get_many(&missing_keys).await?;
+            for (position, value) in missing_positions.into_iter().zip(base_values) {
+                out[position] = value;
+            }
+        }
+
+        Ok(out)
+    }
+
+    async fn compare_and_set_many(
+        &self,
+        counter_key: &StoreKey,
+        expected_counter: u64,
+        entries: Vec<(StoreKey, StoreValue<N>)>,
+    ) -> Result<bool, StoreError> {
+        let current_counter = match self.get(counter_key).await? {
+            Some(value) => value.expect_u64(counter_key)?,
+            None => 0,
+        };
+        if current_counter != expected_counter {
+            return Ok(false);
+        }
+
+        self.set_many(entries).await?;
+        Ok(true)
+    }
+
+    async fn write_batch(&self, batch: WriteBatch<N>) -> Result<bool, StoreError> {
+        for (counter_key, expected_counter) in &batch.guards {
+            let current_counter = match self.get(counter_key).await? {
+                Some(value) => value.expect_u64(counter_key)?,
+                None => 0,
+            };
+            if current_counter != *expected_counter {
+                return Ok(false);
+            }
+        }
+
+        self.stage(batch.deletes, batch.sets)?;
+        Ok(true)
+    }
+
+    async fn delete_many(&self, keys: &[StoreKey]) -> Result<(), StoreError> {
+        self.stage(keys.to_vec(), Vec::new())
+    }
+}
@@ -1,12 +1,24 @@
-use sqlx::postgres::{PgPoolOptions, PgRow};
+use sqlx::postgres::{PgPoolCopyExt, PgPoolOptions, PgRow};
 use sqlx::{PgPool, Postgres, Row, Transaction};
+use tiny_keccak::{Hasher as TinyHasher, Keccak};
 
 use crate::error::StoreError;
+use crate::types::{Hash32, MmrId};
 
-use super::{KeyKind, Store, StoreKey, StoreValue};
+use super::{KeyKind, Store, StoreKey, StoreValue, WriteBatch, allocate_mmr_id_from_counter};
 
 const DEFAULT_TABLE_NAME: &str = "mmr_nodes";
 const DEFAULT_MAX_CONNECTIONS: u32 = 20;
+const COPY_CHUNK_BYTES: usize = 1 << 20;
+const MMR_ID_TYPE_SQL: &str = "SELECT format_type(atttypid, atttypmod)
+    FROM pg_attribute
+    WHERE attrelid = to_regclass($1) AND attname = 'mmr_id'";
+const CHECK_CONSTRAINTS_SQL: &str = "SELECT conname::TEXT, convalidated
+    FROM pg_constraint
+    WHERE conrelid = to_regclass($1) AND contype = 'c'";
+/// Highest `KeyKind` the CHECK constraints admit. The constraint names carry it, so adding a
+/// kind renames them and `init_schema` replaces the constraints of older tables.
+const MAX_KIND: i16 = KeyKind::LeafKey as i16;
 
 #[derive(Debug, Clone, Copy)]
 pub struct PostgresStoreOptions {
@@ -66,6 +78,75 @@ impl PostgresStore {
         sqlx::query(&self.create_table_sql())
             .execute(&self.pool)
             .await?;
+        // Tables from before mmr ids were widened hold them as INT4. Only those are
+        // rewritten; `ALTER COLUMN ... TYPE` takes an exclusive lock even when it is a no-op.
+        let mmr_id_type: String = sqlx::query_scalar(MMR_ID_TYPE_SQL)
+            .bind(&self.table_name)
+            .fetch_one(&self.pool)
+            .await?;
+        if mmr_id_type != "bigint" {
+            sqlx::query(&self.widen_mmr_id_sql())
+                .execute(&self.pool)
+                .await?;
+        }
+
+        // Tables created for fewer kinds reject writes of the newer ones, so their CHECK
+        // constraints are swapped for the current ones. Constraints added by hand are kept.
+        // The new ones are added `NOT VALID`, so the swap does not hold the table's exclusive
+        // lock while existing rows are checked; `VALIDATE` checks them afterwards under a
+        // lock that lets writes through.
+        let checks: Vec<(String, bool)> = sqlx::query_as(CHECK_CONSTRAINTS_SQL)
+            .bind(&self.table_name)
+            .fetch_all(&self.pool)
+            .await?;
+        let expected_names = [self.kind_check_name(), self.value_check_name()];
+        let stale: Vec<&String> = checks
+            .iter()
+            .map(|(name, _)| name)
+            .filter(|name| self.is_own_check(name) && !expected_names.contains(name))
+            .collect();
+        let missing: Vec<&String> = expected_names
+            .iter()
+            .filter(|expected| !checks.iter().any(|(name, _)| name == *expected))
+            .collect();
+        if !stale.is_empty() || !missing.is_empty() {
+            let mut tx = self.pool.begin().await?;
+            for name in &stale {
+                sqlx::query(&format!(
+                    "ALTER TABLE {} DROP CONSTRAINT \"{name}\"",
+                    self.table_name
+                ))
+                .execute(&mut *tx)
+                .await?;
+            }
+            for name in &missing {
+                let check_sql = if **name == expected_names[0] {
+                    self.kind_check_sql()
+                } else {
+                    self.value_check_sql()
+                };
+                sqlx::query(&format!(
+                    "ALTER TABLE {} ADD {check_sql} NOT VALID",
+                    self.table_name
+                ))
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await?;
+        }
+        for name in &expected_names {
+            let validated = checks
+                .iter()
+                .any(|(existing, validated)| existing == name && *validated);
+            if !validated {
+                sqlx::query(&format!(
+                    "ALTER TABLE {} VALIDATE CONSTRAINT \"{name}\"",
+                    self.table_name
+                ))
+                .execute(&self.pool)
+                .await?;
+            }
+        }
 
         Ok(())
     }
@@ -83,7 +164,7 @@ impl PostgresStore {
             return Ok(());
         }
 
-        let (mmr_ids, kinds, indices, values) = prepare_entries(entries)?;
+        let ((mmr_ids, kinds, indices), values) = prepare_entries(entries)?;
         let query = self.set_many_query();
 
         sqlx::query(&query)
@@ -97,6 +178,34 @@ impl PostgresStore {
         Ok(())
     }
 
+    /// `write_batch` inside the caller's transaction. The guarded mmrs stay locked against
+    /// other guarded writers until `tx` ends, so the guards hold through its commit. Nothing
+    /// is written when a guard fails.
+    pub(crate) async fn write_batch_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        batch: WriteBatch,
+    ) -> Result<bool, StoreError> {
+        let mmr_ids = batch.guards.iter().map(|(key, _)| key.mmr_id).collect();
+        lock_mmrs_in_tx(tx, self.lock_class(), mmr_ids).await?;
+
+        let guard_keys: Vec<StoreKey> = batch.guards.iter().map(|(key, _)| key.clone()).collect();
+        let current = self.get_many_in_tx(tx, &guard_keys).await?;
+        for ((counter_key, expected_counter), value) in batch.guards.iter().zip(current) {
+            let current_counter = match value {
+                Some(value) => value.expect_u64(counter_key)?,
+                None => 0,
+            };
+            if current_counter != *expected_counter {
+                return Ok(false);
+            }
+        }
+
+        self.delete_many_in_tx(tx, &batch.deletes).await?;
+        self.set_many_in_tx(tx, batch.sets).await?;
+        Ok(true)
+    }
+
     pub(crate) async fn get_many_in_tx(
         &self,
         tx: &mut Transaction<'_, Postgres>,
@@ -119,22 +228,98 @@ impl PostgresStore {
         decode_many_values(keys, rows)
     }
 
+    pub(crate) async fn delete_many_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        keys: &[StoreKey],
+    ) -> Result<(), StoreError> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let (mmr_ids, kinds, indices) = prepare_keys(keys)?;
+        let query = self.delete_many_query();
+
+        sqlx::query(&query)
+            .bind(&mmr_ids)
+            .bind(&kinds)
+            .bind(&indices)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
     fn create_table_sql(&self) -> String {
         format!(
             "CREATE TABLE IF NOT EXISTS {table} (
-                mmr_id INT4 NOT NULL,
+                mmr_id INT8 NOT NULL,
                 kind INT2 NOT NULL,
                 idx INT8 NOT NULL,
                 value BYTEA NOT NULL,
                 PRIMARY KEY (mmr_id, kind, idx),
-                CHECK (kind BETWEEN 0 AND 3),
-                CHECK (
-                    (kind IN (0, 1) AND octet_length(value) = 8)
-                    OR
-                    (kind IN (2, 3) AND octet_length(value) = 32)
-                )
+                {kind_check},
+                {value_check}
             );",
-            table = self.table_name
+            table = self.table_name,
+            kind_check = self.kind_check_sql(),
+            value_check = self.value_check_sql()
+        )
+    }
+
+    fn kind_check_name(&self) -> String {
+        format!("{}_kind_check_{MAX_KIND}", self.table_name)
+    }
+
+    fn value_check_name(&self) -> String {
+        format!("{}_value_check_{MAX_KIND}", self.table_name)
+    }
+
+    /// First key of this table's advisory locks, so locks of other tables and of other
+    /// applications sharing the database do not contend with them.
+    fn lock_class(&self) -> i32 {
+        let mut keccak = Keccak::v256();
+        keccak.update(self.table_name.as_bytes());
+        let mut digest = [0u8; 32];
+        keccak.finalize(&mut digest);
+        i32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])
+    }
+
+    /// Matches every CHECK constraint this crate created, including the unnamed ones of
+    /// tables from before the names carried `MAX_KIND`, which Postgres named after `kind`.
+    fn is_own_check(&self, name: &str) -> bool {
+        [
+            format!("{}_kind_check", self.table_name),
+            format!("{}_value_check", self.table_name),
+        ]
+        .iter()
+        .any(|prefix| name.starts_with(prefix.as_str()))
+    }
+
+    fn kind_check_sql(&self) -> String {
+        format!(
+            "CONSTRAINT {} CHECK (kind BETWEEN 0 AND {MAX_KIND})",
+            self.kind_check_name()
+        )
+    }
+
+    fn value_check_sql(&self) -> String {
+        format!(
+            "CONSTRAINT {} CHECK (
+                (kind IN (0, 1, 4, 6, 7, 8, 9, 10, 13) AND octet_length(value) = 8)
+                OR
+                (kind IN (2, 3) AND octet_length(value) = 32)
+                OR
+                kind IN (5, 11, 12)
+            )",
+            self.value_check_name()
+        )
+    }
+
+    fn widen_mmr_id_sql(&self) -> String {
+        format!(
+            "ALTER TABLE {} ALTER COLUMN mmr_id TYPE INT8",
+            self.table_name
         )
     }
 
@@ -158,7 +343,7 @@ impl PostgresStore {
         format!(
             "WITH input AS (
                 SELECT *
-                FROM unnest($1::int4[], $2::int2[], $3::int8[], $4::bytea[])
+                FROM unnest($1::int8[], $2::int2[], $3::int8[], $4::bytea[])
                 AS t(mmr_id, kind, idx, value)
             )
             INSERT INTO {table} (mmr_id, kind, idx, value)
@@ -168,11 +353,18 @@ impl PostgresStore {
         )
     }
 
+    fn copy_in_query(&self) -> String {
+        format!(
+            "COPY {} (mmr_id, kind, idx, value) FROM STDIN",
+            self.table_name
+        )
+    }
+
     fn get_many_query(&self) -> String {
         format!(
             "WITH requested AS (
                 SELECT *
-                FROM unnest($1::int4[], $2::int2[], $3::int8[])
+                FROM unnest($1::int8[], $2::int2[], $3::int8[])
                 WITH ORDINALITY AS req(mmr_id, kind, idx, ord)
             )
             SELECT req.ord, store.value
@@ -185,6 +377,31 @@ impl PostgresStore {
             table = self.table_name
         )
     }
+
+    fn delete_many_query(&self) -> String {
+        format!(
+            "DELETE FROM {table} store
+            USING unnest($1::int8[], $2::int2[], $3::int8[]) AS req(mmr_id, kind, idx)
+            WHERE store.mmr_id = req.mmr_id
+              AND store.kind = req.kind
+              AND store.idx = req.idx",
+            table = self.table_name
+        )
+    }
+
+    fn list_mmr_ids_query(&self) -> String {
+        format!(
+            "SELECT DISTINCT mmr_id FROM {} ORDER BY mmr_id",
+            self.table_name
+        )
+    }
+
+    fn scan_keys_query(&self) -> String {
+        format!(
+            "SELECT kind, idx FROM {} WHERE mmr_id = $1 ORDER BY kind, idx",
+            self.table_name
+        )
+    }
 }
 
 impl Store for PostgresStore {
@@ -233,7 +450,7 @@ impl Store for PostgresStore {
             return Ok(());
         }
 
-        let (mmr_ids, kinds, indices, values) = prepare_entries(entries)?;
+        let ((mmr_ids, kinds, indices), values) = prepare_entries(entries)?;
         let query = self.set_many_query();
 
         sqlx::query(&query)
@@ -247,6 +464,32 @@ impl Store for PostgresStore {
         Ok(())
     }
 
+    async fn bulk_load(&self, entries: Vec<(StoreKey, StoreValue)>) -> Result<(), StoreError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        // COPY is a single statement, so a failed load leaves none of `entries` behind and
+        // the caller can rerun it. Rows are sent in chunks rather than as one payload.
+        let mut copy = self.pool.copy_in_raw(&self.copy_in_query()).await?;
+        let mut payload = String::with_capacity(COPY_CHUNK_BYTES);
+        for (key, value) in &entries {
+            if let Err(err) = push_copy_row(&mut payload, key, value) {
+                copy.abort(err.to_string()).await?;
+                return Err(err);
+            }
+            if payload.len() >= COPY_CHUNK_BYTES {
+                copy.send(std::mem::take(&mut payload).into_bytes()).await?;
+            }
+        }
+        if !payload.is_empty() {
+            copy.send(payload.into_bytes()).await?;
+        }
+        copy.finish().await?;
+
+        Ok(())
+    }
+
     async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue>>, StoreError> {
         if keys.is_empty() {
             return Ok(Vec::new());
@@ -264,11 +507,96 @@ impl Store for PostgresStore {
 
         decode_many_values(keys, rows)
     }
+
+    async fn compare_and_set_many(
+        &self,
+        counter_key: &StoreKey,
+        expected_counter: u64,
+        entries: Vec<(StoreKey, StoreValue)>,
+    ) -> Result<bool, StoreError> {
+        self.write_batch(
+            WriteBatch::new()
+                .guard(counter_key.clone(), expected_counter)
+                .set_many(entries),
+        )
+        .await
+    }
+
+    async fn write_batch(&self, batch: WriteBatch) -> Result<bool, StoreError> {
+        let mut tx = self.pool.begin().await?;
+        if !self.write_batch_in_tx(&mut tx, batch).await? {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn delete_many(&self, keys: &[StoreKey]) -> Result<(), StoreError> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let (mmr_ids, kinds, indices) = prepare_keys(keys)?;
+        let query = self.delete_many_query();
+
+        sqlx::query(&query)
+            .bind(&mmr_ids)
+            .bind(&kinds)
+            .bind(&indices)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn scan_keys(&self, mmr_id: MmrId) -> Result<Vec<StoreKey>, StoreError> {
+        let query = self.scan_keys_query();
+
+        let rows = sqlx::query(&query)
+            .bind(to_pg_mmr_id(mmr_id)?)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter()
+            .map(|row| -> Result<StoreKey, StoreError> {
+                let kind: i16 = row.try_get("kind")?;
+                let idx: i64 = row.try_get("idx")?;
+                let index = u64::try_from(idx).map_err(|_| {
+                    StoreError::Internal(format!("negative index returned by postgres: {idx}"))
+                })?;
+                Ok(StoreKey::new(mmr_id, kind_from_i16(kind)?, index))
+            })
+            .collect()
+    }
+
+    async fn list_mmr_ids(&self) -> Result<Vec<MmrId>, StoreError> {
+        let query = self.list_mmr_ids_query();
+
+        let rows = sqlx::query(&query).fetch_all(&self.pool).await?;
+
+        rows.into_iter()
+            .map(|row| -> Result<MmrId, StoreError> {
+                let mmr_id: i64 = row.try_get("mmr_id")?;
+                MmrId::try_from(mmr_id).map_err(|_| {
+                    StoreError::Internal(format!("negative mmr_id returned by postgres: {mmr_id}"))
+                })
+            })
+            .collect()
+    }
+
+    async fn allocate_mmr_id(&self) -> Result<MmrId, StoreError> {
+        allocate_mmr_id_from_counter(self).await
+    }
 }
 
+/// `mmr_id`, `kind` and `idx` columns of a batch, bound as arrays.
+type KeyColumns = (Vec<i64>, Vec<i16>, Vec<i64>);
+
 fn prepare_entries(
     entries: Vec<(StoreKey, StoreValue)>,
-) -> Result<(Vec<i32>, Vec<i16>, Vec<i64>, Vec<Vec<u8>>), StoreError> {
+) -> Result<(KeyColumns, Vec<Vec<u8>>), StoreError> {
     let mut mmr_ids = Vec::with_capacity(entries.len());
     let mut kinds = Vec::with_capacity(entries.len());
     let mut indices = Vec::with_capacity(entries.len());
@@ -281,10 +609,29 @@ fn prepare_entries(
         values.push(encode_store_value(&key, &value)?);
     }
 
-    Ok((mmr_ids, kinds, indices, values))
+    Ok(((mmr_ids, kinds, indices), values))
+}
+
+/// Appends one `COPY ... FROM STDIN` text row for `key`, checking the value the same way a
+/// read decodes it.
+fn push_copy_row(
+    payload: &mut String,
+    key: &StoreKey,
+    value: &StoreValue,
+) -> Result<(), StoreError> {
+    let bytes = encode_store_value(key, value)?;
+    check_value_len(key, &bytes)?;
+    payload.push_str(&format!(
+        "{}\t{}\t{}\t\\\\x{}\n",
+        to_pg_mmr_id(key.mmr_id)?,
+        kind_to_i16(key.kind),
+        to_pg_idx(key.index)?,
+        hex::encode(bytes)
+    ));
+    Ok(())
 }
 
-fn prepare_keys(keys: &[StoreKey]) -> Result<(Vec<i32>, Vec<i16>, Vec<i64>), StoreError> {
+fn prepare_keys(keys: &[StoreKey]) -> Result<KeyColumns, StoreError> {
     let mut mmr_ids = Vec::with_capacity(keys.len());
     let mut kinds = Vec::with_capacity(keys.len());
     let mut indices = Vec::with_capacity(keys.len());
@@ -317,18 +664,75 @@ fn decode_many_values(
     Ok(out)
 }
 
+/// Takes the transaction-scoped advisory lock every guarded write holds for its mmrs,
+/// keyed by the table's `class` and the mmr id folded to 32 bits. Ids that fold together
+/// only share a lock.
+async fn lock_mmrs_in_tx(
+    tx: &mut Transaction<'_, Postgres>,
+    class: i32,
+    mmr_ids: Vec<MmrId>,
+) -> Result<(), StoreError> {
+    let mut lock_keys: Vec<i32> = mmr_ids
+        .into_iter()
+        .map(|mmr_id| (mmr_id ^ (mmr_id >> 32)) as u32 as i32)
+        .collect();
+    // Lock in a fixed order so two batches guarding the same mmrs cannot deadlock.
+    lock_keys.sort_unstable();
+    lock_keys.dedup();
+    for lock_key in lock_keys {
+        sqlx::query("SELECT pg_advisory_xact_lock($1, $2)")
+            .bind(class)
+            .bind(lock_key)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
 fn kind_to_i16(kind: KeyKind) -> i16 {
     match kind {
         KeyKind::LeafCount => 0,
         KeyKind::ElementsCount => 1,
         KeyKind::RootHash => 2,
         KeyKind::NodeHash => 3,
+        KeyKind::WriterLease => 4,
+        KeyKind::LeafData => 5,
+        KeyKind::ExternalKey => 6,
+        KeyKind::HashIndex => 7,
+        KeyKind::RegistryEntry => 8,
+        KeyKind::IdAllocator => 9,
+        KeyKind::BranchParent => 10,
+        KeyKind::Checkpoint => 11,
+        KeyKind::Peaks => 12,
+        KeyKind::LeafKey => 13,
     }
 }
 
-fn to_pg_mmr_id(mmr_id: u32) -> Result<i32, StoreError> {
-    i32::try_from(mmr_id)
-        .map_err(|_| StoreError::Internal(format!("mmr_id out of i32 range: {mmr_id}")))
+fn kind_from_i16(kind: i16) -> Result<KeyKind, StoreError> {
+    match kind {
+        0 => Ok(KeyKind::LeafCount),
+        1 => Ok(KeyKind::ElementsCount),
+        2 => Ok(KeyKind::RootHash),
+        3 => Ok(KeyKind::NodeHash),
+        4 => Ok(KeyKind::WriterLease),
+        5 => Ok(KeyKind::LeafData),
+        6 => Ok(KeyKind::ExternalKey),
+        7 => Ok(KeyKind::HashIndex),
+        8 => Ok(KeyKind::RegistryEntry),
+        9 => Ok(KeyKind::IdAllocator),
+        10 => Ok(KeyKind::BranchParent),
+        11 => Ok(KeyKind::Checkpoint),
+        12 => Ok(KeyKind::Peaks),
+        13 => Ok(KeyKind::LeafKey),
+        other => Err(StoreError::Internal(format!(
+            "unknown key kind returned by postgres: {other}"
+        ))),
+    }
+}
+
+fn to_pg_mmr_id(mmr_id: MmrId) -> Result<i64, StoreError> {
+    i64::try_from(mmr_id)
+        .map_err(|_| StoreError::Internal(format!("mmr_id out of i64 range: {mmr_id}")))
 }
 
 fn to_pg_idx(index: u64) -> Result<i64, StoreError> {
@@ -338,51 +742,94 @@ fn to_pg_idx(index: u64) -> Result<i64, StoreError> {
 
 fn encode_store_value(key: &StoreKey, value: &StoreValue) -> Result<Vec<u8>, StoreError> {
     match (key.kind, value) {
-        (KeyKind::LeafCount | KeyKind::ElementsCount, StoreValue::U64(raw)) => {
-            Ok(raw.to_be_bytes().to_vec())
-        }
+        (
+            KeyKind::LeafCount
+            | KeyKind::ElementsCount
+            | KeyKind::WriterLease
+            | KeyKind::ExternalKey
+            | KeyKind::HashIndex
+            | KeyKind::RegistryEntry
+            | KeyKind::IdAllocator
+            | KeyKind::BranchParent
+            | KeyKind::LeafKey,
+            StoreValue::U64(raw),
+        ) => Ok(raw.to_be_bytes().to_vec()),
         (KeyKind::RootHash | KeyKind::NodeHash, StoreValue::Hash(hash)) => Ok(hash.to_vec()),
+        (KeyKind::LeafData | KeyKind::Checkpoint | KeyKind::Peaks, StoreValue::Bytes(bytes)) => {
+            Ok(bytes.clone())
+        }
         _ => Err(StoreError::TypeMismatch {
             key: key.clone(),
             expected: expected_type_for_kind(key.kind),
-            actual: value.clone(),
+            actual: value.kind_name(),
         }),
     }
 }
 
 fn decode_store_value(key: &StoreKey, bytes: &[u8]) -> Result<StoreValue, StoreError> {
+    check_value_len(key, bytes)?;
     match key.kind {
-        KeyKind::LeafCount | KeyKind::ElementsCount => {
-            if bytes.len() != 8 {
-                return Err(StoreError::Internal(format!(
-                    "expected 8 bytes for {:?}, got {}",
-                    key.kind,
-                    bytes.len()
-                )));
-            }
+        KeyKind::LeafCount
+        | KeyKind::ElementsCount
+        | KeyKind::WriterLease
+        | KeyKind::ExternalKey
+        | KeyKind::HashIndex
+        | KeyKind::RegistryEntry
+        | KeyKind::IdAllocator
+        | KeyKind::BranchParent
+        | KeyKind::LeafKey => {
             let mut out = [0u8; 8];
             out.copy_from_slice(bytes);
             Ok(StoreValue::U64(u64::from_be_bytes(out)))
         }
         KeyKind::RootHash | KeyKind::NodeHash => {
-            if bytes.len() != 32 {
-                return Err(StoreError::Internal(format!(
-                    "expected 32 bytes for {:?}, got {}",
-                    key.kind,
-                    bytes.len()
-                )));
-            }
             let mut out = [0u8; 32];
             out.copy_from_slice(bytes);
-            Ok(StoreValue::Hash(out))
+            Ok(StoreValue::Hash(Hash32::new(out)))
+        }
+        KeyKind::LeafData | KeyKind::Checkpoint | KeyKind::Peaks => {
+            Ok(StoreValue::Bytes(bytes.to_vec()))
         }
     }
 }
 
+fn check_value_len(key: &StoreKey, bytes: &[u8]) -> Result<(), StoreError> {
+    let expected = match key.kind {
+        KeyKind::LeafCount
+        | KeyKind::ElementsCount
+        | KeyKind::WriterLease
+        | KeyKind::ExternalKey
+        | KeyKind::HashIndex
+        | KeyKind::RegistryEntry
+        | KeyKind::IdAllocator
+        | KeyKind::BranchParent
+        | KeyKind::LeafKey => 8,
+        KeyKind::RootHash | KeyKind::NodeHash => 32,
+        KeyKind::LeafData | KeyKind::Checkpoint | KeyKind::Peaks => return Ok(()),
+    };
+    if bytes.len() != expected {
+        return Err(StoreError::Internal(format!(
+            "expected {expected} bytes for {:?}, got {}",
+            key.kind,
+            bytes.len()
+        )));
+    }
+    Ok(())
+}
+
 fn expected_type_for_kind(kind: KeyKind) -> &'static str {
     match kind {
-        KeyKind::LeafCount | KeyKind::ElementsCount => "u64",
+        KeyKind::LeafCount
+        | KeyKind::ElementsCount
+        | KeyKind::WriterLease
+        | KeyKind::ExternalKey
+        | KeyKind::HashIndex
+        | KeyKind::RegistryEntry
+        | KeyKind::IdAllocator
+        | KeyKind::BranchParent
+        | KeyKind::LeafKey => "u64",
         KeyKind::RootHash | KeyKind::NodeHash => "hash32",
+        KeyKind::LeafData | KeyKind::Checkpoint | KeyKind::Peaks => "bytes",
     }
 }
 
@@ -394,7 +841,7 @@ mod tests {
     #[test]
     fn value_encoding_for_node_hash_is_compact() {
         let key = StoreKey::new(1, KeyKind::NodeHash, 42);
-        let value = StoreValue::Hash([9u8; 32]);
+        let value = StoreValue::Hash(Hash32::new([9u8; 32]));
         let encoded = encode_store_value(&key, &value).unwrap();
         assert_eq!(encoded.len(), 32);
     }
@@ -407,6 +854,22 @@ mod tests {
         assert_eq!(encoded.len(), 8);
     }
 
+    #[test]
+    fn copy_rows_are_checked_before_they_are_sent() {
+        let mut payload = String::new();
+        let key = StoreKey::metadata(1, KeyKind::LeafCount);
+        push_copy_row(&mut payload, &key, &StoreValue::U64(7)).unwrap();
+        assert_eq!(payload, "1\t0\t0\t\\\\x0000000000000007\n");
+
+        let err = push_copy_row(
+            &mut payload,
+            &StoreKey::new(1, KeyKind::NodeHash, 3),
+            &StoreValue::U64(7),
+        )
+        .unwrap_err();
+        assert!(matches!(err, StoreError::TypeMismatch { .. }));
+    }
+
     #[tokio::test]
     async fn set_many_roundtrip_works_when_database_url_is_available() {
         let database_url = match std::env::var("DATABASE_URL") {
@@ -428,7 +891,7 @@ mod tests {
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_nanos() as u64;
-        let mmr_id = ((nonce % ((i32::MAX as u64) - 10_000)) as u32) + 10_000;
+        let mmr_id = (nonce % ((i64::MAX as u64) - 10_000)) + 10_000;
         let node_index = nonce;
 
         let keys = vec![
@@ -439,7 +902,7 @@ mod tests {
         store
             .set_many(vec![
                 (keys[0].clone(), StoreValue::U64(12)),
-                (keys[1].clone(), StoreValue::Hash([7u8; 32])),
+                (keys[1].clone(), StoreValue::Hash(Hash32::new([7u8; 32]))),
             ])
             .await
             .unwrap();
@@ -463,6 +926,89 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn init_schema_upgrades_a_baseline_table_when_database_url_is_available() {
+        let database_url = match std::env::var("DATABASE_URL") {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let store = PostgresStore {
+            pool: PgPoolOptions::new()
+                .max_connections(2)
+                .connect(&database_url)
+                .await
+                .unwrap(),
+            table_name: format!("mmr_nodes_baseline_{nonce}"),
+        };
+        sqlx::query(&format!(
+            "CREATE TABLE {table} (
+                mmr_id INT4 NOT NULL,
+                kind INT2 NOT NULL,
+                idx INT8 NOT NULL,
+                value BYTEA NOT NULL,
+                PRIMARY KEY (mmr_id, kind, idx),
+                CHECK (kind BETWEEN 0 AND 3),
+                CHECK (
+                    (kind IN (0, 1) AND octet_length(value) = 8)
+                    OR
+                    (kind IN (2, 3) AND octet_length(value) = 32)
+                ),
+                CONSTRAINT {table}_operator_idx CHECK (idx >= 0)
+            )",
+            table = store.table_name
+        ))
+        .execute(&store.pool)
+        .await
+        .unwrap();
+
+        store.init_schema().await.unwrap();
+        store.init_schema().await.unwrap();
+        let checks: Vec<(String, bool)> = sqlx::query_as(CHECK_CONSTRAINTS_SQL)
+            .bind(&store.table_name)
+            .fetch_all(&store.pool)
+            .await
+            .unwrap();
+        let mut names: Vec<String> = checks
+            .iter()
+            .filter(|(_, validated)| *validated)
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        let mut expected = vec![
+            store.kind_check_name(),
+            format!("{}_operator_idx", store.table_name),
+            store.value_check_name(),
+        ];
+        expected.sort();
+        let result = store
+            .set_many(vec![
+                (
+                    StoreKey::metadata(1, KeyKind::WriterLease),
+                    StoreValue::U64(3),
+                ),
+                (
+                    StoreKey::new(1, KeyKind::LeafData, 1),
+                    StoreValue::Bytes(vec![1, 2, 3]),
+                ),
+                (
+                    StoreKey::metadata(1, KeyKind::Peaks),
+                    StoreValue::Bytes(vec![0; 32]),
+                ),
+            ])
+            .await;
+        sqlx::query(&format!("DROP TABLE {}", store.table_name))
+            .execute(&store.pool)
+            .await
+            .unwrap();
+        assert_eq!(names, expected);
+        result.unwrap();
+    }
+
     #[tokio::test]
     async fn dropping_store_in_async_context_does_not_panic() {
         let database_url = match std::env::var("DATABASE_URL") {
@@ -0,0 +1,205 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+
+use crate::error::StoreError;
+use crate::types::MmrId;
+
+use super::{Store, StoreKey, StoreValue, WriteBatch};
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(50);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per operation, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled on every further retry.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32.checked_shl(retry).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+type SleepFn = Arc<dyn Fn(Duration) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Retries idempotent store operations that fail with a transient error (see
+/// `StoreError::is_transient`). After an ambiguous `compare_and_set_many` failure the
+/// counter is read back to tell whether the write landed before retrying it.
+/// `write_batch` and `allocate_mmr_id` are passed through once.
+pub struct RetryingStore<S> {
+    inner: S,
+    policy: RetryPolicy,
+    sleep: SleepFn,
+}
+
+impl<S> RetryingStore<S> {
+    /// `sleep` is the runtime's timer, e.g. `tokio::time::sleep`, used to back off
+    /// between attempts.
+    pub fn new<F, Fut>(inner: S, policy: RetryPolicy, sleep: F) -> Self
+    where
+        F: Fn(Duration) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self {
+            inner,
+            policy,
+            sleep: Arc::new(move |delay| Box::pin(sleep(delay))),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    async fn retry<T, Fut>(&self, mut call: impl FnMut() -> Fut) -> Result<T, StoreError>
+    where
+        Fut: Future<Output = Result<T, StoreError>>,
+    {
+        let mut retry = 0u32;
+        loop {
+            match call().await {
+                Err(err) if err.is_transient() && retry + 1 < self.policy.max_attempts => {
+                    self.back_off(retry).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn back_off(&self, retry: u32) {
+        let delay = self.policy.backoff(retry);
+        if !delay.is_zero() {
+            (self.sleep)(delay).await;
+        }
+    }
+}
+
+impl<S> RetryingStore<S> {
+    /// Decides whether a compare-and-set that failed ambiguously was applied: `None` if
+    /// the counter is unchanged and the call can be retried, otherwise whether every
+    /// entry now holds the value this call wrote.
+    async fn resolve_ambiguous_cas<const N: usize>(
+        &self,
+        counter_key: &StoreKey,
+        expected_counter: u64,
+        entries: &[(StoreKey, StoreValue<N>)],
+    ) -> Result<Option<bool>, StoreError>
+    where
+        S: Store<N>,
+    {
+        let counter = match self.inner.get(counter_key).await? {
+            Some(value) => value.expect_u64(counter_key)?,
+            None => 0,
+        };
+        if counter == expected_counter {
+            return Ok(None);
+        }
+
+        let keys: Vec<StoreKey> = entries.iter().map(|(key, _)| key.clone()).collect();
+        let stored = self.inner.get_many(&keys).await?;
+        let applied = entries
+            .iter()
+            .zip(stored)
+            .all(|((_, value), stored)| stored.as_ref() == Some(value));
+        Ok(Some(applied))
+    }
+}
+
+impl<S: Store<N>, const N: usize> Store<N> for RetryingStore<S> {
+    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue<N>>, StoreError> {
+        self.retry(|| self.inner.get(key)).await
+    }
+
+    async fn set(&self, key: StoreKey, value: StoreValue<N>) -> Result<(), StoreError> {
+        self.retry(|| self.inner.set(key.clone(), value.clone()))
+            .await
+    }
+
+    async fn set_many(&self, entries: Vec<(StoreKey, StoreValue<N>)>) -> Result<(), StoreError> {
+        self.retry(|| self.inner.set_many(entries.clone())).await
+    }
+
+    async fn bulk_load(&self, entries: Vec<(StoreKey, StoreValue<N>)>) -> Result<(), StoreError> {
+        self.retry(|| self.inner.bulk_load(entries.clone())).await
+    }
+
+    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue<N>>>, StoreError> {
+        self.retry(|| self.inner.get_many(keys)).await
+    }
+
+    async fn compare_and_set_many(
+        &self,
+        counter_key: &StoreKey,
+        expected_counter: u64,
+        entries: Vec<(StoreKey, StoreValue<N>)>,
+    ) -> Result<bool, StoreError> {
+        let mut retry = 0u32;
+        loop {
+            let err = match self
+                .inner
+                .compare_and_set_many(counter_key, expected_counter, entries.clone())
+                .await
+            {
+                Err(err) if err.is_transient() && retry + 1 < self.policy.max_attempts => err,
+                result => return result,
+            };
+            match self
+                .resolve_ambiguous_cas(counter_key, expected_counter, &entries)
+                .await
+            {
+                Ok(None) => {}
+                Ok(Some(applied)) => return Ok(applied),
+                Err(_) => return Err(err),
+            }
+
+            self.back_off(retry).await;
+            retry += 1;
+        }
+    }
+
+    async fn write_batch(&self, batch: WriteBatch<N>) -> Result<bool, StoreError> {
+        self.inner.write_batch(batch).await
+    }
+
+    async fn delete_many(&self, keys: &[StoreKey]) -> Result<(), StoreError> {
+        self.retry(|| self.inner.delete_many(keys)).await
+    }
+
+    async fn scan_keys(&self, mmr_id: MmrId) -> Result<Vec<StoreKey>, StoreError> {
+        self.retry(|| self.inner.scan_keys(mmr_id)).await
+    }
+
+    async fn list_mmr_ids(&self) -> Result<Vec<MmrId>, StoreError> {
+        self.retry(|| self.inner.list_mmr_ids()).await
+    }
+
+    async fn allocate_mmr_id(&self) -> Result<MmrId, StoreError> {
+        self.inner.allocate_mmr_id().await
+    }
+}
@@ -0,0 +1,319 @@
+//! Helpers for writing accumulator tests in downstream crates: an instrumented store,
+//! deterministic leaves and known roots for the built-in hashers.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use tiny_keccak::{Hasher as TinyHasher, Keccak};
+
+use crate::error::StoreError;
+use crate::store::{Store, StoreKey, StoreValue, WriteBatch, allocate_mmr_id_from_counter};
+use crate::types::{Hash32, MmrId, hash_from_hex};
+
+/// Parses a test leaf: `0x`-prefixed hex left-padded to 32 bytes, or a decimal `u128`
+/// stored big-endian in the low 16 bytes. Panics on malformed input.
+pub fn lv(value: &str) -> Hash32 {
+    if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        return hash_from_hex(&format!("{hex:0>64}")).unwrap();
+    }
+
+    let parsed = value.parse::<u128>().unwrap();
+    let mut out = [0u8; 32];
+    out[16..].copy_from_slice(&parsed.to_be_bytes());
+    out.into()
+}
+
+/// `count` pseudo-random leaves derived from `seed` with Keccak-256. The top bits are
+/// cleared, so every leaf is also a valid Starknet field element for `PoseidonHasher`.
+pub fn deterministic_leaves(seed: u64, count: usize) -> Vec<Hash32> {
+    (0..count as u64)
+        .map(|index| {
+            let mut keccak = Keccak::v256();
+            keccak.update(&seed.to_be_bytes());
+            keccak.update(&index.to_be_bytes());
+            let mut leaf = [0u8; 32];
+            keccak.finalize(&mut leaf);
+            leaf[0] &= 0x07;
+            Hash32::new(leaf)
+        })
+        .collect()
+}
+
+/// Root of an MMR built by appending `lv("1")..=lv(leaves_count)` in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RootFixture {
+    pub leaves_count: u64,
+    pub elements_count: u64,
+    pub root_hash: &'static str,
+}
+
+impl RootFixture {
+    pub fn leaves(&self) -> Vec<Hash32> {
+        (1..=self.leaves_count)
+            .map(|value| lv(&value.to_string()))
+            .collect()
+    }
+
+    pub fn root(&self) -> Hash32 {
+        hash_from_hex(self.root_hash).unwrap()
+    }
+}
+
+pub const KECCAK_ROOT_FIXTURES: &[RootFixture] = &[
+    RootFixture {
+        leaves_count: 1,
+        elements_count: 1,
+        root_hash: "0xcc69885fda6bcc1a4ace058b4a62bf5e179ea78fd58a1ccd71c22cc9b688792f",
+    },
+    RootFixture {
+        leaves_count: 2,
+        elements_count: 3,
+        root_hash: "0x9b0225f2c6f59eeaf8302811ea290e95258763189b82dc033158e99a6ef45a87",
+    },
+    RootFixture {
+        leaves_count: 3,
+        elements_count: 4,
+        root_hash: "0xda17729a0f5f73c4df98b68ff4594cc40ebe750cac8ff62cf71bacd99451602e",
+    },
+    RootFixture {
+        leaves_count: 4,
+        elements_count: 7,
+        root_hash: "0x4cab9bd4f2a70f5a6988e8741e74f8a7504bf1ebe8c57e765ee7875731360cd0",
+    },
+    RootFixture {
+        leaves_count: 5,
+        elements_count: 8,
+        root_hash: "0x4a170760ae313bfab9213c80ec3dc7501e476df6689bfdb6c64d4fdb6c14bb91",
+    },
+    RootFixture {
+        leaves_count: 10,
+        elements_count: 18,
+        root_hash: "0x514e7f557ca7d3b4a1fac485b6e0774cb16923c5640f182ddcd0b40b048cbacc",
+    },
+];
+
+pub const POSEIDON_ROOT_FIXTURES: &[RootFixture] = &[
+    RootFixture {
+        leaves_count: 1,
+        elements_count: 1,
+        root_hash: "0x00b2b24ff607f861b3ed0a9868eeef700b7607ac6d71664afdd14a1f4c33f97d",
+    },
+    RootFixture {
+        leaves_count: 2,
+        elements_count: 3,
+        root_hash: "0x0097e6c17ea05508f6aef7a8195dee3da638bc44d22cbfff3a1f4d9ad215eb6d",
+    },
+    RootFixture {
+        leaves_count: 3,
+        elements_count: 4,
+        root_hash: "0x05d784ea43f66c13886fad278055a42797a2d8f90a5d2220435ff8bf707701d5",
+    },
+    RootFixture {
+        leaves_count: 4,
+        elements_count: 7,
+        root_hash: "0x070fb23b5802c6d66e7db988dd2c1067644fdec6b5ed8dc5a51d05b3ee33e3fd",
+    },
+    RootFixture {
+        leaves_count: 5,
+        elements_count: 8,
+        root_hash: "0x05f71f4a9b08283fa7f2f99e4c5a4dd3def691e242f3e2c04d01496f70ae46d3",
+    },
+    RootFixture {
+        leaves_count: 10,
+        elements_count: 18,
+        root_hash: "0x02f0916d3f3a2cb0e25500258fbe8762b77a96c7e27020b435dc8cd0692f6757",
+    },
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SpyStoreMetrics {
+    pub get_calls: usize,
+    pub set_calls: usize,
+    pub get_many_calls: usize,
+    pub get_many_keys: usize,
+    pub set_many_calls: usize,
+    pub write_batch_calls: usize,
+}
+
+/// In-memory store that counts calls and can inject failures.
+#[derive(Debug, Default)]
+pub struct SpyStore {
+    inner: Mutex<HashMap<StoreKey, StoreValue>>,
+    get_calls: AtomicUsize,
+    set_calls: AtomicUsize,
+    get_many_calls: AtomicUsize,
+    get_many_keys: AtomicUsize,
+    set_many_calls: AtomicUsize,
+    write_batch_calls: AtomicUsize,
+    fail_set_many: AtomicBool,
+    transient_get_many_failures: AtomicUsize,
+    transient_set_many_failures: AtomicUsize,
+}
+
+impl SpyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn metrics(&self) -> SpyStoreMetrics {
+        SpyStoreMetrics {
+            get_calls: self.get_calls.load(Ordering::Relaxed),
+            set_calls: self.set_calls.load(Ordering::Relaxed),
+            get_many_calls: self.get_many_calls.load(Ordering::Relaxed),
+            get_many_keys: self.get_many_keys.load(Ordering::Relaxed),
+            set_many_calls: self.set_many_calls.load(Ordering::Relaxed),
+            write_batch_calls: self.write_batch_calls.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Makes every `set_many` and `write_batch` fail with `StoreError::Internal` while enabled.
+    pub fn set_fail_set_many(&self, fail: bool) {
+        self.fail_set_many.store(fail, Ordering::Relaxed);
+    }
+
+    /// Makes the next `times` calls to `get_many` fail with `StoreError::Transient`.
+    pub fn fail_next_get_many_transiently(&self, times: usize) {
+        self.transient_get_many_failures
+            .store(times, Ordering::Relaxed);
+    }
+
+    /// Makes the next `times` calls to `set_many` store their entries and then fail with
+    /// `StoreError::Transient`, like a commit whose acknowledgement was lost.
+    pub fn fail_next_set_many_after_writing(&self, times: usize) {
+        self.transient_set_many_failures
+            .store(times, Ordering::Relaxed);
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.entries().len()
+    }
+
+    fn write_entries(
+        &self,
+        guard: Option<(&StoreKey, u64)>,
+        entries: Vec<(StoreKey, StoreValue)>,
+    ) -> Result<bool, StoreError> {
+        self.set_many_calls.fetch_add(1, Ordering::Relaxed);
+        if self.fail_set_many.load(Ordering::Relaxed) {
+            return Err(StoreError::Internal("forced set_many failure".to_string()));
+        }
+
+        {
+            let mut stored = self.entries();
+            if let Some((counter_key, expected_counter)) = guard {
+                let current_counter = match stored.get(counter_key) {
+                    Some(value) => value.clone().expect_u64(counter_key)?,
+                    None => 0,
+                };
+                if current_counter != expected_counter {
+                    return Ok(false);
+                }
+            }
+            stored.extend(entries);
+        }
+        if self
+            .transient_set_many_failures
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            })
+            .is_ok()
+        {
+            return Err(StoreError::Transient(
+                "forced lost set_many ack".to_string(),
+            ));
+        }
+        Ok(true)
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<StoreKey, StoreValue>> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Store for SpyStore {
+    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue>, StoreError> {
+        self.get_calls.fetch_add(1, Ordering::Relaxed);
+        Ok(self.entries().get(key).cloned())
+    }
+
+    async fn set(&self, key: StoreKey, value: StoreValue) -> Result<(), StoreError> {
+        self.set_calls.fetch_add(1, Ordering::Relaxed);
+        self.entries().insert(key, value);
+        Ok(())
+    }
+
+    async fn set_many(&self, entries: Vec<(StoreKey, StoreValue)>) -> Result<(), StoreError> {
+        self.write_entries(None, entries).map(|_| ())
+    }
+
+    /// Counted as a `set_many` call, so tests see one write per guarded commit.
+    async fn compare_and_set_many(
+        &self,
+        counter_key: &StoreKey,
+        expected_counter: u64,
+        entries: Vec<(StoreKey, StoreValue)>,
+    ) -> Result<bool, StoreError> {
+        self.write_entries(Some((counter_key, expected_counter)), entries)
+    }
+
+    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue>>, StoreError> {
+        self.get_many_calls.fetch_add(1, Ordering::Relaxed);
+        self.get_many_keys.fetch_add(keys.len(), Ordering::Relaxed);
+        if self
+            .transient_get_many_failures
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            })
+            .is_ok()
+        {
+            return Err(StoreError::Transient("forced get_many blip".to_string()));
+        }
+
+        let guard = self.entries();
+        Ok(keys.iter().map(|key| guard.get(key).cloned()).collect())
+    }
+
+    async fn write_batch(&self, batch: WriteBatch) -> Result<bool, StoreError> {
+        self.write_batch_calls.fetch_add(1, Ordering::Relaxed);
+        if self.fail_set_many.load(Ordering::Relaxed) {
+            return Err(StoreError::Internal(
+                "forced write_batch failure".to_string(),
+            ));
+        }
+
+        let mut entries = self.entries();
+        for (counter_key, expected_counter) in &batch.guards {
+            let current_counter = match entries.get(counter_key) {
+                Some(value) => value.clone().expect_u64(counter_key)?,
+                None => 0,
+            };
+            if current_counter != *expected_counter {
+                return Ok(false);
+            }
+        }
+        for key in &batch.deletes {
+            entries.remove(key);
+        }
+        entries.extend(batch.sets);
+        Ok(true)
+    }
+
+    async fn scan_keys(&self, mmr_id: MmrId) -> Result<Vec<StoreKey>, StoreError> {
+        Ok(self
+            .entries()
+            .keys()
+            .filter(|key| key.mmr_id == mmr_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn allocate_mmr_id(&self) -> Result<MmrId, StoreError> {
+        allocate_mmr_id_from_counter(self).await
+    }
+}
@@ -1,35 +1,459 @@
-pub type Hash32 = [u8; 32];
-pub type MmrId = u32;
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+
+use crate::error::HasherError;
+
+pub type MmrId = u64;
 pub type ElementIndex = u64;
 pub type ElementsCount = u64;
 pub type LeavesCount = u64;
 
-pub const ZERO_HASH: Hash32 = [0u8; 32];
+pub const ZERO_HASH: Hash32 = Hash::ZERO;
+
+/// Fixed-width hash; `N` defaults to the 32 bytes every built-in hasher uses. Other widths
+/// run through `Hasher<N>`, `Proof<N>`, `Store<N>` and the core of `Mmr<S, N>`; the
+/// persistent stores, `Mmr` extensions, codecs and interop formats are 32-byte.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct Hash<const N: usize = 32>(pub [u8; N]);
+
+/// Construct with `Hash32::new(bytes)` or `Hash(bytes)`; an alias cannot be called as a
+/// tuple constructor.
+pub type Hash32 = Hash<32>;
+pub type Hash20 = Hash<20>;
+pub type Hash64 = Hash<64>;
+
+impl<const N: usize> Hash<N> {
+    pub const ZERO: Self = Self([0u8; N]);
+
+    pub const fn new(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+
+    pub const fn as_bytes(&self) -> &[u8; N] {
+        &self.0
+    }
+
+    pub const fn to_bytes(self) -> [u8; N] {
+        self.0
+    }
+}
+
+impl<const N: usize> Default for Hash<N> {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl<const N: usize> Deref for Hash<N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const N: usize> DerefMut for Hash<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for Hash<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const N: usize> Borrow<[u8; N]> for Hash<N> {
+    fn borrow(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Hash<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl<const N: usize> From<Hash<N>> for [u8; N] {
+    fn from(hash: Hash<N>) -> Self {
+        hash.0
+    }
+}
+
+impl<const N: usize> TryFrom<&[u8]> for Hash<N> {
+    type Error = std::array::TryFromSliceError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        <[u8; N]>::try_from(bytes).map(Self)
+    }
+}
+
+impl<const N: usize> PartialEq<[u8; N]> for Hash<N> {
+    fn eq(&self, other: &[u8; N]) -> bool {
+        &self.0 == other
+    }
+}
+
+impl<const N: usize> fmt::Display for Hash<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+impl<const N: usize> fmt::Debug for Hash<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hash{N}({self})")
+    }
+}
+
+impl<const N: usize> FromStr for Hash<N> {
+    type Err = HasherError;
+
+    /// Accepts exactly `2 * N` hex digits, with or without a `0x` prefix; shorter input is
+    /// rejected rather than left-padded, so a truncated hash never parses.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let raw = value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+            .unwrap_or(value);
+        let mut out = [0u8; N];
+        hex::decode_to_slice(raw, &mut out).map_err(|source| HasherError::InvalidHex {
+            value: value.to_string(),
+            source,
+        })?;
+        Ok(Self(out))
+    }
+}
+
+/// Parses a 0x-prefixed (or bare) hex string; see [`Hash32::from_str`].
+pub fn hash_from_hex(value: &str) -> Result<Hash32, HasherError> {
+    value.parse()
+}
+
+/// Parses a decimal string (e.g. a Cairo felt) as a big-endian 256-bit integer.
+pub fn hash_from_decimal(value: &str) -> Result<Hash32, HasherError> {
+    if value.is_empty() {
+        return Err(HasherError::InvalidDecimal {
+            value: value.to_string(),
+        });
+    }
+
+    let mut out = [0u8; 32];
+    for ch in value.chars() {
+        let Some(digit) = ch.to_digit(10) else {
+            return Err(HasherError::InvalidDecimal {
+                value: value.to_string(),
+            });
+        };
+        let mut carry = digit;
+        for byte in out.iter_mut().rev() {
+            let next = u32::from(*byte) * 10 + carry;
+            *byte = next as u8;
+            carry = next >> 8;
+        }
+        if carry != 0 {
+            return Err(HasherError::InputTooLarge {
+                value: value.to_string(),
+                max_bytes: 32,
+            });
+        }
+    }
+    Ok(Hash32::new(out))
+}
+
+#[cfg(feature = "serde")]
+impl<const N: usize> serde::Serialize for Hash<N> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const N: usize> serde::Deserialize<'de> for Hash<N> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "primitive-types")]
+impl From<primitive_types::H256> for Hash32 {
+    fn from(value: primitive_types::H256) -> Self {
+        Self(value.0)
+    }
+}
+
+#[cfg(feature = "primitive-types")]
+impl From<Hash32> for primitive_types::H256 {
+    fn from(hash: Hash32) -> Self {
+        Self(hash.0)
+    }
+}
+
+#[cfg(feature = "alloy")]
+impl From<alloy_primitives::B256> for Hash32 {
+    fn from(value: alloy_primitives::B256) -> Self {
+        Self(value.0)
+    }
+}
+
+#[cfg(feature = "alloy")]
+impl From<Hash32> for alloy_primitives::B256 {
+    fn from(hash: Hash32) -> Self {
+        Self(hash.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct Proof<const N: usize = 32> {
+    pub element_index: ElementIndex,
+    pub element_hash: Hash<N>,
+    pub siblings_hashes: Vec<Hash<N>>,
+    pub peaks_hashes: Vec<Hash<N>>,
+    pub elements_count: ElementsCount,
+    /// Advisory label naming the MMR that issued the proof. No hash commits to it, so it
+    /// catches proofs routed to the wrong MMR but anyone holding the proof can rewrite it;
+    /// it does not stop a deliberate replay against another MMR with the same peaks.
+    pub mmr_id: Option<MmrId>,
+}
+
+/// Proofs archived together, so a verifier can map one buffer and check each proof in
+/// place through [`ArchivedProofArchive`].
+#[cfg(feature = "rkyv")]
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize,
+)]
+pub struct ProofArchive {
+    pub proofs: Vec<Proof>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct CompactProof {
+    pub element_index: ElementIndex,
+    pub element_hash: Hash32,
+    pub siblings_hashes: Vec<Hash32>,
+    pub other_peaks_hashes: Vec<Hash32>,
+    pub elements_count: ElementsCount,
+    /// Advisory label carried over from [`Proof::mmr_id`].
+    pub mmr_id: Option<MmrId>,
+}
+
+/// JSON-friendly proof: hashes are 0x-prefixed hex, counters are decimal strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ProofJson {
+    /// Missing in documents written before versioning; those decode as version 1.
+    #[cfg_attr(feature = "serde", serde(default = "legacy_proof_json_version"))]
+    pub version: u8,
+    pub element_index: String,
+    pub element_hash: String,
+    pub siblings_hashes: Vec<String>,
+    pub peaks_hashes: Vec<String>,
+    pub elements_count: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub mmr_id: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+fn legacy_proof_json_version() -> u8 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct ZeroBasedProof {
+    /// Zero-based element position, i.e. `element_index - 1`.
+    pub position: u64,
+    pub element_hash: Hash32,
+    pub siblings_hashes: Vec<Hash32>,
+    pub peaks_hashes: Vec<Hash32>,
+    pub elements_count: ElementsCount,
+    /// Advisory label carried over from [`Proof::mmr_id`].
+    pub mmr_id: Option<MmrId>,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Proof {
+pub struct PeaksCommitment {
+    pub elements_count: ElementsCount,
+    pub peaks_hashes: Vec<Hash32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct PathProof {
     pub element_index: ElementIndex,
     pub element_hash: Hash32,
     pub siblings_hashes: Vec<Hash32>,
+    /// Bit `i` is set when `siblings_hashes[i]` is the left input of its hash.
+    pub directions: u64,
+    pub peak_index: u64,
     pub peaks_hashes: Vec<Hash32>,
     pub elements_count: ElementsCount,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct AppendResult {
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct MerkleProof {
+    pub leaf_index: u64,
+    pub siblings_hashes: Vec<Hash32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofVerification {
+    Valid,
+    InvalidElementIndex,
+    /// The size the proof is checked against is not a valid MMR size.
+    InvalidElementCount,
+    StaleElementsCount {
+        proof_elements_count: ElementsCount,
+        elements_count: ElementsCount,
+    },
+    WrongPeaksCount {
+        expected: usize,
+        actual: usize,
+    },
+    WrongSiblingCount {
+        expected: usize,
+        actual: usize,
+    },
+    PeakMismatch {
+        peak_index: usize,
+    },
+    MissingStoreNode(ElementIndex),
+    WrongMmrId {
+        expected: MmrId,
+        actual: MmrId,
+    },
+    /// The verifying MMR binds proofs but this one carries no `mmr_id`.
+    UnboundProof {
+        expected: MmrId,
+    },
+}
+
+impl ProofVerification {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct AppendResult<const N: usize = 32> {
     pub leaves_count: LeavesCount,
     pub elements_count: ElementsCount,
     pub element_index: ElementIndex,
-    pub root_hash: Hash32,
+    /// The zero hash when `root_deferred` is set; see `root_hash_if_computed`.
+    pub root_hash: Hash<N>,
+    /// Set when the append ran in lazy-root mode and left the root for `finalize_root`.
+    pub root_deferred: bool,
+    pub peaks_hashes: Vec<Hash<N>>,
+    pub previous_elements_count: ElementsCount,
+    pub previous_root_hash: Option<Hash<N>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct BatchAppendResult {
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct BatchAppendResult<const N: usize = 32> {
     pub appended_count: u64,
     pub first_element_index: ElementIndex,
     pub last_element_index: ElementIndex,
     pub leaves_count: LeavesCount,
     pub elements_count: ElementsCount,
-    pub root_hash: Hash32,
-    pub peaks_hashes: Vec<Hash32>,
+    /// The zero hash when `root_deferred` is set; see `root_hash_if_computed`.
+    pub root_hash: Hash<N>,
+    /// Set when the append ran in lazy-root mode and left the root for `finalize_root`.
+    pub root_deferred: bool,
+    pub peaks_hashes: Vec<Hash<N>>,
+    pub previous_elements_count: ElementsCount,
+    pub previous_root_hash: Option<Hash<N>>,
+}
+
+impl<const N: usize> AppendResult<N> {
+    /// The new root, or `None` when lazy-root mode deferred it.
+    pub fn root_hash_if_computed(&self) -> Option<Hash<N>> {
+        (!self.root_deferred).then_some(self.root_hash)
+    }
+}
+
+impl<const N: usize> BatchAppendResult<N> {
+    /// The new root, or `None` when lazy-root mode deferred it.
+    pub fn root_hash_if_computed(&self) -> Option<Hash<N>> {
+        (!self.root_deferred).then_some(self.root_hash)
+    }
+}
+
+impl<const N: usize> From<BatchAppendResult<N>> for AppendResult<N> {
+    fn from(result: BatchAppendResult<N>) -> Self {
+        Self {
+            leaves_count: result.leaves_count,
+            elements_count: result.elements_count,
+            element_index: result.first_element_index,
+            root_hash: result.root_hash,
+            root_deferred: result.root_deferred,
+            peaks_hashes: result.peaks_hashes,
+            previous_elements_count: result.previous_elements_count,
+            previous_root_hash: result.previous_root_hash,
+        }
+    }
+}
+
+impl<const N: usize> From<AppendResult<N>> for BatchAppendResult<N> {
+    fn from(result: AppendResult<N>) -> Self {
+        Self {
+            appended_count: 1,
+            first_element_index: result.element_index,
+            last_element_index: result.element_index,
+            leaves_count: result.leaves_count,
+            elements_count: result.elements_count,
+            root_hash: result.root_hash,
+            root_deferred: result.root_deferred,
+            peaks_hashes: result.peaks_hashes,
+            previous_elements_count: result.previous_elements_count,
+            previous_root_hash: result.previous_root_hash,
+        }
+    }
 }
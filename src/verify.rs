@@ -0,0 +1,487 @@
+//! Proof verification and index arithmetic that only depend on `core`, for `no_std`
+//! light clients. Hashes are plain `[u8; N]` (32 bytes unless a `PairHasher<N>` says
+//! otherwise) and nothing here allocates.
+
+use core::borrow::Borrow;
+use core::fmt;
+
+use tiny_keccak::{Hasher as TinyHasher, Keccak};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyError {
+    InvalidElementIndex,
+    InvalidElementCount,
+    InvalidPeaksCount,
+    Overflow,
+    Hasher,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidElementIndex => "invalid element index",
+            Self::InvalidElementCount => "invalid element count",
+            Self::InvalidPeaksCount => "invalid peaks count",
+            Self::Overflow => "arithmetic overflow",
+            Self::Hasher => "hasher error",
+        })
+    }
+}
+
+/// The two hashing operations proof verification needs. The verify functions return
+/// `Self::Error`, so a hasher keeps the source of its own failures.
+pub trait PairHasher<const N: usize = 32> {
+    type Error: From<VerifyError>;
+
+    fn hash_pair(&self, left: &[u8; N], right: &[u8; N]) -> Result<[u8; N], Self::Error>;
+    fn hash_count_and_bag(
+        &self,
+        elements_count: u64,
+        bag: &[u8; N],
+    ) -> Result<[u8; N], Self::Error>;
+}
+
+/// Keccak-256 with the same encoding as `KeccakHasher`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Keccak256;
+
+impl PairHasher for Keccak256 {
+    type Error = VerifyError;
+
+    fn hash_pair(&self, left: &[u8; 32], right: &[u8; 32]) -> Result<[u8; 32], VerifyError> {
+        let mut keccak = Keccak::v256();
+        keccak.update(left);
+        keccak.update(right);
+        let mut output = [0u8; 32];
+        keccak.finalize(&mut output);
+        Ok(output)
+    }
+
+    fn hash_count_and_bag(
+        &self,
+        elements_count: u64,
+        bag: &[u8; 32],
+    ) -> Result<[u8; 32], VerifyError> {
+        let mut count_hash = [0u8; 32];
+        count_hash[24..].copy_from_slice(&elements_count.to_be_bytes());
+        self.hash_pair(&count_hash, bag)
+    }
+}
+
+/// Checks that `element_value` at `element_index` climbs through `siblings_hashes` to its
+/// peak in `peaks_hashes`.
+pub fn verify_proof<H, B, const N: usize>(
+    hasher: &H,
+    element_index: u64,
+    element_value: &[u8; N],
+    siblings_hashes: &[B],
+    peaks_hashes: &[B],
+    elements_count: u64,
+) -> Result<bool, H::Error>
+where
+    H: PairHasher<N> + ?Sized,
+    B: Borrow<[u8; N]>,
+{
+    match compute_peak(
+        hasher,
+        element_index,
+        element_value,
+        siblings_hashes,
+        peaks_hashes.len(),
+        elements_count,
+    )? {
+        Some((peak_index, peak_hash)) => {
+            Ok(peaks_hashes.get(peak_index).map(Borrow::borrow) == Some(&peak_hash))
+        }
+        None => Ok(false),
+    }
+}
+
+/// Like [`verify_proof`], but recomputes the root from the climbed peak and the other
+/// peaks and compares it with `root_hash`.
+pub fn verify_proof_against_root<H, B, const N: usize>(
+    hasher: &H,
+    element_index: u64,
+    element_value: &[u8; N],
+    siblings_hashes: &[B],
+    peaks_hashes: &[B],
+    elements_count: u64,
+    root_hash: &[u8; N],
+) -> Result<bool, H::Error>
+where
+    H: PairHasher<N> + ?Sized,
+    B: Borrow<[u8; N]>,
+{
+    let Some((peak_index, peak_hash)) = compute_peak(
+        hasher,
+        element_index,
+        element_value,
+        siblings_hashes,
+        peaks_hashes.len(),
+        elements_count,
+    )?
+    else {
+        return Ok(false);
+    };
+
+    let bag = bag_peaks_replacing(hasher, peaks_hashes, Some((peak_index, &peak_hash)))?;
+    Ok(&hasher.hash_count_and_bag(elements_count, &bag)? == root_hash)
+}
+
+pub fn root_from_peaks<H, B, const N: usize>(
+    hasher: &H,
+    peaks_hashes: &[B],
+    elements_count: u64,
+) -> Result<[u8; N], H::Error>
+where
+    H: PairHasher<N> + ?Sized,
+    B: Borrow<[u8; N]>,
+{
+    let expected_peaks = iter_peaks(elements_count).count();
+    if elements_count > 0 && expected_peaks == 0 {
+        return Err(VerifyError::InvalidElementCount.into());
+    }
+    if peaks_hashes.len() != expected_peaks {
+        return Err(VerifyError::InvalidPeaksCount.into());
+    }
+
+    let bag = bag_peaks(hasher, peaks_hashes)?;
+    hasher.hash_count_and_bag(elements_count, &bag)
+}
+
+/// Tag a bound root commits to: `mmr_id` big-endian in the last 8 bytes of an otherwise
+/// zero hash, so `N` must be at least 8.
+pub fn mmr_domain_tag<const N: usize>(mmr_id: u64) -> [u8; N] {
+    let mut tag = [0u8; N];
+    tag[N - 8..].copy_from_slice(&mmr_id.to_be_bytes());
+    tag
+}
+
+/// Like [`root_from_peaks`], but also commits to `mmr_id`:
+/// `hash_count_and_bag(elements_count, hash_pair(mmr_domain_tag(mmr_id), bag))`. Two MMRs
+/// with the same leaves have different bound roots, so a proof issued for one does not
+/// verify against the other's.
+pub fn bound_root_from_peaks<H, B, const N: usize>(
+    hasher: &H,
+    peaks_hashes: &[B],
+    elements_count: u64,
+    mmr_id: u64,
+) -> Result<[u8; N], H::Error>
+where
+    H: PairHasher<N> + ?Sized,
+    B: Borrow<[u8; N]>,
+{
+    let expected_peaks = iter_peaks(elements_count).count();
+    if elements_count > 0 && expected_peaks == 0 {
+        return Err(VerifyError::InvalidElementCount.into());
+    }
+    if peaks_hashes.len() != expected_peaks {
+        return Err(VerifyError::InvalidPeaksCount.into());
+    }
+
+    let bag = bag_peaks(hasher, peaks_hashes)?;
+    bind_bag(hasher, elements_count, &bag, mmr_id)
+}
+
+/// Like [`verify_proof_against_root`], but against a root from [`bound_root_from_peaks`]
+/// for `mmr_id`.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_proof_against_bound_root<H, B, const N: usize>(
+    hasher: &H,
+    element_index: u64,
+    element_value: &[u8; N],
+    siblings_hashes: &[B],
+    peaks_hashes: &[B],
+    elements_count: u64,
+    mmr_id: u64,
+    bound_root: &[u8; N],
+) -> Result<bool, H::Error>
+where
+    H: PairHasher<N> + ?Sized,
+    B: Borrow<[u8; N]>,
+{
+    let Some((peak_index, peak_hash)) = compute_peak(
+        hasher,
+        element_index,
+        element_value,
+        siblings_hashes,
+        peaks_hashes.len(),
+        elements_count,
+    )?
+    else {
+        return Ok(false);
+    };
+
+    let bag = bag_peaks_replacing(hasher, peaks_hashes, Some((peak_index, &peak_hash)))?;
+    Ok(&bind_bag(hasher, elements_count, &bag, mmr_id)? == bound_root)
+}
+
+fn bind_bag<H, const N: usize>(
+    hasher: &H,
+    elements_count: u64,
+    bag: &[u8; N],
+    mmr_id: u64,
+) -> Result<[u8; N], H::Error>
+where
+    H: PairHasher<N> + ?Sized,
+{
+    let bound_bag = hasher.hash_pair(&mmr_domain_tag(mmr_id), bag)?;
+    hasher.hash_count_and_bag(elements_count, &bound_bag)
+}
+
+/// Folds `peaks_hashes` right to left into the bag `hash_count_and_bag` commits to; no
+/// peaks bag to the zero hash.
+pub fn bag_peaks<H, B, const N: usize>(hasher: &H, peaks_hashes: &[B]) -> Result<[u8; N], H::Error>
+where
+    H: PairHasher<N> + ?Sized,
+    B: Borrow<[u8; N]>,
+{
+    bag_peaks_replacing(hasher, peaks_hashes, None)
+}
+
+pub(crate) fn compute_peak<H, B, const N: usize>(
+    hasher: &H,
+    element_index: u64,
+    element_value: &[u8; N],
+    siblings_hashes: &[B],
+    peaks_count: usize,
+    elements_count: u64,
+) -> Result<Option<(usize, [u8; N])>, H::Error>
+where
+    H: PairHasher<N> + ?Sized,
+    B: Borrow<[u8; N]>,
+{
+    let leaf_count = mmr_size_to_leaf_count(elements_count);
+    if peaks_count != leaf_count_to_peaks_count(leaf_count) as usize {
+        return Err(VerifyError::InvalidPeaksCount.into());
+    }
+
+    let (peak_index, peak_height) = get_peak_info(elements_count, element_index)?;
+    if siblings_hashes.len() != peak_height {
+        return Ok(None);
+    }
+
+    let hash = climb_to_peak(hasher, element_index, element_value, siblings_hashes)?;
+    Ok(Some((peak_index, hash)))
+}
+
+/// Hashes `element_value` up through `siblings_hashes` without checking that the path
+/// ends at a peak.
+pub(crate) fn climb_to_peak<H, B, const N: usize>(
+    hasher: &H,
+    element_index: u64,
+    element_value: &[u8; N],
+    siblings_hashes: &[B],
+) -> Result<[u8; N], H::Error>
+where
+    H: PairHasher<N> + ?Sized,
+    B: Borrow<[u8; N]>,
+{
+    let mut hash = *element_value;
+    let mut leaf_index = element_index_to_leaf_index(element_index)?;
+    for sibling_hash in siblings_hashes {
+        let sibling_hash = sibling_hash.borrow();
+        hash = if leaf_index % 2 == 1 {
+            hasher.hash_pair(sibling_hash, &hash)?
+        } else {
+            hasher.hash_pair(&hash, sibling_hash)?
+        };
+        leaf_index /= 2;
+    }
+
+    Ok(hash)
+}
+
+fn bag_peaks_replacing<H, B, const N: usize>(
+    hasher: &H,
+    peaks_hashes: &[B],
+    replace: Option<(usize, &[u8; N])>,
+) -> Result<[u8; N], H::Error>
+where
+    H: PairHasher<N> + ?Sized,
+    B: Borrow<[u8; N]>,
+{
+    let peak = |index: usize| match replace {
+        Some((replace_index, hash)) if replace_index == index => *hash,
+        _ => *peaks_hashes[index].borrow(),
+    };
+
+    let mut indices = (0..peaks_hashes.len()).rev();
+    let Some(last) = indices.next() else {
+        return Ok([0u8; N]);
+    };
+    let mut acc = peak(last);
+    for index in indices {
+        acc = hasher.hash_pair(&peak(index), &acc)?;
+    }
+    Ok(acc)
+}
+
+#[derive(Debug, Clone)]
+pub struct PeaksIter {
+    shift: u128,
+    remaining: u128,
+    mountain_elements_count: u128,
+}
+
+impl Iterator for PeaksIter {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        while self.mountain_elements_count > 0 {
+            let mountain_elements_count = self.mountain_elements_count;
+            self.mountain_elements_count >>= 1;
+            if mountain_elements_count <= self.remaining {
+                self.shift += mountain_elements_count;
+                self.remaining -= mountain_elements_count;
+                return Some(self.shift as u64);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.mountain_elements_count.count_ones() as usize))
+    }
+}
+
+pub fn iter_peaks(elements_count: u64) -> PeaksIter {
+    let mountain_elements_count = if elements_count == 0 {
+        0u128
+    } else {
+        (1u128 << bit_length(elements_count)) - 1
+    };
+    let mut peaks = PeaksIter {
+        shift: 0,
+        remaining: elements_count as u128,
+        mountain_elements_count,
+    };
+
+    if peaks
+        .clone()
+        .last()
+        .map_or(elements_count, |peak| elements_count - peak)
+        > 0
+    {
+        peaks.mountain_elements_count = 0;
+    }
+    peaks
+}
+
+pub const fn map_leaf_index_to_element_index(leaf_index: u64) -> u64 {
+    2 * leaf_index + 1 - leaf_index.count_ones() as u64
+}
+
+pub const fn leaf_count_to_peaks_count(leaf_count: u64) -> u32 {
+    leaf_count.count_ones()
+}
+
+pub const fn leaf_count_to_mmr_size(leaf_count: u64) -> u64 {
+    2 * leaf_count - leaf_count_to_peaks_count(leaf_count) as u64
+}
+
+pub const fn leaf_count_to_append_no_merges(leaf_count: u64) -> u64 {
+    leaf_count.trailing_ones() as u64
+}
+
+pub fn try_leaf_count_to_mmr_size(leaf_count: u64) -> Result<u64, VerifyError> {
+    let mmr_size = 2 * u128::from(leaf_count) - u128::from(leaf_count_to_peaks_count(leaf_count));
+    u64::try_from(mmr_size).map_err(|_| VerifyError::Overflow)
+}
+
+pub fn element_index_to_leaf_index(element_index: u64) -> Result<u64, VerifyError> {
+    if element_index == 0 {
+        return Err(VerifyError::InvalidElementIndex);
+    }
+    elements_count_to_leaf_count(element_index - 1)
+}
+
+pub fn elements_count_to_leaf_count(elements_count: u64) -> Result<u64, VerifyError> {
+    let mut leaf_count = 0u128;
+    let mut current = elements_count as u128;
+
+    let mut mountain_leaf_count = if elements_count == 0 {
+        1u128
+    } else {
+        1u128 << bit_length(elements_count)
+    };
+
+    while mountain_leaf_count > 0 {
+        let mountain_elements_count = 2 * mountain_leaf_count - 1;
+        if mountain_elements_count <= current {
+            leaf_count += mountain_leaf_count;
+            current -= mountain_elements_count;
+        }
+        mountain_leaf_count >>= 1;
+    }
+
+    if current > 0 {
+        Err(VerifyError::InvalidElementCount)
+    } else {
+        u64::try_from(leaf_count).map_err(|_| VerifyError::Overflow)
+    }
+}
+
+pub fn get_peak_info(
+    mut elements_count: u64,
+    mut element_index: u64,
+) -> Result<(usize, usize), VerifyError> {
+    if element_index == 0 || element_index > elements_count {
+        return Err(VerifyError::InvalidElementIndex);
+    }
+    if try_leaf_count_to_mmr_size(mmr_size_to_leaf_count(elements_count))? != elements_count {
+        return Err(VerifyError::InvalidElementCount);
+    }
+
+    let mut mountain_height = bit_length(elements_count);
+    let mut mountain_elements_count = if mountain_height == 0 {
+        0u128
+    } else {
+        (1u128 << mountain_height) - 1
+    };
+    let mut mountain_index = 0usize;
+
+    loop {
+        if mountain_elements_count <= elements_count as u128 {
+            if element_index as u128 <= mountain_elements_count {
+                return Ok((mountain_index, mountain_height.saturating_sub(1) as usize));
+            }
+            elements_count -= mountain_elements_count as u64;
+            element_index -= mountain_elements_count as u64;
+            mountain_index += 1;
+        }
+
+        if mountain_height == 0 {
+            return Err(VerifyError::InvalidElementIndex);
+        }
+
+        mountain_elements_count >>= 1;
+        mountain_height -= 1;
+    }
+}
+
+pub fn mmr_size_to_leaf_count(mmr_size: u64) -> u64 {
+    let mut remaining = mmr_size as u128;
+    let bits = bit_length_u128(remaining + 1);
+    let mut mountain_tips = 1u128 << bits.saturating_sub(1);
+    let mut leaf_count = 0u128;
+
+    while mountain_tips != 0 {
+        let mountain_size = 2 * mountain_tips - 1;
+        if mountain_size <= remaining {
+            remaining -= mountain_size;
+            leaf_count += mountain_tips;
+        }
+        mountain_tips >>= 1;
+    }
+
+    leaf_count as u64
+}
+
+pub(crate) const fn bit_length(num: u64) -> u32 {
+    64 - num.leading_zeros()
+}
+
+const fn bit_length_u128(num: u128) -> u32 {
+    128 - num.leading_zeros()
+}
@@ -0,0 +1,136 @@
+use wasm_bindgen::prelude::*;
+
+use crate::hasher::{Hasher, KeccakHasher, PoseidonHasher};
+use crate::mmr::{append_to_peaks, root_from_peaks, verify_proof, verify_proof_against_root};
+use crate::types::{Hash32, Proof, ProofJson};
+
+/// Stateless MMR for JavaScript callers: holds only the elements count and peaks, so a
+/// frontend can follow appends and recompute roots without a store.
+#[wasm_bindgen]
+pub struct WasmPeaks {
+    hasher: Box<dyn Hasher>,
+    elements_count: u64,
+    peaks_hashes: Vec<Hash32>,
+}
+
+#[wasm_bindgen]
+impl WasmPeaks {
+    #[wasm_bindgen(constructor)]
+    pub fn new(hasher: &str) -> Result<WasmPeaks, JsError> {
+        Ok(Self {
+            hasher: hasher_by_name(hasher)?,
+            elements_count: 0,
+            peaks_hashes: Vec::new(),
+        })
+    }
+
+    #[wasm_bindgen(js_name = fromPeaks)]
+    pub fn from_peaks(
+        hasher: &str,
+        elements_count: u64,
+        peaks_hashes: Vec<String>,
+    ) -> Result<WasmPeaks, JsError> {
+        let hasher = hasher_by_name(hasher)?;
+        let peaks_hashes = parse_hashes(&peaks_hashes)?;
+        root_from_peaks(hasher.as_ref(), &peaks_hashes, elements_count).map_err(js_error)?;
+        Ok(Self {
+            hasher,
+            elements_count,
+            peaks_hashes,
+        })
+    }
+
+    /// Appends a 0x-prefixed leaf hash and returns its element index.
+    pub fn append(&mut self, leaf: &str) -> Result<u64, JsError> {
+        let leaf = parse_hash(leaf)?;
+        let (elements_count, peaks_hashes) = append_to_peaks(
+            self.hasher.as_ref(),
+            self.elements_count,
+            &self.peaks_hashes,
+            leaf,
+        )
+        .map_err(js_error)?;
+        let element_index = self.elements_count + 1;
+        self.elements_count = elements_count;
+        self.peaks_hashes = peaks_hashes;
+        Ok(element_index)
+    }
+
+    #[wasm_bindgen(getter, js_name = elementsCount)]
+    pub fn elements_count(&self) -> u64 {
+        self.elements_count
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn peaks(&self) -> Vec<String> {
+        self.peaks_hashes.iter().map(ToString::to_string).collect()
+    }
+
+    pub fn root(&self) -> Result<String, JsError> {
+        root_from_peaks(
+            self.hasher.as_ref(),
+            &self.peaks_hashes,
+            self.elements_count,
+        )
+        .map(|root| root.to_string())
+        .map_err(js_error)
+    }
+}
+
+/// Verifies a proof in the `ProofJson` schema against its own peaks.
+#[wasm_bindgen(js_name = verifyProof)]
+pub fn verify_proof_json(
+    hasher: &str,
+    proof_json: &str,
+    element_value: &str,
+) -> Result<bool, JsError> {
+    let hasher = hasher_by_name(hasher)?;
+    let proof = parse_proof(proof_json)?;
+    verify_proof(hasher.as_ref(), &proof, parse_hash(element_value)?).map_err(js_error)
+}
+
+/// Verifies a proof in the `ProofJson` schema against a trusted root.
+#[wasm_bindgen(js_name = verifyProofAgainstRoot)]
+pub fn verify_proof_json_against_root(
+    hasher: &str,
+    proof_json: &str,
+    element_value: &str,
+    root_hash: &str,
+) -> Result<bool, JsError> {
+    let hasher = hasher_by_name(hasher)?;
+    let proof = parse_proof(proof_json)?;
+    verify_proof_against_root(
+        hasher.as_ref(),
+        &proof,
+        parse_hash(element_value)?,
+        &parse_hash(root_hash)?,
+    )
+    .map_err(js_error)
+}
+
+fn hasher_by_name(name: &str) -> Result<Box<dyn Hasher>, JsError> {
+    match name {
+        "keccak" => Ok(Box::new(KeccakHasher::new())),
+        "poseidon" => Ok(Box::new(PoseidonHasher::new())),
+        #[cfg(feature = "blake2")]
+        "blake2b" => Ok(Box::new(crate::hasher::Blake2bHasher::new())),
+        _ => Err(JsError::new(&format!("unknown hasher: {name}"))),
+    }
+}
+
+fn parse_hash(value: &str) -> Result<Hash32, JsError> {
+    value.parse().map_err(js_error)
+}
+
+fn parse_hashes(values: &[String]) -> Result<Vec<Hash32>, JsError> {
+    values.iter().map(|value| parse_hash(value)).collect()
+}
+
+fn parse_proof(proof_json: &str) -> Result<Proof, JsError> {
+    let proof: ProofJson = serde_json::from_str(proof_json).map_err(js_error)?;
+    proof.to_proof().map_err(js_error)
+}
+
+fn js_error(err: impl std::fmt::Display) -> JsError {
+    JsError::new(&err.to_string())
+}
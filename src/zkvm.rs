@@ -0,0 +1,118 @@
+//! Guest-side API for SP1 / RISC Zero: depends only on `core` and `tiny-keccak`, never
+//! allocates, and is fully deterministic. Build with `default-features = false,
+//! features = ["zkvm"]`.
+
+use core::borrow::Borrow;
+use core::fmt;
+
+pub use crate::verify::{
+    Keccak256, PairHasher, VerifyError, root_from_peaks, verify_proof, verify_proof_against_root,
+};
+use crate::verify::{elements_count_to_leaf_count, iter_peaks, leaf_count_to_append_no_merges};
+
+/// An MMR of u64 elements has at most 64 peaks.
+pub const MAX_PEAKS: usize = 64;
+
+/// Elements count and peaks of an MMR in a fixed-size buffer, for stateless appends.
+#[derive(Clone)]
+pub struct FixedPeaks {
+    elements_count: u64,
+    peaks_count: usize,
+    peaks_hashes: [[u8; 32]; MAX_PEAKS],
+}
+
+impl PartialEq for FixedPeaks {
+    fn eq(&self, other: &Self) -> bool {
+        self.elements_count == other.elements_count && self.peaks() == other.peaks()
+    }
+}
+
+impl Eq for FixedPeaks {}
+
+impl fmt::Debug for FixedPeaks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixedPeaks")
+            .field("elements_count", &self.elements_count)
+            .field("peaks_hashes", &self.peaks())
+            .finish()
+    }
+}
+
+impl Default for FixedPeaks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FixedPeaks {
+    pub const fn new() -> Self {
+        Self {
+            elements_count: 0,
+            peaks_count: 0,
+            peaks_hashes: [[0u8; 32]; MAX_PEAKS],
+        }
+    }
+
+    pub fn from_peaks<B: Borrow<[u8; 32]>>(
+        elements_count: u64,
+        peaks_hashes: &[B],
+    ) -> Result<Self, VerifyError> {
+        elements_count_to_leaf_count(elements_count)?;
+        if peaks_hashes.len() != iter_peaks(elements_count).count() {
+            return Err(VerifyError::InvalidPeaksCount);
+        }
+
+        let mut peaks = Self::new();
+        for (slot, hash) in peaks.peaks_hashes.iter_mut().zip(peaks_hashes) {
+            *slot = *hash.borrow();
+        }
+        peaks.elements_count = elements_count;
+        peaks.peaks_count = peaks_hashes.len();
+        Ok(peaks)
+    }
+
+    pub fn elements_count(&self) -> u64 {
+        self.elements_count
+    }
+
+    pub fn peaks(&self) -> &[[u8; 32]] {
+        &self.peaks_hashes[..self.peaks_count]
+    }
+
+    /// Appends `leaf` and returns its element index.
+    pub fn append<H: PairHasher + ?Sized>(
+        &mut self,
+        hasher: &H,
+        leaf: &[u8; 32],
+    ) -> Result<u64, H::Error> {
+        let leaves_count = elements_count_to_leaf_count(self.elements_count)?;
+        let merges = leaf_count_to_append_no_merges(leaves_count);
+        let elements_count = self
+            .elements_count
+            .checked_add(merges + 1)
+            .ok_or(VerifyError::Overflow)?;
+
+        let mut peaks_count = self.peaks_count;
+        let mut hash = *leaf;
+        for _ in 0..merges {
+            peaks_count = peaks_count
+                .checked_sub(1)
+                .ok_or(VerifyError::InvalidPeaksCount)?;
+            hash = hasher.hash_pair(&self.peaks_hashes[peaks_count], &hash)?;
+        }
+        let slot = self
+            .peaks_hashes
+            .get_mut(peaks_count)
+            .ok_or(VerifyError::Overflow)?;
+        *slot = hash;
+        self.peaks_count = peaks_count + 1;
+
+        let element_index = self.elements_count + 1;
+        self.elements_count = elements_count;
+        Ok(element_index)
+    }
+
+    pub fn root<H: PairHasher + ?Sized>(&self, hasher: &H) -> Result<[u8; 32], H::Error> {
+        root_from_peaks(hasher, self.peaks(), self.elements_count)
+    }
+}
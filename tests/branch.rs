@@ -0,0 +1,274 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+mod common;
+
+use common::RecordingHook;
+use mmr::error::MmrError;
+use mmr::hasher::{Hasher, KeccakHasher};
+use mmr::test_utils::lv;
+use mmr::{InMemoryStore, KeyKind, Mmr, Store, StoreKey, StoreValue};
+
+#[tokio::test]
+async fn branches_share_nodes_below_fork_point_and_can_be_promoted() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+    let canonical = Mmr::new(store.clone(), hasher.clone(), Some(77)).unwrap();
+    canonical
+        .batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+
+    let branch = canonical.fork(Some(78)).await.unwrap();
+    let branch_result = branch.batch_append(&[lv("104"), lv("105")]).await.unwrap();
+    canonical.append(lv("204")).await.unwrap();
+
+    let reference = Mmr::new(Arc::new(InMemoryStore::default()), hasher.clone(), Some(79)).unwrap();
+    let expected = reference
+        .batch_append(&[lv("1"), lv("2"), lv("3"), lv("104"), lv("105")])
+        .await
+        .unwrap();
+    assert_eq!(branch_result.root_hash, expected.root_hash);
+    assert_eq!(
+        store
+            .scan_keys(78)
+            .await
+            .unwrap()
+            .iter()
+            .filter(|key| key.kind == KeyKind::NodeHash)
+            .count(),
+        4
+    );
+
+    let proof = branch.get_proof(1, None).await.unwrap();
+    assert!(branch.verify_proof(&proof, lv("1"), None).await.unwrap());
+
+    let nested = branch.fork(Some(80)).await.unwrap();
+    let nested_result = nested.append(lv("306")).await.unwrap();
+    let reopened = Mmr::open_branch(store.clone(), hasher.clone(), 80)
+        .await
+        .unwrap();
+    assert_eq!(reopened.get_leaf_hash(0).await.unwrap(), lv("1"));
+    assert_eq!(reopened.get_leaf_hash(3).await.unwrap(), lv("104"));
+    assert_eq!(
+        reopened.get_root_hash().await.unwrap(),
+        Some(nested_result.root_hash)
+    );
+    assert!(matches!(
+        Mmr::open_branch(store.clone(), hasher.clone(), 77)
+            .await
+            .unwrap_err(),
+        MmrError::NotABranch(77)
+    ));
+
+    assert_eq!(branch.promote().await.unwrap(), 77);
+    let promoted = Mmr::new(store, hasher, Some(77)).unwrap();
+    assert_eq!(
+        promoted.get_root_hash().await.unwrap(),
+        Some(expected.root_hash)
+    );
+    assert!(promoted.verify_integrity().await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn promote_migrates_every_key_kind_in_one_batch() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+    let canonical = Mmr::new(store.clone(), hasher.clone(), Some(90))
+        .unwrap()
+        .with_hash_index(true);
+    canonical
+        .batch_append_with_keys(&[(1, lv("1")), (2, lv("2"))])
+        .await
+        .unwrap();
+
+    let branch = canonical
+        .fork(Some(91))
+        .await
+        .unwrap()
+        .with_hash_index(true);
+    let replaced = canonical
+        .batch_append_with_keys(&[(3, lv("203")), (4, lv("204"))])
+        .await
+        .unwrap();
+    branch
+        .batch_append_with_keys(&[(3, lv("103"))])
+        .await
+        .unwrap();
+
+    let late = canonical.fork(Some(92)).await.unwrap();
+    assert!(matches!(
+        branch.promote().await.unwrap_err(),
+        MmrError::PromoteSharedParent {
+            parent: 90,
+            sibling: 92
+        }
+    ));
+    late.destroy().await.unwrap();
+
+    assert_eq!(branch.promote().await.unwrap(), 90);
+    let promoted = Mmr::new(store.clone(), hasher, Some(90))
+        .unwrap()
+        .with_hash_index(true);
+    assert_eq!(
+        promoted.get_root_hash().await.unwrap(),
+        branch.get_root_hash().await.unwrap()
+    );
+    assert!(promoted.verify_integrity().await.unwrap().is_ok());
+    assert_eq!(promoted.get_element_index_by_key(3).await.unwrap(), Some(4));
+    assert_eq!(promoted.get_element_index_by_key(4).await.unwrap(), None);
+    assert_eq!(
+        promoted.find_element_index(&lv("103")).await.unwrap(),
+        Some(4)
+    );
+    assert_eq!(promoted.find_element_index(&lv("204")).await.unwrap(), None);
+    assert_eq!(
+        promoted.find_element_index(&lv("2")).await.unwrap(),
+        Some(2)
+    );
+    let parent_nodes = store
+        .scan_keys(90)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|key| key.kind == KeyKind::NodeHash)
+        .count() as u64;
+    assert!(replaced.elements_count > parent_nodes);
+    assert_eq!(parent_nodes, promoted.get_elements_count().await.unwrap());
+}
+
+#[tokio::test]
+async fn branches_inherit_parent_keys_up_to_the_fork_point() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+    let canonical = Mmr::new(store.clone(), hasher.clone(), Some(95))
+        .unwrap()
+        .with_hash_index(true);
+    canonical
+        .batch_append_with_keys(&[(1, lv("1")), (2, lv("2"))])
+        .await
+        .unwrap();
+    let branch = canonical
+        .fork(Some(96))
+        .await
+        .unwrap()
+        .with_hash_index(true);
+    canonical.append_with_key(3, lv("203")).await.unwrap();
+
+    assert!(branch.contains(&lv("2")).await.unwrap());
+    assert!(!branch.contains(&lv("203")).await.unwrap());
+    assert_eq!(branch.get_element_index_by_key(2).await.unwrap(), Some(2));
+    assert_eq!(branch.get_element_index_by_key(3).await.unwrap(), None);
+    assert!(matches!(
+        branch.append_with_key(1, lv("103")).await.unwrap_err(),
+        MmrError::DuplicateExternalKey(1)
+    ));
+    branch.append_with_key(3, lv("103")).await.unwrap();
+    assert!(branch.contains(&lv("103")).await.unwrap());
+
+    let conflicting = StoreKey::new(96, KeyKind::ExternalKey, 2);
+    let leaf_key = StoreKey::new(96, KeyKind::LeafKey, 4);
+    store
+        .set_many(vec![
+            (conflicting.clone(), StoreValue::U64(4)),
+            (leaf_key.clone(), StoreValue::U64(2)),
+        ])
+        .await
+        .unwrap();
+    assert!(matches!(
+        branch.promote().await.unwrap_err(),
+        MmrError::DuplicateExternalKey(2)
+    ));
+    assert_eq!(
+        canonical.get_element_index_by_key(2).await.unwrap(),
+        Some(2)
+    );
+
+    store.delete_many(&[conflicting]).await.unwrap();
+    store.set(leaf_key, StoreValue::U64(3)).await.unwrap();
+    assert_eq!(branch.promote().await.unwrap(), 95);
+    canonical.refresh().await;
+    assert_eq!(
+        canonical.get_element_index_by_key(2).await.unwrap(),
+        Some(2)
+    );
+    assert_eq!(
+        canonical.get_element_index_by_key(3).await.unwrap(),
+        Some(4)
+    );
+    assert!(canonical.contains(&lv("103")).await.unwrap());
+    assert!(!canonical.contains(&lv("203")).await.unwrap());
+}
+
+#[tokio::test]
+async fn promote_replaces_the_parent_range_and_checkpoint() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+    let canonical = Mmr::new(store.clone(), hasher.clone(), Some(97)).unwrap();
+    canonical.batch_append(&[lv("1"), lv("2")]).await.unwrap();
+    let branch = canonical.fork(Some(98)).await.unwrap();
+    canonical
+        .batch_append_with_data(vec![(lv("203"), b"stale".to_vec())])
+        .await
+        .unwrap();
+    canonical
+        .batch_append_with_checkpoint(&[lv("204")], 7)
+        .await
+        .unwrap();
+    branch.append(lv("103")).await.unwrap();
+    let resumed = branch
+        .batch_append_with_checkpoint(&[lv("104")], 5)
+        .await
+        .unwrap();
+
+    branch.promote().await.unwrap();
+    canonical.refresh().await;
+    assert_eq!(canonical.get_leaf_data(4).await.unwrap(), None);
+    let checkpoint = canonical.resume_from_checkpoint().await.unwrap().unwrap();
+    assert_eq!(checkpoint.cursor, 5);
+    assert_eq!(checkpoint.elements_count, resumed.elements_count);
+    assert!(canonical.verify_integrity().await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn forks_keep_the_parent_settings() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+    let hook = Arc::new(RecordingHook::default());
+    let canonical = Mmr::new(store, hasher, Some(99))
+        .unwrap()
+        .with_hash_index(true)
+        .with_lazy_root(true)
+        .with_proof_binding(true)
+        .with_hook(hook.clone());
+    canonical.append(lv("1")).await.unwrap();
+
+    let branch = canonical.fork(Some(100)).await.unwrap();
+    let result = branch.append(lv("102")).await.unwrap();
+    assert_eq!(result.root_hash_if_computed(), None);
+    assert!(branch.contains(&lv("102")).await.unwrap());
+    let proof = branch.get_proof(result.element_index, None).await.unwrap();
+    assert_eq!(proof.mmr_id, Some(100));
+    assert_eq!(hook.before.load(Ordering::SeqCst), 2);
+    assert_eq!(*hook.after.lock().unwrap(), vec![1, 3]);
+}
+
+#[tokio::test]
+async fn refresh_drops_node_cache_after_an_external_promote() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+    let canonical = Mmr::new(store, hasher, Some(93))
+        .unwrap()
+        .with_node_cache(64);
+    canonical.batch_append(&[lv("1"), lv("2")]).await.unwrap();
+    let branch = canonical.fork(Some(94)).await.unwrap();
+    canonical.append(lv("203")).await.unwrap();
+    branch.append(lv("103")).await.unwrap();
+
+    let stale = canonical.get_peaks(None).await.unwrap();
+    branch.promote().await.unwrap();
+    canonical.refresh().await;
+    let peaks = canonical.get_peaks(None).await.unwrap();
+    assert_ne!(peaks, stale);
+    assert_eq!(peaks, branch.get_peaks(None).await.unwrap());
+}
@@ -1,37 +1,47 @@
-use mmr::error::HasherError;
+// Each test crate uses only some of these helpers.
+#![allow(dead_code)]
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use mmr::error::MmrError;
 use mmr::types::Hash32;
+use mmr::{AppendHook, InMemoryStore, Store, StoreError, StoreKey, StoreValue};
+
+#[derive(Default)]
+pub struct RecordingHook {
+    pub before: AtomicUsize,
+    pub after: Mutex<Vec<u64>>,
+    pub reject_over: Option<usize>,
+}
 
-pub fn hash_to_hex(hash: &Hash32) -> String {
-    format!("0x{}", hex::encode(hash))
+impl AppendHook for RecordingHook {
+    fn on_before_append(&self, _mmr_id: u64, values: &[Hash32]) -> Result<(), MmrError> {
+        if self.reject_over.is_some_and(|limit| values.len() > limit) {
+            return Err(MmrError::HookRejected("batch too large".to_string()));
+        }
+        self.before.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn on_after_append(&self, _mmr_id: u64, result: &mmr::BatchAppendResult) {
+        self.after.lock().unwrap().push(result.elements_count);
+    }
 }
 
-pub fn hash_from_hex(value: &str) -> Result<Hash32, HasherError> {
-    let raw = value.strip_prefix("0x").unwrap_or(value);
+/// Store with only the required methods, so every optional one takes its default.
+pub struct NoCasStore(pub InMemoryStore);
 
-    if raw.is_empty() {
-        return Ok([0u8; 32]);
+impl Store for NoCasStore {
+    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue>, StoreError> {
+        self.0.get(key).await
     }
 
-    let normalized = if raw.len() % 2 == 1 {
-        format!("0{raw}")
-    } else {
-        raw.to_string()
-    };
-
-    let bytes = hex::decode(&normalized).map_err(|source| HasherError::InvalidHex {
-        value: value.to_string(),
-        source,
-    })?;
-
-    if bytes.len() > 32 {
-        return Err(HasherError::InputTooLarge {
-            value: value.to_string(),
-            max_bytes: 32,
-        });
+    async fn set(&self, key: StoreKey, value: StoreValue) -> Result<(), StoreError> {
+        self.0.set(key, value).await
     }
 
-    let mut out = [0u8; 32];
-    let start = 32 - bytes.len();
-    out[start..].copy_from_slice(&bytes);
-    Ok(out)
+    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue>>, StoreError> {
+        self.0.get_many(keys).await
+    }
 }
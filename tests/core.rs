@@ -1,74 +1,39 @@
-use std::collections::HashMap;
 use std::sync::Arc;
-use std::sync::Mutex;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
 mod common;
 
-use common::{hash_from_hex, hash_to_hex};
-use mmr::error::MmrError;
+use common::{NoCasStore, RecordingHook};
+use futures::future::join_all;
+use futures::{StreamExt, TryStreamExt, stream};
+use mmr::error::{HasherError, MmrError};
 use mmr::hasher::{Hasher, KeccakHasher, PoseidonHasher};
-use mmr::types::{Hash32, ZERO_HASH};
-use mmr::{InMemoryStore, KeyKind, Mmr, Store, StoreError, StoreKey, StoreValue};
-#[cfg(feature = "postgres-store")]
-use mmr::{PostgresStore, PostgresStoreOptions};
+use mmr::test_utils::{SpyStore, lv};
+use mmr::types::{
+    Hash, Hash20, Hash32, Hash64, PeaksCommitment, Proof, ProofVerification, ZERO_HASH,
+    hash_from_decimal, hash_from_hex,
+};
+use mmr::verify::{self as core_verify, Keccak256, VerifyError};
+use mmr::{
+    CopyOptions, ID_ALLOCATOR_MMR_ID, InMemoryStore, KeyKind, LeafEncode, MAX_HASH_INDEX_PROBES,
+    Mmr, MmrWriter, OpStats, PROOF_ENCODING_VERSION, PROOF_JSON_VERSION, PeaksAccumulator,
+    PhaseTimings, RetryPolicy, RetryingStore, Store, StoreError, StoreKey, StoreOpTarget,
+    StoreValue, TypedMmr, WriteBatch, append_to_peaks, bag_peaks, bound_root_from_peaks,
+    compute_peaks_from_leaves, compute_root_from_leaves, element_index_to_position,
+    map_leaf_index_to_element_index, position_to_element_index, prove_from_leaves,
+    verify_bound_proof, verify_compact_proof, verify_path_proof, verify_proof,
+    verify_proof_against_root,
+};
 
 const LEAVES: [&str; 5] = ["1", "2", "3", "4", "5"];
 
-fn lv(value: &str) -> mmr::Hash32 {
-    if value.starts_with("0x") || value.starts_with("0X") {
-        return hash_from_hex(value).unwrap();
-    }
-
-    let parsed = value.parse::<u128>().unwrap();
-    let mut out = [0u8; 32];
-    out[16..].copy_from_slice(&parsed.to_be_bytes());
-    out
-}
-
-fn bag_from_peaks(hasher: &dyn Hasher, peaks_hashes: &[Hash32]) -> Hash32 {
-    match peaks_hashes.len() {
-        0 => ZERO_HASH,
-        1 => peaks_hashes[0],
-        _ => {
-            let mut acc = hasher
-                .hash_pair(
-                    &peaks_hashes[peaks_hashes.len() - 2],
-                    &peaks_hashes[peaks_hashes.len() - 1],
-                )
-                .unwrap();
-
-            for peak_hash in peaks_hashes[..peaks_hashes.len() - 2].iter().rev() {
-                acc = hasher.hash_pair(peak_hash, &acc).unwrap();
-            }
-
-            acc
-        }
-    }
-}
-
-fn root_from_peaks(hasher: &dyn Hasher, peaks_hashes: &[Hash32], elements_count: u64) -> Hash32 {
-    let bag = bag_from_peaks(hasher, peaks_hashes);
-    hasher.hash_count_and_bag(elements_count, &bag).unwrap()
-}
-
-#[cfg(feature = "postgres-store")]
-fn unique_test_mmr_id() -> u32 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-
-    let nonce = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos() as u64;
-    ((nonce % ((i32::MAX as u64) - 10_000)) as u32) + 10_000
-}
-
 #[tokio::test]
 async fn should_compute_parent_tree_for_keccak_hasher() {
     let store = Arc::new(InMemoryStore::default());
     let hasher = Arc::new(KeccakHasher::new());
 
-    let mut mmr = Mmr::new(store, hasher.clone(), Some(1)).unwrap();
+    let mmr = Mmr::new(store, hasher.clone(), Some(1)).unwrap();
 
     let mut appends = Vec::new();
     for leaf in LEAVES {
@@ -91,6 +56,7 @@ async fn should_compute_parent_tree_for_keccak_hasher() {
     assert_eq!(append.leaves_count, 6);
     assert_eq!(append.elements_count, 10);
     assert_eq!(append.root_hash, root);
+    assert_eq!(append.peaks_hashes, vec![node7, node10]);
 
     assert_eq!(mmr.get_peaks(None).await.unwrap(), vec![node7, node10]);
     assert_eq!(mmr.bag_the_peaks(None).await.unwrap(), bag);
@@ -104,7 +70,7 @@ async fn batch_append_matches_repeated_append_for_identical_values() {
     let hasher = Arc::new(KeccakHasher::new());
     let leaves = ["1", "2", "3", "4", "5", "6", "7", "8"];
 
-    let mut single = Mmr::new(
+    let single = Mmr::new(
         Arc::new(InMemoryStore::default()),
         hasher.clone(),
         Some(101),
@@ -115,7 +81,7 @@ async fn batch_append_matches_repeated_append_for_identical_values() {
         single_appends.push(single.append(lv(leaf)).await.unwrap());
     }
 
-    let mut batched = Mmr::new(
+    let batched = Mmr::new(
         Arc::new(InMemoryStore::default()),
         hasher.clone(),
         Some(102),
@@ -154,11 +120,12 @@ async fn batch_append_matches_repeated_append_for_identical_values() {
     );
     assert_eq!(
         batch_result.root_hash,
-        root_from_peaks(
+        mmr::root_from_peaks(
             hasher.as_ref(),
             &batch_result.peaks_hashes,
             batch_result.elements_count,
         )
+        .unwrap()
     );
 
     assert_eq!(
@@ -194,13 +161,13 @@ async fn append_matches_batch_append_single_value() {
     let hasher = Arc::new(KeccakHasher::new());
     let prefill = ["1", "2", "3", "4", "5"];
 
-    let mut append_mmr = Mmr::new(
+    let append_mmr = Mmr::new(
         Arc::new(InMemoryStore::default()),
         hasher.clone(),
         Some(103),
     )
     .unwrap();
-    let mut batch_mmr = Mmr::new(
+    let batch_mmr = Mmr::new(
         Arc::new(InMemoryStore::default()),
         hasher.clone(),
         Some(104),
@@ -233,18 +200,19 @@ async fn append_matches_batch_append_single_value() {
     );
     assert_eq!(
         batch_result.root_hash,
-        root_from_peaks(
+        mmr::root_from_peaks(
             hasher.as_ref(),
             &batch_result.peaks_hashes,
             batch_result.elements_count,
         )
+        .unwrap()
     );
 }
 
 #[tokio::test]
 async fn batch_append_result_peaks_and_root_are_consistent_for_poseidon() {
     let hasher = Arc::new(PoseidonHasher::new());
-    let mut mmr = Mmr::new(
+    let mmr = Mmr::new(
         Arc::new(InMemoryStore::default()),
         hasher.clone(),
         Some(106),
@@ -263,15 +231,59 @@ async fn batch_append_result_peaks_and_root_are_consistent_for_poseidon() {
     );
     assert_eq!(
         result.root_hash,
-        root_from_peaks(hasher.as_ref(), &result.peaks_hashes, result.elements_count)
+        mmr::root_from_peaks(hasher.as_ref(), &result.peaks_hashes, result.elements_count).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn append_stream_commits_chunks_and_matches_batch_append() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let values = (1..=10u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+
+    let streamed = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        hasher.clone(),
+        Some(107),
+    )
+    .unwrap();
+    let results: Vec<_> = streamed
+        .append_stream(stream::iter(values.clone()), 4)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        results
+            .iter()
+            .map(|result| result.appended_count)
+            .collect::<Vec<_>>(),
+        vec![4, 4, 2]
+    );
+    assert_eq!(
+        results[1].first_element_index,
+        results[0].elements_count + 1
     );
+
+    let batched = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        hasher.clone(),
+        Some(108),
+    )
+    .unwrap();
+    let batch_result = batched.batch_append(&values).await.unwrap();
+
+    let last = results.last().unwrap();
+    assert_eq!(last.elements_count, batch_result.elements_count);
+    assert_eq!(last.leaves_count, batch_result.leaves_count);
+    assert_eq!(last.root_hash, batch_result.root_hash);
+    assert_eq!(last.peaks_hashes, batch_result.peaks_hashes);
 }
 
 #[tokio::test]
 async fn batch_append_rejects_empty_values() {
     let store = Arc::new(InMemoryStore::default());
     let hasher = Arc::new(KeccakHasher::new());
-    let mut mmr = Mmr::new(store, hasher, Some(105)).unwrap();
+    let mmr = Mmr::new(store, hasher, Some(105)).unwrap();
 
     assert!(matches!(
         mmr.batch_append(&[]).await,
@@ -284,7 +296,7 @@ async fn should_create_from_peaks_and_match_followup_appends() {
     let hasher = Arc::new(KeccakHasher::new());
 
     let store1 = Arc::new(InMemoryStore::default());
-    let mut original = Mmr::new(store1.clone(), hasher.clone(), Some(11)).unwrap();
+    let original = Mmr::new(store1.clone(), hasher.clone(), Some(11)).unwrap();
 
     let mut original_appends = Vec::new();
     for leaf in LEAVES {
@@ -298,7 +310,7 @@ async fn should_create_from_peaks_and_match_followup_appends() {
     let original_root = original.get_root_hash().await.unwrap().unwrap();
 
     let store2 = Arc::new(InMemoryStore::default());
-    let mut from_peaks = Mmr::create_from_peaks(
+    let from_peaks = Mmr::create_from_peaks(
         store2,
         hasher.clone(),
         Some(12),
@@ -400,7 +412,7 @@ async fn should_handle_create_from_peaks_edge_cases() {
     let hasher = Arc::new(KeccakHasher::new());
 
     let store = Arc::new(InMemoryStore::default());
-    let mut non_empty = Mmr::new(store.clone(), hasher.clone(), Some(21)).unwrap();
+    let non_empty = Mmr::new(store.clone(), hasher.clone(), Some(21)).unwrap();
     non_empty.append(lv("1")).await.unwrap();
 
     let non_empty_res =
@@ -420,7 +432,7 @@ async fn should_handle_create_from_peaks_edge_cases() {
         Err(MmrError::InvalidPeaksCountForElements)
     ));
 
-    let mut zero_mmr = Mmr::create_from_peaks(
+    let zero_mmr = Mmr::create_from_peaks(
         Arc::new(InMemoryStore::default()),
         hasher.clone(),
         Some(23),
@@ -446,7 +458,7 @@ async fn should_handle_create_from_peaks_edge_cases() {
     assert_eq!(zero_append.leaves_count, 1);
 
     let single = lv("0x1001");
-    let mut one_mmr = Mmr::create_from_peaks(
+    let one_mmr = Mmr::create_from_peaks(
         Arc::new(InMemoryStore::default()),
         hasher,
         Some(24),
@@ -475,8 +487,8 @@ async fn should_keep_multiple_mmrs_isolated_in_one_store() {
     let shared_store = Arc::new(InMemoryStore::default());
     let hasher = Arc::new(KeccakHasher::new());
 
-    let mut mmr_a = Mmr::new(shared_store.clone(), hasher.clone(), Some(31)).unwrap();
-    let mut mmr_b = Mmr::new(shared_store, hasher.clone(), Some(32)).unwrap();
+    let mmr_a = Mmr::new(shared_store.clone(), hasher.clone(), Some(31)).unwrap();
+    let mmr_b = Mmr::new(shared_store, hasher.clone(), Some(32)).unwrap();
 
     let a1 = mmr_a.append(lv("1")).await.unwrap();
     let a2 = mmr_a.append(lv("2")).await.unwrap();
@@ -489,8 +501,8 @@ async fn should_keep_multiple_mmrs_isolated_in_one_store() {
     assert_eq!(mmr_a.get_leaves_count().await.unwrap(), 2);
     assert_eq!(mmr_b.get_leaves_count().await.unwrap(), 1);
     assert_ne!(
-        hash_to_hex(&mmr_a.get_root_hash().await.unwrap().unwrap()),
-        hash_to_hex(&mmr_b.get_root_hash().await.unwrap().unwrap())
+        mmr_a.get_root_hash().await.unwrap(),
+        mmr_b.get_root_hash().await.unwrap()
     );
 
     let proof_a = mmr_a.get_proof(a1.element_index, None).await.unwrap();
@@ -501,381 +513,3094 @@ async fn should_keep_multiple_mmrs_isolated_in_one_store() {
 }
 
 #[tokio::test]
-async fn should_reject_invalid_index_and_fail_on_malformed_siblings() {
+async fn get_leaf_hash_returns_appended_values_by_leaf_index() {
     let store = Arc::new(InMemoryStore::default());
     let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(42)).unwrap();
 
-    let mut mmr = Mmr::new(store, hasher, Some(41)).unwrap();
-    mmr.append(lv("1")).await.unwrap();
-    mmr.append(lv("2")).await.unwrap();
-    mmr.append(lv("3")).await.unwrap();
+    for leaf in LEAVES {
+        mmr.append(lv(leaf)).await.unwrap();
+    }
+
+    for (leaf_index, leaf) in LEAVES.iter().enumerate() {
+        assert_eq!(
+            mmr.get_leaf_hash(leaf_index as u64).await.unwrap(),
+            lv(leaf)
+        );
+    }
 
     assert!(matches!(
-        mmr.get_proof(0, None).await,
-        Err(MmrError::InvalidElementIndex)
+        mmr.get_leaf_hash(LEAVES.len() as u64).await,
+        Err(MmrError::InvalidLeafIndex)
     ));
-
-    let mut proof = mmr.get_proof(1, None).await.unwrap();
-    proof.siblings_hashes.push([0u8; 32]);
-
-    assert!(!mmr.verify_proof(&proof, lv("1"), None).await.unwrap());
 }
 
-#[cfg(feature = "stateless-verify")]
 #[tokio::test]
-async fn stateless_verify_is_available_and_independent() {
+async fn iter_leaves_yields_every_leaf_in_order() {
     let store = Arc::new(InMemoryStore::default());
     let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(43)).unwrap();
 
-    let mut mmr = Mmr::new(store, hasher, Some(51)).unwrap();
-    mmr.append(lv("1")).await.unwrap();
-    mmr.append(lv("2")).await.unwrap();
-    mmr.append(lv("3")).await.unwrap();
-
-    let proof = mmr.get_proof(1, None).await.unwrap();
-    assert!(
-        mmr.verify_proof_stateless(&proof, lv("1"), None)
-            .await
-            .unwrap()
-    );
-
-    let mut tampered = proof.clone();
-    tampered.peaks_hashes[0] = [0u8; 32];
-
-    assert!(
-        !mmr.verify_proof_stateless(&tampered, lv("1"), None)
-            .await
-            .unwrap()
-    );
+    let values = (0..2500u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    mmr.batch_append(&values).await.unwrap();
 
-    assert!(mmr.verify_proof(&tampered, lv("1"), None).await.unwrap());
-}
+    let leaves: Vec<(u64, u64, Hash32)> = mmr.iter_leaves().try_collect().await.unwrap();
 
-#[derive(Debug, Default)]
-struct SpyStoreMetrics {
-    get_calls: usize,
-    set_calls: usize,
-    get_many_calls: usize,
-    set_many_calls: usize,
+    assert_eq!(leaves.len(), values.len());
+    for (leaf_index, (leaf, value)) in leaves.iter().zip(values.iter()).enumerate() {
+        assert_eq!(leaf.0, leaf_index as u64);
+        assert_eq!(leaf.1, map_leaf_index_to_element_index(leaf_index as u64));
+        assert_eq!(leaf.2, *value);
+    }
 }
 
-#[derive(Default)]
-struct SpyStore {
-    inner: Mutex<HashMap<StoreKey, StoreValue>>,
-    get_calls: AtomicUsize,
-    set_calls: AtomicUsize,
-    get_many_calls: AtomicUsize,
-    set_many_calls: AtomicUsize,
-    fail_set_many: AtomicBool,
-}
+#[tokio::test]
+async fn free_verify_proof_checks_proofs_without_a_store() {
+    let hasher = KeccakHasher::new();
+    let mmr = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        Arc::new(hasher),
+        Some(44),
+    )
+    .unwrap();
 
-impl SpyStore {
-    fn metrics(&self) -> SpyStoreMetrics {
-        SpyStoreMetrics {
-            get_calls: self.get_calls.load(Ordering::Relaxed),
-            set_calls: self.set_calls.load(Ordering::Relaxed),
-            get_many_calls: self.get_many_calls.load(Ordering::Relaxed),
-            set_many_calls: self.set_many_calls.load(Ordering::Relaxed),
-        }
+    let mut appends = Vec::new();
+    for leaf in LEAVES {
+        appends.push(mmr.append(lv(leaf)).await.unwrap());
     }
 
-    fn set_fail_set_many(&self, fail: bool) {
-        self.fail_set_many.store(fail, Ordering::Relaxed);
+    for (leaf, append) in LEAVES.iter().zip(appends.iter()) {
+        let proof = mmr.get_proof(append.element_index, None).await.unwrap();
+        assert!(verify_proof(&hasher, &proof, lv(leaf)).unwrap());
+        assert!(!verify_proof(&hasher, &proof, lv("999")).unwrap());
     }
 
-    fn entry_count(&self) -> usize {
-        self.inner.lock().unwrap().len()
-    }
+    let mut tampered = mmr.get_proof(1, None).await.unwrap();
+    tampered.peaks_hashes[0] = ZERO_HASH;
+    assert!(!verify_proof(&hasher, &tampered, lv("1")).unwrap());
 }
 
-impl Store for SpyStore {
-    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue>, StoreError> {
-        self.get_calls.fetch_add(1, Ordering::Relaxed);
-        Ok(self.inner.lock().unwrap().get(key).cloned())
+#[tokio::test]
+async fn verify_proof_against_root_matches_published_root() {
+    let hasher = KeccakHasher::new();
+    let mmr = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        Arc::new(hasher),
+        Some(45),
+    )
+    .unwrap();
+
+    let mut appends = Vec::new();
+    for leaf in LEAVES {
+        appends.push(mmr.append(lv(leaf)).await.unwrap());
     }
+    let root = mmr.get_root_hash().await.unwrap().unwrap();
 
-    async fn set(&self, key: StoreKey, value: StoreValue) -> Result<(), StoreError> {
-        self.set_calls.fetch_add(1, Ordering::Relaxed);
-        self.inner.lock().unwrap().insert(key, value);
-        Ok(())
+    for (leaf, append) in LEAVES.iter().zip(appends.iter()) {
+        let proof = mmr.get_proof(append.element_index, None).await.unwrap();
+        assert!(verify_proof_against_root(&hasher, &proof, lv(leaf), &root).unwrap());
+        assert!(!verify_proof_against_root(&hasher, &proof, lv("999"), &root).unwrap());
     }
 
-    async fn set_many(&self, entries: Vec<(StoreKey, StoreValue)>) -> Result<(), StoreError> {
-        self.set_many_calls.fetch_add(1, Ordering::Relaxed);
-        if self.fail_set_many.load(Ordering::Relaxed) {
-            return Err(StoreError::Internal("forced set_many failure".to_string()));
-        }
+    let stale_root = appends[2].root_hash;
+    let proof = mmr.get_proof(1, None).await.unwrap();
+    assert!(!verify_proof_against_root(&hasher, &proof, lv("1"), &stale_root).unwrap());
+}
 
-        let mut guard = self.inner.lock().unwrap();
-        for (key, value) in entries {
-            guard.insert(key, value);
-        }
+#[tokio::test]
+async fn compact_proofs_round_trip_and_verify_against_root() {
+    let hasher = KeccakHasher::new();
+    let mmr = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        Arc::new(hasher),
+        Some(46),
+    )
+    .unwrap();
 
-        Ok(())
-    }
+    let values = (1..=11u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    mmr.batch_append(&values).await.unwrap();
+    let root = mmr.get_root_hash().await.unwrap().unwrap();
 
-    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue>>, StoreError> {
-        self.get_many_calls.fetch_add(1, Ordering::Relaxed);
-        let guard = self.inner.lock().unwrap();
-        Ok(keys.iter().map(|key| guard.get(key).cloned()).collect())
+    for (leaf_index, value) in values.iter().enumerate() {
+        let element_index = map_leaf_index_to_element_index(leaf_index as u64);
+        let proof = mmr.get_proof(element_index, None).await.unwrap();
+        let compact = proof.to_compact().unwrap();
+
+        assert_eq!(
+            compact.other_peaks_hashes.len() + 1,
+            proof.peaks_hashes.len()
+        );
+        assert_eq!(compact.to_proof(&hasher).unwrap(), proof);
+        assert!(verify_compact_proof(&hasher, &compact, *value, &root).unwrap());
+        assert!(!verify_compact_proof(&hasher, &compact, lv("999"), &root).unwrap());
     }
 }
 
 #[tokio::test]
-async fn append_uses_one_get_many_and_one_set_many_in_steady_state() {
-    let store = Arc::new(SpyStore::default());
+async fn shared_mmr_serializes_concurrent_appends() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Mmr<Arc<InMemoryStore>>>();
+
+    let mmr = Arc::new(
+        Mmr::new(
+            Arc::new(InMemoryStore::default()),
+            Arc::new(KeccakHasher::new()),
+            Some(47),
+        )
+        .unwrap(),
+    );
+
+    let appends = join_all((1..=8u64).map(|i| {
+        let mmr = mmr.clone();
+        async move { mmr.append(lv(&i.to_string())).await.unwrap() }
+    }))
+    .await;
+
+    let mut element_indices = appends
+        .iter()
+        .map(|append| append.element_index)
+        .collect::<Vec<_>>();
+    element_indices.sort_unstable();
+    assert_eq!(
+        element_indices,
+        (0..8u64)
+            .map(map_leaf_index_to_element_index)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 8);
+    assert_eq!(mmr.get_elements_count().await.unwrap(), 15);
+}
+
+#[tokio::test]
+async fn writers_sharing_an_mmr_id_retry_instead_of_corrupting_peaks() {
+    let store = Arc::new(InMemoryStore::default());
     let hasher = Arc::new(KeccakHasher::new());
-    let mut mmr = Mmr::new(store.clone(), hasher, Some(61)).unwrap();
+    let writer_a = Mmr::new(store.clone(), hasher.clone(), Some(48)).unwrap();
+    let writer_b = Mmr::new(store.clone(), hasher.clone(), Some(48)).unwrap();
 
-    mmr.append(lv("1")).await.unwrap();
+    writer_a.append(lv("1")).await.unwrap();
+    writer_b.append(lv("2")).await.unwrap();
+    let last = writer_a.append(lv("3")).await.unwrap();
 
-    let before = store.metrics();
-    mmr.append(lv("2")).await.unwrap();
-    let after = store.metrics();
+    assert_eq!(last.element_index, 4);
+    assert_eq!(last.leaves_count, 3);
 
-    assert_eq!(after.get_many_calls - before.get_many_calls, 1);
-    assert_eq!(after.set_many_calls - before.set_many_calls, 1);
-    assert_eq!(after.get_calls - before.get_calls, 0);
-    assert_eq!(after.set_calls - before.set_calls, 0);
+    let reference = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(49)).unwrap();
+    let expected = reference
+        .batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    assert_eq!(last.root_hash, expected.root_hash);
+    assert_eq!(
+        writer_b.get_peaks(None).await.unwrap(),
+        expected.peaks_hashes
+    );
 }
 
+fn assert_send<T: Send>(_: T) {}
+
 #[tokio::test]
-async fn batch_append_uses_one_get_many_and_one_set_many_in_steady_state() {
-    let store = Arc::new(SpyStore::default());
+async fn append_futures_are_send() {
+    let mmr = Arc::new(Mmr::in_memory_keccak());
+    assert_send(mmr.append(lv("1")));
+    assert_send(mmr.batch_append(&[lv("1"), lv("2")]));
+    assert_send(mmr.witness_tracker().batch_append(&[lv("1")]));
+
+    let spawned = mmr.clone();
+    let appended = tokio::spawn(async move { spawned.batch_append(&[lv("1"), lv("2")]).await })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(appended.leaves_count, 2);
+    assert_eq!(
+        tokio::spawn(async move { mmr.append(lv("3")).await })
+            .await
+            .unwrap()
+            .unwrap()
+            .leaves_count,
+        3
+    );
+}
+
+#[tokio::test]
+async fn writer_lease_blocks_second_writer_until_released() {
+    let store = Arc::new(InMemoryStore::default());
     let hasher = Arc::new(KeccakHasher::new());
-    let mut mmr = Mmr::new(store.clone(), hasher, Some(63)).unwrap();
+    let writer_a = Mmr::new(store.clone(), hasher.clone(), Some(50)).unwrap();
+    let writer_b = Mmr::new(store.clone(), hasher.clone(), Some(50)).unwrap();
 
-    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+    let lease = writer_a
+        .acquire_writer_lease(Duration::from_secs(60))
         .await
         .unwrap();
+    assert!(matches!(
+        writer_b.acquire_writer_lease(Duration::from_secs(60)).await,
+        Err(MmrError::WriterLeaseHeld)
+    ));
+    assert!(matches!(
+        writer_b.append(lv("1")).await,
+        Err(MmrError::WriterLeaseHeld)
+    ));
 
-    let before = store.metrics();
-    mmr.batch_append(&[lv("4"), lv("5"), lv("6"), lv("7")])
+    writer_a.append(lv("1")).await.unwrap();
+    lease.renew(Duration::from_secs(60)).await.unwrap();
+    lease.release().await.unwrap();
+
+    let lease_b = writer_b
+        .acquire_writer_lease(Duration::from_secs(60))
         .await
         .unwrap();
-    let after = store.metrics();
+    writer_b.append(lv("2")).await.unwrap();
+    assert_eq!(writer_b.get_leaves_count().await.unwrap(), 2);
 
-    assert_eq!(after.get_many_calls - before.get_many_calls, 1);
-    assert_eq!(after.set_many_calls - before.set_many_calls, 1);
-    assert_eq!(after.get_calls - before.get_calls, 0);
-    assert_eq!(after.set_calls - before.set_calls, 0);
+    store
+        .set(
+            StoreKey::new(50, KeyKind::WriterLease, 0),
+            StoreValue::U64(lease_b.owner() ^ 1),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        writer_b.append(lv("3")).await,
+        Err(MmrError::WriterLeaseLost)
+    ));
 }
 
 #[tokio::test]
-async fn append_returns_error_and_avoids_partial_writes_when_set_many_fails() {
-    let store = Arc::new(SpyStore::default());
-    store.set_fail_set_many(true);
+async fn expired_writer_lease_can_be_taken_over() {
+    let store = Arc::new(InMemoryStore::default());
     let hasher = Arc::new(KeccakHasher::new());
-    let mut mmr = Mmr::new(store.clone(), hasher, Some(62)).unwrap();
+    let writer_a = Mmr::new(store.clone(), hasher.clone(), Some(52)).unwrap();
+    let writer_b = Mmr::new(store, hasher, Some(52)).unwrap();
 
-    let result = mmr.append(lv("1")).await;
-    assert!(result.is_err());
-    assert_eq!(store.entry_count(), 0);
+    writer_a.acquire_writer_lease(Duration::ZERO).await.unwrap();
+    writer_b
+        .acquire_writer_lease(Duration::from_secs(60))
+        .await
+        .unwrap();
 
-    assert_eq!(mmr.get_elements_count().await.unwrap(), 0);
-    assert_eq!(mmr.get_leaves_count().await.unwrap(), 0);
+    assert!(matches!(
+        writer_a.append(lv("1")).await,
+        Err(MmrError::WriterLeaseExpired)
+    ));
+    writer_b.append(lv("1")).await.unwrap();
+}
 
-    let key = StoreKey::new(62, KeyKind::NodeHash, 1);
-    assert!(store.get(&key).await.unwrap().is_none());
+#[tokio::test]
+async fn writer_without_lease_appends_past_an_expired_lease() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let leased = Mmr::new(store.clone(), hasher.clone(), Some(54)).unwrap();
+    let unleased = Mmr::new(store, hasher, Some(54)).unwrap();
+
+    leased.acquire_writer_lease(Duration::ZERO).await.unwrap();
+    unleased.append(lv("1")).await.unwrap();
+    unleased.finalize_root().await.unwrap();
+    assert_eq!(unleased.get_leaves_count().await.unwrap(), 1);
 }
 
 #[tokio::test]
-async fn batch_append_returns_error_and_avoids_partial_writes_when_set_many_fails() {
-    let store = Arc::new(SpyStore::default());
-    store.set_fail_set_many(true);
+async fn destroy_removes_every_key_for_the_mmr_only() {
+    let store = Arc::new(InMemoryStore::default());
     let hasher = Arc::new(KeccakHasher::new());
-    let mut mmr = Mmr::new(store.clone(), hasher, Some(64)).unwrap();
+    let doomed = Mmr::new(store.clone(), hasher.clone(), Some(60)).unwrap();
+    let kept = Mmr::new(store.clone(), hasher, Some(61)).unwrap();
+    doomed
+        .batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    kept.batch_append(&[lv("1"), lv("2")]).await.unwrap();
+    let kept_keys = store.scan_keys(61).await.unwrap().len();
 
-    let result = mmr.batch_append(&[lv("1"), lv("2"), lv("3")]).await;
-    assert!(result.is_err());
-    assert_eq!(store.entry_count(), 0);
+    doomed.destroy().await.unwrap();
 
-    assert_eq!(mmr.get_elements_count().await.unwrap(), 0);
-    assert_eq!(mmr.get_leaves_count().await.unwrap(), 0);
+    assert!(store.scan_keys(60).await.unwrap().is_empty());
+    assert_eq!(store.scan_keys(61).await.unwrap().len(), kept_keys);
+    assert_eq!(doomed.get_elements_count().await.unwrap(), 0);
+    assert_eq!(doomed.get_root_hash().await.unwrap(), None);
 
-    let key = StoreKey::new(64, KeyKind::NodeHash, 1);
-    assert!(store.get(&key).await.unwrap().is_none());
+    let result = doomed.append(lv("9")).await.unwrap();
+    assert_eq!(result.elements_count, 1);
 }
 
-#[cfg(feature = "postgres-store")]
 #[tokio::test]
-async fn postgres_batch_append_in_tx_rollback_leaves_store_unchanged() {
-    let database_url = match std::env::var("DATABASE_URL") {
-        Ok(url) => url,
-        Err(_) => return,
-    };
-
-    let store = Arc::new(
-        PostgresStore::connect_with_options(
-            &database_url,
-            PostgresStoreOptions {
-                initialize_schema: true,
-                max_connections: 2,
-            },
-        )
-        .await
-        .unwrap(),
-    );
+async fn destroy_deletes_in_one_batch_and_leaves_the_mmr_whole_on_failure() {
+    let store = Arc::new(SpyStore::new());
     let hasher = Arc::new(KeccakHasher::new());
-    let mut mmr = Mmr::new(store.clone(), hasher.clone(), Some(unique_test_mmr_id())).unwrap();
-
-    let mut tx = store.begin_write_tx().await.unwrap();
-    let result = mmr
-        .batch_append_in_tx(&mut tx, &[lv("1"), lv("2"), lv("3")])
+    let doomed = Mmr::new(store.clone(), hasher, Some(198)).unwrap();
+    doomed
+        .batch_append(&[lv("1"), lv("2"), lv("3")])
         .await
         .unwrap();
-    assert_eq!(result.appended_count, 3);
-    assert!(!result.peaks_hashes.is_empty());
-    assert_eq!(
-        result.root_hash,
-        root_from_peaks(hasher.as_ref(), &result.peaks_hashes, result.elements_count)
-    );
-    tx.rollback().await.unwrap();
+    let keys = store.scan_keys(198).await.unwrap().len();
 
-    assert_eq!(mmr.get_elements_count().await.unwrap(), 0);
-    assert_eq!(mmr.get_leaves_count().await.unwrap(), 0);
-    assert!(mmr.get_root_hash().await.unwrap().is_none());
+    store.set_fail_set_many(true);
+    assert!(doomed.destroy().await.is_err());
+    store.set_fail_set_many(false);
+    assert_eq!(store.scan_keys(198).await.unwrap().len(), keys);
+    assert_eq!(doomed.get_elements_count().await.unwrap(), 4);
+
+    let write_batch_calls = store.metrics().write_batch_calls;
+    doomed.destroy().await.unwrap();
+    assert_eq!(store.metrics().write_batch_calls, write_batch_calls + 1);
+    assert!(store.scan_keys(198).await.unwrap().is_empty());
 }
 
-#[cfg(feature = "postgres-store")]
 #[tokio::test]
-async fn postgres_batch_append_in_tx_returns_peaks_matching_committed_state() {
-    let database_url = match std::env::var("DATABASE_URL") {
-        Ok(url) => url,
-        Err(_) => return,
-    };
-
-    let store = Arc::new(
-        PostgresStore::connect_with_options(
-            &database_url,
-            PostgresStoreOptions {
-                initialize_schema: true,
-                max_connections: 2,
-            },
+async fn verify_integrity_reports_corrupted_missing_nodes_and_root() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(62)).unwrap();
+    let values = (1..=11u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    mmr.batch_append(&values).await.unwrap();
+
+    let report = mmr.verify_integrity().await.unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.elements_count, 19);
+    assert_eq!(report.leaves_count, 11);
+
+    store
+        .set(
+            StoreKey::new(62, KeyKind::NodeHash, 4),
+            StoreValue::Hash(Hash32::new([0xab; 32])),
         )
         .await
-        .unwrap(),
-    );
-    let hasher = Arc::new(KeccakHasher::new());
-    let mut mmr = Mmr::new(store.clone(), hasher.clone(), Some(unique_test_mmr_id())).unwrap();
-
-    let mut tx = store.begin_write_tx().await.unwrap();
-    let result = mmr
-        .batch_append_in_tx(&mut tx, &[lv("1"), lv("2"), lv("3")])
+        .unwrap();
+    store
+        .delete_many(&[StoreKey::new(62, KeyKind::NodeHash, 10)])
+        .await
+        .unwrap();
+    store
+        .set(
+            StoreKey::metadata(62, KeyKind::RootHash),
+            StoreValue::Hash(ZERO_HASH),
+        )
         .await
         .unwrap();
-    tx.commit().await.unwrap();
 
-    assert!(!result.peaks_hashes.is_empty());
-    assert_eq!(
-        result.root_hash,
-        root_from_peaks(hasher.as_ref(), &result.peaks_hashes, result.elements_count)
-    );
-    assert_eq!(
-        result.peaks_hashes,
-        mmr.get_peaks(Some(result.elements_count)).await.unwrap()
-    );
-    assert_eq!(
-        result.root_hash,
-        mmr.get_root_hash().await.unwrap().unwrap()
-    );
+    let report = mmr.verify_integrity().await.unwrap();
+    assert!(!report.is_ok());
+    assert!(report.counts_consistent);
+    assert_eq!(report.mismatched_nodes, vec![6]);
+    assert_eq!(report.missing_nodes, vec![10]);
+    assert_eq!(report.root_matches, Some(false));
 }
 
-#[cfg(feature = "postgres-store")]
 #[tokio::test]
-async fn postgres_append_in_tx_commit_persists_write() {
-    let database_url = match std::env::var("DATABASE_URL") {
-        Ok(url) => url,
-        Err(_) => return,
-    };
-
-    let store = Arc::new(
-        PostgresStore::connect_with_options(
-            &database_url,
-            PostgresStoreOptions {
-                initialize_schema: true,
-                max_connections: 2,
-            },
+async fn rebuild_interior_nodes_repairs_parents_and_root_from_leaves() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(63)).unwrap();
+    let values = (1..=11u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    let appended = mmr.batch_append(&values).await.unwrap();
+
+    let clean = mmr.rebuild_interior_nodes().await.unwrap();
+    assert!(clean.rewritten_nodes.is_empty());
+    assert!(!clean.root_rewritten);
+
+    store
+        .delete_many(&[
+            StoreKey::new(63, KeyKind::NodeHash, 7),
+            StoreKey::new(63, KeyKind::RootHash, 0),
+        ])
+        .await
+        .unwrap();
+    store
+        .set(
+            StoreKey::new(63, KeyKind::NodeHash, 14),
+            StoreValue::Hash(Hash32::new([0xab; 32])),
         )
         .await
-        .unwrap(),
-    );
-    let mut mmr = Mmr::new(
-        store.clone(),
-        Arc::new(KeccakHasher::new()),
-        Some(unique_test_mmr_id()),
-    )
-    .unwrap();
+        .unwrap();
 
-    let mut tx = store.begin_write_tx().await.unwrap();
-    let append = mmr.append_in_tx(&mut tx, lv("10")).await.unwrap();
-    tx.commit().await.unwrap();
+    let repaired = mmr.rebuild_interior_nodes().await.unwrap();
+    assert_eq!(repaired.rewritten_nodes, vec![7, 14]);
+    assert!(repaired.root_rewritten);
+    assert_eq!(repaired.root_hash, appended.root_hash);
+    assert!(mmr.verify_integrity().await.unwrap().is_ok());
 
-    assert_eq!(append.element_index, 1);
-    assert_eq!(mmr.get_elements_count().await.unwrap(), 1);
-    assert_eq!(mmr.get_leaves_count().await.unwrap(), 1);
-    assert!(mmr.get_root_hash().await.unwrap().is_some());
-}
+    store
+        .delete_many(&[StoreKey::new(63, KeyKind::NodeHash, 1)])
+        .await
+        .unwrap();
+    let err = mmr.rebuild_interior_nodes().await.unwrap_err();
+    assert!(matches!(err, MmrError::NoHashFoundForIndex(1)));
+}
+
+/// Bumps the elements count the first time node hashes are read, like an append landing
+/// while a rebuild scans.
+struct AppendDuringScanStore {
+    inner: InMemoryStore,
+    node_reads: AtomicUsize,
+}
+
+impl Store for AppendDuringScanStore {
+    async fn get(&self, key: &StoreKey) -> Result<Option<StoreValue>, StoreError> {
+        self.inner.get(key).await
+    }
+
+    async fn set(&self, key: StoreKey, value: StoreValue) -> Result<(), StoreError> {
+        self.inner.set(key, value).await
+    }
+
+    async fn get_many(&self, keys: &[StoreKey]) -> Result<Vec<Option<StoreValue>>, StoreError> {
+        let values = self.inner.get_many(keys).await?;
+        if let Some(key) = keys.iter().find(|key| key.kind == KeyKind::NodeHash)
+            && self.node_reads.fetch_add(1, Ordering::SeqCst) == 0
+        {
+            let count_key = StoreKey::new(key.mmr_id, KeyKind::ElementsCount, 0);
+            let count = self
+                .inner
+                .get(&count_key)
+                .await?
+                .unwrap()
+                .expect_u64(&count_key)?;
+            self.inner
+                .set(count_key, StoreValue::U64(count + 1))
+                .await?;
+        }
+        Ok(values)
+    }
+
+    async fn compare_and_set_many(
+        &self,
+        counter_key: &StoreKey,
+        expected_counter: u64,
+        entries: Vec<(StoreKey, StoreValue)>,
+    ) -> Result<bool, StoreError> {
+        self.inner
+            .compare_and_set_many(counter_key, expected_counter, entries)
+            .await
+    }
+
+    async fn write_batch(&self, batch: WriteBatch) -> Result<bool, StoreError> {
+        self.inner.write_batch(batch).await
+    }
+}
 
-#[cfg(feature = "postgres-store")]
 #[tokio::test]
-async fn postgres_multiple_appends_in_same_tx_are_composable() {
-    let database_url = match std::env::var("DATABASE_URL") {
-        Ok(url) => url,
-        Err(_) => return,
-    };
+async fn rebuild_interior_nodes_stops_when_the_count_moves_during_the_scan() {
+    let store = Arc::new(AppendDuringScanStore {
+        inner: InMemoryStore::default(),
+        node_reads: AtomicUsize::new(1),
+    });
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(200)).unwrap();
+    let values = (1..=4u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    mmr.batch_append(&values).await.unwrap();
+
+    let damaged = StoreKey::new(200, KeyKind::NodeHash, 3);
+    let bogus = StoreValue::Hash(Hash32::new([0xab; 32]));
+    store
+        .inner
+        .set(damaged.clone(), bogus.clone())
+        .await
+        .unwrap();
+    store.node_reads.store(0, Ordering::SeqCst);
 
-    let store = Arc::new(
-        PostgresStore::connect_with_options(
-            &database_url,
-            PostgresStoreOptions {
-                initialize_schema: true,
-                max_connections: 2,
-            },
-        )
+    let err = mmr.rebuild_interior_nodes().await.unwrap_err();
+    assert!(matches!(err, MmrError::ConcurrentAppendConflict(_)));
+    assert_eq!(store.inner.get(&damaged).await.unwrap(), Some(bogus));
+}
+
+#[tokio::test]
+async fn builder_honors_init_modes() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+
+    let err = Mmr::builder(store.clone())
+        .mmr_id(67)
+        .open_existing()
+        .build()
         .await
-        .unwrap(),
+        .unwrap_err();
+    assert!(matches!(err, MmrError::MmrNotFound(67)));
+
+    let created = Mmr::builder(store.clone())
+        .hasher(hasher.clone())
+        .mmr_id(67)
+        .create_new()
+        .build()
+        .await
+        .unwrap();
+    let appended = created
+        .batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+
+    let err = Mmr::builder(store.clone())
+        .mmr_id(67)
+        .create_new()
+        .build()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, MmrError::NonEmptyMmr));
+
+    let reopened = Mmr::builder(store.clone())
+        .hasher(hasher.clone())
+        .mmr_id(67)
+        .open_existing()
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(
+        reopened.get_root_hash().await.unwrap(),
+        Some(appended.root_hash)
+    );
+
+    let from_peaks = Mmr::builder(store)
+        .hasher(hasher)
+        .mmr_id(68)
+        .from_peaks(appended.peaks_hashes.clone(), appended.elements_count)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(
+        from_peaks.get_root_hash().await.unwrap(),
+        Some(appended.root_hash)
     );
+}
+
+#[tokio::test]
+async fn builder_opens_without_allocating_and_applies_options() {
+    let store = Arc::new(InMemoryStore::default());
+    let err = Mmr::builder(store.clone())
+        .open_existing()
+        .build()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, MmrError::MissingMmrId));
+    let allocator_key = StoreKey::metadata(ID_ALLOCATOR_MMR_ID, KeyKind::IdAllocator);
+    assert_eq!(store.get(&allocator_key).await.unwrap(), None);
+
+    let hook = Arc::new(RecordingHook::default());
+    let mmr = Mmr::builder(store)
+        .mmr_id(201)
+        .node_cache(16)
+        .always_reload(true)
+        .lazy_root(true)
+        .paranoid_reads(true)
+        .proof_binding(true)
+        .hook(hook.clone())
+        .build()
+        .await
+        .unwrap();
+    let appended = mmr.batch_append(&[lv("1"), lv("2")]).await.unwrap();
+    assert_eq!(appended.root_hash_if_computed(), None);
+    assert_eq!(*hook.after.lock().unwrap(), vec![3]);
+
+    mmr.finalize_root().await.unwrap();
+    let proof = mmr.get_proof(1, None).await.unwrap();
+    assert_eq!(proof.mmr_id, Some(201));
+    assert!(mmr.verify_proof(&proof, lv("1"), None).await.unwrap());
+}
+
+#[tokio::test]
+async fn append_with_proof_matches_stored_proofs() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(69)).unwrap();
+
+    for i in 1..=5u64 {
+        let value = lv(&i.to_string());
+        let (result, proof) = mmr.append_with_proof(value).await.unwrap();
+        assert_eq!(
+            proof,
+            mmr.get_proof(result.element_index, None).await.unwrap()
+        );
+        assert!(mmr.verify_proof(&proof, value, None).await.unwrap());
+    }
+
+    let values = (6..=12u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    let (result, proofs) = mmr.batch_append_with_proofs(&values).await.unwrap();
+    assert_eq!(proofs.len(), values.len());
+    assert_eq!(proofs[0].element_index, result.first_element_index);
+    for (proof, value) in proofs.iter().zip(values) {
+        assert_eq!(
+            proof,
+            &mmr.get_proof(proof.element_index, None).await.unwrap()
+        );
+        assert!(mmr.verify_proof(proof, value, None).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn append_results_chain_previous_root_to_new_root() {
     let hasher = Arc::new(KeccakHasher::new());
-    let mut mmr = Mmr::new(store.clone(), hasher.clone(), Some(unique_test_mmr_id())).unwrap();
+    let mmr = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(70)).unwrap();
 
-    let mut tx = store.begin_write_tx().await.unwrap();
-    let first = mmr.append_in_tx(&mut tx, lv("21")).await.unwrap();
-    let second = mmr.append_in_tx(&mut tx, lv("22")).await.unwrap();
-    tx.commit().await.unwrap();
+    let first = mmr.append(lv("1")).await.unwrap();
+    assert_eq!(first.previous_elements_count, 0);
+    assert_eq!(first.previous_root_hash, None);
 
-    assert_eq!(first.elements_count, 1);
-    assert_eq!(second.elements_count, 3);
-    assert_eq!(mmr.get_elements_count().await.unwrap(), 3);
-    assert_eq!(mmr.get_leaves_count().await.unwrap(), 2);
+    let second = mmr.append(lv("2")).await.unwrap();
+    assert_eq!(second.previous_elements_count, first.elements_count);
+    assert_eq!(second.previous_root_hash, Some(first.root_hash));
+
+    let batch = mmr
+        .batch_append(&[lv("3"), lv("4"), lv("5")])
+        .await
+        .unwrap();
+    assert_eq!(batch.previous_elements_count, second.elements_count);
+    assert_eq!(batch.previous_root_hash, Some(second.root_hash));
+}
+
+#[tokio::test]
+async fn leaf_data_is_stored_with_appends_and_returned_with_proofs() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(71)).unwrap();
 
-    let mut tx = store.begin_write_tx().await.unwrap();
-    let first_batch = mmr.batch_append_in_tx(&mut tx, &[lv("31")]).await.unwrap();
-    let second_batch = mmr.batch_append_in_tx(&mut tx, &[lv("32")]).await.unwrap();
-    tx.commit().await.unwrap();
+    let first = mmr
+        .append_with_data(lv("1"), b"header-1".to_vec())
+        .await
+        .unwrap();
+    mmr.append(lv("2")).await.unwrap();
+    let batch = mmr
+        .batch_append_with_data(vec![(lv("3"), b"header-3".to_vec()), (lv("4"), Vec::new())])
+        .await
+        .unwrap();
 
-    assert!(!first_batch.peaks_hashes.is_empty());
     assert_eq!(
-        first_batch.root_hash,
-        root_from_peaks(
-            hasher.as_ref(),
-            &first_batch.peaks_hashes,
-            first_batch.elements_count,
-        )
+        mmr.get_leaf_data(first.element_index).await.unwrap(),
+        Some(b"header-1".to_vec())
     );
-    assert!(!second_batch.peaks_hashes.is_empty());
+    assert_eq!(mmr.get_leaf_data(2).await.unwrap(), None);
     assert_eq!(
-        second_batch.root_hash,
-        root_from_peaks(
-            hasher.as_ref(),
-            &second_batch.peaks_hashes,
-            second_batch.elements_count,
+        mmr.get_leaf_data(batch.first_element_index).await.unwrap(),
+        Some(b"header-3".to_vec())
+    );
+    assert_eq!(
+        mmr.get_leaf_data(batch.last_element_index).await.unwrap(),
+        Some(Vec::new())
+    );
+
+    let (proof, data) = mmr
+        .get_proof_with_data(batch.first_element_index, None)
+        .await
+        .unwrap();
+    assert!(mmr.verify_proof(&proof, lv("3"), None).await.unwrap());
+    assert_eq!(data, Some(b"header-3".to_vec()));
+
+    let copied = mmr
+        .copy_to(
+            Arc::new(InMemoryStore::default()),
+            72,
+            CopyOptions::default(),
+            |_| {},
         )
+        .await
+        .unwrap();
+    assert_eq!(
+        copied.get_leaf_data(first.element_index).await.unwrap(),
+        Some(b"header-1".to_vec())
+    );
+}
+
+#[tokio::test]
+async fn external_keys_map_to_element_indices_for_proof_lookup() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(73)).unwrap();
+
+    let first = mmr.append_with_key(1000, lv("1")).await.unwrap();
+    let batch = mmr
+        .batch_append_with_keys(&[(1001, lv("2")), (1002, lv("3"))])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        mmr.get_element_index_by_key(1000).await.unwrap(),
+        Some(first.element_index)
+    );
+    assert_eq!(
+        mmr.get_element_index_by_key(1002).await.unwrap(),
+        Some(batch.last_element_index)
+    );
+    assert_eq!(mmr.get_element_index_by_key(999).await.unwrap(), None);
+
+    let proof = mmr.get_proof_by_key(1001, None).await.unwrap();
+    assert_eq!(proof.element_index, batch.first_element_index);
+    assert!(mmr.verify_proof(&proof, lv("2"), None).await.unwrap());
+
+    let err = mmr.get_proof_by_key(999, None).await.unwrap_err();
+    assert!(matches!(err, MmrError::ExternalKeyNotFound(999)));
+
+    let err = mmr.append_with_key(1001, lv("4")).await.unwrap_err();
+    assert!(matches!(err, MmrError::DuplicateExternalKey(1001)));
+    let err = mmr
+        .batch_append_with_keys(&[(2000, lv("4")), (2000, lv("5"))])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, MmrError::DuplicateExternalKey(2000)));
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn hash_index_finds_leaves_and_resolves_prefix_collisions() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(74))
+        .unwrap()
+        .with_hash_index(true);
+
+    let mut colliding = Hash32::new([7u8; 32]);
+    colliding[31] = 1;
+    let mut other = colliding;
+    other[31] = 2;
+
+    mmr.append(lv("1")).await.unwrap();
+    let batch = mmr
+        .batch_append(&[colliding, other, colliding])
+        .await
+        .unwrap();
+    mmr.append(other).await.unwrap();
+
+    assert_eq!(mmr.find_element_index(&lv("1")).await.unwrap(), Some(1));
+    assert_eq!(
+        mmr.find_element_index(&colliding).await.unwrap(),
+        Some(batch.first_element_index)
+    );
+    assert_eq!(
+        mmr.find_element_index(&other).await.unwrap(),
+        Some(map_leaf_index_to_element_index(2))
+    );
+    assert!(mmr.contains(&other).await.unwrap());
+
+    let mut missing = colliding;
+    missing[31] = 3;
+    assert!(!mmr.contains(&missing).await.unwrap());
+    assert!(!mmr.contains(&lv("2")).await.unwrap());
+
+    let unindexed = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        Arc::new(KeccakHasher::new()),
+        Some(75),
+    )
+    .unwrap();
+    unindexed.append(lv("1")).await.unwrap();
+    assert!(!unindexed.contains(&lv("1")).await.unwrap());
+}
+
+#[tokio::test]
+async fn hash_index_slots_fit_i64_and_probing_is_bounded() {
+    let store = Arc::new(InMemoryStore::default());
+    let mmr = Mmr::new(store.clone(), Arc::new(KeccakHasher::new()), Some(76))
+        .unwrap()
+        .with_hash_index(true);
+
+    let high = Hash32::new([0xff; 32]);
+    mmr.append(high).await.unwrap();
+    assert_eq!(
+        store
+            .get(&StoreKey::new(76, KeyKind::HashIndex, i64::MAX as u64))
+            .await
+            .unwrap(),
+        Some(StoreValue::U64(1))
+    );
+    assert_eq!(mmr.find_element_index(&high).await.unwrap(), Some(1));
+
+    let crowded = Hash32::new([0x11; 32]);
+    let first_slot = u64::from_be_bytes([0x11; 8]);
+    let occupied: Vec<_> = (0..u64::from(MAX_HASH_INDEX_PROBES))
+        .map(|offset| {
+            (
+                StoreKey::new(76, KeyKind::HashIndex, first_slot + offset),
+                StoreValue::U64(1),
+            )
+        })
+        .collect();
+    store.set_many(occupied).await.unwrap();
+    assert!(matches!(
+        mmr.find_element_index(&crowded).await,
+        Err(MmrError::HashIndexProbeLimit(MAX_HASH_INDEX_PROBES))
+    ));
+    assert!(matches!(
+        mmr.append(crowded).await,
+        Err(MmrError::HashIndexProbeLimit(MAX_HASH_INDEX_PROBES))
+    ));
+}
+
+#[tokio::test]
+async fn checkpoints_are_written_with_appends_and_guard_resumes() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(81)).unwrap();
+    assert_eq!(mmr.resume_from_checkpoint().await.unwrap(), None);
+
+    mmr.batch_append_with_checkpoint(&[lv("1"), lv("2")], 10)
+        .await
+        .unwrap();
+    let last = mmr
+        .batch_append_with_checkpoint(&[lv("3")], 11)
+        .await
+        .unwrap();
+
+    let checkpoint = mmr.resume_from_checkpoint().await.unwrap().unwrap();
+    assert_eq!(checkpoint.cursor, 11);
+    assert_eq!(checkpoint.leaves_count, 3);
+    assert_eq!(checkpoint.elements_count, last.elements_count);
+    assert_eq!(checkpoint.root_hash, Some(last.root_hash));
+
+    mmr.append(lv("4")).await.unwrap();
+    assert_eq!(mmr.get_checkpoint().await.unwrap(), Some(checkpoint));
+    let err = mmr.resume_from_checkpoint().await.unwrap_err();
+    assert!(matches!(
+        err,
+        MmrError::CheckpointMismatch {
+            checkpoint_elements_count: 4,
+            elements_count: 7,
+        }
+    ));
+}
+
+#[tokio::test]
+async fn merge_from_appends_other_leaves_in_order() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+    let shard_a = Mmr::new(store.clone(), hasher.clone(), Some(82)).unwrap();
+    let shard_b = Mmr::new(store.clone(), hasher.clone(), Some(83)).unwrap();
+    let combined = Mmr::new(store.clone(), hasher.clone(), Some(84)).unwrap();
+    let reference = Mmr::new(store, hasher, Some(85)).unwrap();
+
+    let values_a = (1..=5u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    let values_b = (6..=12u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    shard_a.batch_append(&values_a).await.unwrap();
+    shard_b.batch_append(&values_b).await.unwrap();
+    reference.batch_append(&values_a).await.unwrap();
+    let expected = reference.batch_append(&values_b).await.unwrap();
+
+    combined.merge_from(&shard_a, 2).await.unwrap();
+    let merged = combined.merge_from(&shard_b, 3).await.unwrap().unwrap();
+    assert_eq!(merged.root_hash, expected.root_hash);
+    assert_eq!(merged.leaves_count, 12);
+
+    let empty = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        Arc::new(KeccakHasher::new()),
+        Some(86),
+    )
+    .unwrap();
+    assert_eq!(combined.merge_from(&empty, 4).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn should_build_offline_and_bulk_load_matching_incremental_appends() {
+    let hasher = Arc::new(KeccakHasher::new());
+    for leaves_count in [0usize, 1, 2, 3, 7, 8, 11, 33] {
+        let leaves: Vec<Hash32> = (1..=leaves_count).map(|i| lv(&i.to_string())).collect();
+
+        let incremental =
+            Mmr::new(Arc::new(InMemoryStore::default()), hasher.clone(), Some(87)).unwrap();
+        if !leaves.is_empty() {
+            incremental.batch_append(&leaves).await.unwrap();
+        }
+
+        for threads in [1, 4] {
+            let build = mmr::build_offline_with_threads(hasher.as_ref(), &leaves, threads).unwrap();
+            assert_eq!(
+                build.elements_count,
+                incremental.get_elements_count().await.unwrap()
+            );
+            if leaves_count == 0 {
+                continue;
+            }
+            assert_eq!(
+                Some(build.root_hash),
+                incremental.get_root_hash().await.unwrap()
+            );
+
+            let loaded = Mmr::new(Arc::new(InMemoryStore::default()), hasher.clone(), Some(88))
+                .unwrap()
+                .with_hash_index(true);
+            let result = loaded.bulk_load(&build).await.unwrap();
+            assert_eq!(result.leaves_count, leaves_count as u64);
+            assert_eq!(
+                Some(result.root_hash),
+                loaded.get_root_hash().await.unwrap()
+            );
+
+            let element_index = map_leaf_index_to_element_index(leaves_count as u64 - 1);
+            let proof = loaded.get_proof(element_index, None).await.unwrap();
+            assert!(
+                loaded
+                    .verify_proof(&proof, leaves[leaves_count - 1], None)
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(
+                loaded.find_element_index(&leaves[0]).await.unwrap(),
+                Some(1)
+            );
+
+            assert!(matches!(
+                loaded.bulk_load(&build).await,
+                Err(MmrError::NonEmptyMmr)
+            ));
+        }
+    }
+}
+
+#[tokio::test]
+async fn bulk_load_reruns_skip_nodes_an_interrupted_load_already_wrote() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let leaves: Vec<Hash32> = (1..=11).map(|i| lv(&i.to_string())).collect();
+    let build = mmr::build_offline(hasher.as_ref(), &leaves).unwrap();
+
+    let store = Arc::new(SpyStore::new());
+    let nodes = (1..)
+        .zip(&build.nodes)
+        .map(|(index, hash)| {
+            (
+                StoreKey::new(196, KeyKind::NodeHash, index),
+                StoreValue::Hash(*hash),
+            )
+        })
+        .collect();
+    store.set_many(nodes).await.unwrap();
+    let set_many_calls = store.metrics().set_many_calls;
+
+    let mmr = Mmr::new(store.clone(), hasher, Some(196)).unwrap();
+    let result = mmr.bulk_load(&build).await.unwrap();
+    assert_eq!(result.root_hash, build.root_hash);
+    assert_eq!(store.metrics().set_many_calls, set_many_calls + 1);
+    assert_eq!(mmr.get_root_hash().await.unwrap(), Some(build.root_hash));
+}
+
+#[tokio::test]
+async fn should_return_original_result_for_idempotent_append_retries() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(89)).unwrap();
+
+    let first = mmr.append_idempotent(7, lv("1")).await.unwrap();
+    let second = mmr.append_idempotent(8, lv("2")).await.unwrap();
+    mmr.append(lv("3")).await.unwrap();
+
+    assert_eq!(mmr.append_idempotent(7, lv("1")).await.unwrap(), first);
+    assert_eq!(mmr.append_idempotent(8, lv("2")).await.unwrap(), second);
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 3);
+
+    assert!(matches!(
+        mmr.append_idempotent(7, lv("9")).await,
+        Err(MmrError::IdempotentValueMismatch(7))
+    ));
+}
+
+#[tokio::test]
+async fn idempotent_retries_on_a_lazy_root_mmr_report_no_roots() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(94))
+        .unwrap()
+        .with_lazy_root(true);
+
+    mmr.append(lv("1")).await.unwrap();
+    let first = mmr.append_idempotent(7, lv("2")).await.unwrap();
+    assert_eq!(first.root_hash_if_computed(), None);
+    assert_eq!(mmr.append_idempotent(7, lv("2")).await.unwrap(), first);
+}
+
+#[tokio::test]
+async fn should_invoke_append_hooks_around_commits() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let hook = Arc::new(RecordingHook {
+        reject_over: Some(2),
+        ..RecordingHook::default()
+    });
+    let mmr = Mmr::new(store, hasher, Some(90))
+        .unwrap()
+        .with_hook(hook.clone());
+
+    mmr.append(lv("1")).await.unwrap();
+    mmr.batch_append(&[lv("2"), lv("3")]).await.unwrap();
+    assert!(matches!(
+        mmr.batch_append(&[lv("4"), lv("5"), lv("6")]).await,
+        Err(MmrError::HookRejected(_))
+    ));
+
+    assert_eq!(hook.before.load(Ordering::SeqCst), 2);
+    assert_eq!(*hook.after.lock().unwrap(), vec![1, 4]);
+    assert_eq!(mmr.get_elements_count().await.unwrap(), 4);
+}
+
+#[tokio::test]
+async fn should_notify_subscribers_after_each_append() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(91)).unwrap();
+
+    let mut updates = mmr.subscribe();
+    let dropped = mmr.subscribe();
+    drop(dropped);
+
+    let first = mmr.append(lv("1")).await.unwrap();
+    let second = mmr.batch_append(&[lv("2"), lv("3")]).await.unwrap();
+
+    assert_eq!(
+        updates.next().await,
+        Some((first.elements_count, first.root_hash))
+    );
+    assert_eq!(
+        updates.next().await,
+        Some((second.elements_count, second.root_hash))
     );
+}
+
+#[tokio::test]
+async fn slow_subscribers_drop_updates_instead_of_buffering_them_all() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(197)).unwrap();
+    let mut updates = mmr.subscribe();
+
+    let first = mmr.append(lv("0")).await.unwrap();
+    for i in 1..200 {
+        mmr.append(lv(&i.to_string())).await.unwrap();
+    }
+
+    let mut received = Vec::new();
+    while let Ok(Some(update)) = updates.try_next() {
+        received.push(update);
+    }
+    assert!(received.len() < 200);
+    assert_eq!(received[0], (first.elements_count, first.root_hash));
+
+    let last = mmr.append(lv("200")).await.unwrap();
     assert_eq!(
-        second_batch.peaks_hashes,
-        mmr.get_peaks(Some(second_batch.elements_count))
+        updates.next().await,
+        Some((last.elements_count, last.root_hash))
+    );
+}
+
+#[tokio::test]
+async fn should_defer_root_computation_in_lazy_root_mode() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let leaves: Vec<Hash32> = (1..=9).map(|i| lv(&i.to_string())).collect();
+
+    let eager = Mmr::new(Arc::new(InMemoryStore::default()), hasher.clone(), Some(94)).unwrap();
+    eager.batch_append(&leaves).await.unwrap();
+    let expected_root = eager.get_root_hash().await.unwrap().unwrap();
+
+    let lazy = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(94))
+        .unwrap()
+        .with_lazy_root(true);
+    let mut updates = lazy.subscribe();
+    for (cursor, chunk) in leaves.chunks(4).enumerate() {
+        let result = lazy
+            .batch_append_with_checkpoint(chunk, cursor as u64)
             .await
-            .unwrap()
+            .unwrap();
+        assert!(result.root_deferred);
+        assert_eq!(result.root_hash_if_computed(), None);
+        assert_eq!(result.previous_root_hash, None);
+    }
+    assert_eq!(lazy.get_root_hash().await.unwrap(), None);
+    let checkpoint = lazy.resume_from_checkpoint().await.unwrap().unwrap();
+    assert_eq!(checkpoint.cursor, 2);
+    assert_eq!(checkpoint.root_hash, None);
+    assert_eq!(
+        lazy.get_elements_count().await.unwrap(),
+        eager.get_elements_count().await.unwrap()
+    );
+
+    let report = lazy.verify_integrity().await.unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.root_matches, None);
+
+    assert_eq!(lazy.finalize_root().await.unwrap(), expected_root);
+    assert_eq!(lazy.get_root_hash().await.unwrap(), Some(expected_root));
+    assert_eq!(
+        updates.next().await,
+        Some((eager.get_elements_count().await.unwrap(), expected_root))
     );
+    assert_eq!(
+        lazy.verify_integrity().await.unwrap().root_matches,
+        Some(true)
+    );
+
+    let proof = lazy.get_proof(1, None).await.unwrap();
+    assert!(lazy.verify_proof(&proof, leaves[0], None).await.unwrap());
+
+    lazy.append(lv("10")).await.unwrap();
+    assert_eq!(lazy.get_root_hash().await.unwrap(), None);
+    lazy.finalize_root().await.unwrap();
+    let draft = lazy.draft().await.unwrap();
+    draft.append(lv("11")).await.unwrap();
+    assert_eq!(draft.get_root_hash().await.unwrap(), None);
+    assert!(lazy.get_root_hash().await.unwrap().is_some());
+    draft.commit().await.unwrap();
+    assert_eq!(lazy.get_root_hash().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn should_track_root_with_peaks_only_accumulator() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let leaves: Vec<Hash32> = (1..=11).map(|i| lv(&i.to_string())).collect();
+
+    let mmr = Mmr::new(Arc::new(InMemoryStore::default()), hasher.clone(), Some(95)).unwrap();
+    let store = Arc::new(InMemoryStore::default());
+    let accumulator = PeaksAccumulator::new(store.clone(), hasher, 95);
+    assert_eq!(accumulator.get_root_hash().await.unwrap(), None);
+
+    for leaf in &leaves[..4] {
+        let expected = mmr.append(*leaf).await.unwrap();
+        assert_eq!(accumulator.append(*leaf).await.unwrap(), expected);
+    }
+    let expected = mmr.batch_append(&leaves[4..]).await.unwrap();
+    assert_eq!(
+        accumulator.batch_append(&leaves[4..]).await.unwrap(),
+        expected
+    );
+
+    assert_eq!(
+        accumulator.get_root_hash().await.unwrap(),
+        mmr.get_root_hash().await.unwrap()
+    );
+    assert_eq!(
+        accumulator.get_peaks().await.unwrap(),
+        mmr.get_peaks(None).await.unwrap()
+    );
+    assert_eq!(accumulator.get_leaves_count().await.unwrap(), 11);
+    assert_eq!(accumulator.get_elements_count().await.unwrap(), 19);
+    assert!(
+        store
+            .get(&StoreKey::new(95, KeyKind::NodeHash, 1))
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn should_return_proof_for_the_most_recent_leaf() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(96)).unwrap();
+    assert_eq!(mmr.get_tip_proof().await.unwrap(), None);
+
+    for leaf in LEAVES {
+        let result = mmr.append(lv(leaf)).await.unwrap();
+        let proof = mmr.get_tip_proof().await.unwrap().unwrap();
+        assert_eq!(proof.element_index, result.element_index);
+        assert_eq!(proof.elements_count, result.elements_count);
+        assert!(mmr.verify_proof(&proof, lv(leaf), None).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn should_verify_path_proofs_with_explicit_directions() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher.clone(), Some(97)).unwrap();
+    let leaves: Vec<Hash32> = (1..=11).map(|i| lv(&i.to_string())).collect();
+    mmr.batch_append(&leaves).await.unwrap();
+
+    for (leaf_index, leaf) in (0u64..).zip(&leaves) {
+        let element_index = map_leaf_index_to_element_index(leaf_index);
+        let path_proof = mmr.get_path_proof(element_index, None).await.unwrap();
+        assert!(verify_path_proof(hasher.as_ref(), &path_proof, *leaf).unwrap());
+        assert!(!verify_path_proof(hasher.as_ref(), &path_proof, lv("99")).unwrap());
+    }
+
+    let path_proof = mmr.get_path_proof(4, None).await.unwrap();
+    assert_eq!(path_proof.directions, 0b10);
+    assert_eq!(path_proof.peak_index, 0);
+
+    let mut flipped = path_proof.clone();
+    flipped.directions ^= 1;
+    assert!(!verify_path_proof(hasher.as_ref(), &flipped, leaves[2]).unwrap());
+
+    let mut malformed = mmr.get_proof(4, None).await.unwrap();
+    malformed.siblings_hashes.pop();
+    assert!(matches!(
+        malformed.to_path_proof(),
+        Err(MmrError::InvalidSiblingsCount)
+    ));
+}
+
+#[tokio::test]
+async fn should_report_structured_proof_verification_failures() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(98)).unwrap();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+
+    let proof = mmr.get_proof(1, None).await.unwrap();
+    assert_eq!(
+        mmr.verify_proof_detailed(&proof, lv("1"), None)
+            .await
+            .unwrap(),
+        ProofVerification::Valid
+    );
+    assert_eq!(
+        mmr.verify_proof_detailed(&proof, lv("9"), None)
+            .await
+            .unwrap(),
+        ProofVerification::PeakMismatch { peak_index: 0 }
+    );
+
+    let mut short = proof.clone();
+    short.siblings_hashes.pop();
+    assert_eq!(
+        mmr.verify_proof_detailed(&short, lv("1"), None)
+            .await
+            .unwrap(),
+        ProofVerification::WrongSiblingCount {
+            expected: 1,
+            actual: 0
+        }
+    );
+
+    let mut invalid_size = proof.clone();
+    invalid_size.elements_count = 5;
+    assert_eq!(
+        mmr.verify_proof_detailed(&invalid_size, lv("1"), Some(5))
+            .await
+            .unwrap(),
+        ProofVerification::InvalidElementCount
+    );
+    let mut out_of_range = proof.clone();
+    out_of_range.element_index = 5;
+    assert_eq!(
+        mmr.verify_proof_detailed(&out_of_range, lv("1"), None)
+            .await
+            .unwrap(),
+        ProofVerification::InvalidElementIndex
+    );
+
+    mmr.append(lv("4")).await.unwrap();
+    assert_eq!(
+        mmr.verify_proof_detailed(&proof, lv("1"), None)
+            .await
+            .unwrap(),
+        ProofVerification::StaleElementsCount {
+            proof_elements_count: 4,
+            elements_count: 7
+        }
+    );
+
+    let proof = mmr.get_proof(1, None).await.unwrap();
+    store
+        .delete_many(&[StoreKey::new(98, KeyKind::NodeHash, 7)])
+        .await
+        .unwrap();
+    let verification = mmr
+        .verify_proof_detailed(&proof, lv("1"), None)
+        .await
+        .unwrap();
+    assert_eq!(verification, ProofVerification::MissingStoreNode(7));
+    assert!(!verification.is_valid());
+}
+
+#[tokio::test]
+async fn should_return_peaks_with_heights() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(99)).unwrap();
+    assert!(mmr.get_peaks_with_heights(None).await.unwrap().is_empty());
+
+    let leaves: Vec<Hash32> = (1..=11).map(|i| lv(&i.to_string())).collect();
+    mmr.batch_append(&leaves).await.unwrap();
+
+    let peaks = mmr.get_peaks_with_heights(None).await.unwrap();
+    let hashes = mmr.get_peaks(None).await.unwrap();
+    assert_eq!(
+        peaks,
+        vec![(15, 3, hashes[0]), (18, 1, hashes[1]), (19, 0, hashes[2])]
+    );
+
+    let historical = mmr.get_peaks_with_heights(Some(10)).await.unwrap();
+    assert_eq!(
+        historical
+            .iter()
+            .map(|(idx, height, _)| (*idx, *height))
+            .collect::<Vec<_>>(),
+        vec![(7, 2), (10, 1)]
+    );
+}
+
+#[tokio::test]
+async fn should_report_mmr_stats() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(100)).unwrap();
+    assert_eq!(mmr.stats().await.unwrap().height, 0);
+
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    mmr.append_with_data(lv("4"), vec![7; 10]).await.unwrap();
+    mmr.append(lv("5")).await.unwrap();
+
+    let stats = mmr.stats().await.unwrap();
+    assert_eq!(stats.leaves_count, 5);
+    assert_eq!(stats.elements_count, 8);
+    assert_eq!(stats.peaks_count, 2);
+    assert_eq!(stats.height, 2);
+    assert_eq!(stats.persisted_nodes, 8);
+    assert_eq!(stats.approximate_bytes, 8 * 32 + 32 + 8 + 8);
+
+    let draft = mmr.draft().await.unwrap();
+    assert_eq!(draft.stats().await.unwrap(), stats);
+}
+
+#[tokio::test]
+async fn should_read_stored_nodes_in_a_range() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(101)).unwrap();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let synced_elements_count = mmr.get_elements_count().await.unwrap();
+    let result = mmr.batch_append(&[lv("4"), lv("5")]).await.unwrap();
+
+    let nodes = mmr
+        .get_nodes_in_range(synced_elements_count + 1, result.elements_count)
+        .await
+        .unwrap();
+    assert_eq!(
+        nodes.iter().map(|(idx, _)| *idx).collect::<Vec<_>>(),
+        vec![5, 6, 7, 8]
+    );
+    assert_eq!(nodes[0].1, lv("4"));
+    assert_eq!(nodes[3].1, lv("5"));
+
+    assert_eq!(mmr.get_nodes_in_range(8, 20).await.unwrap().len(), 1);
+    assert_eq!(mmr.get_nodes_in_range(8, u64::MAX).await.unwrap().len(), 1);
+    assert!(
+        mmr.get_nodes_in_range(9, u64::MAX)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert!(matches!(
+        mmr.get_nodes_in_range(0, 3).await,
+        Err(MmrError::InvalidElementIndex)
+    ));
+    assert!(matches!(
+        mmr.get_nodes_in_range(4, 3).await,
+        Err(MmrError::InvalidElementIndex)
+    ));
+}
+
+#[tokio::test]
+async fn should_pin_reads_to_one_size_while_appends_continue() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(104)).unwrap();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let expected_root = mmr.get_root_hash().await.unwrap().unwrap();
+
+    let pinned = mmr.at_current_size().await.unwrap();
+    mmr.batch_append(&[lv("4"), lv("5")]).await.unwrap();
+
+    assert_eq!(pinned.elements_count(), 4);
+    assert_eq!(pinned.leaves_count(), 3);
+    assert_eq!(pinned.get_root_hash().await.unwrap(), expected_root);
+    assert_eq!(pinned.get_peaks().await.unwrap().len(), 2);
+
+    let proof = pinned.get_proof(1).await.unwrap();
+    assert_eq!(proof.elements_count, 4);
+    assert!(pinned.verify_proof(&proof, lv("1")).await.unwrap());
+    assert!(
+        verify_proof_against_root(&KeccakHasher::new(), &proof, lv("1"), &expected_root).unwrap()
+    );
+    assert_eq!(pinned.get_proofs(&[1, 2]).await.unwrap().len(), 2);
+    assert!(!mmr.verify_proof(&proof, lv("1"), None).await.unwrap());
+}
+
+#[tokio::test]
+async fn should_compute_root_from_leaves_without_a_store() {
+    let hasher = Arc::new(KeccakHasher::new());
+    for leaves_count in [1u64, 2, 3, 4, 7, 12] {
+        let leaves: Vec<Hash32> = (1..=leaves_count).map(|i| lv(&i.to_string())).collect();
+        let mmr = Mmr::new(
+            Arc::new(InMemoryStore::default()),
+            hasher.clone(),
+            Some(106),
+        )
+        .unwrap();
+        let result = mmr.batch_append(&leaves).await.unwrap();
+
+        let (elements_count, peaks) = compute_peaks_from_leaves(hasher.as_ref(), &leaves).unwrap();
+        assert_eq!(elements_count, result.elements_count);
+        assert_eq!(peaks, result.peaks_hashes);
+        assert_eq!(
+            compute_root_from_leaves(hasher.as_ref(), &leaves).unwrap(),
+            result.root_hash
+        );
+    }
+}
+
+#[test]
+fn should_append_to_peaks_like_a_stored_mmr() {
+    let hasher = KeccakHasher::new();
+    let leaves: Vec<Hash32> = (1..=13u64).map(|i| lv(&i.to_string())).collect();
+    let mut elements_count = 0;
+    let mut peaks = Vec::new();
+    for (i, leaf) in leaves.iter().enumerate() {
+        (elements_count, peaks) = append_to_peaks(&hasher, elements_count, &peaks, *leaf).unwrap();
+        assert_eq!(
+            (elements_count, peaks.clone()),
+            compute_peaks_from_leaves(&hasher, &leaves[..=i]).unwrap()
+        );
+    }
+
+    assert!(matches!(
+        append_to_peaks(&hasher, elements_count, &peaks[1..], leaves[0]),
+        Err(MmrError::InvalidPeaksCountForElements)
+    ));
+    assert!(matches!(
+        append_to_peaks(&hasher, 2, &[], leaves[0]),
+        Err(MmrError::InvalidElementCount)
+    ));
+}
+
+fn assert_no_std_verifies<H>(hasher: &H, proof: &Proof, element_value: Hash32, root: Hash32)
+where
+    H: core_verify::PairHasher + ?Sized,
+    H::Error: std::fmt::Debug,
+{
+    assert!(
+        core_verify::verify_proof(
+            hasher,
+            proof.element_index,
+            &element_value,
+            &proof.siblings_hashes,
+            &proof.peaks_hashes,
+            proof.elements_count,
+        )
+        .unwrap()
+    );
+    assert!(
+        core_verify::verify_proof_against_root(
+            hasher,
+            proof.element_index,
+            &element_value,
+            &proof.siblings_hashes,
+            &proof.peaks_hashes,
+            proof.elements_count,
+            &root,
+        )
+        .unwrap()
+    );
+    assert!(
+        !core_verify::verify_proof(
+            hasher,
+            proof.element_index,
+            &lv("99").0,
+            &proof.siblings_hashes,
+            &proof.peaks_hashes,
+            proof.elements_count,
+        )
+        .unwrap()
+    );
+}
+
+struct FailingHasher;
+
+impl Hasher for FailingHasher {
+    fn hash_pair(&self, _left: &Hash32, _right: &Hash32) -> Result<Hash32, HasherError> {
+        Err(HasherError::Unsupported("hash_pair"))
+    }
+
+    fn hash_count_and_bag(&self, _count: u64, _bag: &Hash32) -> Result<Hash32, HasherError> {
+        Err(HasherError::Unsupported("hash_count_and_bag"))
+    }
+}
+
+#[tokio::test]
+async fn verify_keeps_the_hasher_error() {
+    let mmr = Mmr::in_memory_keccak();
+    mmr.batch_append(&[lv("1"), lv("2")]).await.unwrap();
+    let proof = mmr.get_proof(1, None).await.unwrap();
+
+    assert!(matches!(
+        verify_proof(&FailingHasher, &proof, proof.element_hash),
+        Err(MmrError::Hasher(HasherError::Unsupported("hash_pair")))
+    ));
+    assert!(matches!(
+        core_verify::root_from_peaks(&FailingHasher, &proof.peaks_hashes, 3),
+        Err(MmrError::Hasher(HasherError::Unsupported(
+            "hash_count_and_bag"
+        )))
+    ));
+}
+
+#[tokio::test]
+async fn should_verify_proofs_with_the_no_std_core() {
+    let mmr = Mmr::in_memory_keccak();
+    let leaves: Vec<Hash32> = (1..=11u64).map(|i| lv(&i.to_string())).collect();
+    mmr.batch_append(&leaves).await.unwrap();
+    let root = mmr.get_root_hash().await.unwrap().unwrap();
+
+    for element_index in [1, 2, 4, 8, 19] {
+        let proof = mmr.get_proof(element_index, None).await.unwrap();
+        let element_value = mmr
+            .get_proof(element_index, None)
+            .await
+            .unwrap()
+            .element_hash;
+        assert_no_std_verifies(&Keccak256, &proof, element_value, root);
+        assert_no_std_verifies(&KeccakHasher::new(), &proof, element_value, root);
+    }
+
+    let peaks = mmr.get_peaks(None).await.unwrap();
+    let elements_count = mmr.get_elements_count().await.unwrap();
+    assert_eq!(
+        core_verify::root_from_peaks(&Keccak256, &peaks, elements_count).unwrap(),
+        root.0
+    );
+    assert_eq!(
+        core_verify::root_from_peaks(&Keccak256, &peaks[1..], elements_count),
+        Err(VerifyError::InvalidPeaksCount)
+    );
+    assert!(matches!(
+        MmrError::from(VerifyError::InvalidElementIndex),
+        MmrError::InvalidElementIndex
+    ));
+}
+
+#[cfg(feature = "zkvm")]
+#[tokio::test]
+async fn should_append_and_verify_with_zkvm_fixed_peaks() {
+    use mmr::zkvm::{FixedPeaks, Keccak256, VerifyError, verify_proof_against_root};
+
+    let mmr = Mmr::in_memory_keccak();
+    let mut peaks = FixedPeaks::new();
+    for i in 1..=21u64 {
+        let leaf = lv(&i.to_string());
+        let appended = mmr.append(leaf).await.unwrap();
+        assert_eq!(
+            peaks.append(&Keccak256, &leaf.0).unwrap(),
+            appended.element_index
+        );
+        assert_eq!(peaks.elements_count(), appended.elements_count);
+        assert_eq!(peaks.root(&Keccak256).unwrap(), appended.root_hash.0);
+    }
+
+    let resumed = FixedPeaks::from_peaks(peaks.elements_count(), peaks.peaks()).unwrap();
+    assert_eq!(resumed, peaks);
+    assert_eq!(
+        FixedPeaks::from_peaks(peaks.elements_count(), &peaks.peaks()[1..]),
+        Err(VerifyError::InvalidPeaksCount)
+    );
+
+    let proof = mmr.get_proof(8, None).await.unwrap();
+    assert!(
+        verify_proof_against_root(
+            &Keccak256,
+            proof.element_index,
+            &proof.element_hash,
+            &proof.siblings_hashes,
+            &proof.peaks_hashes,
+            proof.elements_count,
+            &peaks.root(&Keccak256).unwrap(),
+        )
+        .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn should_prove_from_leaves_without_a_store() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let leaves: Vec<Hash32> = (1..=11u64).map(|i| lv(&i.to_string())).collect();
+    let mmr = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        hasher.clone(),
+        Some(109),
+    )
+    .unwrap();
+    mmr.batch_append(&leaves).await.unwrap();
+
+    for leaf_index in 0..leaves.len() as u64 {
+        let proof = prove_from_leaves(hasher.as_ref(), &leaves, leaf_index).unwrap();
+        let expected = mmr.get_proof(proof.element_index, None).await.unwrap();
+        assert_eq!(proof, expected);
+        assert!(
+            mmr.verify_proof(&proof, leaves[leaf_index as usize], None)
+                .await
+                .unwrap()
+        );
+    }
+
+    assert!(matches!(
+        prove_from_leaves(hasher.as_ref(), &leaves, leaves.len() as u64),
+        Err(MmrError::InvalidLeafIndex)
+    ));
+}
+
+#[tokio::test]
+async fn should_build_ready_to_use_in_memory_mmr() {
+    let first = Mmr::in_memory_keccak();
+    let second = Mmr::in_memory(Arc::new(KeccakHasher::new()));
+    assert_ne!(first.mmr_id, second.mmr_id);
+
+    first.append(lv("1")).await.unwrap();
+    let result = second.append(lv("1")).await.unwrap();
+    assert_eq!(first.get_root_hash().await.unwrap(), Some(result.root_hash));
+}
+
+#[tokio::test]
+async fn should_initialize_with_genesis_leaf_once() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let genesis = lv("42");
+
+    let mmr = Mmr::new_with_genesis(store.clone(), hasher.clone(), Some(110), genesis)
+        .await
+        .unwrap();
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 1);
+    assert_eq!(mmr.get_proof(1, None).await.unwrap().element_hash, genesis);
+    mmr.append(lv("1")).await.unwrap();
+
+    let reopened = Mmr::new_with_genesis(store.clone(), hasher.clone(), Some(110), genesis)
+        .await
+        .unwrap();
+    assert_eq!(reopened.get_leaves_count().await.unwrap(), 2);
+
+    assert!(matches!(
+        Mmr::new_with_genesis(store, hasher, Some(110), lv("43")).await,
+        Err(MmrError::GenesisMismatch)
+    ));
+}
+
+#[tokio::test]
+async fn should_reject_appends_beyond_size_limits() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+
+    let mmr = Mmr::new(store.clone(), hasher.clone(), Some(111))
+        .unwrap()
+        .with_max_leaves(3);
+    mmr.batch_append(&[lv("1"), lv("2")]).await.unwrap();
+    assert!(matches!(
+        mmr.batch_append(&[lv("3"), lv("4")]).await,
+        Err(MmrError::MaxLeavesExceeded {
+            limit: 3,
+            requested: 4
+        })
+    ));
+    mmr.append(lv("3")).await.unwrap();
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 3);
+
+    let mmr = Mmr::new(store, hasher, Some(112))
+        .unwrap()
+        .with_max_elements(3);
+    mmr.batch_append(&[lv("1"), lv("2")]).await.unwrap();
+    assert!(matches!(
+        mmr.append(lv("3")).await,
+        Err(MmrError::MaxElementsExceeded {
+            limit: 3,
+            requested: 4
+        })
+    ));
+    assert_eq!(mmr.get_elements_count().await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn size_limits_apply_to_every_append_path() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let leaves_limit = |result: Result<_, MmrError>| {
+        matches!(
+            result,
+            Err(MmrError::MaxLeavesExceeded {
+                limit: 2,
+                requested: 3
+            })
+        )
+    };
+
+    let built = Mmr::builder(store.clone())
+        .hasher(hasher.clone())
+        .mmr_id(191)
+        .max_leaves(2)
+        .build()
+        .await
+        .unwrap();
+    built.batch_append(&[lv("1"), lv("2")]).await.unwrap();
+    assert!(leaves_limit(built.append(lv("3")).await.map(|_| ())));
+
+    let draft = built.draft().await.unwrap();
+    assert!(leaves_limit(draft.append(lv("3")).await.map(|_| ())));
+    draft.discard();
+
+    let branch = built.fork(Some(192)).await.unwrap();
+    assert!(leaves_limit(branch.append(lv("3")).await.map(|_| ())));
+
+    let from_peaks = Mmr::builder(store.clone())
+        .hasher(hasher.clone())
+        .mmr_id(193)
+        .max_leaves(2)
+        .from_peaks(vec![lv("7")], 4)
+        .build()
+        .await;
+    assert!(leaves_limit(from_peaks.map(|_| ())));
+
+    let accumulator = PeaksAccumulator::new(store, hasher, 194).with_max_leaves(2);
+    assert!(leaves_limit(
+        accumulator
+            .batch_append(&[lv("1"), lv("2"), lv("3")])
+            .await
+            .map(|_| ())
+    ));
+    assert_eq!(accumulator.get_leaves_count().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn should_pick_up_foreign_appends_after_refresh_or_with_always_reload() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let writer = Mmr::new(store.clone(), hasher.clone(), Some(113)).unwrap();
+    let refreshed = Mmr::new(store.clone(), hasher.clone(), Some(113)).unwrap();
+    let reloading = Mmr::new(store.clone(), hasher.clone(), Some(113))
+        .unwrap()
+        .with_always_reload(true);
+
+    writer.append(lv("1")).await.unwrap();
+    refreshed.append(lv("2")).await.unwrap();
+    reloading.append(lv("3")).await.unwrap();
+
+    writer.refresh().await;
+    writer.append(lv("4")).await.unwrap();
+    refreshed.refresh().await;
+    let result = refreshed.append(lv("5")).await.unwrap();
+    assert_eq!(result.leaves_count, 5);
+    let result = reloading.append(lv("6")).await.unwrap();
+    assert_eq!(result.leaves_count, 6);
+
+    let expected = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(113)).unwrap();
+    let expected = expected
+        .batch_append(&[lv("1"), lv("2"), lv("3"), lv("4"), lv("5"), lv("6")])
+        .await
+        .unwrap();
+    assert_eq!(result.root_hash, expected.root_hash);
+}
+
+#[tokio::test]
+async fn should_convert_proofs_to_and_from_zero_based_positions() {
+    let mmr = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        Arc::new(KeccakHasher::new()),
+        Some(114),
+    )
+    .unwrap();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+
+    assert_eq!(element_index_to_position(4).unwrap(), 3);
+    assert_eq!(position_to_element_index(3).unwrap(), 4);
+    assert!(matches!(
+        element_index_to_position(0),
+        Err(MmrError::InvalidElementIndex)
+    ));
+    assert!(matches!(
+        position_to_element_index(u64::MAX),
+        Err(MmrError::Overflow)
+    ));
+
+    let proof = mmr.get_proof(4, None).await.unwrap();
+    let zero_based = mmr.get_zero_based_proof(3, None).await.unwrap();
+    assert_eq!(zero_based.position, 3);
+    assert_eq!(zero_based, proof.to_zero_based().unwrap());
+    assert_eq!(zero_based.to_proof().unwrap(), proof);
+    assert!(mmr.verify_proof(&proof, lv("3"), None).await.unwrap());
+}
+
+#[tokio::test]
+async fn should_recompute_root_from_announced_peaks() {
+    let hasher = KeccakHasher::new();
+    let mmr = Mmr::in_memory_keccak();
+    let result = mmr
+        .batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        mmr::root_from_peaks(&hasher, &result.peaks_hashes, result.elements_count).unwrap(),
+        result.root_hash
+    );
+    assert_eq!(
+        bag_peaks(&hasher, &result.peaks_hashes).unwrap(),
+        hasher
+            .hash_pair(&result.peaks_hashes[0], &result.peaks_hashes[1])
+            .unwrap()
+    );
+    assert!(matches!(
+        mmr::root_from_peaks(&hasher, &result.peaks_hashes[..1], result.elements_count),
+        Err(MmrError::InvalidPeaksCountForElements)
+    ));
+    assert!(matches!(
+        mmr::root_from_peaks(&hasher, &result.peaks_hashes, 5),
+        Err(MmrError::InvalidElementCount)
+    ));
+}
+
+#[tokio::test]
+async fn should_round_trip_proof_bytes() {
+    let mmr = Mmr::in_memory_keccak();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3"), lv("4"), lv("5")])
+        .await
+        .unwrap();
+    let proof = mmr.get_proof(4, None).await.unwrap();
+
+    let bytes = proof.to_bytes().unwrap();
+    assert_eq!(bytes.len(), 1 + 8 + 8 + 32 + 4 + 2 * 32 + 4 + 2 * 32 + 1);
+    assert_eq!(bytes[0], PROOF_ENCODING_VERSION);
+    assert_eq!(Proof::from_bytes(&bytes).unwrap(), proof);
+
+    assert!(matches!(
+        Proof::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(MmrError::InvalidProofEncoding(_))
+    ));
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(matches!(
+        Proof::from_bytes(&trailing),
+        Err(MmrError::InvalidProofEncoding(_))
+    ));
+    let mut future = bytes;
+    future[0] = PROOF_ENCODING_VERSION + 1;
+    assert!(matches!(
+        Proof::from_bytes(&future),
+        Err(MmrError::UnsupportedProofEncodingVersion(version)) if version == PROOF_ENCODING_VERSION + 1
+    ));
+}
+
+#[tokio::test]
+async fn should_decode_and_migrate_v1_proof_bytes() {
+    let mmr = Mmr::in_memory_keccak();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let proof = mmr.get_proof(1, None).await.unwrap();
+
+    let mut v1 = vec![1u8];
+    v1.extend_from_slice(&proof.element_index.to_be_bytes());
+    v1.extend_from_slice(&proof.elements_count.to_be_bytes());
+    v1.extend_from_slice(proof.element_hash.as_slice());
+    for hashes in [&proof.siblings_hashes, &proof.peaks_hashes] {
+        v1.extend_from_slice(&(hashes.len() as u32).to_be_bytes());
+        for hash in hashes {
+            v1.extend_from_slice(hash.as_slice());
+        }
+    }
+
+    assert_eq!(Proof::from_bytes(&v1).unwrap(), proof);
+    let migrated = Proof::migrate_bytes(&v1).unwrap();
+    assert_eq!(migrated, proof.to_bytes().unwrap());
+    assert_eq!(migrated[0], PROOF_ENCODING_VERSION);
+}
+
+#[tokio::test]
+async fn should_round_trip_proof_json_representation() {
+    let mmr = Mmr::in_memory_keccak();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let proof = mmr.get_proof(2, None).await.unwrap();
+
+    let json = proof.to_json();
+    assert_eq!(json.version, PROOF_JSON_VERSION);
+    assert_eq!(json.element_index, "2");
+    assert_eq!(json.elements_count, "4");
+    assert_eq!(json.element_hash, lv("2").to_string());
+    assert!(json.peaks_hashes.iter().all(|hash| hash.starts_with("0x")));
+    assert_eq!(json.to_proof().unwrap(), proof);
+
+    let mut short = json.clone();
+    short.element_hash = "0x2".to_string();
+    assert!(matches!(
+        short.to_proof(),
+        Err(MmrError::InvalidProofJson {
+            field: "elementHash",
+            ..
+        })
+    ));
+
+    let mut unprefixed = json.clone();
+    unprefixed.peaks_hashes[0] = lv("3").to_string()[2..].to_string();
+    assert!(matches!(
+        unprefixed.to_proof(),
+        Err(MmrError::InvalidProofJson {
+            field: "peaksHashes",
+            ..
+        })
+    ));
+
+    let mut future = json.clone();
+    future.version = PROOF_JSON_VERSION + 1;
+    assert!(matches!(
+        future.to_proof(),
+        Err(MmrError::UnsupportedProofEncodingVersion(_))
+    ));
+
+    let mut bad = json;
+    bad.elements_count = "-1".to_string();
+    assert!(matches!(
+        bad.to_proof(),
+        Err(MmrError::InvalidProofJson {
+            field: "elementsCount",
+            ..
+        })
+    ));
+}
+
+#[cfg(feature = "serde")]
+#[tokio::test]
+async fn should_serialize_proof_json_with_camel_case_fields() {
+    let mmr = Mmr::in_memory_keccak();
+    mmr.batch_append(&[lv("1"), lv("2")]).await.unwrap();
+    let json = mmr.get_proof(1, None).await.unwrap().to_json();
+
+    let encoded = serde_json::to_value(&json).unwrap();
+    assert_eq!(encoded["elementIndex"], "1");
+    assert_eq!(encoded["elementsCount"], "3");
+    assert_eq!(encoded["version"], PROOF_JSON_VERSION);
+    let decoded: mmr::types::ProofJson = serde_json::from_value(encoded.clone()).unwrap();
+    assert_eq!(decoded, json);
+
+    let mut legacy = encoded;
+    legacy.as_object_mut().unwrap().remove("version");
+    let decoded: mmr::types::ProofJson = serde_json::from_value(legacy).unwrap();
+    assert_eq!(decoded.to_proof().unwrap(), json.to_proof().unwrap());
+}
+
+#[cfg(feature = "borsh")]
+#[tokio::test]
+async fn should_round_trip_proofs_and_results_through_borsh() {
+    let mmr = Mmr::in_memory_keccak();
+    let result = mmr
+        .batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let proof = mmr.get_proof(4, None).await.unwrap();
+
+    let encoded = borsh::to_vec(&proof).unwrap();
+    assert_eq!(borsh::from_slice::<Proof>(&encoded).unwrap(), proof);
+    let encoded = borsh::to_vec(&result).unwrap();
+    assert_eq!(
+        borsh::from_slice::<mmr::types::BatchAppendResult>(&encoded).unwrap(),
+        result
+    );
+}
+
+#[tokio::test]
+async fn should_round_trip_proofs_and_peaks_through_ssz() {
+    let mmr = Mmr::in_memory_keccak();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let proof = mmr.get_proof(2, None).await.unwrap();
+
+    let bytes = proof.to_ssz_bytes().unwrap();
+    assert_eq!(bytes.len(), 60 + 32 + 2 * 32);
+    assert_eq!(&bytes[..8], &2u64.to_le_bytes());
+    assert_eq!(Proof::from_ssz_bytes(&bytes).unwrap(), proof);
+    assert!(matches!(
+        Proof::from_ssz_bytes(&bytes[..bytes.len() - 1]),
+        Err(MmrError::InvalidSszEncoding(_))
+    ));
+
+    let bound = Proof {
+        mmr_id: Some(7),
+        ..proof.clone()
+    };
+    let bytes = bound.to_ssz_bytes().unwrap();
+    assert_eq!(bytes.len(), 60 + 32 + 2 * 32 + 8);
+    assert_eq!(Proof::from_ssz_bytes(&bytes).unwrap(), bound);
+    assert_eq!(
+        bound
+            .to_compact()
+            .unwrap()
+            .to_proof(&KeccakHasher::new())
+            .unwrap(),
+        bound
+    );
+    assert_eq!(bound.to_zero_based().unwrap().to_proof().unwrap(), bound);
+
+    let commitment = mmr.get_peaks_commitment(None).await.unwrap();
+    assert_eq!(commitment.elements_count, 4);
+    assert_eq!(commitment.peaks_hashes, proof.peaks_hashes);
+    let bytes = commitment.to_ssz_bytes().unwrap();
+    assert_eq!(PeaksCommitment::from_ssz_bytes(&bytes).unwrap(), commitment);
+}
+
+#[cfg(feature = "ssz")]
+#[test]
+fn should_compute_ssz_hash_tree_root_of_peaks_commitment() {
+    let commitment = PeaksCommitment {
+        elements_count: 4,
+        peaks_hashes: vec![lv("1"), lv("2")],
+    };
+    assert_eq!(
+        commitment.hash_tree_root().unwrap().to_string(),
+        "0xb2e7a3acce69381ab193cda761c77b12171e8a7ffeab529ab717c6fa8299d3e8"
+    );
+}
+
+#[cfg(feature = "rkyv")]
+#[tokio::test]
+async fn should_verify_archived_proofs_without_deserializing() {
+    use mmr::types::ArchivedProof;
+
+    let mmr = Mmr::in_memory_keccak();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let proof = mmr.get_proof(2, None).await.unwrap();
+
+    let bytes = proof.to_archive_bytes().unwrap();
+    let archived = ArchivedProof::from_archive_bytes(&bytes).unwrap();
+    let hasher = KeccakHasher::new();
+    assert!(archived.verify(&hasher, lv("2")).unwrap());
+    assert!(!archived.verify(&hasher, lv("9")).unwrap());
+    let bound_root_7 =
+        bound_root_from_peaks(&hasher, &proof.peaks_hashes, proof.elements_count, 7).unwrap();
+    assert!(
+        !archived
+            .verify_bound(&hasher, lv("2"), &bound_root_7)
+            .unwrap()
+    );
+    assert_eq!(
+        rkyv::deserialize::<Proof, rkyv::rancor::Error>(archived).unwrap(),
+        proof
+    );
+
+    let bound = Proof {
+        mmr_id: Some(7),
+        ..proof
+    };
+    let bytes = bound.to_archive_bytes().unwrap();
+    let archived = ArchivedProof::from_archive_bytes(&bytes).unwrap();
+    let bound_root_8 =
+        bound_root_from_peaks(&hasher, &bound.peaks_hashes, bound.elements_count, 8).unwrap();
+    assert!(
+        archived
+            .verify_bound(&hasher, lv("2"), &bound_root_7)
+            .unwrap()
+    );
+    assert!(
+        !archived
+            .verify_bound(&hasher, lv("2"), &bound_root_8)
+            .unwrap()
+    );
+}
+
+#[cfg(feature = "rkyv")]
+#[tokio::test]
+async fn should_verify_a_proof_archive_in_place() {
+    use mmr::types::{ArchivedProofArchive, ProofArchive};
+
+    let mmr = Mmr::in_memory_keccak();
+    let values = [lv("1"), lv("2"), lv("3")];
+    mmr.batch_append(&values).await.unwrap();
+    let mut proofs = Vec::new();
+    for element_index in [1, 2, 4] {
+        proofs.push(mmr.get_proof(element_index, None).await.unwrap());
+    }
+    let archive = ProofArchive { proofs };
+
+    let bytes = archive.to_archive_bytes().unwrap();
+    let archived = ArchivedProofArchive::from_archive_bytes(&bytes).unwrap();
+    let hasher = KeccakHasher::new();
+    assert!(archived.verify(&hasher, &values).unwrap());
+    assert!(archived.proofs[2].verify(&hasher, lv("3")).unwrap());
+    assert!(
+        !archived
+            .verify(&hasher, &[lv("1"), lv("9"), lv("3")])
+            .unwrap()
+    );
+    assert!(!archived.verify(&hasher, &values[..2]).unwrap());
+    assert_eq!(
+        rkyv::deserialize::<ProofArchive, rkyv::rancor::Error>(archived).unwrap(),
+        archive
+    );
+    assert!(matches!(
+        ArchivedProofArchive::from_archive_bytes(&bytes[..bytes.len() - 1]),
+        Err(MmrError::InvalidProofArchive)
+    ));
+}
+
+#[test]
+fn should_format_and_parse_hash32_as_hex() {
+    let hash = lv("0x1234");
+    assert_eq!(
+        hash.to_string(),
+        "0x0000000000000000000000000000000000000000000000000000000000001234"
+    );
+    assert_eq!(hash.to_string().parse::<Hash32>().unwrap(), hash);
+    assert_eq!(format!("{:0>64}", "1234").parse::<Hash32>().unwrap(), hash);
+    assert!(matches!(
+        "0x1234".parse::<Hash32>(),
+        Err(HasherError::InvalidHex { .. })
+    ));
+    assert!(matches!(
+        format!("0x{:0>63}", "1234").parse::<Hash32>(),
+        Err(HasherError::InvalidHex { .. })
+    ));
+    assert!(matches!(
+        format!("0x{:0>6}", "1234").parse::<Hash20>(),
+        Err(HasherError::InvalidHex { .. })
+    ));
+
+    let bytes: [u8; 32] = hash.into();
+    assert_eq!(Hash32::from(bytes), hash);
+    assert_eq!(Hash32::try_from(&bytes[..]).unwrap(), hash);
+    assert!(Hash32::try_from(&bytes[1..]).is_err());
+
+    assert!(matches!(
+        "0xzz".parse::<Hash32>(),
+        Err(HasherError::InvalidHex { .. })
+    ));
+    assert!(matches!(
+        format!("0x{}", "ff".repeat(33)).parse::<Hash32>(),
+        Err(HasherError::InvalidHex { .. })
+    ));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn should_serialize_hash32_as_hex_string() {
+    let hash = lv("0x1234");
+    let encoded = serde_json::to_value(hash).unwrap();
+    assert_eq!(encoded, serde_json::Value::String(hash.to_string()));
+    assert_eq!(serde_json::from_value::<Hash32>(encoded).unwrap(), hash);
+}
+
+#[cfg(feature = "primitive-types")]
+#[test]
+fn should_convert_hash32_to_and_from_h256() {
+    let hash = lv("0x1234");
+    let h256 = primitive_types::H256::from(hash);
+    assert_eq!(h256.as_bytes(), hash.as_slice());
+    assert_eq!(Hash32::from(h256), hash);
+}
+
+#[cfg(feature = "alloy")]
+#[test]
+fn should_convert_hash32_to_and_from_b256() {
+    let hash = lv("0x1234");
+    let b256 = alloy_primitives::B256::from(hash);
+    assert_eq!(b256.as_slice(), hash.as_slice());
+    assert_eq!(Hash32::from(b256), hash);
+}
+
+#[tokio::test]
+async fn should_reject_bound_proofs_replayed_against_another_mmr() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let leaves = [lv("1"), lv("2"), lv("3")];
+    let mmr_a = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        hasher.clone(),
+        Some(115),
+    )
+    .unwrap()
+    .with_proof_binding(true);
+    let mmr_b = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(116)).unwrap();
+    mmr_a.batch_append(&leaves).await.unwrap();
+    mmr_b.batch_append(&leaves).await.unwrap();
+
+    let proof = mmr_a.get_proof(2, None).await.unwrap();
+    assert_eq!(proof.mmr_id, Some(115));
+    assert!(mmr_a.verify_proof(&proof, lv("2"), None).await.unwrap());
+    assert!(!mmr_b.verify_proof(&proof, lv("2"), None).await.unwrap());
+    let results = mmr_b
+        .verify_proofs(&[(proof.clone(), lv("2"))])
+        .await
+        .unwrap();
+    assert!(matches!(results.as_slice(), [Ok(false)]));
+    assert_eq!(
+        mmr_b
+            .verify_proof_detailed(&proof, lv("2"), None)
+            .await
+            .unwrap(),
+        ProofVerification::WrongMmrId {
+            expected: 116,
+            actual: 115,
+        }
+    );
+
+    let unbound = mmr_b.get_proof(2, None).await.unwrap();
+    assert_eq!(unbound.mmr_id, None);
+    assert!(!mmr_a.verify_proof(&unbound, lv("2"), None).await.unwrap());
+    assert_eq!(
+        mmr_a
+            .verify_proof_detailed(&unbound, lv("2"), None)
+            .await
+            .unwrap(),
+        ProofVerification::UnboundProof { expected: 115 }
+    );
+    let keccak = KeccakHasher::new();
+    let root_a = mmr_a.get_bound_root_hash(None).await.unwrap();
+    let root_b = mmr_b.get_bound_root_hash(None).await.unwrap();
+    assert_ne!(root_a, root_b);
+    assert_eq!(
+        root_a,
+        bound_root_from_peaks(&keccak, &proof.peaks_hashes, proof.elements_count, 115).unwrap()
+    );
+    assert!(verify_bound_proof(&keccak, &proof, lv("2"), &root_a).unwrap());
+    assert!(!verify_bound_proof(&keccak, &proof, lv("2"), &root_b).unwrap());
+    assert!(!verify_bound_proof(&keccak, &proof, lv("9"), &root_a).unwrap());
+    assert!(!verify_bound_proof(&keccak, &unbound, lv("2"), &root_b).unwrap());
+
+    assert_eq!(
+        Proof::from_bytes(&proof.to_bytes().unwrap()).unwrap(),
+        proof
+    );
+    assert_eq!(proof.to_json().mmr_id.as_deref(), Some("115"));
+    assert_eq!(proof.to_json().to_proof().unwrap(), proof);
+}
+
+#[test]
+fn should_parse_hashes_from_decimal_strings() {
+    assert_eq!(hash_from_decimal("0").unwrap(), ZERO_HASH);
+    assert_eq!(hash_from_decimal("4660").unwrap(), lv("0x1234"));
+    assert_eq!(
+        hash_from_decimal("340282366920938463463374607431768211455").unwrap(),
+        lv("0xffffffffffffffffffffffffffffffff")
+    );
+    let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+    assert_eq!(hash_from_decimal(max).unwrap(), Hash32::new([0xff; 32]));
+
+    assert!(matches!(
+        hash_from_decimal(""),
+        Err(HasherError::InvalidDecimal { .. })
+    ));
+    assert!(matches!(
+        hash_from_decimal("12a"),
+        Err(HasherError::InvalidDecimal { .. })
+    ));
+    assert!(matches!(
+        hash_from_decimal(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+        ),
+        Err(HasherError::InputTooLarge { .. })
+    ));
+}
+
+#[tokio::test]
+async fn should_append_hex_strings_and_raw_bytes() {
+    let hasher = KeccakHasher::new();
+    let mmr = Mmr::in_memory_keccak();
+
+    assert!(matches!(
+        mmr.append_hex("0x1234").await,
+        Err(MmrError::Hasher(HasherError::InvalidHex { .. }))
+    ));
+    mmr.append_hex(&lv("0x1234").to_string()).await.unwrap();
+    mmr.append_raw(b"hello").await.unwrap();
+    assert_eq!(
+        mmr.get_proof(1, None).await.unwrap().element_hash,
+        lv("0x1234")
+    );
+    assert_eq!(
+        mmr.get_proof(2, None).await.unwrap().element_hash,
+        hasher.hash_leaf(b"hello").unwrap()
+    );
+    let empty = hasher.hash_bytes(b"").unwrap();
+    assert_eq!(
+        empty,
+        hash_from_hex("0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+            .unwrap()
+    );
+    assert_eq!(
+        hasher.hash_leaf(b"").unwrap(),
+        hasher
+            .hash_bytes(&[&[0u8][..], &empty[..]].concat())
+            .unwrap()
+    );
+    let (left, right) = (lv("1"), lv("2"));
+    assert_ne!(
+        hasher.hash_leaf(&[left.0, right.0].concat()).unwrap(),
+        hasher.hash_pair(&left, &right).unwrap()
+    );
+
+    assert!(matches!(
+        mmr.append_hex("0xzz").await,
+        Err(MmrError::Hasher(HasherError::InvalidHex { .. }))
+    ));
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 2);
+
+    let poseidon = PoseidonHasher::new();
+    let short = poseidon.hash_leaf(b"hello").unwrap();
+    assert_ne!(short, poseidon.hash_leaf(b"hello\0").unwrap());
+    assert!(poseidon.hash_leaf(&[0xff; 100]).is_ok());
+}
+
+struct Transfer {
+    from: u64,
+    to: u64,
+    amount: u64,
+}
+
+impl LeafEncode for Transfer {
+    fn leaf_bytes(&self) -> Vec<u8> {
+        [self.from, self.to, self.amount]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+}
+
+#[tokio::test]
+async fn should_append_and_verify_typed_leaves() {
+    let mmr = TypedMmr::<Transfer, _>::new(Mmr::in_memory_keccak());
+    let transfers = [
+        Transfer {
+            from: 1,
+            to: 2,
+            amount: 10,
+        },
+        Transfer {
+            from: 2,
+            to: 3,
+            amount: 5,
+        },
+    ];
+    mmr.batch_append(&transfers).await.unwrap();
+    let appended = mmr
+        .append(&Transfer {
+            from: 3,
+            to: 1,
+            amount: 1,
+        })
+        .await
+        .unwrap();
+    assert_eq!(appended.leaves_count, 3);
+
+    let proof = mmr.get_proof(2, None).await.unwrap();
+    assert_eq!(proof.element_hash, mmr.leaf_hash(&transfers[1]).unwrap());
+    assert!(mmr.verify_proof(&proof, &transfers[1], None).await.unwrap());
+    assert!(!mmr.verify_proof(&proof, &transfers[0], None).await.unwrap());
+    assert_eq!(mmr.inner().get_leaves_count().await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn should_count_appends_store_round_trips_and_hashes() {
+    let mmr = Mmr::in_memory_keccak();
+    assert_eq!(mmr.op_stats(), OpStats::default());
+
+    mmr.append(lv("1")).await.unwrap();
+    mmr.batch_append(&[lv("2"), lv("3")]).await.unwrap();
+    let stats = mmr.op_stats();
+    assert_eq!(stats.appends, 3);
+    assert_eq!(stats.store_round_trips, 4);
+    assert!(stats.hashes > 0);
+
+    mmr.reset_op_stats();
+    mmr.get_proof(1, None).await.unwrap();
+    let stats = mmr.op_stats();
+    assert_eq!(stats.appends, 0);
+    assert_eq!(stats.hashes, 0);
+    assert!(stats.store_round_trips > 0);
+
+    let draft = mmr.draft().await.unwrap();
+    draft.append(lv("4")).await.unwrap();
+    assert_eq!(draft.op_stats().appends, 1);
+    assert_eq!(mmr.op_stats().appends, 0);
+}
+
+#[tokio::test]
+async fn should_serve_repeated_proofs_from_the_node_cache() {
+    let leaves: Vec<Hash32> = (1..=20u64).map(|i| lv(&i.to_string())).collect();
+    let uncached = Mmr::in_memory_keccak();
+    uncached.batch_append(&leaves).await.unwrap();
+    let cached = Mmr::in_memory_keccak().with_node_cache(64);
+    cached.batch_append(&leaves).await.unwrap();
+    let elements_count = cached.get_elements_count().await.unwrap();
+
+    let expected = uncached.get_proof(8, None).await.unwrap();
+    assert_eq!(cached.get_proof(8, None).await.unwrap(), expected);
+    cached.reset_op_stats();
+    assert_eq!(
+        cached.get_proof(8, Some(elements_count)).await.unwrap(),
+        expected
+    );
+    assert_eq!(cached.op_stats().store_round_trips, 0);
+    assert_eq!(
+        cached.get_peaks(Some(elements_count)).await.unwrap(),
+        expected.peaks_hashes
+    );
+    assert_eq!(cached.op_stats().store_round_trips, 0);
+
+    let tiny = Mmr::in_memory_keccak().with_node_cache(2);
+    tiny.batch_append(&leaves).await.unwrap();
+    for element_index in [1, 2, 8, 16, 1, 32, 8] {
+        assert_eq!(
+            tiny.get_proof(element_index, None).await.unwrap(),
+            uncached.get_proof(element_index, None).await.unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn should_generate_proofs_in_parallel_in_request_order() {
+    let mmr = Mmr::in_memory_keccak();
+    let leaves: Vec<Hash32> = (1..=150u64).map(|i| lv(&i.to_string())).collect();
+    mmr.batch_append(&leaves).await.unwrap();
+
+    let mut element_indices: Vec<u64> = (0..150u64).map(map_leaf_index_to_element_index).collect();
+    element_indices.reverse();
+    let expected = mmr.get_proofs(&element_indices, None).await.unwrap();
+    for max_concurrency in [0, 1, 4] {
+        assert_eq!(
+            mmr.get_proofs_parallel(&element_indices, None, max_concurrency)
+                .await
+                .unwrap(),
+            expected
+        );
+    }
+
+    assert!(
+        mmr.get_proofs_parallel(&[], None, 4)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert!(matches!(
+        mmr.get_proofs_parallel(&[1, 0], None, 4).await,
+        Err(MmrError::InvalidElementIndex)
+    ));
+}
+
+#[tokio::test]
+async fn should_coalesce_queued_appends_through_mmr_writer() {
+    let mmr = Arc::new(Mmr::in_memory_keccak());
+    let (writer, driver) = MmrWriter::new(mmr.clone(), 4);
+
+    let values: Vec<Hash32> = (1..=10).map(|i| lv(&i.to_string())).collect();
+    let (_, results) = futures::future::join(driver, async move {
+        let results = join_all(values.iter().map(|value| writer.append(*value))).await;
+        drop(writer);
+        results
+    })
+    .await;
+
+    let mut element_indices: Vec<u64> = results
+        .iter()
+        .map(|result| result.as_ref().unwrap().element_index)
+        .collect();
+    element_indices.sort_unstable();
+    let expected: Vec<u64> = (0..10).map(map_leaf_index_to_element_index).collect();
+    assert_eq!(element_indices, expected);
+
+    let last = results
+        .iter()
+        .map(|result| result.as_ref().unwrap())
+        .max_by_key(|queued| queued.elements_count)
+        .unwrap();
+    assert_eq!(last.elements_count, mmr.get_elements_count().await.unwrap());
+    assert_eq!(last.root_hash, mmr.get_root_hash().await.unwrap().unwrap());
+    assert_eq!(mmr.op_stats().appends, 10);
+
+    let (writer, driver) = MmrWriter::new(mmr, 4);
+    drop(driver);
+    assert!(matches!(
+        writer.append(lv("11")).await,
+        Err(MmrError::WriterClosed)
+    ));
+}
+
+#[tokio::test]
+async fn mmr_writer_driver_runs_on_a_spawned_task() {
+    let mmr = Arc::new(Mmr::in_memory_keccak());
+    let (writer, driver) = MmrWriter::new(mmr.clone(), 4);
+    let driver = tokio::spawn(driver);
+
+    let appends = (1..=6).map(|i| {
+        let writer = writer.clone();
+        tokio::spawn(async move { writer.append(lv(&i.to_string())).await })
+    });
+    for append in join_all(appends).await {
+        append.unwrap().unwrap();
+    }
+    drop(writer);
+    driver.await.unwrap();
+
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 6);
+}
+
+#[tokio::test]
+async fn mmr_writer_reports_a_failed_batch_per_caller() {
+    let mmr = Arc::new(Mmr::in_memory_keccak().with_max_leaves(3));
+    let (writer, driver) = MmrWriter::new(mmr.clone(), 4);
+
+    let values: Vec<Hash32> = (1..=4).map(|i| lv(&i.to_string())).collect();
+    let (_, results) = futures::future::join(driver, async move {
+        let results = join_all(values.iter().map(|value| writer.append(*value))).await;
+        drop(writer);
+        results
+    })
+    .await;
+
+    assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 3);
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Err(MmrError::MaxLeavesExceeded {
+            limit: 3,
+            requested: 4
+        })
+    )));
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn mmr_writer_does_not_retry_a_batch_after_a_store_error() {
+    let store = Arc::new(SpyStore::default());
+    let hook = Arc::new(RecordingHook::default());
+    let mmr = Arc::new(
+        Mmr::new(store.clone(), Arc::new(KeccakHasher::new()), Some(68))
+            .unwrap()
+            .with_hook(hook.clone()),
+    );
+    let (writer, driver) = MmrWriter::new(mmr.clone(), 4);
+
+    // The batch lands but its acknowledgement is lost.
+    store.fail_next_set_many_after_writing(1);
+    let values: Vec<Hash32> = (1..=3).map(|i| lv(&i.to_string())).collect();
+    let (_, results) = futures::future::join(driver, async move {
+        let results = join_all(values.iter().map(|value| writer.append(*value))).await;
+        drop(writer);
+        results
+    })
+    .await;
+
+    for result in &results {
+        let Err(MmrError::WriterBatchFailed(err)) = result else {
+            panic!("expected the shared batch error, got {result:?}");
+        };
+        assert!(err.store_error().is_some_and(StoreError::is_transient));
+    }
+    assert_eq!(hook.before.load(Ordering::SeqCst), 1);
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn should_record_phase_timings_only_when_profiling() {
+    let plain = Mmr::in_memory_keccak();
+    plain.append(lv("1")).await.unwrap();
+    assert_eq!(plain.phase_timings(), PhaseTimings::default());
+
+    let mmr = Mmr::in_memory_keccak().with_profiling();
+    let values = (1..=8u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    mmr.batch_append(&values).await.unwrap();
+    mmr.get_proof(1, None).await.unwrap();
+
+    let timings = mmr.phase_timings();
+    assert!(timings.store_reads > Duration::ZERO);
+    assert!(timings.hashing > Duration::ZERO);
+    assert!(timings.store_writes > Duration::ZERO);
+
+    mmr.reset_op_stats();
+    assert_eq!(mmr.phase_timings(), PhaseTimings::default());
+}
+
+#[tokio::test]
+async fn should_report_missing_siblings_and_peaks_instead_of_skipping_them() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(171)).unwrap();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let proof = mmr.get_proof(1, None).await.unwrap();
+    let intact_proof = mmr.get_proof(1, Some(1)).await.unwrap();
+
+    store
+        .delete_many(&[StoreKey::new(171, KeyKind::NodeHash, 2)])
+        .await
+        .unwrap();
+    assert!(matches!(
+        mmr.get_proof(1, None).await,
+        Err(MmrError::NoHashFoundForIndex(2))
+    ));
+    assert!(matches!(
+        mmr.get_proofs(&[1], None).await,
+        Err(MmrError::NoHashFoundForIndex(2))
+    ));
+
+    store
+        .delete_many(&[StoreKey::new(171, KeyKind::NodeHash, 3)])
+        .await
+        .unwrap();
+    assert!(matches!(
+        mmr.get_peaks(None).await,
+        Err(MmrError::NoHashFoundForIndex(3))
+    ));
+    assert!(matches!(
+        mmr.verify_proof(&proof, lv("1"), None).await,
+        Err(MmrError::NoHashFoundForIndex(3))
+    ));
+    let results = mmr
+        .verify_proofs(&[(proof, lv("1")), (intact_proof, lv("1"))])
+        .await
+        .unwrap();
+    assert!(matches!(
+        results.as_slice(),
+        [Err(MmrError::NoHashFoundForIndex(3)), Ok(true)]
+    ));
+    assert!(matches!(
+        mmr.append(lv("4")).await,
+        Err(MmrError::NoHashFoundForIndex(3))
+    ));
+}
+
+#[tokio::test]
+async fn paranoid_reads_detect_tampered_siblings_and_peaks() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+    let trusting = Mmr::new(store.clone(), hasher.clone(), Some(172)).unwrap();
+    let paranoid = Mmr::new(store.clone(), hasher, Some(172))
+        .unwrap()
+        .with_paranoid_reads(true);
+    trusting
+        .batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    assert_eq!(
+        paranoid.get_proof(1, None).await.unwrap(),
+        trusting.get_proof(1, None).await.unwrap()
+    );
+
+    store
+        .set(
+            StoreKey::new(172, KeyKind::NodeHash, 2),
+            StoreValue::Hash(lv("99")),
+        )
+        .await
+        .unwrap();
+    assert!(trusting.get_proof(1, None).await.is_ok());
+    assert!(matches!(
+        paranoid.get_proof(1, None).await,
+        Err(MmrError::CorruptedProofPath(1))
+    ));
+    assert!(matches!(
+        paranoid.get_proofs(&[1], None).await,
+        Err(MmrError::CorruptedProofPath(1))
+    ));
+    assert!(matches!(
+        paranoid.get_proofs_parallel(&[1, 2], None, 2).await,
+        Err(MmrError::CorruptedProofPath(1))
+    ));
+
+    store
+        .set(
+            StoreKey::new(172, KeyKind::NodeHash, 4),
+            StoreValue::Hash(lv("98")),
+        )
+        .await
+        .unwrap();
+    assert!(trusting.get_peaks(None).await.is_ok());
+    assert!(matches!(
+        paranoid.get_peaks(None).await,
+        Err(MmrError::CorruptedPeaks(4))
+    ));
+    assert!(matches!(
+        paranoid.get_proof(4, None).await,
+        Err(MmrError::CorruptedPeaks(4))
+    ));
+}
+
+struct TruncatedKeccak;
+
+impl Hasher<20> for TruncatedKeccak {
+    fn hash_pair(&self, left: &Hash20, right: &Hash20) -> Result<Hash20, HasherError> {
+        let full = KeccakHasher::new().hash_bytes(&[left.as_slice(), right.as_slice()].concat())?;
+        Ok(Hash(full.0[12..].try_into().unwrap()))
+    }
+
+    fn hash_count_and_bag(&self, elements_count: u64, bag: &Hash20) -> Result<Hash20, HasherError> {
+        let mut count = Hash20::default();
+        count[12..].copy_from_slice(&elements_count.to_be_bytes());
+        self.hash_pair(&count, bag)
+    }
+
+    fn hash_leaf(&self, data: &[u8]) -> Result<Hash20, HasherError> {
+        let full = KeccakHasher::new().hash_leaf(data)?;
+        Ok(Hash(full.0[12..].try_into().unwrap()))
+    }
+}
+
+#[test]
+fn verification_machinery_accepts_20_byte_hashes() {
+    let hasher = TruncatedKeccak;
+    let [a, b, c]: [Hash20; 3] = [Hash([1; 20]), Hash([2; 20]), Hash([3; 20])];
+    let ab = hasher.hash_pair(&a, &b).unwrap();
+    let proof = Proof {
+        element_index: 2,
+        element_hash: b,
+        siblings_hashes: vec![a],
+        peaks_hashes: vec![ab, c],
+        elements_count: 4,
+        mmr_id: None,
+    };
+
+    let root = mmr::root_from_peaks(&hasher, &proof.peaks_hashes, 4).unwrap();
+    assert_eq!(
+        root,
+        hasher
+            .hash_count_and_bag(4, &hasher.hash_pair(&ab, &c).unwrap())
+            .unwrap()
+    );
+    assert!(verify_proof(&hasher, &proof, b).unwrap());
+    assert!(verify_proof_against_root(&hasher, &proof, b, &root).unwrap());
+    assert!(!verify_proof(&hasher, &proof, c).unwrap());
+
+    let no_std_ok = core_verify::verify_proof(&hasher, 2, &b.0, &[a.0], &[ab.0, c.0], 4).unwrap();
+    assert!(no_std_ok);
+    assert_eq!(format!("{a:?}"), format!("Hash20(0x{})", "01".repeat(20)));
+}
+
+/// 64-byte digests from two domain-separated keccak calls.
+struct WideKeccak;
+
+impl Hasher<64> for WideKeccak {
+    fn hash_pair(&self, left: &Hash64, right: &Hash64) -> Result<Hash64, HasherError> {
+        self.hash_bytes(&[left.as_slice(), right.as_slice()].concat())
+    }
+
+    fn hash_count_and_bag(&self, elements_count: u64, bag: &Hash64) -> Result<Hash64, HasherError> {
+        self.hash_bytes(&[&elements_count.to_be_bytes()[..], bag.as_slice()].concat())
+    }
+
+    fn hash_bytes(&self, data: &[u8]) -> Result<Hash64, HasherError> {
+        let keccak = KeccakHasher::new();
+        let low = keccak.hash_bytes(&[&[0u8][..], data].concat())?;
+        let high = keccak.hash_bytes(&[&[1u8][..], data].concat())?;
+        Ok(Hash([low.0, high.0].concat().try_into().unwrap()))
+    }
+}
+
+async fn assert_stateful_mmr_with_width<const N: usize>(hasher: Arc<dyn Hasher<N>>) {
+    let leaves: Vec<Hash<N>> = (1..=5).map(|byte| Hash([byte; N])).collect();
+    let mmr = Mmr::new(
+        Arc::new(InMemoryStore::<N>::default()),
+        hasher.clone(),
+        None,
+    )
+    .unwrap()
+    .with_hash_index(true)
+    .with_paranoid_reads(true);
+
+    let result = mmr.batch_append(&leaves).await.unwrap();
+    assert_eq!(result.elements_count, 8);
+    let root = mmr::root_from_peaks(hasher.as_ref(), &result.peaks_hashes, 8).unwrap();
+    assert_eq!(result.root_hash, root);
+    assert_eq!(mmr.get_root_hash().await.unwrap(), Some(root));
+
+    let proof = mmr.get_proof(4, None).await.unwrap();
+    assert_eq!(proof.element_hash, leaves[2]);
+    assert!(mmr.verify_proof(&proof, leaves[2], None).await.unwrap());
+    assert!(verify_proof_against_root(hasher.as_ref(), &proof, leaves[2], &root).unwrap());
+    assert!(!mmr.verify_proof(&proof, leaves[3], None).await.unwrap());
+    assert_eq!(mmr.find_element_index(&leaves[4]).await.unwrap(), Some(8));
+
+    let accumulator = PeaksAccumulator::new(InMemoryStore::<N>::default(), hasher, 1);
+    let accumulated = accumulator.batch_append(&leaves).await.unwrap();
+    assert_eq!(accumulated.root_hash, root);
+    assert_eq!(accumulator.get_peaks().await.unwrap(), result.peaks_hashes);
+}
+
+#[tokio::test]
+async fn stateful_mmr_accepts_20_and_64_byte_hashes() {
+    assert_stateful_mmr_with_width::<20>(Arc::new(TruncatedKeccak)).await;
+    assert_stateful_mmr_with_width::<64>(Arc::new(WideKeccak)).await;
+}
+
+#[tokio::test]
+async fn batched_reads_request_each_key_once() {
+    let store = Arc::new(SpyStore::default());
+    let mmr = Mmr::new(store.clone(), Arc::new(KeccakHasher::new()), None).unwrap();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3"), lv("4")])
+        .await
+        .unwrap();
+
+    let keys_before = store.metrics().get_many_keys;
+    let proofs = mmr.get_proofs(&[1, 2, 4], Some(7)).await.unwrap();
+    // Peak 7, then elements 1, 2, 4 with siblings {2, 6}, {1, 6} and {5, 3}.
+    assert_eq!(store.metrics().get_many_keys - keys_before, 7);
+
+    for (proof, element_index) in proofs.iter().zip([1, 2, 4]) {
+        assert_eq!(proof, &mmr.get_proof(element_index, Some(7)).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn appends_fall_back_to_a_checked_set_many_without_compare_and_set() {
+    let store = Arc::new(NoCasStore(InMemoryStore::default()));
+    let mmr = Mmr::new(store.clone(), Arc::new(KeccakHasher::new()), Some(1)).unwrap();
+    mmr.batch_append(&[lv("1"), lv("2")]).await.unwrap();
+    assert_eq!(mmr.get_elements_count().await.unwrap(), 3);
+    assert!(mmr.verify_integrity().await.unwrap().is_ok());
+
+    let counter_key = StoreKey::metadata(1, KeyKind::ElementsCount);
+    let stale = StoreKey::new(1, KeyKind::NodeHash, 9);
+    let written = store
+        .compare_and_set_many(
+            &counter_key,
+            1,
+            vec![(stale.clone(), StoreValue::Hash(lv("9")))],
+        )
+        .await
+        .unwrap();
+    assert!(!written);
+    assert_eq!(store.get(&stale).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn should_reject_invalid_index_and_fail_on_malformed_siblings() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+
+    let mmr = Mmr::new(store, hasher, Some(41)).unwrap();
+    mmr.append(lv("1")).await.unwrap();
+    mmr.append(lv("2")).await.unwrap();
+    mmr.append(lv("3")).await.unwrap();
+
+    assert!(matches!(
+        mmr.get_proof(0, None).await,
+        Err(MmrError::InvalidElementIndex)
+    ));
+
+    let mut proof = mmr.get_proof(1, None).await.unwrap();
+    proof.siblings_hashes.push(ZERO_HASH);
+
+    assert!(!mmr.verify_proof(&proof, lv("1"), None).await.unwrap());
+}
+
+#[cfg(feature = "stateless-verify")]
+#[tokio::test]
+async fn stateless_verify_is_available_and_independent() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+
+    let mmr = Mmr::new(store, hasher, Some(51)).unwrap();
+    mmr.append(lv("1")).await.unwrap();
+    mmr.append(lv("2")).await.unwrap();
+    mmr.append(lv("3")).await.unwrap();
+
+    let proof = mmr.get_proof(1, None).await.unwrap();
+    assert!(
+        mmr.verify_proof_stateless(&proof, lv("1"), None)
+            .await
+            .unwrap()
+    );
+
+    let mut tampered = proof.clone();
+    tampered.peaks_hashes[0] = ZERO_HASH;
+
+    assert!(
+        !mmr.verify_proof_stateless(&tampered, lv("1"), None)
+            .await
+            .unwrap()
+    );
+
+    assert!(mmr.verify_proof(&tampered, lv("1"), None).await.unwrap());
+}
+
+#[tokio::test]
+async fn append_uses_one_get_many_and_one_set_many_in_steady_state() {
+    let store = Arc::new(SpyStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(61)).unwrap();
+
+    mmr.append(lv("1")).await.unwrap();
+
+    let before = store.metrics();
+    mmr.append(lv("2")).await.unwrap();
+    let after = store.metrics();
+
+    assert_eq!(after.get_many_calls - before.get_many_calls, 1);
+    assert_eq!(after.set_many_calls - before.set_many_calls, 1);
+    assert_eq!(after.get_calls - before.get_calls, 0);
+    assert_eq!(after.set_calls - before.set_calls, 0);
+}
+
+#[tokio::test]
+async fn retrying_store_retries_transient_errors_of_idempotent_operations_only() {
+    assert!(StoreError::Transient("reset".to_string()).is_transient());
+    assert!(!StoreError::Internal("broken".to_string()).is_transient());
+
+    let spy = Arc::new(SpyStore::default());
+    let policy = RetryPolicy {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(2),
+    };
+    let mmr = Mmr::new(
+        RetryingStore::new(spy.clone(), policy, tokio::time::sleep),
+        Arc::new(KeccakHasher::new()),
+        Some(67),
+    )
+    .unwrap();
+    mmr.append(lv("1")).await.unwrap();
+
+    spy.fail_next_get_many_transiently(2);
+    let before = spy.metrics();
+    mmr.append(lv("2")).await.unwrap();
+    assert_eq!(spy.metrics().get_many_calls - before.get_many_calls, 3);
+
+    spy.fail_next_get_many_transiently(3);
+    let err = mmr.append(lv("3")).await.unwrap_err();
+    let MmrError::StoreOp {
+        mmr_id: 67,
+        operation: "get_many",
+        target: StoreOpTarget::Batch(_),
+        source,
+    } = &err
+    else {
+        panic!("expected a tagged store error, got {err:?}");
+    };
+    assert!(matches!(**source, StoreError::Transient(_)));
+    assert!(err.store_error().is_some_and(StoreError::is_transient));
+    assert!(err.to_string().contains("`get_many` failed for mmr 67"));
+    spy.fail_next_get_many_transiently(0);
+
+    // A compare-and-set whose ack was lost is read back instead of being reapplied.
+    spy.fail_next_set_many_after_writing(1);
+    let before = spy.metrics();
+    let appended = mmr.append(lv("3")).await.unwrap();
+    assert_eq!(appended.leaves_count, 3);
+    assert_eq!(spy.metrics().set_many_calls - before.set_many_calls, 1);
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 3);
+
+    spy.set_fail_set_many(true);
+    let before = spy.metrics();
+    assert!(mmr.append(lv("4")).await.is_err());
+    assert_eq!(spy.metrics().set_many_calls - before.set_many_calls, 1);
+}
+
+#[tokio::test]
+async fn batch_append_uses_one_get_many_and_one_set_many_in_steady_state() {
+    let store = Arc::new(SpyStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(63)).unwrap();
+
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+
+    let before = store.metrics();
+    mmr.batch_append(&[lv("4"), lv("5"), lv("6"), lv("7")])
+        .await
+        .unwrap();
+    let after = store.metrics();
+
+    assert_eq!(after.get_many_calls - before.get_many_calls, 1);
+    assert_eq!(after.set_many_calls - before.set_many_calls, 1);
+    assert_eq!(after.get_calls - before.get_calls, 0);
+    assert_eq!(after.set_calls - before.set_calls, 0);
+}
+
+#[tokio::test]
+async fn get_proofs_matches_get_proof_with_one_store_round_trip() {
+    let store = Arc::new(SpyStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(65)).unwrap();
+
+    let values = (1..=11u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    let result = mmr.batch_append(&values).await.unwrap();
+    let element_indices = (0..values.len() as u64)
+        .map(map_leaf_index_to_element_index)
+        .collect::<Vec<_>>();
+
+    let before = store.metrics();
+    let proofs = mmr
+        .get_proofs(&element_indices, Some(result.elements_count))
+        .await
+        .unwrap();
+    let after = store.metrics();
+
+    assert_eq!(after.get_many_calls - before.get_many_calls, 1);
+    assert_eq!(after.get_calls - before.get_calls, 0);
+
+    assert_eq!(proofs.len(), element_indices.len());
+    for (proof, element_index) in proofs.iter().zip(element_indices.iter()) {
+        assert_eq!(*proof, mmr.get_proof(*element_index, None).await.unwrap());
+    }
+
+    assert!(matches!(
+        mmr.get_proofs(&[1, 0], None).await,
+        Err(MmrError::InvalidElementIndex)
+    ));
+}
+
+#[tokio::test]
+async fn verify_proofs_reads_peaks_once_per_batch() {
+    let store = Arc::new(SpyStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(66)).unwrap();
+
+    let first = mmr
+        .batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let second = mmr.batch_append(&[lv("4"), lv("5")]).await.unwrap();
+
+    let mut items = Vec::new();
+    for (leaf_index, leaf) in LEAVES.iter().enumerate() {
+        let element_index = map_leaf_index_to_element_index(leaf_index as u64);
+        let proof = mmr
+            .get_proof(element_index, Some(second.elements_count))
+            .await
+            .unwrap();
+        items.push((proof, lv(leaf)));
+    }
+    let old_proof = mmr.get_proof(1, Some(first.elements_count)).await.unwrap();
+    items.push((old_proof.clone(), lv("1")));
+    items.push((old_proof, lv("999")));
+
+    let before = store.metrics();
+    let results = mmr
+        .verify_proofs(&items)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    let after = store.metrics();
+
+    assert_eq!(after.get_many_calls - before.get_many_calls, 1);
+    assert_eq!(after.get_calls - before.get_calls, 0);
+    assert_eq!(results, vec![true, true, true, true, true, true, false]);
+}
+
+#[tokio::test]
+async fn append_returns_error_and_avoids_partial_writes_when_set_many_fails() {
+    let store = Arc::new(SpyStore::default());
+    store.set_fail_set_many(true);
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(62)).unwrap();
+
+    let result = mmr.append(lv("1")).await;
+    assert!(result.is_err());
+    assert_eq!(store.entry_count(), 0);
+
+    assert_eq!(mmr.get_elements_count().await.unwrap(), 0);
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 0);
+
+    let key = StoreKey::new(62, KeyKind::NodeHash, 1);
+    assert!(store.get(&key).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn batch_append_returns_error_and_avoids_partial_writes_when_set_many_fails() {
+    let store = Arc::new(SpyStore::default());
+    store.set_fail_set_many(true);
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(64)).unwrap();
+
+    let result = mmr.batch_append(&[lv("1"), lv("2"), lv("3")]).await;
+    assert!(result.is_err());
+    assert_eq!(store.entry_count(), 0);
+
+    assert_eq!(mmr.get_elements_count().await.unwrap(), 0);
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 0);
+
+    let key = StoreKey::new(64, KeyKind::NodeHash, 1);
+    assert!(store.get(&key).await.unwrap().is_none());
 }
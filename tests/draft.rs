@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+mod common;
+
+use common::RecordingHook;
+use mmr::error::MmrError;
+use mmr::hasher::KeccakHasher;
+use mmr::test_utils::lv;
+use mmr::{InMemoryStore, Mmr};
+
+#[tokio::test]
+async fn draft_stages_appends_until_commit_or_discard() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(76)).unwrap();
+    let base = mmr.batch_append(&[lv("1"), lv("2")]).await.unwrap();
+
+    let draft = mmr.draft().await.unwrap();
+    let staged = draft.batch_append(&[lv("3"), lv("4")]).await.unwrap();
+    let proof = draft
+        .get_proof(staged.last_element_index, None)
+        .await
+        .unwrap();
+    assert!(draft.verify_proof(&proof, lv("4"), None).await.unwrap());
+    assert_eq!(draft.get_root_hash().await.unwrap(), Some(staged.root_hash));
+    assert_eq!(mmr.get_root_hash().await.unwrap(), Some(base.root_hash));
+    draft.discard();
+    assert_eq!(mmr.get_elements_count().await.unwrap(), base.elements_count);
+
+    let draft = mmr.draft().await.unwrap();
+    let staged = draft.batch_append(&[lv("3"), lv("4")]).await.unwrap();
+    assert!(draft.staged_len().unwrap() > 0);
+    draft.commit().await.unwrap();
+    assert_eq!(mmr.get_root_hash().await.unwrap(), Some(staged.root_hash));
+    let next = mmr.append(lv("5")).await.unwrap();
+    assert_eq!(next.previous_root_hash, Some(staged.root_hash));
+
+    let stale = mmr.draft().await.unwrap();
+    stale.append(lv("6")).await.unwrap();
+    mmr.append(lv("7")).await.unwrap();
+    let err = stale.commit().await.unwrap_err();
+    assert!(matches!(err, MmrError::DraftConflict(8)));
+}
+
+#[tokio::test]
+async fn drafts_keep_the_base_hooks_and_lazy_root() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let hook = Arc::new(RecordingHook {
+        reject_over: Some(2),
+        ..RecordingHook::default()
+    });
+    let mmr = Mmr::new(store, hasher, Some(92))
+        .unwrap()
+        .with_lazy_root(true)
+        .with_hook(hook.clone());
+    mmr.append(lv("1")).await.unwrap();
+
+    let draft = mmr.draft().await.unwrap();
+    assert!(matches!(
+        draft.batch_append(&[lv("2"), lv("3"), lv("4")]).await,
+        Err(MmrError::HookRejected(_))
+    ));
+    draft.batch_append(&[lv("2"), lv("3")]).await.unwrap();
+    draft.append(lv("4")).await.unwrap();
+    assert_eq!(draft.get_root_hash().await.unwrap(), None);
+    assert_eq!(*hook.after.lock().unwrap(), vec![1]);
+
+    draft.commit().await.unwrap();
+    assert_eq!(hook.before.load(Ordering::SeqCst), 3);
+    assert_eq!(*hook.after.lock().unwrap(), vec![1, 7]);
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 4);
+}
+
+#[tokio::test]
+async fn drafts_of_a_bound_mmr_produce_bound_proofs() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(93))
+        .unwrap()
+        .with_proof_binding(true)
+        .with_paranoid_reads(true)
+        .with_node_cache(16);
+    mmr.append(lv("1")).await.unwrap();
+
+    let draft = mmr.draft().await.unwrap();
+    let appended = draft.append(lv("2")).await.unwrap();
+    let proof = draft.get_proof(appended.element_index, None).await.unwrap();
+    assert_eq!(proof.mmr_id, Some(93));
+    draft.commit().await.unwrap();
+
+    assert!(mmr.verify_proof(&proof, lv("2"), None).await.unwrap());
+}
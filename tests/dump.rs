@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use mmr::error::MmrError;
+use mmr::hasher::{Hasher, KeccakHasher};
+use mmr::test_utils::lv;
+use mmr::types::Hash32;
+use mmr::{
+    InMemoryStore, KECCAK_HASHER_CODE, KeyKind, Mmr, MmrRegistry, POSEIDON_HASHER_CODE, Store,
+    StoreKey, StoreValue,
+};
+
+#[tokio::test]
+async fn export_import_round_trips_and_rejects_corrupted_dumps() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let source = Mmr::new(Arc::new(InMemoryStore::default()), hasher.clone(), Some(56)).unwrap();
+    let values = (1..=9u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    source.batch_append(&values[..7]).await.unwrap();
+    let with_data = source
+        .append_with_data(values[7], b"payload".to_vec())
+        .await
+        .unwrap();
+    let appended = source.append_with_key(900, values[8]).await.unwrap();
+
+    let mut dump = Vec::new();
+    source.export_to_writer(&mut dump).await.unwrap();
+
+    let imported = Mmr::import_from_reader(
+        Arc::new(InMemoryStore::default()),
+        hasher.clone(),
+        Some(57),
+        &mut dump.as_slice(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        imported.get_root_hash().await.unwrap(),
+        Some(appended.root_hash)
+    );
+    assert_eq!(imported.get_elements_count().await.unwrap(), 16);
+    let proof = imported.get_proof(8, None).await.unwrap();
+    assert!(imported.verify_proof(&proof, lv("5"), None).await.unwrap());
+    assert_eq!(
+        imported.get_element_index_by_key(900).await.unwrap(),
+        Some(appended.element_index)
+    );
+    assert_eq!(
+        imported
+            .get_leaf_data(with_data.element_index)
+            .await
+            .unwrap(),
+        Some(b"payload".to_vec())
+    );
+
+    // Magic, version, mmr id, counts, root flag and hash, node count and first node index.
+    let first_node_hash = 8 + 1 + 8 + 8 + 8 + 1 + 32 + 8 + 8;
+    let mut corrupted = dump.clone();
+    corrupted[first_node_hash] ^= 0xff;
+    let err = Mmr::import_from_reader(
+        Arc::new(InMemoryStore::default()),
+        hasher.clone(),
+        Some(58),
+        &mut corrupted.as_slice(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, MmrError::DumpChecksumMismatch));
+
+    let mut truncated = &dump[..dump.len() - 1];
+    let err = Mmr::import_from_reader(
+        Arc::new(InMemoryStore::default()),
+        hasher,
+        Some(59),
+        &mut truncated,
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, MmrError::Io(_)));
+
+    let mut unknown_version = dump.clone();
+    unknown_version[8] = 2;
+    let err = Mmr::import_from_reader(
+        Arc::new(InMemoryStore::default()),
+        Arc::new(KeccakHasher::new()),
+        Some(59),
+        &mut unknown_version.as_slice(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, MmrError::UnsupportedDumpVersion(2)));
+
+    // A header announcing far more nodes than the input holds must not preallocate them.
+    let elements_count = (1u64 << 41) - 1;
+    let mut oversized = b"MMRDUMP\0".to_vec();
+    oversized.push(1);
+    oversized.extend_from_slice(&56u64.to_be_bytes());
+    oversized.extend_from_slice(&(1u64 << 40).to_be_bytes());
+    oversized.extend_from_slice(&elements_count.to_be_bytes());
+    oversized.push(0);
+    oversized.extend_from_slice(&elements_count.to_be_bytes());
+    let err = Mmr::import_from_reader(
+        Arc::new(InMemoryStore::default()),
+        Arc::new(KeccakHasher::new()),
+        Some(60),
+        &mut oversized.as_slice(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, MmrError::Io(_)));
+}
+
+#[tokio::test]
+async fn failed_imports_remove_the_chunks_already_written() {
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+    let source = Mmr::new(Arc::new(InMemoryStore::default()), hasher.clone(), Some(61)).unwrap();
+    let values: Vec<Hash32> = (0..2100).map(|i| lv(&i.to_string())).collect();
+    source.batch_append(&values).await.unwrap();
+    let mut dump = Vec::new();
+    source.export_to_writer(&mut dump).await.unwrap();
+
+    // The checksum is only known at the end, after the first node chunks were stored.
+    let mut corrupted = dump.clone();
+    *corrupted.last_mut().unwrap() ^= 0xff;
+    let store = Arc::new(InMemoryStore::default());
+    let err = Mmr::import_from_reader(
+        store.clone(),
+        hasher.clone(),
+        Some(62),
+        &mut corrupted.as_slice(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, MmrError::DumpChecksumMismatch));
+    assert!(store.scan_keys(62).await.unwrap().is_empty());
+
+    let imported = Mmr::import_from_reader(store, hasher, Some(62), &mut dump.as_slice())
+        .await
+        .unwrap();
+    assert_eq!(
+        imported.get_root_hash().await.unwrap(),
+        source.get_root_hash().await.unwrap()
+    );
+}
+
+/// Writer that destroys the MMR it is exporting once the header has been written.
+struct DestroyingWriter {
+    store: Arc<InMemoryStore>,
+    mmr_id: u64,
+    written: usize,
+}
+
+impl std::io::Write for DestroyingWriter {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        if self.written == 0 {
+            futures::executor::block_on(self.store.set(
+                StoreKey::metadata(self.mmr_id, KeyKind::ElementsCount),
+                StoreValue::U64(0),
+            ))
+            .unwrap();
+        }
+        self.written += bytes.len();
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn export_streams_nodes_and_rejects_a_count_that_moved_underneath() {
+    let store = Arc::new(InMemoryStore::default());
+    let mmr = Mmr::new(store.clone(), Arc::new(KeccakHasher::new()), Some(199)).unwrap();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+
+    let mut dump = Vec::new();
+    mmr.export_to_writer(&mut dump).await.unwrap();
+    // Header, four `(index, hash)` nodes, no external keys, the leaf data terminator and
+    // the checksum.
+    assert_eq!(
+        dump.len(),
+        8 + 1 + 8 + 8 + 8 + 1 + 32 + 8 + 4 * (8 + 32) + 8 + 8 + 32
+    );
+
+    let mut writer = DestroyingWriter {
+        store,
+        mmr_id: 199,
+        written: 0,
+    };
+    assert!(matches!(
+        mmr.export_to_writer(&mut writer).await,
+        Err(MmrError::ExportConflict {
+            expected: 4,
+            actual: 0
+        })
+    ));
+}
+
+#[tokio::test]
+async fn node_file_export_checks_the_registered_hasher() {
+    let registry = MmrRegistry::new(Arc::new(InMemoryStore::default()));
+    let mmr = registry.create(KECCAK_HASHER_CODE).await.unwrap();
+    mmr.append(lv("1")).await.unwrap();
+
+    let mut node_file = Vec::new();
+    assert!(matches!(
+        mmr.export_node_file(&mut node_file, POSEIDON_HASHER_CODE)
+            .await,
+        Err(MmrError::NodeFileHasherMismatch {
+            expected: KECCAK_HASHER_CODE,
+            found: POSEIDON_HASHER_CODE
+        })
+    ));
+    mmr.export_node_file(&mut node_file, KECCAK_HASHER_CODE)
+        .await
+        .unwrap();
+}
+
+#[cfg(feature = "mmap-store")]
+#[tokio::test]
+async fn should_serve_proofs_from_a_memory_mapped_node_file() {
+    use mmr::{MmapStore, StoreError};
+
+    let hasher = Arc::new(KeccakHasher::new());
+    let source = Mmr::new(Arc::new(InMemoryStore::default()), hasher.clone(), Some(61)).unwrap();
+    let values = (1..=9u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    source.batch_append(&values).await.unwrap();
+
+    let mut node_file = Vec::new();
+    source
+        .export_node_file(&mut node_file, KECCAK_HASHER_CODE)
+        .await
+        .unwrap();
+    let path = std::env::temp_dir().join(format!("mmr-node-file-{}.bin", std::process::id()));
+    std::fs::write(&path, &node_file).unwrap();
+
+    assert!(matches!(
+        MmapStore::open(&path, POSEIDON_HASHER_CODE),
+        Err(MmrError::NodeFileHasherMismatch {
+            expected: POSEIDON_HASHER_CODE,
+            found: KECCAK_HASHER_CODE
+        })
+    ));
+    let store = MmapStore::open(&path, KECCAK_HASHER_CODE).unwrap();
+    assert_eq!(store.mmr_id(), 61);
+    assert_eq!(store.hasher_code(), KECCAK_HASHER_CODE);
+    assert_eq!(store.elements_count(), 16);
+    let frozen = Mmr::new(store, hasher, Some(61)).unwrap();
+    assert_eq!(
+        frozen.get_root_hash().await.unwrap(),
+        source.get_root_hash().await.unwrap()
+    );
+    assert_eq!(
+        frozen.get_proof(8, None).await.unwrap(),
+        source.get_proof(8, None).await.unwrap()
+    );
+    assert!(matches!(
+        frozen.append(lv("10")).await,
+        Err(err) if matches!(err.store_error(), Some(StoreError::Unsupported(_)))
+    ));
+
+    std::fs::write(&path, &node_file[..node_file.len() - 1]).unwrap();
+    assert!(matches!(
+        MmapStore::open(&path, KECCAK_HASHER_CODE),
+        Err(MmrError::InvalidNodeFile(_))
+    ));
+    std::fs::remove_file(&path).unwrap();
+}
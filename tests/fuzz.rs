@@ -0,0 +1,68 @@
+#![cfg(feature = "arbitrary")]
+
+use std::sync::Arc;
+
+use arbitrary::{Arbitrary, Unstructured};
+use mmr::fuzz::{
+    LeafSequence, MAX_FUZZ_LEAVES, assert_append_invariants, assert_arbitrary_proof_is_sound,
+    assert_proof_invariants,
+};
+use mmr::hasher::KeccakHasher;
+use mmr::store::{StoreKey, StoreValue};
+use mmr::types::{Hash32, Proof};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use tokio::runtime::{Builder, Runtime};
+
+fn runtime() -> Runtime {
+    Builder::new_current_thread().build().unwrap()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn arbitrary_leaf_sequences_satisfy_append_and_proof_invariants(
+        data in vec(any::<u8>(), 0..4096),
+    ) {
+        let mut u = Unstructured::new(&data);
+        let LeafSequence(leaves) = LeafSequence::arbitrary(&mut u).unwrap();
+        prop_assert!(leaves.len() <= MAX_FUZZ_LEAVES);
+        prop_assert!(leaves.iter().all(|leaf| leaf.0[0] < 0x08));
+
+        let leaves = &leaves[..leaves.len().min(32)];
+        runtime().block_on(async {
+            assert_append_invariants(Arc::new(KeccakHasher::new()), leaves).await;
+            assert_proof_invariants(Arc::new(KeccakHasher::new()), leaves).await;
+        });
+    }
+
+    #[test]
+    fn arbitrary_proofs_are_never_accepted_for_foreign_values(
+        data in vec(any::<u8>(), 0..1024),
+    ) {
+        let leaves: Vec<Hash32> = (1u8..=9).map(|value| Hash32::new([value; 32])).collect();
+        let mut u = Unstructured::new(&data);
+        let mut proof = Proof::arbitrary(&mut u).unwrap();
+        let value = Hash32::arbitrary(&mut u).unwrap();
+        runtime().block_on(async {
+            assert_arbitrary_proof_is_sound(Arc::new(KeccakHasher::new()), &leaves, &proof, value)
+                .await;
+
+            proof.element_index %= 17;
+            proof.elements_count = 16;
+            proof.mmr_id = None;
+            assert_arbitrary_proof_is_sound(Arc::new(KeccakHasher::new()), &leaves, &proof, value)
+                .await;
+        });
+    }
+
+    #[test]
+    fn arbitrary_store_entries_never_panic(data in vec(any::<u8>(), 0..256)) {
+        let mut u = Unstructured::new(&data);
+        let key = StoreKey::arbitrary(&mut u).unwrap();
+        let value = StoreValue::arbitrary(&mut u).unwrap();
+        let _ = value.clone().expect_u64(&key);
+        let _ = value.expect_hash(&key);
+    }
+}
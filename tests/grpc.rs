@@ -0,0 +1,91 @@
+#![cfg(feature = "grpc-server")]
+
+use std::sync::Arc;
+
+use mmr::Mmr;
+use mmr::grpc::GrpcMmrService;
+use mmr::grpc::proto::mmr_service_server::MmrService;
+use mmr::grpc::proto::{
+    AppendRequest, BatchAppendRequest, GetPeaksRequest, GetProofRequest, GetRootRequest,
+    VerifyProofRequest,
+};
+use tonic::{Code, Request};
+
+fn leaf(value: u8) -> Vec<u8> {
+    let mut out = vec![0u8; 32];
+    out[31] = value;
+    out
+}
+
+#[tokio::test]
+async fn should_serve_append_proof_and_root_rpcs() {
+    let service = GrpcMmrService::new(Arc::new(Mmr::in_memory_keccak()));
+
+    let appended = service
+        .append(Request::new(AppendRequest { value: leaf(1) }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(appended.element_index, 1);
+    let batch = service
+        .batch_append(Request::new(BatchAppendRequest {
+            values: vec![leaf(2), leaf(3)],
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(batch.leaves_count, 3);
+    assert_eq!(batch.elements_count, 4);
+
+    let root = service
+        .get_root(Request::new(GetRootRequest {}))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(root.elements_count, 4);
+    assert_eq!(root.root_hash, batch.root_hash);
+
+    let peaks = service
+        .get_peaks(Request::new(GetPeaksRequest {
+            elements_count: None,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(peaks.peaks_hashes.len(), 2);
+
+    let proof = service
+        .get_proof(Request::new(GetProofRequest {
+            element_index: 2,
+            elements_count: None,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    let verified = service
+        .verify_proof(Request::new(VerifyProofRequest {
+            proof: Some(proof.clone()),
+            element_value: leaf(2),
+            elements_count: None,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(verified.valid);
+    let rejected = service
+        .verify_proof(Request::new(VerifyProofRequest {
+            proof: Some(proof),
+            element_value: leaf(9),
+            elements_count: None,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(!rejected.valid);
+
+    let err = service
+        .append(Request::new(AppendRequest { value: vec![1, 2] }))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+}
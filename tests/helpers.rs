@@ -1,6 +1,9 @@
 use mmr::{
-    element_index_to_leaf_index, elements_count_to_leaf_count, find_peaks, find_siblings,
-    get_peak_info, map_leaf_index_to_element_index,
+    MmrError, element_index_to_height, element_index_to_leaf_index, elements_count_to_leaf_count,
+    find_peaks, find_siblings, get_peak_info, leaf_count_to_append_no_merges,
+    leaf_count_to_mmr_size, leaf_count_to_peaks_count, left_child_index,
+    map_leaf_index_to_element_index, parent_index, proof_siblings_count, right_child_index,
+    sibling_index, try_leaf_count_to_mmr_size, try_map_leaf_index_to_element_index,
 };
 
 #[test]
@@ -133,6 +136,7 @@ fn test_find_siblings() {
 }
 
 #[test]
+#[allow(clippy::collapsible_if)]
 fn test_get_peak_info() {
     let peak_indices: Vec<Option<Vec<usize>>> = vec![
         Some(vec![0]),
@@ -238,7 +242,7 @@ fn test_get_peak_info() {
                         output2_vec[element_index - 1],
                     );
                     assert_eq!(
-                        get_peak_info(elements_count as u64, element_index as u64),
+                        get_peak_info(elements_count as u64, element_index as u64).unwrap(),
                         expected
                     );
                 }
@@ -246,3 +250,124 @@ fn test_get_peak_info() {
         }
     }
 }
+
+#[test]
+fn test_element_index_to_height() {
+    let heights = [0, 0, 1, 0, 0, 1, 2, 0, 0, 1, 0, 0, 1, 2, 3, 0];
+    for (element_index, expected) in (1u64..).zip(heights) {
+        assert_eq!(element_index_to_height(element_index).unwrap(), expected);
+    }
+    assert!(element_index_to_height(0).is_err());
+}
+
+#[test]
+fn test_node_navigation() {
+    let tests = [
+        (1, 2, 3, None),
+        (2, 1, 3, None),
+        (3, 6, 7, Some((1, 2))),
+        (4, 5, 6, None),
+        (6, 3, 7, Some((4, 5))),
+        (7, 14, 15, Some((3, 6))),
+        (10, 13, 14, Some((8, 9))),
+        (14, 7, 15, Some((10, 13))),
+        (15, 30, 31, Some((7, 14))),
+    ];
+
+    for (element_index, sibling, parent, children) in tests {
+        assert_eq!(sibling_index(element_index).unwrap(), sibling);
+        assert_eq!(parent_index(element_index).unwrap(), parent);
+        assert_eq!(
+            left_child_index(element_index).unwrap(),
+            children.map(|(left, _)| left)
+        );
+        assert_eq!(
+            right_child_index(element_index).unwrap(),
+            children.map(|(_, right)| right)
+        );
+    }
+}
+
+#[test]
+fn test_node_navigation_at_u64_max() {
+    assert_eq!(element_index_to_height(u64::MAX).unwrap(), 63);
+    assert_eq!(left_child_index(u64::MAX).unwrap(), Some(u64::MAX >> 1));
+    assert_eq!(right_child_index(u64::MAX).unwrap(), Some(u64::MAX - 1));
+    assert!(matches!(sibling_index(u64::MAX), Err(MmrError::Overflow)));
+    assert!(matches!(parent_index(u64::MAX), Err(MmrError::Overflow)));
+}
+
+#[test]
+fn test_proof_siblings_count() {
+    for elements_count in [1u64, 3, 4, 7, 8, 10, 11, 15, 49] {
+        let leaves_count = elements_count_to_leaf_count(elements_count).unwrap();
+        for leaf_index in 0..leaves_count {
+            let element_index = map_leaf_index_to_element_index(leaf_index);
+            assert_eq!(
+                proof_siblings_count(elements_count, element_index).unwrap(),
+                find_siblings(element_index, elements_count).unwrap().len()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_get_peak_info_rejects_invalid_input() {
+    assert!(matches!(
+        get_peak_info(7, 0),
+        Err(MmrError::InvalidElementIndex)
+    ));
+    assert!(matches!(
+        get_peak_info(7, 8),
+        Err(MmrError::InvalidElementIndex)
+    ));
+    assert!(matches!(
+        get_peak_info(0, 1),
+        Err(MmrError::InvalidElementIndex)
+    ));
+    assert!(matches!(
+        get_peak_info(5, 1),
+        Err(MmrError::InvalidElementCount)
+    ));
+    assert!(matches!(
+        proof_siblings_count(6, 4),
+        Err(MmrError::InvalidElementCount)
+    ));
+}
+
+#[test]
+fn test_checked_size_conversions() {
+    for leaf_count in 0u64..64 {
+        assert_eq!(
+            try_leaf_count_to_mmr_size(leaf_count).unwrap(),
+            leaf_count_to_mmr_size(leaf_count)
+        );
+        assert_eq!(
+            try_map_leaf_index_to_element_index(leaf_count).unwrap(),
+            map_leaf_index_to_element_index(leaf_count)
+        );
+    }
+
+    assert!(matches!(
+        try_leaf_count_to_mmr_size(u64::MAX),
+        Err(MmrError::Overflow)
+    ));
+    assert!(matches!(
+        try_map_leaf_index_to_element_index(1 << 63),
+        Err(MmrError::Overflow)
+    ));
+    assert_eq!(get_peak_info(u64::MAX, 1).unwrap(), (0, 63));
+}
+
+#[test]
+fn test_const_helpers() {
+    const ELEMENT_INDEX: u64 = map_leaf_index_to_element_index(3);
+    const MMR_SIZE: u64 = leaf_count_to_mmr_size(4);
+    const PEAKS: [u64; leaf_count_to_peaks_count(7) as usize] = [7, 10, 11];
+    const MERGES: u64 = leaf_count_to_append_no_merges(3);
+
+    assert_eq!(ELEMENT_INDEX, 5);
+    assert_eq!(MMR_SIZE, 7);
+    assert_eq!(PEAKS.to_vec(), find_peaks(11));
+    assert_eq!(MERGES, 2);
+}
@@ -0,0 +1,109 @@
+#![cfg(feature = "http-server")]
+
+use std::sync::Arc;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Method, Request, StatusCode};
+use mmr::Mmr;
+use mmr::http::HttpMmrService;
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+fn leaf(value: u8) -> String {
+    format!("0x{value:064x}")
+}
+
+async fn call(
+    router: &Router,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json");
+    let request = match body {
+        Some(body) => request.body(Body::from(body.to_string())).unwrap(),
+        None => request.body(Body::empty()).unwrap(),
+    };
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+#[tokio::test]
+async fn should_serve_json_append_proof_and_root_endpoints() {
+    let router = HttpMmrService::new(Arc::new(Mmr::in_memory_keccak())).into_router();
+
+    let (status, appended) = call(
+        &router,
+        Method::POST,
+        "/append",
+        Some(json!({ "value": leaf(1) })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(appended["elementIndex"], "1");
+
+    let (_, batch) = call(
+        &router,
+        Method::POST,
+        "/batch-append",
+        Some(json!({ "values": [leaf(2), leaf(3)] })),
+    )
+    .await;
+    assert_eq!(batch["leavesCount"], "3");
+    assert_eq!(batch["elementsCount"], "4");
+
+    let (_, root) = call(&router, Method::GET, "/root", None).await;
+    assert_eq!(root["elementsCount"], "4");
+    assert_eq!(root["rootHash"], batch["rootHash"]);
+
+    let (_, peaks) = call(&router, Method::GET, "/peaks?elementsCount=4", None).await;
+    assert_eq!(peaks["peaksHashes"].as_array().unwrap().len(), 2);
+
+    let (status, proof) = call(&router, Method::GET, "/proofs/2", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(proof["elementIndex"], "2");
+    assert_eq!(proof["version"], 1);
+
+    let (_, verified) = call(
+        &router,
+        Method::POST,
+        "/proofs/verify",
+        Some(json!({ "proof": proof, "elementValue": leaf(2) })),
+    )
+    .await;
+    assert_eq!(verified["valid"], true);
+    let (_, rejected) = call(
+        &router,
+        Method::POST,
+        "/proofs/verify",
+        Some(json!({ "proof": proof, "elementValue": leaf(9) })),
+    )
+    .await;
+    assert_eq!(rejected["valid"], false);
+
+    let (status, error) = call(&router, Method::GET, "/proofs/99", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(error["error"].is_string());
+    let (status, _) = call(&router, Method::GET, "/proofs/0", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, proof_at_2) = call(&router, Method::GET, "/proofs/2?elementsCount=3", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(proof_at_2["elementsCount"], "3");
+    let (status, _) = call(&router, Method::GET, "/peaks?elementsCount=abc", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (_, verified) = call(
+        &router,
+        Method::POST,
+        "/proofs/verify",
+        Some(json!({ "proof": proof_at_2, "elementValue": leaf(2), "elementsCount": "3" })),
+    )
+    .await;
+    assert_eq!(verified["valid"], true);
+}
@@ -0,0 +1,436 @@
+use std::sync::Arc;
+
+use mmr::error::{HasherError, MmrError};
+use mmr::hasher::{Hasher, KeccakHasher, PoseidonHasher};
+use mmr::interop::{self, ckb, evm, substrate};
+use mmr::test_utils::lv;
+use mmr::types::{Hash32, ZERO_HASH};
+use mmr::{InMemoryStore, Mmr, map_leaf_index_to_element_index, verify_merkle_proof};
+use starknet::core::types::FieldElement;
+
+#[tokio::test]
+async fn should_export_padded_merkle_root_and_translate_proofs() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let filler = Hash32::new([0xee; 32]);
+
+    for leaves_count in [1u64, 2, 3, 5, 8, 11] {
+        let mmr = Mmr::new(
+            Arc::new(InMemoryStore::default()),
+            hasher.clone(),
+            Some(105),
+        )
+        .unwrap();
+        let leaves: Vec<Hash32> = (1..=leaves_count).map(|i| lv(&i.to_string())).collect();
+        mmr.batch_append(&leaves).await.unwrap();
+
+        let mut level = leaves.clone();
+        level.resize(leaves_count.next_power_of_two() as usize, filler);
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| hasher.hash_pair(&pair[0], &pair[1]).unwrap())
+                .collect();
+        }
+        let root = mmr.padded_merkle_root(&filler, None).await.unwrap();
+        assert_eq!(root, level[0]);
+
+        for (leaf_index, leaf) in (0u64..).zip(&leaves) {
+            let proof = mmr
+                .get_proof(map_leaf_index_to_element_index(leaf_index), None)
+                .await
+                .unwrap();
+            let merkle_proof = proof
+                .to_padded_merkle_proof(hasher.as_ref(), &filler)
+                .unwrap();
+            assert_eq!(merkle_proof.leaf_index, leaf_index);
+            assert!(verify_merkle_proof(hasher.as_ref(), &merkle_proof, *leaf, &root).unwrap());
+            assert!(!verify_merkle_proof(hasher.as_ref(), &merkle_proof, filler, &root).unwrap());
+        }
+    }
+}
+
+#[tokio::test]
+async fn should_abi_encode_proofs_for_solidity_verifiers() {
+    let mmr = Mmr::in_memory_keccak();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let proof = mmr.get_proof(2, None).await.unwrap();
+    let root = mmr.get_root_hash().await.unwrap().unwrap();
+
+    let word = |value: u64| {
+        let mut out = [0u8; 32];
+        out[24..].copy_from_slice(&value.to_be_bytes());
+        out
+    };
+    let encoded = evm::encode_proof(&proof, &root);
+    assert_eq!(encoded.len(), 32 * (6 + 1 + 1 + 1 + 2));
+    assert_eq!(encoded[..32], word(2));
+    assert_eq!(encoded[32..64], lv("2").0);
+    assert_eq!(encoded[64..96], word(192));
+    assert_eq!(encoded[96..128], word(256));
+    assert_eq!(encoded[128..160], word(4));
+    assert_eq!(encoded[160..192], root.0);
+    assert_eq!(encoded[192..224], word(1));
+    assert_eq!(encoded[224..256], proof.siblings_hashes[0].0);
+    assert_eq!(encoded[256..288], word(2));
+    assert_eq!(encoded[288..320], proof.peaks_hashes[0].0);
+    assert_eq!(encoded[320..352], proof.peaks_hashes[1].0);
+
+    let selector = KeccakHasher::new()
+        .hash_bytes(evm::VERIFY_PROOF_SIGNATURE.as_bytes())
+        .unwrap();
+    let calldata = evm::verify_proof_calldata(&proof, &root);
+    assert_eq!(calldata[..4], selector[..4]);
+    assert_eq!(calldata[4..], encoded[..]);
+}
+
+#[tokio::test]
+async fn should_round_trip_poseidon_proofs_through_felts() {
+    let mmr = Mmr::in_memory(Arc::new(PoseidonHasher::new()));
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let proof = mmr.get_proof(2, None).await.unwrap();
+    let root = mmr.get_root_hash().await.unwrap().unwrap();
+
+    let felts = interop::starknet::proof_to_felts(&proof, &root).unwrap();
+    assert_eq!(felts.len(), 4 + 1 + 2 + 1 + 1);
+    assert_eq!(felts[1], FieldElement::from(4u64));
+    assert_eq!(felts[2], FieldElement::from(2u64));
+    assert_eq!(felts[4], FieldElement::from(2u64));
+    assert_eq!(felts[7], FieldElement::from(1u64));
+    assert_eq!(
+        interop::starknet::proof_from_felts(&felts).unwrap(),
+        (proof.clone(), root)
+    );
+
+    assert!(matches!(
+        interop::starknet::proof_from_felts(&felts[..felts.len() - 1]),
+        Err(MmrError::InvalidProofEncoding(_))
+    ));
+    assert!(matches!(
+        interop::starknet::proof_to_felts(&proof, &Hash32::new([0xff; 32])),
+        Err(MmrError::Hasher(HasherError::InvalidFieldElement { .. }))
+    ));
+}
+
+#[tokio::test]
+async fn should_emit_and_verify_ckb_compatible_proofs() {
+    let hasher = KeccakHasher::new();
+    let mmr = Mmr::in_memory_keccak();
+    let leaves: Vec<Hash32> = (1..=7).map(|i| lv(&i.to_string())).collect();
+    mmr.batch_append(&leaves).await.unwrap();
+    let peaks = mmr.get_peaks(None).await.unwrap();
+    assert_eq!(peaks.len(), 3);
+
+    let root = mmr.get_ckb_root(None).await.unwrap();
+    let rhs = hasher.hash_pair(&peaks[2], &peaks[1]).unwrap();
+    assert_eq!(root, hasher.hash_pair(&rhs, &peaks[0]).unwrap());
+
+    let first = mmr.get_ckb_proof(0, None).await.unwrap();
+    assert_eq!(first.mmr_size, 11);
+    let siblings = mmr.get_proof(1, None).await.unwrap().siblings_hashes;
+    assert_eq!(siblings[0], leaves[1]);
+    assert_eq!(first.proof_items, vec![siblings[0], siblings[1], rhs]);
+    assert!(ckb::verify_ckb_proof(&hasher, &root, &first, 0, leaves[0]).unwrap());
+    assert!(!ckb::verify_ckb_proof(&hasher, &root, &first, 0, leaves[1]).unwrap());
+
+    let position = ckb::element_index_to_ckb_position(8).unwrap();
+    let middle = mmr.get_ckb_proof(position, None).await.unwrap();
+    assert_eq!(middle.proof_items, vec![peaks[0], leaves[5], peaks[2]]);
+    assert!(ckb::verify_ckb_proof(&hasher, &root, &middle, position, leaves[4]).unwrap());
+
+    let last = mmr.get_ckb_proof(10, None).await.unwrap();
+    assert_eq!(last.proof_items, vec![peaks[0], peaks[1]]);
+    assert!(ckb::verify_ckb_proof(&hasher, &root, &last, 10, leaves[6]).unwrap());
+    assert!(!ckb::verify_ckb_proof(&hasher, &root, &last, 7, leaves[6]).unwrap());
+}
+
+/// `test_mmr_root` from nervosnetwork/merkle-mountain-range: eleven leaves hashing the
+/// little-endian `u32` index with unkeyed BLAKE2b-256.
+#[cfg(feature = "blake2")]
+#[tokio::test]
+async fn should_match_upstream_ckb_root_vector() {
+    let hasher = mmr::Blake2bHasher::new();
+    let mmr = Mmr::in_memory(Arc::new(hasher));
+    let leaves: Vec<Hash32> = (0u32..11)
+        .map(|i| hasher.hash_bytes(&i.to_le_bytes()).unwrap())
+        .collect();
+    mmr.batch_append(&leaves).await.unwrap();
+
+    let root = mmr.get_ckb_root(None).await.unwrap();
+    assert_eq!(
+        root,
+        lv("0xf6794677f37a57df6a5ec36ce61036e43a36c1a009d05c81c9aa685dde1fd6e3")
+    );
+    for position in [0, 15, 18] {
+        let leaf_index = mmr::element_index_to_leaf_index(position + 1).unwrap();
+        let proof = mmr.get_ckb_proof(position, None).await.unwrap();
+        assert!(
+            ckb::verify_ckb_proof(
+                &hasher,
+                &root,
+                &proof,
+                position,
+                leaves[leaf_index as usize]
+            )
+            .unwrap()
+        );
+    }
+}
+
+/// SCALE encoding of a `(u32, [u8; 32])` leaf, the shape of `ParentNumberAndHash`.
+fn substrate_leaf(index: u8) -> Vec<u8> {
+    let mut encoded = u32::from(index).to_le_bytes().to_vec();
+    encoded.extend_from_slice(&[index; 32]);
+    encoded
+}
+
+// Root and proof items below come from polkadot-ckb-merkle-mountain-range 0.8.1, the MMR
+// pallet-mmr runs on, merging with `H(left || right)` over leaves hashed as `H(leaf.encode())`.
+
+#[tokio::test]
+async fn should_produce_substrate_leaf_proofs() {
+    let hasher = KeccakHasher::new();
+    let mmr = Mmr::in_memory_keccak();
+    for i in 0..7 {
+        mmr.append_substrate_leaf(&substrate_leaf(i)).await.unwrap();
+    }
+
+    let latest = mmr.get_substrate_root(None).await.unwrap();
+    assert_eq!(
+        latest,
+        lv("0x499c2c1f3f924dd57e91d1ec1c19d6c45611550f3b5099ea4d3a71dc4782b3fe")
+    );
+    let expected_items = [
+        (
+            0,
+            vec![
+                "0xf9668b4588192680e6ef9174c11d6e26819d1c5cf5f8918b8cbcb4c8ef8164a9",
+                "0xe5122ad59100dd94f8dffd2c0c7d7025becf8ec00ca58413b1d26b0daa0ee866",
+                "0x1b8f3492d63b91b32184ca40555fe5eea0aa1b6e6f6b2646f41053c075a356c5",
+            ],
+        ),
+        (
+            4,
+            vec![
+                "0xfb47eff078ae72af4f37e2e75d62edade50d6d7c1d7241027137dbd9e4043547",
+                "0xa08ebb6d1c91dd94097302d3ce3a84ee4eb2a705a98c20259bdb1090a1600f14",
+                "0x6d28b60f9789a8973f88d84733c99c5bf3704b67c22dd24674e2a4e6cb0402ce",
+            ],
+        ),
+        (
+            6,
+            vec![
+                "0xfb47eff078ae72af4f37e2e75d62edade50d6d7c1d7241027137dbd9e4043547",
+                "0x219e67438f9a0d01a32ffdb2b687a996d24ad1c2f0450da373fb54bcbf1537d4",
+            ],
+        ),
+    ];
+    for (leaf_index, items) in expected_items {
+        let proof = mmr.get_substrate_proof(leaf_index, None).await.unwrap();
+        assert_eq!(proof.leaf_indices, vec![leaf_index]);
+        assert_eq!(proof.leaf_count, 7);
+        assert_eq!(proof.items, items.into_iter().map(lv).collect::<Vec<_>>());
+        let leaf = substrate_leaf(leaf_index as u8);
+        assert!(substrate::verify_substrate_proof(&hasher, &latest, &proof, &leaf).unwrap());
+        assert!(!substrate::verify_substrate_proof(&hasher, &latest, &proof, &leaf[1..]).unwrap());
+    }
+
+    let root = mmr.get_substrate_root(Some(5)).await.unwrap();
+    assert_eq!(root, mmr.get_ckb_root(Some(8)).await.unwrap());
+    let proof = mmr.get_substrate_proof(4, Some(5)).await.unwrap();
+    assert_eq!(proof.leaf_count, 5);
+    assert_eq!(proof.items, vec![mmr.get_peaks(Some(8)).await.unwrap()[0]]);
+    assert!(substrate::verify_substrate_proof(&hasher, &root, &proof, &substrate_leaf(4)).unwrap());
+    assert!(
+        !substrate::verify_substrate_proof(&hasher, &root, &proof, &substrate_leaf(3)).unwrap()
+    );
+    assert!(matches!(
+        mmr.get_substrate_proof(7, None).await,
+        Err(MmrError::InvalidLeafIndex)
+    ));
+
+    let mut multi = mmr.get_substrate_proof(2, None).await.unwrap();
+    multi.leaf_indices.push(3);
+    assert!(matches!(
+        substrate::verify_substrate_proof(&hasher, &latest, &multi, &substrate_leaf(2)),
+        Err(MmrError::InvalidProofEncoding(_))
+    ));
+}
+
+#[cfg(feature = "blake2")]
+#[tokio::test]
+async fn should_hash_with_blake2b_for_substrate_roots() {
+    let hasher = mmr::Blake2bHasher::new();
+    assert_eq!(
+        hasher.hash_bytes(b"").unwrap(),
+        lv("0x0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8")
+    );
+
+    let mmr = Mmr::in_memory(Arc::new(hasher));
+    for i in 0..7 {
+        mmr.append_substrate_leaf(&substrate_leaf(i)).await.unwrap();
+    }
+    let root = mmr.get_substrate_root(None).await.unwrap();
+    assert_eq!(
+        root,
+        lv("0x63625251bf7b6e52065a86b429d1f89643a91d314d17c6bea3034e6c1210c387")
+    );
+    let proof = mmr.get_substrate_proof(2, None).await.unwrap();
+    assert_eq!(
+        proof.items,
+        vec![
+            lv("0x3cf9d76cc35039415dc7b74300e5848a488d924725b674d32d25e885dba15df7"),
+            lv("0x737d786aa448a56e999016ce8ffeb626e21ef8386a1d6308b30c592dc0587327"),
+            lv("0x19d95d925b51fae376acdf7deade8de681fb41753dc7f418c212b8bc3b697ba2"),
+        ]
+    );
+    assert!(substrate::verify_substrate_proof(&hasher, &root, &proof, &substrate_leaf(2)).unwrap());
+}
+
+#[tokio::test]
+async fn should_import_herodotus_accumulator_layout() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let source = Mmr::in_memory(hasher.clone());
+    let leaves: Vec<Hash32> = (1..=5).map(|i| lv(&i.to_string())).collect();
+    let appended = source.batch_append(&leaves).await.unwrap();
+
+    let mut entries = vec![
+        ("7:leaf_count".to_string(), "5".to_string()),
+        ("7:elements_count".to_string(), "8".to_string()),
+        ("7:root_hash".to_string(), appended.root_hash.to_string()),
+        ("8:leaf_count".to_string(), "1".to_string()),
+    ];
+    for (index, hash) in source.get_nodes_in_range(1, 8).await.unwrap() {
+        entries.push((format!("7:hashes:{index}"), hash.to_string()));
+    }
+
+    let imported = Mmr::import_herodotus(
+        Arc::new(InMemoryStore::default()),
+        hasher.clone(),
+        Some(117),
+        "7",
+        entries.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(imported.get_leaves_count().await.unwrap(), 5);
+    assert_eq!(
+        imported.get_root_hash().await.unwrap(),
+        Some(appended.root_hash)
+    );
+    assert_eq!(
+        imported.get_proof(4, None).await.unwrap(),
+        source.get_proof(4, None).await.unwrap()
+    );
+
+    let mut tampered = entries.clone();
+    tampered[2].1 = ZERO_HASH.to_string();
+    assert!(matches!(
+        Mmr::import_herodotus(
+            Arc::new(InMemoryStore::default()),
+            hasher.clone(),
+            Some(117),
+            "7",
+            tampered,
+        )
+        .await,
+        Err(MmrError::RootMismatch)
+    ));
+
+    let mut oversized = entries.clone();
+    oversized[0].1 = u64::MAX.to_string();
+    assert!(matches!(
+        Mmr::import_herodotus(
+            Arc::new(InMemoryStore::default()),
+            hasher.clone(),
+            Some(117),
+            "7",
+            oversized,
+        )
+        .await,
+        Err(MmrError::Overflow)
+    ));
+
+    let mut missing = entries;
+    missing.pop();
+    assert!(matches!(
+        Mmr::import_herodotus(
+            Arc::new(InMemoryStore::default()),
+            hasher,
+            Some(117),
+            "7",
+            missing,
+        )
+        .await,
+        Err(MmrError::InvalidHerodotusImport(_))
+    ));
+}
+
+#[tokio::test]
+async fn should_accumulate_starknet_block_hashes() {
+    let store = Arc::new(InMemoryStore::default());
+    let accumulator = interop::starknet::StarknetAccumulator::new(store.clone(), None, 100)
+        .await
+        .unwrap();
+    assert_eq!(accumulator.next_block_number().await.unwrap(), 100);
+    let mmr_id = accumulator.mmr().mmr_id;
+
+    for block in 100..104u64 {
+        accumulator
+            .append_block(block, lv(&(block * 7).to_string()))
+            .await
+            .unwrap();
+    }
+    assert!(matches!(
+        accumulator.append_block(105, lv("1")).await,
+        Err(MmrError::UnexpectedBlockNumber {
+            expected: 104,
+            actual: 105
+        })
+    ));
+    assert!(matches!(
+        accumulator.append_block(104, Hash32::new([0xff; 32])).await,
+        Err(MmrError::Hasher(HasherError::InvalidFieldElement { .. }))
+    ));
+
+    assert!(matches!(
+        interop::starknet::StarknetAccumulator::new(store.clone(), Some(mmr_id), 90).await,
+        Err(MmrError::FirstBlockMismatch {
+            expected: 90,
+            found: 100
+        })
+    ));
+    let reopened = interop::starknet::StarknetAccumulator::open(store.clone(), mmr_id)
+        .await
+        .unwrap();
+    assert_eq!(reopened.first_block(), 100);
+    reopened.append_block(104, lv("728")).await.unwrap();
+    assert!(matches!(
+        accumulator.append_block(104, lv("1")).await,
+        Err(MmrError::UnexpectedBlockNumber {
+            expected: 105,
+            actual: 104
+        })
+    ));
+
+    let proof = accumulator.get_block_proof(102).await.unwrap();
+    assert_eq!(proof.element_index, map_leaf_index_to_element_index(3));
+    assert!(accumulator.verify_block(&proof, lv("714")).await.unwrap());
+    assert!(!accumulator.verify_block(&proof, lv("715")).await.unwrap());
+
+    let felts = accumulator.get_block_proof_felts(102).await.unwrap();
+    let (decoded, root) = interop::starknet::proof_from_felts(&felts).unwrap();
+    assert_eq!(decoded, proof);
+    assert_eq!(Some(root), accumulator.mmr().get_root_hash().await.unwrap());
+    assert!(matches!(
+        accumulator.get_block_proof(99).await,
+        Err(MmrError::BlockNotAccumulated(99))
+    ));
+    assert!(matches!(
+        accumulator.get_block_proof(105).await,
+        Err(MmrError::BlockNotAccumulated(105))
+    ));
+}
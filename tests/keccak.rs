@@ -1,8 +1,5 @@
-mod common;
-
-use common::{hash_from_hex, hash_to_hex};
 use mmr::hasher::{Hasher, KeccakHasher};
-use mmr::types::Hash32;
+use mmr::types::{Hash32, hash_from_hex};
 
 #[test]
 fn should_compute_a_hash_pair() {
@@ -16,7 +13,7 @@ fn should_compute_a_hash_pair() {
     let result = hasher.hash_pair(&a, &b).unwrap();
 
     assert_eq!(
-        hash_to_hex(&result),
+        result.to_string(),
         "0xa960dc82e45665d5b1340ee84f6c3f27abaac8235a1a3b7e954001c1bc682268"
     );
 }
@@ -30,7 +27,7 @@ fn should_compute_hash_count_and_bag() {
     let result = hasher.hash_count_and_bag(10, &bag).unwrap();
 
     assert_eq!(
-        hash_to_hex(&result),
+        result.to_string(),
         "0x70c01463d822d2205868c5a46eefc55658828015b83e4553c8462d2c6711d0e0"
     );
 }
@@ -38,8 +35,8 @@ fn should_compute_hash_count_and_bag() {
 #[test]
 fn hash_pair_is_deterministic_for_typed_inputs() {
     let hasher = KeccakHasher::new();
-    let a: Hash32 = [1u8; 32];
-    let b: Hash32 = [2u8; 32];
+    let a: Hash32 = Hash32::new([1u8; 32]);
+    let b: Hash32 = Hash32::new([2u8; 32]);
     let first = hasher.hash_pair(&a, &b).unwrap();
     let second = hasher.hash_pair(&a, &b).unwrap();
     assert_eq!(first, second);
@@ -1,18 +1,14 @@
-mod common;
-
-use common::{hash_from_hex, hash_to_hex};
 use mmr::error::HasherError;
 use mmr::hasher::{Hasher, PoseidonHasher};
-use mmr::types::Hash32;
+use mmr::types::{Hash32, hash_from_hex};
+use starknet::core::types::FieldElement;
 
 fn assert_matches_hex(actual: Hash32, expected_hex: &str) {
     let expected = hash_from_hex(expected_hex).unwrap();
     assert_eq!(
-        actual,
-        expected,
+        actual, expected,
         "hash mismatch: actual={} expected={}",
-        hash_to_hex(&actual),
-        expected_hex
+        actual, expected_hex
     );
 }
 
@@ -20,15 +16,15 @@ fn assert_matches_hex(actual: Hash32, expected_hex: &str) {
 fn should_compute_a_hash_pair() {
     let hasher = PoseidonHasher::new();
 
-    let a =
-        hash_from_hex("0x6109f1949f6a7555eccf4e15ce1f10fbd78091dfe715cc2e0c5a244d9d17761").unwrap();
+    let a = hash_from_hex("0x06109f1949f6a7555eccf4e15ce1f10fbd78091dfe715cc2e0c5a244d9d17761")
+        .unwrap();
     let b = hash_from_hex("0x0194791558611599fe4ae0fcfa48f095659c90db18e54de86f2d2f547f7369bf")
         .unwrap();
 
     let result = hasher.hash_pair(&a, &b).unwrap();
     assert_matches_hex(
         result,
-        "0x7b8180db85fa1e0b5041f38f57926743905702c498576991f04998b5d9476b4",
+        "0x07b8180db85fa1e0b5041f38f57926743905702c498576991f04998b5d9476b4",
     );
 }
 
@@ -52,16 +48,28 @@ fn check_genesis_hash() {
 
     assert_matches_hex(
         genesis,
-        "0x2241b3b7f1c4b9cf63e670785891de91f7237b1388f6635c1898ae397ad32dd",
+        "0x02241b3b7f1c4b9cf63e670785891de91f7237b1388f6635c1898ae397ad32dd",
     );
 }
 
 #[test]
 fn should_error_for_non_field_hash_input() {
     let hasher = PoseidonHasher::new();
-    let invalid = [0xffu8; 32];
-    let valid = [0u8; 32];
+    let invalid = Hash32::new([0xffu8; 32]);
+    let valid = Hash32::default();
 
     let err = hasher.hash_pair(&invalid, &valid).unwrap_err();
     assert!(matches!(err, HasherError::InvalidFieldElement { .. }));
 }
+
+#[test]
+fn should_convert_between_hash32_and_field_element() {
+    let hash = hash_from_hex("0x0000000000000000000000000000000000000000000000000000000000001234")
+        .unwrap();
+    let felt = FieldElement::try_from(hash).unwrap();
+    assert_eq!(felt, FieldElement::from(0x1234u64));
+    assert_eq!(Hash32::from(felt), hash);
+
+    let err = FieldElement::try_from(Hash32::new([0xffu8; 32])).unwrap_err();
+    assert!(matches!(err, HasherError::InvalidFieldElement { .. }));
+}
@@ -0,0 +1,342 @@
+#![cfg(feature = "postgres-store")]
+
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+mod common;
+
+use common::RecordingHook;
+use mmr::error::MmrError;
+use mmr::hasher::KeccakHasher;
+use mmr::test_utils::lv;
+use mmr::types::Hash32;
+use mmr::{Mmr, PostgresStore, PostgresStoreOptions};
+
+fn unique_test_mmr_id() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let nonce = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+    (nonce % ((i64::MAX as u64) - 10_000)) + 10_000
+}
+
+#[tokio::test]
+async fn postgres_batch_append_in_tx_rollback_leaves_store_unchanged() {
+    let database_url = match std::env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+
+    let store = Arc::new(
+        PostgresStore::connect_with_options(
+            &database_url,
+            PostgresStoreOptions {
+                initialize_schema: true,
+                max_connections: 2,
+            },
+        )
+        .await
+        .unwrap(),
+    );
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher.clone(), Some(unique_test_mmr_id())).unwrap();
+
+    let mut tx = store.begin_write_tx().await.unwrap();
+    let result = mmr
+        .batch_append_in_tx(&mut tx, &[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    assert_eq!(result.appended_count, 3);
+    assert!(!result.peaks_hashes.is_empty());
+    assert_eq!(
+        result.root_hash,
+        mmr::root_from_peaks(hasher.as_ref(), &result.peaks_hashes, result.elements_count).unwrap()
+    );
+    tx.rollback().await.unwrap();
+
+    assert_eq!(mmr.get_elements_count().await.unwrap(), 0);
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 0);
+    assert!(mmr.get_root_hash().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn postgres_batch_append_in_tx_returns_peaks_matching_committed_state() {
+    let database_url = match std::env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+
+    let store = Arc::new(
+        PostgresStore::connect_with_options(
+            &database_url,
+            PostgresStoreOptions {
+                initialize_schema: true,
+                max_connections: 2,
+            },
+        )
+        .await
+        .unwrap(),
+    );
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher.clone(), Some(unique_test_mmr_id())).unwrap();
+
+    let mut tx = store.begin_write_tx().await.unwrap();
+    let result = mmr
+        .batch_append_in_tx(&mut tx, &[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    assert!(!result.peaks_hashes.is_empty());
+    assert_eq!(
+        result.root_hash,
+        mmr::root_from_peaks(hasher.as_ref(), &result.peaks_hashes, result.elements_count).unwrap()
+    );
+    assert_eq!(
+        result.peaks_hashes,
+        mmr.get_peaks(Some(result.elements_count)).await.unwrap()
+    );
+    assert_eq!(
+        result.root_hash,
+        mmr.get_root_hash().await.unwrap().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn postgres_append_in_tx_commit_persists_write() {
+    let database_url = match std::env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+
+    let store = Arc::new(
+        PostgresStore::connect_with_options(
+            &database_url,
+            PostgresStoreOptions {
+                initialize_schema: true,
+                max_connections: 2,
+            },
+        )
+        .await
+        .unwrap(),
+    );
+    let mmr = Mmr::new(
+        store.clone(),
+        Arc::new(KeccakHasher::new()),
+        Some(unique_test_mmr_id()),
+    )
+    .unwrap();
+
+    let mut tx = store.begin_write_tx().await.unwrap();
+    let append = mmr.append_in_tx(&mut tx, lv("10")).await.unwrap();
+    tx.commit().await.unwrap();
+
+    assert_eq!(append.element_index, 1);
+    assert_eq!(mmr.get_elements_count().await.unwrap(), 1);
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 1);
+    assert!(mmr.get_root_hash().await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn postgres_multiple_appends_in_same_tx_are_composable() {
+    let database_url = match std::env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+
+    let store = Arc::new(
+        PostgresStore::connect_with_options(
+            &database_url,
+            PostgresStoreOptions {
+                initialize_schema: true,
+                max_connections: 2,
+            },
+        )
+        .await
+        .unwrap(),
+    );
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher.clone(), Some(unique_test_mmr_id())).unwrap();
+
+    let mut tx = store.begin_write_tx().await.unwrap();
+    let first = mmr.append_in_tx(&mut tx, lv("21")).await.unwrap();
+    let second = mmr.append_in_tx(&mut tx, lv("22")).await.unwrap();
+    tx.commit().await.unwrap();
+
+    assert_eq!(first.elements_count, 1);
+    assert_eq!(second.elements_count, 3);
+    assert_eq!(mmr.get_elements_count().await.unwrap(), 3);
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 2);
+
+    let mut tx = store.begin_write_tx().await.unwrap();
+    let first_batch = mmr.batch_append_in_tx(&mut tx, &[lv("31")]).await.unwrap();
+    let second_batch = mmr.batch_append_in_tx(&mut tx, &[lv("32")]).await.unwrap();
+    tx.commit().await.unwrap();
+
+    assert!(!first_batch.peaks_hashes.is_empty());
+    assert_eq!(
+        first_batch.root_hash,
+        mmr::root_from_peaks(
+            hasher.as_ref(),
+            &first_batch.peaks_hashes,
+            first_batch.elements_count,
+        )
+        .unwrap()
+    );
+    assert!(!second_batch.peaks_hashes.is_empty());
+    assert_eq!(
+        second_batch.root_hash,
+        mmr::root_from_peaks(
+            hasher.as_ref(),
+            &second_batch.peaks_hashes,
+            second_batch.elements_count,
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        second_batch.peaks_hashes,
+        mmr.get_peaks(Some(second_batch.elements_count))
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn postgres_batch_append_in_tx_updates_the_hash_index() {
+    let database_url = match std::env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+
+    let store = Arc::new(
+        PostgresStore::connect_with_options(
+            &database_url,
+            PostgresStoreOptions {
+                initialize_schema: true,
+                max_connections: 2,
+            },
+        )
+        .await
+        .unwrap(),
+    );
+    let mmr = Mmr::new(
+        store.clone(),
+        Arc::new(KeccakHasher::new()),
+        Some(unique_test_mmr_id()),
+    )
+    .unwrap()
+    .with_hash_index(true);
+
+    // Same slot prefix, so the second append has to see the first one's slot in the tx.
+    let first = Hash32::new([7u8; 32]);
+    let mut second_bytes = [7u8; 32];
+    second_bytes[31] = 8;
+    let second = Hash32::new(second_bytes);
+
+    let mut tx = store.begin_write_tx().await.unwrap();
+    mmr.append_in_tx(&mut tx, first).await.unwrap();
+    mmr.append_in_tx(&mut tx, second).await.unwrap();
+    tx.commit().await.unwrap();
+
+    assert_eq!(mmr.find_element_index(&first).await.unwrap(), Some(1));
+    assert_eq!(mmr.find_element_index(&second).await.unwrap(), Some(2));
+}
+
+#[tokio::test]
+async fn postgres_batch_append_in_tx_runs_hooks_and_publishes_after_commit() {
+    let database_url = match std::env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+
+    let store = Arc::new(
+        PostgresStore::connect_with_options(
+            &database_url,
+            PostgresStoreOptions {
+                initialize_schema: true,
+                max_connections: 2,
+            },
+        )
+        .await
+        .unwrap(),
+    );
+    let hook = Arc::new(RecordingHook {
+        reject_over: Some(2),
+        ..RecordingHook::default()
+    });
+    let mmr = Mmr::new(
+        store.clone(),
+        Arc::new(KeccakHasher::new()),
+        Some(unique_test_mmr_id()),
+    )
+    .unwrap()
+    .with_hook(hook.clone());
+    let mut updates = mmr.subscribe();
+
+    let mut tx = store.begin_write_tx().await.unwrap();
+    assert!(matches!(
+        mmr.batch_append_in_tx(&mut tx, &[lv("1"), lv("2"), lv("3")])
+            .await,
+        Err(MmrError::HookRejected(_))
+    ));
+    let result = mmr
+        .batch_append_in_tx(&mut tx, &[lv("1"), lv("2")])
+        .await
+        .unwrap();
+    assert_eq!(hook.before.load(Ordering::SeqCst), 1);
+    assert!(hook.after.lock().unwrap().is_empty());
+    assert!(updates.try_next().is_err());
+
+    tx.commit().await.unwrap();
+    mmr.publish_tx_append(&result).await;
+    assert_eq!(*hook.after.lock().unwrap(), vec![3]);
+    assert_eq!(updates.try_next().unwrap(), Some((3, result.root_hash)));
+    assert_eq!(mmr.op_stats().appends, 2);
+}
+
+#[tokio::test]
+async fn postgres_batch_append_in_tx_is_guarded_on_the_lease_owner() {
+    let database_url = match std::env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+
+    let store = Arc::new(
+        PostgresStore::connect_with_options(
+            &database_url,
+            PostgresStoreOptions {
+                initialize_schema: true,
+                max_connections: 2,
+            },
+        )
+        .await
+        .unwrap(),
+    );
+    let mmr_id = unique_test_mmr_id();
+    let mmr = Mmr::new(store.clone(), Arc::new(KeccakHasher::new()), Some(mmr_id)).unwrap();
+    let lease = mmr
+        .acquire_writer_lease(Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    // Another instance takes the lease over inside the transaction, after the lease check
+    // outside it has passed.
+    let mut tx = store.begin_write_tx().await.unwrap();
+    sqlx::query("UPDATE mmr_nodes SET value = $1 WHERE mmr_id = $2 AND kind = 4 AND idx = 0")
+        .bind((lease.owner() ^ 1).to_be_bytes().to_vec())
+        .bind(mmr_id as i64)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    assert!(matches!(
+        mmr.append_in_tx(&mut tx, lv("1")).await,
+        Err(MmrError::ConcurrentAppendConflict(_))
+    ));
+    tx.rollback().await.unwrap();
+    assert_eq!(mmr.get_elements_count().await.unwrap(), 0);
+
+    mmr.append(lv("1")).await.unwrap();
+}
@@ -0,0 +1,234 @@
+use std::sync::Arc;
+
+mod common;
+
+use common::NoCasStore;
+use futures::future::join_all;
+use mmr::error::{MmrError, StoreError};
+use mmr::hasher::{Hasher, KeccakHasher};
+use mmr::test_utils::{SpyStore, lv};
+use mmr::{
+    ID_ALLOCATOR_MMR_ID, InMemoryStore, KECCAK_HASHER_CODE, Mmr, MmrRegistry, POSEIDON_HASHER_CODE,
+    Store, derive_mmr_id,
+};
+
+#[tokio::test]
+async fn registry_creates_lists_opens_and_deletes_accumulators() {
+    let store = Arc::new(InMemoryStore::default());
+    let registry = MmrRegistry::new(store.clone());
+
+    let keccak = registry.create(KECCAK_HASHER_CODE).await.unwrap();
+    let poseidon = registry.create(POSEIDON_HASHER_CODE).await.unwrap();
+    assert_ne!(keccak.mmr_id, poseidon.mmr_id);
+    let appended = keccak.append(lv("1")).await.unwrap();
+
+    assert_eq!(
+        registry.list().await.unwrap(),
+        vec![keccak.mmr_id, poseidon.mmr_id]
+    );
+    assert_eq!(
+        registry.hasher_code(poseidon.mmr_id).await.unwrap(),
+        Some(POSEIDON_HASHER_CODE)
+    );
+
+    let reopened = registry.open(keccak.mmr_id).await.unwrap();
+    assert_eq!(
+        reopened.get_root_hash().await.unwrap(),
+        Some(appended.root_hash)
+    );
+
+    registry.delete(keccak.mmr_id).await.unwrap();
+    assert_eq!(registry.list().await.unwrap(), vec![poseidon.mmr_id]);
+    assert!(matches!(
+        registry.open(keccak.mmr_id).await.unwrap_err(),
+        MmrError::MmrNotFound(_)
+    ));
+    assert!(matches!(
+        registry.create(99).await.unwrap_err(),
+        MmrError::UnknownHasher(99)
+    ));
+    assert!(matches!(
+        MmrRegistry::new(store)
+            .with_hasher(0, Arc::new(KeccakHasher::new()))
+            .err(),
+        Some(MmrError::ReservedHasherCode)
+    ));
+}
+
+#[tokio::test]
+async fn wide_and_derived_mmr_ids_isolate_accumulators() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+
+    let tenant_a = derive_mmr_id(&["tenant-a", "chain-1"]);
+    let tenant_b = derive_mmr_id(&["tenant-a", "chain-2"]);
+    assert_ne!(tenant_a, tenant_b);
+    assert_eq!(tenant_a, derive_mmr_id(&["tenant-a", "chain-1"]));
+    assert_ne!(derive_mmr_id(&["ab", "c"]), derive_mmr_id(&["a", "bc"]));
+    assert!(tenant_a <= i64::MAX as u64);
+
+    let wide_id = u64::from(u32::MAX) + 1;
+    let a = Mmr::new(store.clone(), hasher.clone(), Some(tenant_a)).unwrap();
+    let wide = Mmr::new(store.clone(), hasher, Some(wide_id)).unwrap();
+    a.append(lv("1")).await.unwrap();
+    wide.append(lv("2")).await.unwrap();
+
+    assert_eq!(a.get_leaf_hash(0).await.unwrap(), lv("1"));
+    assert_eq!(wide.get_leaf_hash(0).await.unwrap(), lv("2"));
+    assert_eq!(store.scan_keys(wide_id).await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn id_allocation_skips_ids_already_in_the_store() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+    let explicit = Mmr::new(store.clone(), hasher.clone(), Some(41)).unwrap();
+    explicit.append(lv("1")).await.unwrap();
+
+    let allocated = Mmr::allocate(store.clone(), hasher.clone()).await.unwrap();
+    assert_eq!(allocated.mmr_id, 42);
+    let next = Mmr::allocate(store.clone(), hasher.clone()).await.unwrap();
+    assert_eq!(next.mmr_id, 43);
+
+    // Only the first allocation scans the store; later ones read the seeded counter.
+    let late = Mmr::new(store.clone(), hasher.clone(), Some(100)).unwrap();
+    late.append(lv("2")).await.unwrap();
+    let after_late = Mmr::allocate(store.clone(), hasher.clone()).await.unwrap();
+    assert_eq!(after_late.mmr_id, 44);
+
+    assert!(matches!(
+        Mmr::new(store, hasher, Some(ID_ALLOCATOR_MMR_ID)),
+        Err(MmrError::ReservedMmrId)
+    ));
+}
+
+#[tokio::test]
+async fn store_backed_id_allocation_is_unique_across_handles() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+
+    let allocated = join_all((0..8).map(|_| Mmr::allocate(store.clone(), hasher.clone())))
+        .await
+        .into_iter()
+        .map(|mmr| mmr.unwrap().mmr_id)
+        .collect::<Vec<_>>();
+    let mut unique = allocated.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(unique.len(), allocated.len());
+    assert!(!allocated.contains(&ID_ALLOCATOR_MMR_ID));
+
+    let built = Mmr::builder(store.clone())
+        .hasher(hasher.clone())
+        .build()
+        .await
+        .unwrap();
+    assert!(!allocated.contains(&built.mmr_id));
+
+    let from_peaks = Mmr::create_from_peaks(store.clone(), hasher, None, vec![lv("1")], 1)
+        .await
+        .unwrap();
+    assert!(from_peaks.mmr_id > built.mmr_id);
+
+    let registry = MmrRegistry::new(store);
+    let created = registry.create(KECCAK_HASHER_CODE).await.unwrap();
+    assert!(created.mmr_id > from_peaks.mmr_id);
+    assert!(
+        !registry
+            .list()
+            .await
+            .unwrap()
+            .contains(&ID_ALLOCATOR_MMR_ID)
+    );
+}
+
+#[tokio::test]
+async fn append_to_many_commits_all_mmrs_in_one_guarded_batch() {
+    let store = Arc::new(SpyStore::default());
+    let registry = MmrRegistry::new(store.clone());
+    let first = registry.create(KECCAK_HASHER_CODE).await.unwrap();
+    let second = registry.create(KECCAK_HASHER_CODE).await.unwrap();
+    first.append(lv("100")).await.unwrap();
+
+    let first_values = [lv("1"), lv("2"), lv("3")];
+    let second_values = [lv("4")];
+    let before = store.metrics();
+    let results = registry
+        .append_to_many(&[(&second, &second_values), (&first, &first_values)])
+        .await
+        .unwrap();
+    let after = store.metrics();
+    assert_eq!(after.write_batch_calls, before.write_batch_calls + 1);
+    assert_eq!(after.set_many_calls, before.set_many_calls);
+
+    let expected_first = Mmr::in_memory_keccak();
+    expected_first.append(lv("100")).await.unwrap();
+    let expected_first = expected_first.batch_append(&first_values).await.unwrap();
+    let expected_second = Mmr::in_memory_keccak()
+        .batch_append(&second_values)
+        .await
+        .unwrap();
+    assert_eq!(results[0].root_hash, expected_second.root_hash);
+    assert_eq!(results[1].root_hash, expected_first.root_hash);
+    assert_eq!(results[1].leaves_count, 4);
+
+    let next = first.append(lv("5")).await.unwrap();
+    assert_eq!(next.leaves_count, 5);
+    let reopened = registry.open(second.mmr_id).await.unwrap();
+    assert_eq!(
+        reopened.get_root_hash().await.unwrap(),
+        Some(expected_second.root_hash)
+    );
+
+    let err = registry
+        .append_to_many(&[(&first, &second_values), (&first, &second_values)])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, MmrError::DuplicateMmrInBatch(id) if id == first.mmr_id));
+
+    store.set_fail_set_many(true);
+    assert!(
+        registry
+            .append_to_many(&[(&first, &second_values)])
+            .await
+            .is_err()
+    );
+    store.set_fail_set_many(false);
+    assert_eq!(first.get_leaves_count().await.unwrap(), 5);
+
+    // A concurrent append through another handle makes the first attempt's guard fail.
+    let other_handle = registry.open(first.mmr_id).await.unwrap();
+    other_handle.append(lv("6")).await.unwrap();
+    let results = registry
+        .append_to_many(&[(&first, &second_values)])
+        .await
+        .unwrap();
+    assert_eq!(results[0].leaves_count, 7);
+    assert_eq!(other_handle.get_leaves_count().await.unwrap(), 7);
+
+    let foreign = MmrRegistry::new(store.clone())
+        .open(second.mmr_id)
+        .await
+        .unwrap();
+    let err = registry
+        .append_to_many(&[(&foreign, &second_values)])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, MmrError::ForeignRegistryMmr(id) if id == second.mmr_id));
+}
+
+#[tokio::test]
+async fn id_allocation_is_refused_without_an_atomic_compare_and_set() {
+    let store = Arc::new(NoCasStore(InMemoryStore::default()));
+    let hasher: Arc<dyn Hasher> = Arc::new(KeccakHasher::new());
+
+    let err = Mmr::allocate(store.clone(), hasher).await.unwrap_err();
+    assert!(matches!(
+        err.store_error(),
+        Some(StoreError::Unsupported("allocate_mmr_id"))
+    ));
+    assert!(matches!(
+        mmr::allocate_mmr_id_from_counter(&store.0).await,
+        Ok(1)
+    ));
+}
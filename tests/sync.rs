@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+mod common;
+
+use common::NoCasStore;
+use mmr::error::MmrError;
+use mmr::hasher::KeccakHasher;
+use mmr::test_utils::lv;
+use mmr::{CopyOptions, InMemoryStore, Mmr, MmrFollower};
+
+#[tokio::test]
+async fn copy_to_moves_nodes_and_metadata_with_progress_and_resume() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let source = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(53)).unwrap();
+    let values = (1..=20u64).map(|i| lv(&i.to_string())).collect::<Vec<_>>();
+    source.append_with_key(500, values[0]).await.unwrap();
+    let appended = source.batch_append(&values[1..]).await.unwrap();
+
+    let target_store = Arc::new(InMemoryStore::default());
+    let mut progress = Vec::new();
+    let copied = source
+        .copy_to(
+            target_store.clone(),
+            54,
+            CopyOptions {
+                chunk_size: 16,
+                ..CopyOptions::default()
+            },
+            |update| progress.push(update),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(progress.len(), 3);
+    assert_eq!(
+        progress.last().unwrap().next_element_index,
+        appended.elements_count + 1
+    );
+    assert_eq!(
+        copied.get_root_hash().await.unwrap(),
+        Some(appended.root_hash)
+    );
+    assert_eq!(copied.get_leaves_count().await.unwrap(), 20);
+    assert_eq!(copied.get_peaks(None).await.unwrap(), appended.peaks_hashes);
+
+    let proof = copied.get_proof(1, None).await.unwrap();
+    assert!(copied.verify_proof(&proof, lv("1"), None).await.unwrap());
+    assert_eq!(copied.get_element_index_by_key(500).await.unwrap(), Some(1));
+
+    let resumed = source
+        .copy_to(
+            target_store,
+            55,
+            CopyOptions {
+                chunk_size: 16,
+                resume_from: 17,
+            },
+            |_| {},
+        )
+        .await
+        .unwrap();
+    assert!(resumed.get_proof(1, None).await.is_err());
+    assert_eq!(
+        resumed.get_root_hash().await.unwrap(),
+        Some(appended.root_hash)
+    );
+}
+
+#[tokio::test]
+async fn should_follow_a_writer_with_verified_diff_packets() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let writer = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        hasher.clone(),
+        Some(102),
+    )
+    .unwrap();
+    let replica = Mmr::new(Arc::new(InMemoryStore::default()), hasher, Some(102)).unwrap();
+
+    writer
+        .batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let packet = writer.export_diff(0).await.unwrap();
+    replica.apply_diff(&packet).await.unwrap();
+
+    writer
+        .batch_append(&[lv("4"), lv("5"), lv("6")])
+        .await
+        .unwrap();
+    writer.append_with_key(700, lv("7")).await.unwrap();
+    let packet = writer.export_diff(4).await.unwrap();
+    assert_eq!(packet.nodes.len(), 7);
+    assert_eq!(packet.external_keys, vec![(700, 11)]);
+    assert!(packet.leaf_data.is_empty());
+    let result = replica.apply_diff(&packet).await.unwrap().unwrap();
+    assert_eq!(result.elements_count, 11);
+    assert_eq!(
+        replica.get_element_index_by_key(700).await.unwrap(),
+        Some(11)
+    );
+    assert_eq!(
+        replica.get_root_hash().await.unwrap(),
+        writer.get_root_hash().await.unwrap()
+    );
+    assert_eq!(
+        replica.get_proof(5, None).await.unwrap(),
+        writer.get_proof(5, None).await.unwrap()
+    );
+
+    assert_eq!(
+        replica
+            .apply_diff(&writer.export_diff(11).await.unwrap())
+            .await
+            .unwrap(),
+        None
+    );
+    assert!(matches!(
+        replica.apply_diff(&packet).await,
+        Err(MmrError::DiffBaseMismatch {
+            expected: 4,
+            actual: 11
+        })
+    ));
+
+    writer.append(lv("8")).await.unwrap();
+    let mut tampered = writer.export_diff(11).await.unwrap();
+    tampered.nodes[0].1 = lv("9");
+    assert!(matches!(
+        replica.apply_diff(&tampered).await,
+        Err(MmrError::InvalidDiff(_))
+    ));
+    assert_eq!(replica.get_elements_count().await.unwrap(), 11);
+
+    let with_data = writer
+        .append_with_data(lv("10"), b"payload".to_vec())
+        .await
+        .unwrap();
+    let packet = writer.export_diff(11).await.unwrap();
+    assert_eq!(
+        packet.leaf_data,
+        vec![(with_data.element_index, b"payload".to_vec())]
+    );
+    let mut misplaced = packet.clone();
+    misplaced.leaf_data[0].0 = 13;
+    assert!(matches!(
+        replica.apply_diff(&misplaced).await,
+        Err(MmrError::InvalidDiff(_))
+    ));
+    replica.apply_diff(&packet).await.unwrap();
+    assert_eq!(
+        replica
+            .get_leaf_data(with_data.element_index)
+            .await
+            .unwrap(),
+        Some(b"payload".to_vec())
+    );
+}
+
+#[tokio::test]
+async fn diffs_and_dumps_read_external_keys_without_scanning() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let writer = Mmr::new(
+        NoCasStore(InMemoryStore::default()),
+        hasher.clone(),
+        Some(104),
+    )
+    .unwrap();
+    writer
+        .batch_append_with_keys(&[(500, lv("1")), (501, lv("2"))])
+        .await
+        .unwrap();
+    writer.append_with_key(502, lv("3")).await.unwrap();
+    assert_eq!(
+        writer.export_diff(3).await.unwrap().external_keys,
+        vec![(502, 4)]
+    );
+
+    let mut dump = Vec::new();
+    writer.export_to_writer(&mut dump).await.unwrap();
+    let imported = Mmr::import_from_reader(
+        Arc::new(InMemoryStore::default()),
+        hasher,
+        Some(104),
+        &mut dump.as_slice(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        imported.get_element_index_by_key(501).await.unwrap(),
+        Some(2)
+    );
+    assert_eq!(
+        imported.export_diff(3).await.unwrap().external_keys,
+        vec![(502, 4)]
+    );
+}
+
+#[tokio::test]
+async fn should_follow_roots_with_a_light_client() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(
+        Arc::new(InMemoryStore::default()),
+        hasher.clone(),
+        Some(103),
+    )
+    .unwrap();
+    let mut follower = MmrFollower::new(hasher.clone());
+
+    let result = mmr
+        .batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+    let root = follower
+        .apply_append(&[lv("1"), lv("2"), lv("3")], Some(&result.root_hash))
+        .unwrap();
+    assert_eq!(root, result.root_hash);
+    assert_eq!(follower.elements_count(), 4);
+    assert_eq!(follower.leaves_count(), 3);
+
+    let result = mmr.append(lv("4")).await.unwrap();
+    assert!(matches!(
+        follower.apply_append(&[lv("5")], Some(&result.root_hash)),
+        Err(MmrError::RootMismatch)
+    ));
+    assert_eq!(follower.elements_count(), 4);
+    follower.apply_append(&[lv("4")], None).unwrap();
+    assert_eq!(follower.root_hash().unwrap(), result.root_hash);
+
+    let result = mmr.batch_append(&[lv("5"), lv("6")]).await.unwrap();
+    assert!(matches!(
+        follower.apply_peak_update(result.elements_count, result.peaks_hashes.clone(), &lv("1")),
+        Err(MmrError::RootMismatch)
+    ));
+    follower
+        .apply_peak_update(
+            result.elements_count,
+            result.peaks_hashes.clone(),
+            &result.root_hash,
+        )
+        .unwrap();
+    assert_eq!(follower.peaks(), result.peaks_hashes.as_slice());
+
+    let proof = mmr.get_proof(2, None).await.unwrap();
+    assert!(follower.verify_proof(&proof, lv("2")).unwrap());
+    assert!(!follower.verify_proof(&proof, lv("3")).unwrap());
+
+    let restored =
+        MmrFollower::from_peaks(hasher, result.elements_count, result.peaks_hashes).unwrap();
+    assert_eq!(restored.root_hash().unwrap(), result.root_hash);
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "test-utils")]
+
+use std::sync::Arc;
+
+use mmr::Mmr;
+use mmr::hasher::{Hasher, KeccakHasher, PoseidonHasher};
+use mmr::store::Store;
+use mmr::test_utils::{
+    KECCAK_ROOT_FIXTURES, POSEIDON_ROOT_FIXTURES, RootFixture, SpyStore, deterministic_leaves, lv,
+};
+
+async fn assert_fixtures(hasher: Arc<dyn Hasher>, fixtures: &[RootFixture]) {
+    for fixture in fixtures {
+        let mmr = Mmr::new(SpyStore::new(), hasher.clone(), None).unwrap();
+        for leaf in fixture.leaves() {
+            mmr.append(leaf).await.unwrap();
+        }
+
+        assert_eq!(
+            mmr.get_elements_count().await.unwrap(),
+            fixture.elements_count
+        );
+        assert_eq!(
+            mmr.get_root_hash().await.unwrap(),
+            Some(fixture.root()),
+            "leaves_count {}",
+            fixture.leaves_count
+        );
+    }
+}
+
+#[tokio::test]
+async fn keccak_root_fixtures_match_appended_leaves() {
+    assert_fixtures(Arc::new(KeccakHasher::new()), KECCAK_ROOT_FIXTURES).await;
+}
+
+#[tokio::test]
+async fn poseidon_root_fixtures_match_appended_leaves() {
+    assert_fixtures(Arc::new(PoseidonHasher::new()), POSEIDON_ROOT_FIXTURES).await;
+}
+
+#[test]
+fn lv_parses_decimal_and_hex_leaves() {
+    assert_eq!(lv("1").0[31], 1);
+    assert_eq!(lv("256").0[30], 1);
+    assert_eq!(lv("0x01"), lv("1"));
+}
+
+#[tokio::test]
+async fn deterministic_leaves_are_stable_and_accepted_by_poseidon() {
+    let leaves = deterministic_leaves(7, 16);
+    assert_eq!(leaves, deterministic_leaves(7, 16));
+    assert_ne!(leaves, deterministic_leaves(8, 16));
+
+    let mmr = Mmr::new(SpyStore::new(), Arc::new(PoseidonHasher::new()), None).unwrap();
+    mmr.batch_append(&leaves).await.unwrap();
+    assert_eq!(mmr.get_leaves_count().await.unwrap(), 16);
+}
+
+#[tokio::test]
+async fn spy_store_counts_calls_and_injects_failures() {
+    let store = Arc::new(SpyStore::new());
+    let mmr = Mmr::new(store.clone(), Arc::new(KeccakHasher::new()), None).unwrap();
+    mmr.append(lv("1")).await.unwrap();
+    assert!(store.metrics().get_many_calls > 0);
+
+    store.fail_next_get_many_transiently(1);
+    let err = store.get_many(&[]).await.unwrap_err();
+    assert!(err.is_transient());
+    assert!(store.get_many(&[]).await.unwrap().is_empty());
+
+    store.set_fail_set_many(true);
+    assert!(mmr.append(lv("2")).await.is_err());
+}
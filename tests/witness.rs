@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use mmr::error::MmrError;
+use mmr::hasher::KeccakHasher;
+use mmr::test_utils::lv;
+use mmr::types::Hash32;
+use mmr::{InMemoryStore, KeyKind, Mmr, Store, StoreKey};
+
+#[tokio::test]
+async fn should_update_existing_proofs_as_the_mmr_grows() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(92)).unwrap();
+
+    let leaves: Vec<Hash32> = (1..=13).map(|i| lv(&i.to_string())).collect();
+    mmr.batch_append(&leaves[..5]).await.unwrap();
+    let old_proofs = [
+        mmr.get_proof(1, None).await.unwrap(),
+        mmr.get_proof(4, None).await.unwrap(),
+        mmr.get_proof(8, None).await.unwrap(),
+    ];
+
+    let result = mmr.batch_append(&leaves[5..]).await.unwrap();
+    let mut appended_nodes = std::collections::BTreeMap::new();
+    for index in (result.previous_elements_count + 1)..=result.elements_count {
+        let key = StoreKey::new(92, KeyKind::NodeHash, index);
+        let value = store.get(&key).await.unwrap().unwrap();
+        appended_nodes.insert(index, value.expect_hash(&key).unwrap());
+    }
+
+    for (old_proof, leaf) in old_proofs.iter().zip([leaves[0], leaves[2], leaves[4]]) {
+        let expected = mmr.get_proof(old_proof.element_index, None).await.unwrap();
+        let refreshed = mmr.refresh_proof(old_proof, None).await.unwrap();
+        assert_eq!(refreshed, expected);
+        assert_eq!(
+            old_proof
+                .update(result.elements_count, &appended_nodes)
+                .unwrap(),
+            expected
+        );
+        assert!(mmr.verify_proof(&refreshed, leaf, None).await.unwrap());
+    }
+
+    assert!(matches!(
+        old_proofs[0].update(result.elements_count, &Default::default()),
+        Err(MmrError::NoHashFoundForIndex(_))
+    ));
+    assert!(matches!(
+        mmr.refresh_proof(&old_proofs[0], Some(4)).await,
+        Err(MmrError::InvalidElementCount)
+    ));
+}
+
+#[tokio::test]
+async fn should_keep_tracked_witnesses_current_across_appends() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store, hasher, Some(93)).unwrap();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+
+    let tracker = mmr.witness_tracker();
+    tracker.track(1).await.unwrap();
+    tracker.track(4).await.unwrap();
+
+    tracker.append(lv("4")).await.unwrap();
+    tracker
+        .batch_append(&[lv("5"), lv("6"), lv("7")])
+        .await
+        .unwrap();
+    mmr.append(lv("8")).await.unwrap();
+    assert_eq!(tracker.get_witness(1).await.unwrap().elements_count, 11);
+    tracker.append(lv("9")).await.unwrap();
+
+    for (element_index, leaf) in [(1, lv("1")), (4, lv("3"))] {
+        let witness = tracker.get_witness(element_index).await.unwrap();
+        assert_eq!(witness, mmr.get_proof(element_index, None).await.unwrap());
+        assert!(mmr.verify_proof(&witness, leaf, None).await.unwrap());
+    }
+
+    mmr.append(lv("10")).await.unwrap();
+    assert_eq!(tracker.sync().await.unwrap(), 18);
+    assert_eq!(
+        tracker.get_witness(4).await.unwrap(),
+        mmr.get_proof(4, None).await.unwrap()
+    );
+
+    assert!(tracker.untrack(1).await);
+    assert_eq!(tracker.tracked().await, vec![4]);
+    assert_eq!(tracker.witnesses().await.len(), 1);
+}
+
+#[tokio::test]
+async fn witness_tracker_does_not_commit_when_a_witness_cannot_be_updated() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = Mmr::new(store.clone(), hasher, Some(195)).unwrap();
+    mmr.batch_append(&[lv("1"), lv("2"), lv("3")])
+        .await
+        .unwrap();
+
+    let tracker = mmr.witness_tracker();
+    let witness = tracker.track(1).await.unwrap();
+    mmr.append(lv("4")).await.unwrap();
+    store
+        .delete_many(&[StoreKey::new(195, KeyKind::NodeHash, 6)])
+        .await
+        .unwrap();
+
+    let err = tracker.append(lv("5")).await.unwrap_err();
+    assert!(matches!(err, MmrError::NoHashFoundForIndex(6)));
+    assert_eq!(mmr.get_elements_count().await.unwrap(), 7);
+    assert_eq!(tracker.get_witness(1).await, Some(witness));
+}